@@ -0,0 +1,103 @@
+use std::path::PathBuf;
+use std::{env, fs};
+
+/// The appearance of the REPL, read from the config file and overridden by
+/// the command line.
+///
+/// The config file has one `key = value` per line, with `#` starting a
+/// comment:
+///
+/// ```text
+/// prompt = λ❯
+/// continuation_prompt = ...
+/// banner = false
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// Shown before each input.
+    pub prompt: String,
+    /// Shown at the right edge of the terminal on the lines continuing an
+    /// input with unclosed brackets.
+    pub continuation_prompt: String,
+    /// Whether to greet with the version and a hint to type `:help`.
+    pub banner: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            prompt: "monkey❯".into(),
+            continuation_prompt: "…".into(),
+            banner: true,
+        }
+    }
+}
+
+impl Config {
+    /// `$MONKEY_REPL_CONFIG`, else `repl.conf` in the `monkey` directory of
+    /// `$XDG_CONFIG_HOME` or `~/.config`.
+    pub fn default_path() -> Option<PathBuf> {
+        if let Some(path) = env::var_os("MONKEY_REPL_CONFIG") {
+            return Some(path.into());
+        }
+        let config_home = match env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+        Some(config_home.join("monkey").join("repl.conf"))
+    }
+
+    /// The defaults updated with the config file at `path`, if there is one.
+    pub fn load(path: Option<PathBuf>) -> Result<Self, String> {
+        let mut config = Self::default();
+        let Some(path) = path else { return Ok(config) };
+        match fs::read_to_string(&path) {
+            Ok(contents) => config
+                .update(&contents)
+                .map_err(|e| format!("{}: {}", path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("Failed reading {}: {}", path.display(), e)),
+        }
+        Ok(config)
+    }
+
+    /// Applies the `key = value` lines of a config file.
+    pub fn update(&mut self, contents: &str) -> Result<(), String> {
+        for (n, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(format!("line {}: expected `key = value`", n + 1));
+            };
+            self.set(key.trim(), value.trim())
+                .map_err(|e| format!("line {}: {}", n + 1, e))?;
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "prompt" => self.prompt = value.into(),
+            "continuation_prompt" => self.continuation_prompt = value.into(),
+            "banner" => {
+                self.banner = match value {
+                    "true" => true,
+                    "false" => false,
+                    _ => return Err(format!("`banner` must be true or false, got `{}`", value)),
+                }
+            }
+            _ => return Err(format!("unknown key `{}`", key)),
+        }
+        Ok(())
+    }
+
+    /// The banner shown at startup unless `banner` is false.
+    pub fn greeting(&self) -> String {
+        format!(
+            "monkey {}\nType `:help` for a list of commands",
+            monkey::version()
+        )
+    }
+}
@@ -1,47 +1,299 @@
-use std::cell::RefCell;
-use std::io::{self, BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, IsTerminal};
 use std::io::{Read, Write};
-use std::rc::Rc;
+use std::{env, fs, process, thread};
 
-use monkey::Node;
-use monkey::eval;
+use monkey::CommandOutput;
 use monkey::Lexer;
-use monkey::Environment;
+use monkey::LineOutput;
 use monkey::Parser;
+use monkey::Program;
+use monkey::ReplCore;
+use monkey::TokenKind;
 
-const PROMPT: &str = "monkey❯";
+use config::Config;
+
+mod config;
+
+/// The stack of the thread the REPL evaluates on, enough for
+/// `Interpreter::DEFAULT_MAX_CALL_DEPTH` calls in debug builds too.
+const REPL_STACK_SIZE: usize = 64 * 1024 * 1024;
+
+const USAGE: &str = "usage: monkey-repl [[--config <file>] [--prompt <text>] [--no-banner] | ast [--format dot|mermaid|json] <file> | tokens [--verify] <file>... | transpile --target js <file> | compile --target wasm <file>]";
 
 fn main() {
-    let stdin = io::stdin();
-    let stdout = io::stdout();
-    start_repl(stdin, stdout);
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("ast") => run_ast(&args[1..]),
+        Some("tokens") => run_tokens(&args[1..]),
+        Some("transpile") => run_transpile(&args[1..]),
+        Some("compile") => run_compile(&args[1..]),
+        Some(arg) if !arg.starts_with("--") => exit_with_usage(),
+        _ => {
+            let config = repl_config(&args);
+            let repl = thread::Builder::new()
+                .stack_size(REPL_STACK_SIZE)
+                .spawn(move || start_repl(io::stdin(), io::stdout(), &config))
+                .expect("Failed spawning the REPL thread");
+            if repl.join().is_err() {
+                process::exit(101);
+            }
+        }
+    }
+}
+
+/// The config file, `--config <file>` or the default one, overridden by
+/// `--prompt <text>` and `--no-banner`.
+fn repl_config(args: &[String]) -> Config {
+    let mut path = Config::default_path();
+    let mut prompt = None;
+    let mut banner = true;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => match args.next() {
+                Some(p) => path = Some(p.into()),
+                None => exit_with_usage(),
+            },
+            "--prompt" => match args.next() {
+                Some(p) => prompt = Some(p.clone()),
+                None => exit_with_usage(),
+            },
+            "--no-banner" => banner = false,
+            _ => exit_with_usage(),
+        }
+    }
+
+    let mut config = Config::load(path).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    if let Some(prompt) = prompt {
+        config.prompt = prompt;
+    }
+    config.banner &= banner;
+    config
+}
+
+/// How many more brackets `input` opens than it closes, so that the REPL
+/// keeps reading lines while it is positive.
+fn open_brackets(input: &str) -> isize {
+    Lexer::new(input)
+        .map(|token| match token.kind {
+            TokenKind::LParen | TokenKind::LBrace | TokenKind::LBracket => 1,
+            TokenKind::RParen | TokenKind::RBrace | TokenKind::RBracket => -1,
+            _ => 0,
+        })
+        .sum()
+}
+
+fn exit_with_usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+/// Reads the script at `path`, exiting the process on errors.
+fn read_file(path: &str) -> String {
+    fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Failed reading {}: {}", path, e);
+        process::exit(1);
+    })
+}
+
+/// Reads and parses the script at `path`, exiting the process on errors.
+fn parse_file(path: &str) -> Program {
+    let source = read_file(path);
+    let lexer = Lexer::new(&source);
+    let outcome = Parser::new(lexer).parse();
+    for warning in &outcome.warnings {
+        eprintln!("{:?}", warning);
+    }
+    match outcome.into_result() {
+        Ok(program) => program,
+        Err(errors) => {
+            for error in errors {
+                eprintln!("{:?}", error);
+            }
+            process::exit(1);
+        }
+    }
+}
+
+fn run_ast(args: &[String]) {
+    let mut format = "dot";
+    let mut path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => match args.next() {
+                Some(f) => format = f,
+                None => exit_with_usage(),
+            },
+            _ => path = Some(arg),
+        }
+    }
+    let Some(path) = path else { exit_with_usage() };
+
+    let program = parse_file(path);
+    match format {
+        "dot" => print!("{}", monkey::to_dot(&program)),
+        "mermaid" => print!("{}", monkey::to_mermaid(&program)),
+        "json" => println!("{}", monkey::to_json(&program)),
+        _ => {
+            eprintln!(
+                "Unknown format `{}`, expected `dot`, `mermaid` or `json`",
+                format
+            );
+            process::exit(2);
+        }
+    }
 }
 
-fn start_repl(stdin: impl Read, mut stdout: impl Write) {
+/// Parses the `--target <target> <file>` arguments shared by `transpile` and
+/// `compile`.
+fn target_and_path(args: &[String]) -> (&str, &str) {
+    let mut target = None;
+    let mut path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--target" => match args.next() {
+                Some(t) => target = Some(t.as_str()),
+                None => exit_with_usage(),
+            },
+            _ => path = Some(arg.as_str()),
+        }
+    }
+    let (Some(target), Some(path)) = (target, path) else {
+        exit_with_usage()
+    };
+    (target, path)
+}
+
+fn run_transpile(args: &[String]) {
+    let (target, path) = target_and_path(args);
+    let program = parse_file(path);
+    match target {
+        "js" => print!("{}", monkey::to_javascript(&program)),
+        _ => {
+            eprintln!("Unknown target `{}`, expected `js`", target);
+            process::exit(2);
+        }
+    }
+}
+
+fn run_compile(args: &[String]) {
+    let (target, path) = target_and_path(args);
+    let program = parse_file(path);
+    match target {
+        "wasm" => match monkey::to_wat(&program) {
+            Ok(wat) => print!("{}", wat),
+            Err(e) => {
+                eprintln!("{:?}", e);
+                process::exit(1);
+            }
+        },
+        _ => {
+            eprintln!("Unknown target `{}`, expected `wasm`", target);
+            process::exit(2);
+        }
+    }
+}
+
+fn run_tokens(args: &[String]) {
+    let verify = args.iter().any(|arg| arg == "--verify");
+    let paths: Vec<_> = args.iter().filter(|arg| *arg != "--verify").collect();
+    if paths.is_empty() {
+        exit_with_usage();
+    }
+
+    let mut failed = false;
+    for path in paths {
+        let source = read_file(path);
+        if !verify {
+            for token in Lexer::new(&source) {
+                println!(
+                    "{}..={}\t{:?}",
+                    token.span.start, token.span.end, token.kind
+                );
+            }
+            continue;
+        }
+
+        let mismatches = monkey::verify_spans(&source);
+        for (token, text) in &mismatches {
+            println!(
+                "{}: span {}..={} covers {:?}, expected {:?}",
+                path,
+                token.span.start,
+                token.span.end,
+                text,
+                token.kind.to_string()
+            );
+        }
+        failed |= !mismatches.is_empty();
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
+
+fn start_repl(stdin: impl Read, mut stdout: impl Write, config: &Config) {
     let mut stdin = BufReader::new(stdin);
     let mut input = String::new();
-    let environment = Rc::new(RefCell::new(Environment::new()));
+    let repl = ReplCore::new();
+
+    if config.banner {
+        writeln!(stdout, "{}", config.greeting()).expect("Failed writing to stdout");
+    }
 
     loop {
         input.clear();
-        write!(stdout, "{} ", PROMPT).expect("Failed writing to stdout");
+        write!(stdout, "{} ", config.prompt).expect("Failed writing to stdout");
         io::stdout().flush().expect("Failed to flush stdout");
 
         stdin
             .read_line(&mut input)
             .expect("Failed to read line from stdin");
 
-        let lexer = Lexer::new(&input);
-        let mut parser = Parser::new(lexer);
-        let (program, errors) = parser.parse_program();
+        while open_brackets(&input) > 0 {
+            write_continuation_prompt(&mut stdout, config);
+            io::stdout().flush().expect("Failed to flush stdout");
+            let read = stdin
+                .read_line(&mut input)
+                .expect("Failed to read line from stdin");
+            if read == 0 {
+                break;
+            }
+        }
 
-        for error in errors {
-            writeln!(stdout, "{:?}", error).expect("Failed writing to stdout");
+        match repl.eval_line(&input) {
+            LineOutput::Command(CommandOutput::Clear) => {
+                write!(stdout, "\x1b[2J\x1b[H").expect("Failed writing to stdout");
+            }
+            output => {
+                for line in output.lines() {
+                    writeln!(stdout, "{}", line).expect("Failed writing to stdout");
+                }
+            }
         }
+    }
+}
 
-        match eval(Node::Program(program), &environment) {
-            Ok(evaluated) => writeln!(stdout, "{}", evaluated).expect("Failed writing to stdout"),
-            Err(e) => writeln!(stdout, "{:?}", e).expect("Failed writing to stdout"),
-        };
+/// Indents a continuation line like the prompt and shows the continuation
+/// prompt at the right edge, restoring the cursor afterwards.
+fn write_continuation_prompt(stdout: &mut impl Write, config: &Config) {
+    let indent = " ".repeat(config.prompt.chars().count() + 1);
+    let width = config.continuation_prompt.chars().count();
+    let mut right = String::new();
+    if width > 0 && io::stdout().is_terminal() {
+        // Save the cursor, move to the last column and back to fit the prompt.
+        right.push_str("\x1b7\x1b[999C");
+        if width > 1 {
+            right.push_str(&format!("\x1b[{}D", width - 1));
+        }
+        right.push_str(&config.continuation_prompt);
+        right.push_str("\x1b8");
     }
+    write!(stdout, "{}{}", right, indent).expect("Failed writing to stdout");
 }
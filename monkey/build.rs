@@ -0,0 +1,67 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::{env, fs};
+
+/// Embeds the abbreviated hash of the checked out commit as
+/// `MONKEY_GIT_HASH`, or `unknown` outside of a git checkout, and the Monkey
+/// programs, see [`embed_programs`].
+fn main() {
+    embed_programs();
+
+    let hash = git(&["rev-parse", "--short", "HEAD"]).unwrap_or_else(|| "unknown".into());
+    println!("cargo:rustc-env=MONKEY_GIT_HASH={}", hash);
+
+    // Rebuild when a commit is checked out or the current branch moves.
+    let mut watched = vec![git(&["rev-parse", "--git-path", "HEAD"])];
+    if let Some(branch) = git(&["symbolic-ref", "-q", "HEAD"]) {
+        watched.push(git(&["rev-parse", "--git-path", &branch]));
+    }
+    for path in watched.into_iter().flatten() {
+        println!("cargo:rerun-if-changed={}", path);
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+}
+
+/// Writes `programs.rs` to `OUT_DIR`, including `programs/prelude.mky` as
+/// `PRELUDE`, the names its lines starting with `let` bind as `PRELUDE_NAMES`
+/// and each `programs/examples/<name>.mky` as `(name, source)` in `EXAMPLES`,
+/// sorted by name.
+fn embed_programs() {
+    let dir = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("programs");
+    let mut examples: Vec<PathBuf> = fs::read_dir(dir.join("examples"))
+        .expect("Failed reading programs/examples")
+        .map(|entry| entry.expect("Failed reading programs/examples").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "mky"))
+        .collect();
+    examples.sort();
+
+    let prelude = fs::read_to_string(dir.join("prelude.mky")).expect("Failed reading prelude.mky");
+    let names: Vec<&str> = prelude
+        .lines()
+        .filter_map(|line| line.strip_prefix("let "))
+        .filter_map(|rest| rest.split_whitespace().next())
+        .collect();
+
+    let mut out = format!(
+        "const PRELUDE: &str = include_str!({:?});\n\n",
+        dir.join("prelude.mky")
+    );
+    out.push_str(&format!("const PRELUDE_NAMES: &[&str] = &{:?};\n\n", names));
+    out.push_str("const EXAMPLES: &[(&str, &str)] = &[\n");
+    for path in examples {
+        let name = path.file_stem().unwrap().to_string_lossy();
+        out.push_str(&format!("    ({:?}, include_str!({:?})),\n", name, path));
+    }
+    out.push_str("];\n");
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    fs::write(out_dir.join("programs.rs"), out).expect("Failed writing programs.rs");
+    println!("cargo:rerun-if-changed={}", dir.display());
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    let stdout = stdout.trim();
+    (output.status.success() && !stdout.is_empty()).then(|| stdout.to_string())
+}
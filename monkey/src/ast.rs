@@ -1,6 +1,7 @@
+use num_bigint::BigInt;
 use std::{fmt, ops};
 
-use crate::token::Token;
+use crate::token::{escape_bytes, Comment, Span, Token};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Node {
@@ -9,24 +10,52 @@ pub enum Node {
     Expression(Expression),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Program(Vec<Statement>);
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Program {
+    statements: Vec<Statement>,
+    /// Leading comments of each statement, parallel to `statements`.
+    comments: Vec<Vec<Comment>>,
+    trailing_comments: Vec<Comment>,
+}
 
 impl Program {
     pub fn new() -> Self {
-        Self(Vec::new())
+        Self::default()
     }
 
     pub fn push(&mut self, stmt: Statement) {
-        self.0.push(stmt)
+        self.push_with_comments(stmt, Vec::new())
+    }
+
+    pub fn push_with_comments(&mut self, stmt: Statement, comments: Vec<Comment>) {
+        self.statements.push(stmt);
+        self.comments.push(comments);
     }
 
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.statements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.statements.is_empty()
     }
 
     pub fn statements(&self) -> &[Statement] {
-        &self.0
+        &self.statements
+    }
+
+    /// Comments preceding the statement at `index`.
+    pub fn comments(&self, index: usize) -> &[Comment] {
+        &self.comments[index]
+    }
+
+    /// Comments after the last statement.
+    pub fn trailing_comments(&self) -> &[Comment] {
+        &self.trailing_comments
+    }
+
+    pub fn set_trailing_comments(&mut self, comments: Vec<Comment>) {
+        self.trailing_comments = comments;
     }
 }
 
@@ -34,16 +63,26 @@ impl ops::Index<usize> for Program {
     type Output = Statement;
 
     fn index(&self, index: usize) -> &Self::Output {
-        &self.0[index]
+        &self.statements[index]
     }
 }
 
 impl fmt::Display for Program {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut out = String::new();
-        for stmt in &self.0 {
+        for (stmt, comments) in self.statements.iter().zip(&self.comments) {
+            for comment in comments {
+                out.push_str(&comment.text);
+                out.push('\n');
+            }
             out.push_str(&stmt.to_string())
         }
+        for comment in &self.trailing_comments {
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(&comment.text);
+        }
         write!(f, "{}", out)
     }
 }
@@ -55,10 +94,36 @@ pub enum Statement {
         name: String,
         value: Expression,
     },
+    /// `let [a, b] = value;` or `let {a, b} = value;`, binding the parts of
+    /// `value` to several names.
+    Destructure {
+        token: Token,
+        target: Target,
+        value: Expression,
+    },
     Return {
         token: Token,
         value: Expression,
     },
+    /// Leaves the nearest enclosing loop, which evaluates to `value`, or
+    /// `null` without one.
+    Break {
+        token: Token,
+        value: Option<Expression>,
+    },
+    Continue {
+        token: Token,
+    },
+    Throw {
+        token: Token,
+        value: Expression,
+    },
+    /// Hands `value` to whoever drives the generator and suspends it until
+    /// the next value is requested.
+    Yield {
+        token: Token,
+        value: Expression,
+    },
     Expr(Expression),
 }
 
@@ -66,13 +131,108 @@ impl fmt::Display for Statement {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Let { token, name, value } => write!(f, "{} {} = {};", token.kind, name, value),
-            Self::Return { token, value } => write!(f, "{} {};", token.kind, value),
+            Self::Destructure {
+                token,
+                target,
+                value,
+            } => write!(f, "{} {} = {};", token.kind, target, value),
+            Self::Return { token, value }
+            | Self::Throw { token, value }
+            | Self::Yield { token, value } => {
+                write!(f, "{} {};", token.kind, value)
+            }
+            Self::Break {
+                token,
+                value: Some(value),
+            } => write!(f, "{} {};", token.kind, value),
+            Self::Break { token, value: None } | Self::Continue { token } => {
+                write!(f, "{};", token.kind)
+            }
             Self::Expr(expr) => write!(f, "{}", expr),
         }
     }
 }
 
-pub type BlockStatement = Program;
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockStatement {
+    statements: Vec<Statement>,
+    /// Leading comments of each statement, parallel to `statements`.
+    comments: Vec<Vec<Comment>>,
+    trailing_comments: Vec<Comment>,
+}
+
+impl BlockStatement {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, stmt: Statement) {
+        self.push_with_comments(stmt, Vec::new())
+    }
+
+    pub fn push_with_comments(&mut self, stmt: Statement, comments: Vec<Comment>) {
+        self.statements.push(stmt);
+        self.comments.push(comments);
+    }
+
+    pub fn len(&self) -> usize {
+        self.statements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.statements.is_empty()
+    }
+
+    pub fn statements(&self) -> &[Statement] {
+        &self.statements
+    }
+
+    /// Comments preceding the statement at `index`.
+    pub fn comments(&self, index: usize) -> &[Comment] {
+        &self.comments[index]
+    }
+
+    /// Comments between the last statement and the closing brace.
+    pub fn trailing_comments(&self) -> &[Comment] {
+        &self.trailing_comments
+    }
+
+    pub fn set_trailing_comments(&mut self, comments: Vec<Comment>) {
+        self.trailing_comments = comments;
+    }
+}
+
+impl ops::Index<usize> for BlockStatement {
+    type Output = Statement;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.statements[index]
+    }
+}
+
+/// Renders the block with braces and one indented statement per line. Nested
+/// blocks are indented once more for every level, since each line of a
+/// statement's own rendering gets prefixed.
+impl fmt::Display for BlockStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.statements.is_empty() && self.trailing_comments.is_empty() {
+            return write!(f, "{{}}");
+        }
+        writeln!(f, "{{")?;
+        for (stmt, comments) in self.statements.iter().zip(&self.comments) {
+            for comment in comments {
+                writeln!(f, "    {}", comment.text)?;
+            }
+            for line in stmt.to_string().lines() {
+                writeln!(f, "    {}", line)?;
+            }
+        }
+        for comment in &self.trailing_comments {
+            writeln!(f, "    {}", comment.text)?;
+        }
+        write!(f, "}}")
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Identifier(String);
@@ -90,10 +250,46 @@ impl fmt::Display for Identifier {
     }
 }
 
+/// The parameter list of a function without the parentheses, e.g. `x, ..rest`.
+pub fn format_parameters(parameters: &[Identifier], rest: Option<&Identifier>) -> String {
+    let params: Vec<_> = parameters
+        .iter()
+        .map(|param| param.to_string())
+        .chain(rest.map(|rest| format!("..{}", rest)))
+        .collect();
+    params.join(", ")
+}
+
+/// A float that equals another float with the same bits, so that literals and
+/// values can be compared structurally. The `==` of Monkey follows IEEE 754
+/// instead, where `NaN` is unequal to itself.
+#[derive(Debug, Clone, Copy)]
+pub struct Float(pub f64);
+
+impl PartialEq for Float {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for Float {}
+
+impl fmt::Display for Float {
+    /// Always with a fraction or exponent, e.g. `3.0`, to tell floats from
+    /// integers.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Expression {
     Ident(Identifier),
     IntegerLiteral(isize),
+    /// An integer literal too large for [`Expression::IntegerLiteral`].
+    BigIntegerLiteral(BigInt),
+    FloatLiteral(Float),
+    Null,
     Prefix {
         token: Token,
         operator: String,
@@ -111,21 +307,98 @@ pub enum Expression {
         consequence: BlockStatement,
         alternative: Option<BlockStatement>,
     },
+    While {
+        condition: Box<Expression>,
+        body: BlockStatement,
+    },
+    /// Evaluates `body` once, then again while `condition` holds.
+    DoWhile {
+        body: BlockStatement,
+        condition: Box<Expression>,
+    },
+    /// Evaluates `body` until it reaches a `break`.
+    Loop {
+        body: BlockStatement,
+    },
+    /// Evaluates `body` for each element of an array, character of a string or
+    /// integer of a range, bound to `variable`.
+    For {
+        variable: Identifier,
+        iterable: Box<Expression>,
+        body: BlockStatement,
+    },
+    /// Evaluates `handler` with the value thrown by `body`, or the message of
+    /// a runtime error, bound to `error`.
+    Try {
+        body: BlockStatement,
+        error: Identifier,
+        handler: BlockStatement,
+    },
+    /// Evaluates the body of the first arm whose pattern matches `value`.
+    Match {
+        value: Box<Expression>,
+        arms: Vec<(Pattern, BlockStatement)>,
+    },
+    /// Evaluates its statements in a scope of their own, to the value of the
+    /// last one.
+    Block(BlockStatement),
     FunctionLiteral {
         parameters: Vec<Identifier>,
+        /// Collects the arguments beyond `parameters` into an array, written
+        /// `..rest` as the last parameter.
+        rest: Option<Identifier>,
         body: BlockStatement,
+        /// Whether it was written `fn*`, so that calling it gives a generator
+        /// running `body`.
+        generator: bool,
     },
     Call {
         function: Box<Expression>,
         arguments: Vec<Expression>,
+        /// From the start of `function` to the closing parenthesis.
+        span: Span,
     },
     StringLiteral(String),
+    CharLiteral(char),
+    BytesLiteral(Vec<u8>),
     ArrayLiteral(Vec<Expression>),
+    /// `..value` in an array literal or the arguments of a call, standing for
+    /// the elements of `value`.
+    Spread(Box<Expression>),
+    /// `left[index]`, or `left?.[index]` if `optional`.
     IndexExpr {
         left: Box<Expression>,
         index: Box<Expression>,
+        /// Whether this is `null` instead of an error if `left` is `null`.
+        optional: bool,
+    },
+    /// `left.name`, the value of a hash at the string key `"name"`, or
+    /// `left?.name` if `optional`.
+    Field {
+        left: Box<Expression>,
+        name: Identifier,
+        /// Whether this is `null` instead of an error if `left` is `null`.
+        optional: bool,
+    },
+    Slice {
+        left: Box<Expression>,
+        start: Option<Box<Expression>>,
+        end: Option<Box<Expression>>,
     },
     HashLiteral(Vec<(Expression, Expression)>),
+    Assign {
+        token: Token,
+        name: Identifier,
+        value: Box<Expression>,
+    },
+    /// `target = value`, where `target` is an index expression like `a[0]` or
+    /// `a[i][j]` into a variable, which is rebound to a copy with the element
+    /// replaced.
+    IndexAssign {
+        token: Token,
+        target: Box<Expression>,
+        value: Box<Expression>,
+    },
 }
 
 impl fmt::Display for Expression {
@@ -133,6 +406,9 @@ impl fmt::Display for Expression {
         match self {
             Expression::Ident(Identifier(value)) => write!(f, "{}", value),
             Expression::IntegerLiteral(value) => write!(f, "{}", value),
+            Expression::BigIntegerLiteral(value) => write!(f, "{}", value),
+            Expression::FloatLiteral(value) => write!(f, "{}", value),
+            Expression::Null => write!(f, "null"),
             Expression::Prefix {
                 token: _,
                 operator,
@@ -151,32 +427,160 @@ impl fmt::Display for Expression {
                 alternative,
             } => {
                 let alternative = match alternative {
-                    Some(alt) => format!("else {}", alt),
+                    Some(alt) => match alt.statements() {
+                        [stmt @ Statement::Expr(Expression::If { .. })]
+                            if alt.comments(0).is_empty() && alt.trailing_comments().is_empty() =>
+                        {
+                            format!(" else {}", stmt)
+                        }
+                        _ => format!(" else {}", alt),
+                    },
                     None => "".into(),
                 };
-                write!(f, "if{} {} {}", condition, consequence, alternative)
+                write!(f, "if {} {}{}", condition, consequence, alternative)
+            }
+            Expression::While { condition, body } => write!(f, "while {} {}", condition, body),
+            Expression::DoWhile { body, condition } => {
+                write!(f, "do {} while {}", body, condition)
             }
-            Expression::FunctionLiteral { parameters, body } => {
-                let params: Vec<_> = parameters.iter().map(|param| param.to_string()).collect();
-                write!(f, "({}){}", params.join(", "), body)
+            Expression::Loop { body } => write!(f, "loop {}", body),
+            Expression::For {
+                variable,
+                iterable,
+                body,
+            } => write!(f, "for ({} in {}) {}", variable, iterable, body),
+            Expression::Try {
+                body,
+                error,
+                handler,
+            } => write!(f, "try {} catch ({}) {}", body, error, handler),
+            Expression::Match { value, arms } => {
+                writeln!(f, "match {} {{", value)?;
+                for (pattern, body) in arms {
+                    let arm = format!("{} => {},", pattern, body);
+                    for line in arm.lines() {
+                        writeln!(f, "    {}", line)?;
+                    }
+                }
+                write!(f, "}}")
             }
+            Expression::Block(block) => write!(f, "{}", block),
+            Expression::FunctionLiteral {
+                parameters,
+                rest,
+                body,
+                generator,
+            } => write!(
+                f,
+                "fn{}({}) {}",
+                if *generator { "*" } else { "" },
+                format_parameters(parameters, rest.as_ref()),
+                body
+            ),
             Expression::Call {
                 function,
                 arguments,
+                ..
             } => {
                 let args: Vec<_> = arguments.iter().map(|arg| arg.to_string()).collect();
                 write!(f, "{}({})", function, args.join(", "))
             }
             Expression::StringLiteral(s) => write!(f, "{}", s),
+            Expression::CharLiteral(c) => write!(f, "{}", c),
+            Expression::BytesLiteral(b) => write!(f, "b\"{}\"", escape_bytes(b)),
+            Expression::Spread(value) => write!(f, "..{}", value),
             Expression::ArrayLiteral(v) => {
                 let elements: Vec<_> = v.iter().map(|it| it.to_string()).collect();
                 write!(f, "[{}]", elements.join(", "))
             }
-            Expression::IndexExpr { left, index } => write!(f, "({}[{}])", left, index),
+            Expression::IndexExpr {
+                left,
+                index,
+                optional,
+            } => {
+                let access = if *optional { "?." } else { "" };
+                write!(f, "({}{}[{}])", left, access, index)
+            }
+            Expression::Field {
+                left,
+                name,
+                optional,
+            } => {
+                let access = if *optional { "?." } else { "." };
+                write!(f, "({}{}{})", left, access, name)
+            }
+            Expression::Slice { left, start, end } => {
+                let start = start.as_ref().map(|it| it.to_string()).unwrap_or_default();
+                let end = end.as_ref().map(|it| it.to_string()).unwrap_or_default();
+                write!(f, "({}[{}:{}])", left, start, end)
+            }
             Expression::HashLiteral(v) => {
-                let pairs: Vec<_> = v .iter() .map(|(key, val)| format!("{}:{}", key, val)) .collect();
+                let pairs: Vec<_> = v
+                    .iter()
+                    .map(|(key, val)| format!("{}:{}", key, val))
+                    .collect();
                 write!(f, "{{{}}}", pairs.join(", "))
             }
+            Expression::Assign {
+                token: _,
+                name,
+                value,
+            } => write!(f, "{} = {}", name, value),
+            Expression::IndexAssign { target, value, .. } => write!(f, "{} = {}", target, value),
+        }
+    }
+}
+
+/// The names bound by a destructuring `let`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// `[a, b]`, binding the elements of an array by position. Missing
+    /// elements are bound to `null`.
+    Array(Vec<Identifier>),
+    /// `{name, age: a}`, binding the values of a hash by string key, to the
+    /// key itself or to the name after the `:`. Missing keys are bound to
+    /// `null`.
+    Hash(Vec<(String, Identifier)>),
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Array(names) => {
+                let names: Vec<_> = names.iter().map(|name| name.to_string()).collect();
+                write!(f, "[{}]", names.join(", "))
+            }
+            Target::Hash(entries) => {
+                let entries: Vec<_> = entries
+                    .iter()
+                    .map(|(key, name)| {
+                        if key == name.value() {
+                            key.clone()
+                        } else {
+                            format!("{}: {}", key, name)
+                        }
+                    })
+                    .collect();
+                write!(f, "{{{}}}", entries.join(", "))
+            }
+        }
+    }
+}
+
+/// The pattern of a `match` arm.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pattern {
+    /// A literal integer, string, boolean or `null`, matching equal values.
+    Literal(Expression),
+    /// `_`, matching any value.
+    Wildcard,
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pattern::Literal(expr) => write!(f, "{}", expr),
+            Pattern::Wildcard => write!(f, "_"),
         }
     }
 }
@@ -1,18 +1,134 @@
 use miette::Result;
-use std::{cell::LazyCell, collections::HashMap, rc::Rc};
+use num_bigint::BigInt;
+use num_traits::FromPrimitive;
+use std::{collections::HashMap, rc::Rc};
 
-use crate::object::Object;
+use crate::{
+    ast::{format_parameters, Float},
+    evaluator::{self, eval_slice_expression, is_truthy, structurally_equal, with_element},
+    lexer::Lexer,
+    locale::locale,
+    object::{grapheme_object, graphemes, integer, sorted_pairs, Builtin, Channel, Object},
+    parser::Parser,
+    programs, random,
+    scheduler::schedule,
+};
 
-pub const BUILTINS: LazyCell<HashMap<String, Rc<Object>>> = LazyCell::new(|| {
-    let mut b = HashMap::new();
-    b.insert("len".into(), Rc::new(Object::Builtin(len)));
-    b.insert("first".into(), Rc::new(Object::Builtin(first)));
-    b.insert("last".into(), Rc::new(Object::Builtin(last)));
-    b.insert("rest".into(), Rc::new(Object::Builtin(rest)));
-    b.insert("push".into(), Rc::new(Object::Builtin(push)));
-    b.insert("puts".into(), Rc::new(Object::Builtin(puts)));
-    b
-});
+const BUILTINS: &[Builtin] = &[
+    Builtin::new("len", "value", len),
+    Builtin::new("first", "array", first),
+    Builtin::new("last", "array", last),
+    Builtin::new("rest", "array", rest),
+    Builtin::new("push", "array, value", push),
+    Builtin::new("reverse", "value", reverse),
+    Builtin::new("chars", "string", chars),
+    Builtin::new("slice", "value, start, end?", slice),
+    Builtin::new("replace", "string, from, to", replace),
+    Builtin::new("starts_with", "string, prefix", starts_with),
+    Builtin::new("ends_with", "string, suffix", ends_with),
+    Builtin::new("index_of", "collection, value", index_of),
+    Builtin::new("split", "string, separator", split),
+    Builtin::new("join", "array, separator", join),
+    Builtin::new("enumerate", "value", enumerate),
+    Builtin::new("keys", "hash", keys),
+    Builtin::new("values", "hash", values),
+    Builtin::new("contains", "collection, value", contains),
+    Builtin::new("has_key", "hash, key", has_key),
+    Builtin::new("delete", "hash, key", delete),
+    Builtin::new("insert", "hash, key, value", insert),
+    Builtin::new("set_at", "array, index, value", set_at),
+    Builtin::new("int", "value, base?", int),
+    Builtin::new("str", "value, base?", str),
+    Builtin::new("float", "value", float),
+    Builtin::new("char", "code", char),
+    Builtin::new("bytes", "value", bytes),
+    Builtin::new("decode", "bytes", decode),
+    Builtin::new("random", "", random),
+    Builtin::new("random_int", "lo, hi", random_int),
+    Builtin::new("shuffle", "array", shuffle),
+    Builtin::new("error", "message", error),
+    Builtin::new("assert", "condition, message?", assert).returning_null(),
+    Builtin::new("is_error", "value", is_error),
+    Builtin::new("is_generator", "value", is_generator),
+    Builtin::new("type", "value", r#type),
+    Builtin::new("format", "template, values...", format),
+    Builtin::new("sprintf", "template, values...", sprintf),
+    Builtin::new("void", "value", void).returning_null(),
+    Builtin::new("puts", "values...", puts).returning_null(),
+    Builtin::new("print", "values...", print).returning_null(),
+    Builtin::new("help", "function", help),
+    Builtin::new("version", "", version),
+    Builtin::new("examples", "", examples),
+    Builtin::new("load_example", "name", load_example),
+    Builtin::new("locale_compare", "a, b, locale?", locale_compare),
+    Builtin::new("format_number", "number, locale?", format_number),
+    Builtin::new("set_timeout", "callback, ms", set_timeout).returning_null(),
+    Builtin::new("defer", "callback", defer).returning_null(),
+    Builtin::new("channel", "", channel),
+    Builtin::new("send", "sender, value", send).returning_null(),
+    Builtin::new("receive", "receiver", receive),
+    Builtin::new("next", "generator", next),
+];
+
+/// The functions of the `math` hash, which keeps common names like `log` free
+/// for variables.
+const MATH: &[Builtin] = &[
+    Builtin::new("sin", "x", sin),
+    Builtin::new("cos", "x", cos),
+    Builtin::new("exp", "x", exp),
+    Builtin::new("log", "x", log),
+    Builtin::new("floor", "x", floor),
+    Builtin::new("ceil", "x", ceil),
+    Builtin::new("round", "x", round),
+];
+
+/// The constants of the `math` hash.
+const MATH_CONSTANTS: &[(&str, f64)] = &[("PI", std::f64::consts::PI), ("E", std::f64::consts::E)];
+
+pub fn builtins() -> HashMap<String, Rc<Object>> {
+    let mut builtins: HashMap<_, _> = BUILTINS
+        .iter()
+        .map(|builtin| (builtin.name.to_string(), Rc::new(Object::Builtin(*builtin))))
+        .collect();
+    builtins.insert("math".into(), math());
+    builtins
+}
+
+/// Whether `name` is a builtin, without building them all like [`builtins`].
+pub(crate) fn is_builtin(name: &str) -> bool {
+    name == "math" || BUILTINS.iter().any(|builtin| builtin.name == name)
+}
+
+/// Whether `name` is a builtin called for its effects, whose `null` result
+/// need not be used.
+pub(crate) fn returns_null(name: &str) -> bool {
+    BUILTINS
+        .iter()
+        .any(|builtin| builtin.name == name && builtin.returns_null)
+}
+
+/// The builtin called `name`, without building the others like [`builtins`].
+pub(crate) fn builtin(name: &str) -> Option<Rc<Object>> {
+    if name == "math" {
+        return Some(math());
+    }
+    BUILTINS
+        .iter()
+        .find(|builtin| builtin.name == name)
+        .map(|builtin| Rc::new(Object::Builtin(*builtin)))
+}
+
+#[allow(clippy::mutable_key_type)]
+fn math() -> Rc<Object> {
+    let key = |name: &str| Rc::new(Object::String(name.into()));
+    let functions = MATH
+        .iter()
+        .map(|builtin| (key(builtin.name), Rc::new(Object::Builtin(*builtin))));
+    let constants = MATH_CONSTANTS
+        .iter()
+        .map(|(name, x)| (key(name), Rc::new(Object::Float(Float(*x)))));
+    Rc::new(Object::Hash(functions.chain(constants).collect()))
+}
 
 fn len(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
     if args.len() != 1 {
@@ -23,8 +139,13 @@ fn len(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
     }
     let arg = args[0].as_ref();
     match arg {
-        Object::String(s) => Ok(Rc::new(Object::Integer(s.chars().count() as isize))),
+        Object::String(s) => Ok(Rc::new(Object::Integer(graphemes(s).len() as isize))),
         Object::Array(v) => Ok(Rc::new(Object::Integer(v.len() as isize))),
+        Object::Bytes(b) => Ok(Rc::new(Object::Integer(b.len() as isize))),
+        Object::Range { start, end } => {
+            let len = (*end as i128 - *start as i128).max(0);
+            Ok(Rc::new(integer(len.into())))
+        }
         _ => Err(miette::miette!(
             "argument to `len` not supported, got {}",
             arg
@@ -47,6 +168,8 @@ fn first(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
             }
             Ok(Rc::new(Object::Null))
         }
+        Object::Range { start, end } if start < end => Ok(Rc::new(Object::Integer(*start))),
+        Object::Range { .. } => Ok(Rc::new(Object::Null)),
         _ => Err(miette::miette!(
             "argument to `first` must be ARRAY, got {}",
             arg
@@ -71,6 +194,8 @@ fn last(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
             }
             Ok(Rc::new(Object::Null))
         }
+        Object::Range { start, end } if start < end => Ok(Rc::new(Object::Integer(end - 1))),
+        Object::Range { .. } => Ok(Rc::new(Object::Null)),
         _ => Err(miette::miette!(
             "argument to `first` must be ARRAY, got {}",
             arg
@@ -86,7 +211,8 @@ fn rest(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
         ));
     }
 
-    let arg = args[0].as_ref();
+    let elements = range_elements(&args[0]);
+    let arg = elements.as_ref().unwrap_or(&args[0]).as_ref();
 
     match arg {
         Object::Array(v) => {
@@ -111,7 +237,8 @@ fn push(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
         ));
     }
 
-    match args[0].as_ref() {
+    let elements = range_elements(&args[0]);
+    match elements.as_ref().unwrap_or(&args[0]).as_ref() {
         Object::Array(v) => {
             let mut new_elements = v.clone();
             new_elements.push(Rc::clone(&args[1]));
@@ -124,9 +251,1538 @@ fn push(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
     }
 }
 
-fn puts(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
-    for arg in args {
-        println!("{}", arg);
+fn reverse(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 1 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 1",
+            args.len()
+        ));
     }
-    Ok(Rc::new(Object::Null))
+
+    let elements = range_elements(&args[0]);
+    match elements.as_ref().unwrap_or(&args[0]).as_ref() {
+        Object::String(s) => {
+            let mut chars = graphemes(s);
+            chars.reverse();
+            Ok(Rc::new(Object::String(chars.concat())))
+        }
+        Object::Array(v) => Ok(Rc::new(Object::Array(v.iter().rev().cloned().collect()))),
+        _ => Err(miette::miette!(
+            "argument to `reverse` must be STRING or ARRAY, got {}",
+            args[0].r#type()
+        )),
+    }
+}
+
+fn chars(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 1 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 1",
+            args.len()
+        ));
+    }
+
+    match args[0].as_ref() {
+        Object::String(s) => Ok(Rc::new(Object::Array(
+            graphemes(s)
+                .into_iter()
+                .map(|c| Rc::new(grapheme_object(c)))
+                .collect(),
+        ))),
+        _ => Err(miette::miette!(
+            "argument to `chars` must be STRING, got {}",
+            args[0].r#type()
+        )),
+    }
+}
+
+/// The text of a string or a character.
+fn text(obj: &Object) -> Option<String> {
+    match obj {
+        Object::String(s) => Some(s.clone()),
+        Object::Char(c) => Some(c.to_string()),
+        _ => None,
+    }
+}
+
+/// The parts of the string between the occurrences of the separator.
+/// Like `value[start:end]`, except that negative indices count from the end,
+/// so `slice(xs, -2)` gives the last two elements. `null` leaves a side open.
+fn slice(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    let (value, start, end) = match args.as_slice() {
+        [value, start] => (value, start, None),
+        [value, start, end] => (value, start, Some(end)),
+        _ => {
+            return Err(miette::miette!(
+                "wrong number of arguments. got={}, want = 2 or 3",
+                args.len()
+            ))
+        }
+    };
+    let len = match value.as_ref() {
+        Object::String(s) => graphemes(s).len(),
+        Object::Array(v) => v.len(),
+        Object::Bytes(b) => b.len(),
+        other => {
+            return Err(miette::miette!(
+                "argument to `slice` must be ARRAY, STRING or BYTES, got {}",
+                other.r#type()
+            ))
+        }
+    };
+    let bound = |index: Option<&Rc<Object>>| match index.map(Rc::as_ref) {
+        None | Some(Object::Null) => None,
+        Some(Object::Integer(i)) if *i < 0 => Some(Rc::new(Object::Integer(len as isize + i))),
+        Some(_) => index.cloned(),
+    };
+    eval_slice_expression(Rc::clone(value), bound(Some(start)), bound(end))
+}
+
+/// The string `name` searches and the string or character it looks for.
+fn search_args<'a>(name: &str, string: &'a Object, part: &Object) -> Result<(&'a str, String)> {
+    let Object::String(s) = string else {
+        return Err(miette::miette!(
+            "argument to `{}` must be STRING, got {}",
+            name,
+            string.r#type()
+        ));
+    };
+    match text(part) {
+        Some(part) => Ok((s, part)),
+        None => Err(miette::miette!(
+            "`{}` can only find a STRING or CHAR in a STRING, got {}",
+            name,
+            part.r#type()
+        )),
+    }
+}
+
+/// Replaces every occurrence of `from` in the string with `to`.
+fn replace(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 3 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 3",
+            args.len()
+        ));
+    }
+
+    let (s, from) = search_args("replace", &args[0], &args[1])?;
+    if from.is_empty() {
+        return Err(miette::miette!(
+            "string replaced by `replace` must not be empty"
+        ));
+    }
+    let Some(to) = text(&args[2]) else {
+        return Err(miette::miette!(
+            "replacement of `replace` must be STRING or CHAR, got {}",
+            args[2].r#type()
+        ));
+    };
+    Ok(Rc::new(Object::String(s.replace(from.as_str(), &to))))
+}
+
+fn starts_with(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 2 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 2",
+            args.len()
+        ));
+    }
+
+    let (s, prefix) = search_args("starts_with", &args[0], &args[1])?;
+    Ok(Rc::new(Object::Boolean(s.starts_with(prefix.as_str()))))
+}
+
+fn ends_with(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 2 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 2",
+            args.len()
+        ));
+    }
+
+    let (s, suffix) = search_args("ends_with", &args[0], &args[1])?;
+    Ok(Rc::new(Object::Boolean(s.ends_with(suffix.as_str()))))
+}
+
+/// The index of the first element of the array equal to the value, or of the
+/// character where the string or character first occurs in the string, as
+/// indexing counts them. -1 if there is none.
+fn index_of(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 2 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 2",
+            args.len()
+        ));
+    }
+
+    let index = match args[0].as_ref() {
+        Object::Array(v) => v
+            .iter()
+            .position(|element| structurally_equal(element, &args[1])),
+        Object::String(_) => {
+            let (s, part) = search_args("index_of", &args[0], &args[1])?;
+            // Matches only count if they start and end between characters.
+            let mut bounds = vec![0];
+            for grapheme in graphemes(s) {
+                bounds.push(bounds[bounds.len() - 1] + grapheme.len());
+            }
+            match part.is_empty() {
+                true => Some(0),
+                false => bounds.iter().position(|&start| {
+                    s[start..].starts_with(part.as_str()) && bounds.contains(&(start + part.len()))
+                }),
+            }
+        }
+        other => {
+            return Err(miette::miette!(
+                "argument to `index_of` must be ARRAY or STRING, got {}",
+                other.r#type()
+            ))
+        }
+    };
+    Ok(Rc::new(Object::Integer(
+        index.map_or(-1, |idx| idx as isize),
+    )))
+}
+
+fn split(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 2 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 2",
+            args.len()
+        ));
+    }
+
+    let Object::String(s) = args[0].as_ref() else {
+        return Err(miette::miette!(
+            "argument to `split` must be STRING, got {}",
+            args[0].r#type()
+        ));
+    };
+    match text(&args[1]) {
+        Some(separator) if separator.is_empty() => Err(miette::miette!(
+            help = "Use `chars` to split a string into its characters",
+            "separator of `split` must not be empty"
+        )),
+        Some(separator) => Ok(Rc::new(Object::Array(
+            s.split(separator.as_str())
+                .map(|part| Rc::new(Object::String(part.into())))
+                .collect(),
+        ))),
+        None => Err(miette::miette!(
+            "separator of `split` must be STRING or CHAR, got {}",
+            args[1].r#type()
+        )),
+    }
+}
+
+/// The strings and characters of the array, with the separator between them.
+fn join(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 2 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 2",
+            args.len()
+        ));
+    }
+
+    let Object::Array(elements) = args[0].as_ref() else {
+        return Err(miette::miette!(
+            "argument to `join` must be ARRAY, got {}",
+            args[0].r#type()
+        ));
+    };
+    let Some(separator) = text(&args[1]) else {
+        return Err(miette::miette!(
+            "separator of `join` must be STRING or CHAR, got {}",
+            args[1].r#type()
+        ));
+    };
+    let parts = elements
+        .iter()
+        .map(|element| {
+            text(element).ok_or_else(|| {
+                miette::miette!(
+                    help =
+                        "Convert the elements with `str` first, e.g. `join(map(xs, str), \", \")`",
+                    "elements joined by `join` must be STRING or CHAR, got {}",
+                    element.r#type()
+                )
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Rc::new(Object::String(parts.join(&separator))))
+}
+
+/// Pairs the elements with their indices, `[[0, a], [1, b], ...]`, or for
+/// hashes the keys with their values in key order.
+fn enumerate(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 1 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 1",
+            args.len()
+        ));
+    }
+
+    let pair = |a: Rc<Object>, b: Rc<Object>| Rc::new(Object::Array(vec![a, b]));
+    let indexed = |elements: Vec<Rc<Object>>| {
+        let pairs = elements
+            .into_iter()
+            .enumerate()
+            .map(|(i, element)| pair(Rc::new(Object::Integer(i as isize)), element))
+            .collect();
+        Rc::new(Object::Array(pairs))
+    };
+    let elements = range_elements(&args[0]);
+    match elements.as_ref().unwrap_or(&args[0]).as_ref() {
+        Object::Array(v) => Ok(indexed(v.clone())),
+        Object::String(s) => Ok(indexed(
+            graphemes(s)
+                .into_iter()
+                .map(|c| Rc::new(grapheme_object(c)))
+                .collect(),
+        )),
+        Object::Hash(map) => {
+            let pairs = sorted_pairs(map)
+                .into_iter()
+                .map(|(key, value)| pair(Rc::clone(key), Rc::clone(value)))
+                .collect();
+            Ok(Rc::new(Object::Array(pairs)))
+        }
+        _ => Err(miette::miette!(
+            "argument to `enumerate` must be ARRAY, STRING, RANGE or HASH, got {}",
+            args[0].r#type()
+        )),
+    }
+}
+
+/// The keys of a hash, in the order `enumerate` gives its entries.
+fn keys(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 1 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 1",
+            args.len()
+        ));
+    }
+
+    match args[0].as_ref() {
+        Object::Hash(map) => Ok(Rc::new(Object::Array(
+            sorted_pairs(map)
+                .into_iter()
+                .map(|(key, _)| Rc::clone(key))
+                .collect(),
+        ))),
+        _ => Err(miette::miette!(
+            "argument to `keys` must be HASH, got {}",
+            args[0].r#type()
+        )),
+    }
+}
+
+/// The values of a hash, in the order of its [`keys`].
+fn values(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 1 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 1",
+            args.len()
+        ));
+    }
+
+    match args[0].as_ref() {
+        Object::Hash(map) => Ok(Rc::new(Object::Array(
+            sorted_pairs(map)
+                .into_iter()
+                .map(|(_, value)| Rc::clone(value))
+                .collect(),
+        ))),
+        _ => Err(miette::miette!(
+            "argument to `values` must be HASH, got {}",
+            args[0].r#type()
+        )),
+    }
+}
+
+/// Whether the array has an element equal to the value, the range has the
+/// integer or the string has the string or character in it.
+fn contains(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 2 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 2",
+            args.len()
+        ));
+    }
+
+    let found = match (args[0].as_ref(), args[1].as_ref()) {
+        (Object::Array(v), value) => v.iter().any(|element| structurally_equal(element, value)),
+        (Object::Range { start, end }, Object::Integer(i)) => start <= i && i < end,
+        (Object::Range { .. }, _) => false,
+        (Object::String(s), value) => match text(value) {
+            Some(part) => s.contains(part.as_str()),
+            None => {
+                return Err(miette::miette!(
+                    "`contains` can only find a STRING or CHAR in a STRING, got {}",
+                    value.r#type()
+                ))
+            }
+        },
+        (other, _) => {
+            return Err(miette::miette!(
+                "argument to `contains` must be ARRAY, STRING or RANGE, got {}",
+                other.r#type()
+            ))
+        }
+    };
+    Ok(Rc::new(Object::Boolean(found)))
+}
+
+/// Whether the hash has an entry for the key.
+fn has_key(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 2 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 2",
+            args.len()
+        ));
+    }
+
+    let Object::Hash(map) = args[0].as_ref() else {
+        return Err(miette::miette!(
+            "argument to `has_key` must be HASH, got {}",
+            args[0].r#type()
+        ));
+    };
+    if !args[1].is_hashable() {
+        return Err(miette::miette!(
+            "unusable as hash key: {}",
+            args[1].r#type()
+        ));
+    }
+    Ok(Rc::new(Object::Boolean(map.contains_key(&args[1]))))
+}
+
+/// A copy of the hash without the entry for the key, if it has one.
+#[allow(clippy::mutable_key_type)]
+fn delete(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 2 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 2",
+            args.len()
+        ));
+    }
+
+    let Object::Hash(map) = args[0].as_ref() else {
+        return Err(miette::miette!(
+            "argument to `delete` must be HASH, got {}",
+            args[0].r#type()
+        ));
+    };
+    if !args[1].is_hashable() {
+        return Err(miette::miette!(
+            "unusable as hash key: {}",
+            args[1].r#type()
+        ));
+    }
+    if !map.contains_key(&args[1]) {
+        return Ok(Rc::clone(&args[0]));
+    }
+    let mut map = map.clone();
+    map.remove(&args[1]);
+    Ok(Rc::new(Object::Hash(map)))
+}
+
+/// A copy of the hash with the key set to the value, like `h[key] = value`
+/// without changing `h`.
+fn insert(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 3 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 3",
+            args.len()
+        ));
+    }
+
+    if !matches!(args[0].as_ref(), Object::Hash(_)) {
+        return Err(miette::miette!(
+            "argument to `insert` must be HASH, got {}",
+            args[0].r#type()
+        ));
+    }
+    let updated = with_element(&args[0], Rc::clone(&args[1]), Rc::clone(&args[2]))?;
+    Ok(Rc::new(updated))
+}
+
+/// A copy of the array with the element at the index replaced, like
+/// `xs[i] = value` without changing `xs`.
+fn set_at(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 3 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 3",
+            args.len()
+        ));
+    }
+
+    if !matches!(args[0].as_ref(), Object::Array(_)) {
+        return Err(miette::miette!(
+            "argument to `set_at` must be ARRAY, got {}",
+            args[0].r#type()
+        ));
+    }
+    let updated = with_element(&args[0], Rc::clone(&args[1]), Rc::clone(&args[2]))?;
+    Ok(Rc::new(updated))
+}
+
+/// Materializes a range into an array, so builtins producing arrays accept
+/// ranges too.
+fn range_elements(obj: &Object) -> Option<Rc<Object>> {
+    match obj {
+        Object::Range { start, end } => Some(Rc::new(Object::Array(
+            (*start..*end)
+                .map(|i| Rc::new(Object::Integer(i)))
+                .collect(),
+        ))),
+        _ => None,
+    }
+}
+
+fn error(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 1 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 1",
+            args.len()
+        ));
+    }
+
+    match args[0].as_ref() {
+        Object::String(message) => Ok(Rc::new(Object::Error {
+            message: message.clone(),
+            span: None,
+        })),
+        other => Err(miette::miette!(
+            "argument to `error` must be STRING, got {}",
+            other.r#type()
+        )),
+    }
+}
+
+/// Fails with "assertion failed" and the optional message unless the first
+/// argument is truthy. The evaluator labels the error with the call's span.
+fn assert(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 1 or 2",
+            args.len()
+        ));
+    }
+
+    let message = match args.get(1).map(|arg| arg.as_ref()) {
+        None => None,
+        Some(Object::String(message)) => Some(message),
+        Some(other) => {
+            return Err(miette::miette!(
+                "argument to `assert` must be STRING, got {}",
+                other.r#type()
+            ))
+        }
+    };
+    match (is_truthy(&args[0]), message) {
+        (true, _) => Ok(Rc::new(Object::Null)),
+        (false, Some(message)) => Err(miette::miette!("assertion failed: {}", message)),
+        (false, None) => Err(miette::miette!("assertion failed")),
+    }
+}
+
+/// The name of the type of the value, like `"INTEGER"` or `"ARRAY"`, as it
+/// appears in error messages.
+fn r#type(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 1 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 1",
+            args.len()
+        ));
+    }
+
+    Ok(Rc::new(Object::String(args[0].r#type())))
+}
+
+fn is_error(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 1 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 1",
+            args.len()
+        ));
+    }
+
+    let is_error = matches!(args[0].as_ref(), Object::Error { .. });
+    Ok(Rc::new(Object::Boolean(is_error)))
+}
+
+fn is_generator(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 1 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 1",
+            args.len()
+        ));
+    }
+
+    let is_generator = matches!(args[0].as_ref(), Object::Generator(_));
+    Ok(Rc::new(Object::Boolean(is_generator)))
+}
+
+/// Converts a string to an integer, either in the given base or, without one,
+/// in decimal or with a `0x`, `0o` or `0b` prefix like integer literals.
+fn int(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    let (value, base) = match args.as_slice() {
+        [value] => (value, None),
+        [value, base] => (value, Some(radix("int", base)?)),
+        _ => {
+            return Err(miette::miette!(
+                "wrong number of arguments. got={}, want = 1 or 2",
+                args.len()
+            ))
+        }
+    };
+    match (value.as_ref(), base) {
+        (Object::Integer(_) | Object::BigInteger(_), None) => Ok(Rc::clone(value)),
+        (Object::Char(c), None) => Ok(Rc::new(Object::Integer(*c as isize))),
+        // Rounds towards zero.
+        (Object::Float(x), None) => match BigInt::from_f64(x.0.trunc()) {
+            Some(i) => Ok(Rc::new(integer(i))),
+            None => Err(miette::miette!("cannot convert {} to an integer", x)),
+        },
+        (Object::String(s), base) => match parse_int(s, base) {
+            Some(i) => Ok(Rc::new(i)),
+            // Input like "3.9" is a float, which `int(float(s))` truncates.
+            None if base.is_none() && s.trim().parse::<f64>().is_ok_and(f64::is_finite) => {
+                Err(miette::miette!(
+                    help = "`int` only parses whole numbers, `int(float(s))` rounds towards zero",
+                    "cannot parse {} as an integer in base 10",
+                    value.inspect(),
+                ))
+            }
+            None => Err(miette::miette!(
+                "cannot parse {} as an integer in base {}",
+                value.inspect(),
+                base.unwrap_or(10)
+            )),
+        },
+        (other, Some(_)) => Err(miette::miette!(
+            "`int` with a base needs a STRING, got {}",
+            other.r#type()
+        )),
+        (other, None) => Err(miette::miette!(
+            "argument to `int` must be STRING, CHAR or FLOAT, got {}",
+            other.r#type()
+        )),
+    }
+}
+
+/// Converts an integer or parses a string like `"2.5"` or `"1e-3"` into a
+/// float.
+fn float(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 1 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 1",
+            args.len()
+        ));
+    }
+
+    match args[0].as_ref() {
+        Object::Float(_) => Ok(Rc::clone(&args[0])),
+        Object::String(s) => match s.trim().parse() {
+            Ok(x) => Ok(Rc::new(Object::Float(Float(x)))),
+            Err(_) => Err(miette::miette!(
+                "cannot parse {} as a float",
+                args[0].inspect()
+            )),
+        },
+        other => match other.to_f64() {
+            Some(x) => Ok(Rc::new(Object::Float(Float(x)))),
+            None => Err(miette::miette!(
+                "argument to `float` must be INTEGER or STRING, got {}",
+                other.r#type()
+            )),
+        },
+    }
+}
+
+/// The number the math builtin `name` takes, as a float.
+fn number_arg(name: &str, args: &[Rc<Object>]) -> Result<f64> {
+    if args.len() != 1 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 1",
+            args.len()
+        ));
+    }
+
+    args[0].to_f64().ok_or_else(|| {
+        miette::miette!(
+            "argument to `{}` must be INTEGER or FLOAT, got {}",
+            name,
+            args[0].r#type()
+        )
+    })
+}
+
+fn sin(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    let x = number_arg("sin", &args)?;
+    Ok(Rc::new(Object::Float(Float(x.sin()))))
+}
+
+fn cos(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    let x = number_arg("cos", &args)?;
+    Ok(Rc::new(Object::Float(Float(x.cos()))))
+}
+
+fn exp(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    let x = number_arg("exp", &args)?;
+    Ok(Rc::new(Object::Float(Float(x.exp()))))
+}
+
+/// The natural logarithm.
+fn log(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    let x = number_arg("log", &args)?;
+    if x <= 0.0 {
+        return Err(miette::miette!(
+            "`log` is only defined for positive numbers, got {}",
+            args[0]
+        ));
+    }
+    Ok(Rc::new(Object::Float(Float(x.ln()))))
+}
+
+/// Rounds a number to an integer with `round_float`, leaving integers as they
+/// are.
+fn to_integer(name: &str, args: &[Rc<Object>], round_float: fn(f64) -> f64) -> Result<Rc<Object>> {
+    let x = number_arg(name, args)?;
+    if !matches!(args[0].as_ref(), Object::Float(_)) {
+        return Ok(Rc::clone(&args[0]));
+    }
+    match BigInt::from_f64(round_float(x)) {
+        Some(i) => Ok(Rc::new(integer(i))),
+        None => Err(miette::miette!("cannot convert {} to an integer", args[0])),
+    }
+}
+
+fn floor(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    to_integer("floor", &args, f64::floor)
+}
+
+fn ceil(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    to_integer("ceil", &args, f64::ceil)
+}
+
+/// Rounds half-way cases away from zero.
+fn round(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    to_integer("round", &args, f64::round)
+}
+
+/// A float in `[0, 1)`.
+fn random(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if !args.is_empty() {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 0",
+            args.len()
+        ));
+    }
+
+    Ok(Rc::new(Object::Float(Float(random::next_f64()))))
+}
+
+/// An integer from `lo` up to but not including `hi`, like the range `lo..hi`.
+fn random_int(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 2 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 2",
+            args.len()
+        ));
+    }
+
+    if let Some(big) = args
+        .iter()
+        .find(|arg| matches!(arg.as_ref(), Object::BigInteger(_)))
+    {
+        return Err(miette::miette!(
+            "{} is out of range for `random_int`, which supports {} to {}",
+            big,
+            isize::MIN,
+            isize::MAX
+        ));
+    }
+    let (Object::Integer(lo), Object::Integer(hi)) = (args[0].as_ref(), args[1].as_ref()) else {
+        return Err(miette::miette!(
+            "arguments to `random_int` must be INTEGER, got {} and {}",
+            args[0].r#type(),
+            args[1].r#type()
+        ));
+    };
+    if lo >= hi {
+        return Err(miette::miette!(
+            "`random_int` needs `lo` below `hi`, got {} and {}",
+            lo,
+            hi
+        ));
+    }
+    let span = (*hi as i128 - *lo as i128) as u64;
+    Ok(Rc::new(Object::Integer(
+        (*lo as i128 + random::below(span) as i128) as isize,
+    )))
+}
+
+/// A copy of the array with its elements in random order.
+fn shuffle(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 1 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 1",
+            args.len()
+        ));
+    }
+
+    let Object::Array(v) = args[0].as_ref() else {
+        return Err(miette::miette!(
+            "argument to `shuffle` must be ARRAY, got {}",
+            args[0].r#type()
+        ));
+    };
+    let mut v = v.clone();
+    for i in (1..v.len()).rev() {
+        let j = random::below(i as u64 + 1) as usize;
+        v.swap(i, j);
+    }
+    Ok(Rc::new(Object::Array(v)))
+}
+
+/// The character with the code point `code`, the inverse of `int` on a
+/// character.
+fn char(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 1 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 1",
+            args.len()
+        ));
+    }
+
+    match args[0].as_ref() {
+        Object::Integer(i) => match u32::try_from(*i).ok().and_then(char::from_u32) {
+            Some(c) => Ok(Rc::new(Object::Char(c))),
+            None => Err(miette::miette!("{} is not a valid code point", i)),
+        },
+        Object::BigInteger(i) => Err(miette::miette!("{} is not a valid code point", i)),
+        Object::Char(_) => Ok(Rc::clone(&args[0])),
+        other => Err(miette::miette!(
+            "argument to `char` must be INTEGER, got {}",
+            other.r#type()
+        )),
+    }
+}
+
+/// The UTF-8 encoding of a string, or the bytes of an array of integers from
+/// 0 to 255.
+fn bytes(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 1 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 1",
+            args.len()
+        ));
+    }
+
+    match args[0].as_ref() {
+        Object::String(s) => Ok(Rc::new(Object::Bytes(s.as_bytes().to_vec()))),
+        Object::Bytes(_) => Ok(Rc::clone(&args[0])),
+        Object::Array(v) => {
+            let bytes = v
+                .iter()
+                .map(|element| {
+                    match element.as_ref() {
+                        Object::Integer(i) => u8::try_from(*i).ok(),
+                        _ => None,
+                    }
+                    .ok_or_else(|| {
+                        miette::miette!(
+                            "bytes must be INTEGERs from 0 to 255, got {}",
+                            element.inspect()
+                        )
+                    })
+                })
+                .collect::<Result<_>>()?;
+            Ok(Rc::new(Object::Bytes(bytes)))
+        }
+        other => Err(miette::miette!(
+            "argument to `bytes` must be STRING or ARRAY, got {}",
+            other.r#type()
+        )),
+    }
+}
+
+/// Decodes UTF-8 bytes into a string.
+fn decode(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 1 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 1",
+            args.len()
+        ));
+    }
+
+    match args[0].as_ref() {
+        Object::Bytes(b) => match std::str::from_utf8(b) {
+            Ok(s) => Ok(Rc::new(Object::String(s.into()))),
+            Err(_) => Err(miette::miette!("bytes are not valid UTF-8")),
+        },
+        other => Err(miette::miette!(
+            "argument to `decode` must be BYTES, got {}",
+            other.r#type()
+        )),
+    }
+}
+
+/// Converts any value to the string `puts` prints, or an integer to its
+/// digits in the given base.
+fn str(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    match args.as_slice() {
+        [value] => Ok(Rc::new(Object::String(value.to_string()))),
+        [value, base] => match value.as_ref() {
+            // Lowercase letters for digits above 9.
+            Object::Integer(_) | Object::BigInteger(_) => {
+                let digits = value
+                    .to_bigint()
+                    .expect("an integer")
+                    .to_str_radix(radix("str", base)?);
+                Ok(Rc::new(Object::String(digits)))
+            }
+            other => Err(miette::miette!(
+                "`str` with a base needs an INTEGER, got {}",
+                other.r#type()
+            )),
+        },
+        _ => Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 1 or 2",
+            args.len()
+        )),
+    }
+}
+
+fn radix(name: &str, base: &Object) -> Result<u32> {
+    match base {
+        Object::Integer(base) if (2..=36).contains(base) => Ok(*base as u32),
+        Object::Integer(base) => Err(miette::miette!(
+            "base to `{}` must be between 2 and 36, got {}",
+            name,
+            base
+        )),
+        other => Err(miette::miette!(
+            "base to `{}` must be INTEGER, got {}",
+            name,
+            other.r#type()
+        )),
+    }
+}
+
+/// Parses an optionally signed integer surrounded by optional whitespace.
+/// Only ASCII digits and letters are accepted, regardless of the locale.
+fn parse_int(s: &str, base: Option<u32>) -> Option<Object> {
+    let s = s.trim();
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(s) => (true, s),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (radix, digits) = match (base, s.get(..2)) {
+        (Some(base), _) => (base, s),
+        (None, Some("0x")) => (16, &s[2..]),
+        (None, Some("0o")) => (8, &s[2..]),
+        (None, Some("0b")) => (2, &s[2..]),
+        (None, _) => (10, s),
+    };
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    let magnitude = BigInt::parse_bytes(digits.as_bytes(), radix)?;
+    Some(integer(if negative { -magnitude } else { magnitude }))
+}
+
+/// How a `format` placeholder or a `sprintf` directive pads its value.
+#[derive(Default)]
+struct Padding {
+    fill: Option<char>,
+    /// One of `<`, `^` and `>`. Numbers default to the right, everything
+    /// else to the left.
+    align: Option<char>,
+    /// Pads numbers with zeros after the sign.
+    zero: bool,
+    width: usize,
+}
+
+impl Padding {
+    fn apply(&self, value: &Object) -> String {
+        let text = value.to_string();
+        let is_number = matches!(
+            value,
+            Object::Integer(_) | Object::BigInteger(_) | Object::Float(_)
+        );
+        if self.zero && is_number {
+            let (sign, digits) = match text.strip_prefix('-') {
+                Some(digits) => ("-", digits),
+                None => ("", text.as_str()),
+            };
+            let width = self.width.saturating_sub(sign.len());
+            return format!("{}{}", sign, pad(digits, width, '>', '0'));
+        }
+        let align = self.align.unwrap_or(if is_number { '>' } else { '<' });
+        let fill = self.fill.unwrap_or(if self.zero { '0' } else { ' ' });
+        pad(&text, self.width, align, fill)
+    }
+}
+
+/// Pads `text` to `width` characters as counted by `len`.
+fn pad(text: &str, width: usize, align: char, fill: char) -> String {
+    let missing = width.saturating_sub(graphemes(text).len());
+    let left = match align {
+        '>' => missing,
+        '^' => missing / 2,
+        _ => 0,
+    };
+    let fill = |n| fill.to_string().repeat(n);
+    format!("{}{}{}", fill(left), text, fill(missing - left))
+}
+
+/// Splits the arguments of `format` and `sprintf` into the template and the
+/// values to substitute.
+fn template_and_values<'a>(
+    name: &str,
+    args: &'a [Rc<Object>],
+) -> Result<(&'a str, std::slice::Iter<'a, Rc<Object>>)> {
+    let Some((template, values)) = args.split_first() else {
+        return Err(miette::miette!(
+            "wrong number of arguments. got=0, want >= 1"
+        ));
+    };
+    match template.as_ref() {
+        Object::String(template) => Ok((template, values.iter())),
+        other => Err(miette::miette!(
+            "first argument to `{}` must be STRING, got {}",
+            name,
+            other.r#type()
+        )),
+    }
+}
+
+fn next_value<'a>(values: &mut std::slice::Iter<'a, Rc<Object>>) -> Result<&'a Rc<Object>> {
+    values
+        .next()
+        .ok_or_else(|| miette::miette!("format string has more placeholders than arguments"))
+}
+
+fn check_all_used(mut values: std::slice::Iter<Rc<Object>>) -> Result<()> {
+    match values.next() {
+        Some(_) => Err(miette::miette!(
+            "format string has fewer placeholders than arguments"
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Substitutes `{}` placeholders with the arguments in order, or `{0}`, `{1}`,
+/// ... with the argument at that position, counting from 0. They may specify
+/// padding after a colon as `{:[[fill]align][0][width]}`, like `{:>8}`,
+/// `{1:*^5}` or `{:04}`. Literal braces are written as `{{` and `}}`. Every
+/// argument has to be used.
+fn format(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    let (template, values) = template_and_values("format", &args)?;
+    let values = values.as_slice();
+    let mut used = vec![false; values.len()];
+    let mut next = 0;
+    let mut positional = false;
+    let mut out = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let mut spec = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => spec.push(c),
+                        None => {
+                            return Err(miette::miette!(
+                                "unterminated placeholder in format string"
+                            ))
+                        }
+                    }
+                }
+                let (position, padding) = parse_format_spec(&spec)?;
+                let index = match position {
+                    Some(index) => {
+                        positional = true;
+                        index
+                    }
+                    None => {
+                        next += 1;
+                        next - 1
+                    }
+                };
+                let Some(value) = values.get(index) else {
+                    return Err(match position {
+                        Some(_) => miette::miette!(
+                            "placeholder `{{{}}}` refers to argument {}, but there are only {}",
+                            spec,
+                            index,
+                            values.len()
+                        ),
+                        None => {
+                            miette::miette!("format string has more placeholders than arguments")
+                        }
+                    });
+                };
+                used[index] = true;
+                out.push_str(&padding.apply(value));
+            }
+            '}' => {
+                return Err(miette::miette!(
+                    help = "Use `}}` for a literal brace",
+                    "unmatched `}}` in format string"
+                ))
+            }
+            c => out.push(c),
+        }
+    }
+    match used.iter().position(|used| !used) {
+        Some(index) if positional => Err(miette::miette!(
+            "argument {} is never used in the format string",
+            index
+        )),
+        Some(_) => Err(miette::miette!(
+            "format string has fewer placeholders than arguments"
+        )),
+        None => Ok(Rc::new(Object::String(out))),
+    }
+}
+
+/// Splits the inside of a placeholder into the position of its argument, if
+/// it names one, and its padding.
+fn parse_format_spec(spec: &str) -> Result<(Option<usize>, Padding)> {
+    let invalid = || miette::miette!("invalid placeholder `{{{}}}` in format string", spec);
+    let (position, rest) = spec.split_at(spec.find(':').unwrap_or(spec.len()));
+    let position = match position {
+        "" => None,
+        digits if digits.bytes().all(|b| b.is_ascii_digit()) => {
+            Some(digits.parse().map_err(|_| invalid())?)
+        }
+        _ => return Err(invalid()),
+    };
+    let mut padding = Padding::default();
+    if rest.is_empty() {
+        return Ok((position, padding));
+    }
+    let Some(spec) = rest.strip_prefix(':') else {
+        return Err(invalid());
+    };
+    let chars: Vec<_> = spec.chars().collect();
+    let mut rest = &chars[..];
+    let is_align = |c: &char| matches!(c, '<' | '^' | '>');
+    if rest.len() >= 2 && is_align(&rest[1]) {
+        padding.fill = Some(rest[0]);
+        padding.align = Some(rest[1]);
+        rest = &rest[2..];
+    } else if rest.first().is_some_and(is_align) {
+        padding.align = Some(rest[0]);
+        rest = &rest[1..];
+    }
+    if rest.first() == Some(&'0') {
+        padding.zero = true;
+        rest = &rest[1..];
+    }
+    if !rest.is_empty() {
+        let width: String = rest.iter().collect();
+        padding.width = width.parse().map_err(|_| invalid())?;
+    }
+    Ok((position, padding))
+}
+
+/// Substitutes printf-style directives `%[-][0][width]conversion`, where the
+/// conversion is `d` for integers or `s` for any value, like `%05d` or `%-8s`.
+/// `%%` is a literal percent sign. There is no `%f`, floats print with `%s`.
+fn sprintf(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    let (template, mut values) = template_and_values("sprintf", &args)?;
+    let mut out = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            out.push('%');
+            continue;
+        }
+        // Like in C, values are right-aligned unless `-` aligns them left, in
+        // which case zeros do not pad.
+        let left = chars.next_if_eq(&'-').is_some();
+        let mut padding = Padding {
+            align: Some(if left { '<' } else { '>' }),
+            zero: chars.next_if_eq(&'0').is_some() && !left,
+            ..Padding::default()
+        };
+        let mut width = String::new();
+        while let Some(digit) = chars.next_if(|c| c.is_ascii_digit()) {
+            width.push(digit);
+        }
+        if !width.is_empty() {
+            padding.width = width
+                .parse()
+                .map_err(|_| miette::miette!("width `{}` in format string is too large", width))?;
+        }
+        let value = match chars.next() {
+            Some('d') => match next_value(&mut values)?.as_ref() {
+                value @ (Object::Integer(_) | Object::BigInteger(_)) => value,
+                other => {
+                    return Err(miette::miette!(
+                        help = "Use `%s` for other values, including floats",
+                        "`%d` needs an INTEGER, got {}",
+                        other.r#type()
+                    ))
+                }
+            },
+            Some('s') => next_value(&mut values)?.as_ref(),
+            Some(c) => {
+                return Err(miette::miette!(
+                    help = "Use `%d` for integers, `%s` for any value or `%%` for a percent sign",
+                    "unknown conversion `%{}` in format string",
+                    c
+                ))
+            }
+            None => return Err(miette::miette!("incomplete `%` directive in format string")),
+        };
+        out.push_str(&padding.apply(value));
+    }
+    check_all_used(values)?;
+    Ok(Rc::new(Object::String(out)))
+}
+
+/// Ignores its argument, marking a value as deliberately unused.
+fn void(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 1 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 1",
+            args.len()
+        ));
+    }
+
+    Ok(Rc::new(Object::Null))
+}
+
+fn puts(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    for arg in args {
+        evaluator::write_output(&format!("{}\n", arg));
+    }
+    Ok(Rc::new(Object::Null))
+}
+
+/// Writes the values separated by spaces, without starting a new line.
+fn print(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    let texts: Vec<_> = args.iter().map(|arg| arg.to_string()).collect();
+    evaluator::write_output(&texts.join(" "));
+    Ok(Rc::new(Object::Null))
+}
+
+fn help(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 1 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 1",
+            args.len()
+        ));
+    }
+
+    match args[0].as_ref() {
+        Object::Function {
+            parameters,
+            rest,
+            doc,
+            ..
+        } => {
+            let doc = doc.as_deref().unwrap_or("No documentation available");
+            Ok(Rc::new(Object::String(format!(
+                "fn({})\n\n{}",
+                format_parameters(parameters, rest.as_ref()),
+                doc
+            ))))
+        }
+        Object::Builtin(builtin) => Ok(Rc::new(Object::String(builtin.to_string()))),
+        _ => Err(miette::miette!(
+            "argument to `help` must be FUNCTION, got {}",
+            args[0].r#type()
+        )),
+    }
+}
+
+/// Runs `callback` once `ms` milliseconds have passed on the host's clock,
+/// see `Interpreter::run_due_callbacks`.
+fn set_timeout(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 2 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 2",
+            args.len()
+        ));
+    }
+
+    let delay = match args[1].as_ref() {
+        Object::Integer(ms) if *ms >= 0 => *ms as u64,
+        Object::Integer(ms) => {
+            return Err(miette::miette!(
+                "delay of `set_timeout` must not be negative, got {}",
+                ms
+            ))
+        }
+        other => {
+            return Err(miette::miette!(
+                "second argument to `set_timeout` must be INTEGER, got {}",
+                other.r#type()
+            ))
+        }
+    };
+    schedule(delay, callback("set_timeout", &args[0])?);
+    Ok(Rc::new(Object::Null))
+}
+
+/// Runs `callback` the next time the host runs the due callbacks.
+fn defer(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 1 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 1",
+            args.len()
+        ));
+    }
+
+    schedule(0, callback("defer", &args[0])?);
+    Ok(Rc::new(Object::Null))
+}
+
+fn callback(builtin: &str, arg: &Rc<Object>) -> Result<Rc<Object>> {
+    match arg.as_ref() {
+        Object::Function { .. } | Object::Builtin(_) => Ok(Rc::clone(arg)),
+        other => Err(miette::miette!(
+            "first argument to `{}` must be FUNCTION, got {}",
+            builtin,
+            other.r#type()
+        )),
+    }
+}
+
+/// Creates a channel and returns its sending and receiving ends as
+/// `[sender, receiver]`.
+fn channel(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if !args.is_empty() {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 0",
+            args.len()
+        ));
+    }
+
+    let channel = Channel::new();
+    Ok(Rc::new(Object::Array(vec![
+        channel.sender(),
+        channel.receiver(),
+    ])))
+}
+
+fn send(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 2 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 2",
+            args.len()
+        ));
+    }
+
+    match args[0].as_ref() {
+        Object::Sender(channel) => {
+            channel.send(Rc::clone(&args[1]));
+            Ok(Rc::new(Object::Null))
+        }
+        other => Err(miette::miette!(
+            "first argument to `send` must be SENDER, got {}",
+            other.r#type()
+        )),
+    }
+}
+
+/// Takes the oldest value from the channel, or `null` if it is empty.
+fn receive(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 1 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 1",
+            args.len()
+        ));
+    }
+
+    match args[0].as_ref() {
+        Object::Receiver(channel) => Ok(channel.receive().unwrap_or_else(|| Rc::new(Object::Null))),
+        other => Err(miette::miette!(
+            "argument to `receive` must be RECEIVER, got {}",
+            other.r#type()
+        )),
+    }
+}
+
+/// Runs the generator up to its next `yield` and gives the value, or `null`
+/// once it is finished.
+fn next(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 1 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 1",
+            args.len()
+        ));
+    }
+
+    match args[0].as_ref() {
+        Object::Generator(generator) => {
+            Ok(generator.resume()?.unwrap_or_else(|| Rc::new(Object::Null)))
+        }
+        other => Err(miette::miette!(
+            "argument to `next` must be GENERATOR, got {}",
+            other.r#type()
+        )),
+    }
+}
+
+/// The version and commit of the interpreter, see [`crate::version`].
+fn version(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if !args.is_empty() {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 0",
+            args.len()
+        ));
+    }
+
+    Ok(Rc::new(Object::String(crate::version())))
+}
+
+/// The names of the example programs, which `load_example` evaluates.
+fn examples(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if !args.is_empty() {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 0",
+            args.len()
+        ));
+    }
+
+    let names = programs::examples()
+        .iter()
+        .map(|(name, _)| Rc::new(Object::String(name.to_string())))
+        .collect();
+    Ok(Rc::new(Object::Array(names)))
+}
+
+/// Evaluates the example program `name` in the global environment of the
+/// session, so that its bindings can be used afterwards, and returns its
+/// value.
+fn load_example(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 1 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 1",
+            args.len()
+        ));
+    }
+    let Object::String(name) = args[0].as_ref() else {
+        return Err(miette::miette!(
+            "argument to `load_example` must be STRING, got {}",
+            args[0].r#type()
+        ));
+    };
+
+    let Some(src) = programs::example(name) else {
+        return Err(miette::miette!(
+            "unknown example `{}`, see `examples()` for the available ones",
+            name
+        ));
+    };
+    let Some(env) = evaluator::session() else {
+        return Err(miette::miette!(
+            "`load_example` can only be used in an interpreter session"
+        ));
+    };
+    let program = Parser::new(Lexer::new(src))
+        .parse()
+        .into_result()
+        .map_err(|mut errors| errors.remove(0))?;
+    evaluator::eval_program(&program, &env)
+}
+
+/// The optional locale argument of the locale builtins at `idx`.
+fn locale_argument<'a>(
+    builtin: &str,
+    args: &'a [Rc<Object>],
+    idx: usize,
+) -> Result<Option<&'a str>> {
+    match args.get(idx).map(|arg| arg.as_ref()) {
+        None => Ok(None),
+        Some(Object::String(locale)) => Ok(Some(locale)),
+        Some(other) => Err(miette::miette!(
+            "locale argument to `{}` must be STRING, got {}",
+            builtin,
+            other.r#type()
+        )),
+    }
+}
+
+/// Compares two strings as sorted in a locale, `-1`, `0` or `1` like `<`,
+/// `==` and `>`.
+fn locale_compare(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 2 && args.len() != 3 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 2 or 3",
+            args.len()
+        ));
+    }
+
+    let (Object::String(a), Object::String(b)) = (args[0].as_ref(), args[1].as_ref()) else {
+        return Err(miette::miette!(
+            "arguments to `locale_compare` must be STRING, got {} and {}",
+            args[0].r#type(),
+            args[1].r#type()
+        ));
+    };
+    let locale_tag = locale_argument("locale_compare", &args, 2)?;
+    let ordering = locale()
+        .compare(a, b, locale_tag)
+        .map_err(|message| miette::miette!("{}", message))?;
+    Ok(Rc::new(Object::Integer(ordering as isize)))
+}
+
+/// Formats an integer with the digit grouping of a locale. Floats aren't
+/// supported, as the locales only group integers.
+fn format_number(args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+    if args.len() != 1 && args.len() != 2 {
+        return Err(miette::miette!(
+            "wrong number of arguments. got={}, want = 1 or 2",
+            args.len()
+        ));
+    }
+
+    let n = match args[0].as_ref() {
+        Object::Integer(n) => n,
+        Object::BigInteger(n) => {
+            return Err(miette::miette!(
+                "{} is too large for `format_number`, which supports {} to {}",
+                n,
+                isize::MIN,
+                isize::MAX
+            ))
+        }
+        other => {
+            return Err(miette::miette!(
+                help = "Round floats first, as in `format_number(math.round(x))`",
+                "first argument to `format_number` must be INTEGER, got {}",
+                other.r#type()
+            ))
+        }
+    };
+    let locale_tag = locale_argument("format_number", &args, 1)?;
+    let formatted = locale()
+        .format_number(*n, locale_tag)
+        .map_err(|message| miette::miette!("{}", message))?;
+    Ok(Rc::new(Object::String(formatted)))
 }
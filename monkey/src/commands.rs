@@ -0,0 +1,270 @@
+use crate::{interpreter::Interpreter, programs};
+
+/// What a REPL frontend should do after running a command.
+#[derive(Debug, PartialEq, Eq)]
+pub enum CommandOutput {
+    /// Print the text, which may span multiple lines.
+    Print(String),
+    /// Clear the screen.
+    Clear,
+}
+
+/// Runs a command with the text following its name, trimmed.
+pub type Handler = fn(&Commands, &Interpreter, &str) -> CommandOutput;
+
+pub struct Command {
+    pub name: &'static str,
+    pub help: &'static str,
+    handler: Handler,
+}
+
+/// The `:`-prefixed meta commands of a REPL, checked before a line is
+/// evaluated as Monkey code.
+pub struct Commands {
+    commands: Vec<Command>,
+}
+
+impl Default for Commands {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Commands {
+    /// The registry with the builtin `:help`, `:clear`, `:examples`, `:unset`,
+    /// `:set` and `:version`.
+    pub fn new() -> Self {
+        let mut commands = Self {
+            commands: Vec::new(),
+        };
+        commands.register("help", "List the available commands", help);
+        commands.register("clear", "Clear the screen", |_, _, _| CommandOutput::Clear);
+        commands.register("examples", "Show example programs to try", examples);
+        commands.register("unset", "Remove the given global bindings", unset);
+        commands.register(
+            "set",
+            "Show or change an option, e.g. `:set transactional on`",
+            set,
+        );
+        commands.register(
+            "version",
+            "Show the version of the interpreter",
+            |_, _, _| CommandOutput::Print(format!("monkey {}", crate::version())),
+        );
+        commands
+    }
+
+    /// Adds a command, replacing any command with the same name.
+    pub fn register(&mut self, name: &'static str, help: &'static str, handler: Handler) {
+        let command = Command {
+            name,
+            help,
+            handler,
+        };
+        match self.commands.iter_mut().find(|c| c.name == name) {
+            Some(existing) => *existing = command,
+            None => self.commands.push(command),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Command> {
+        self.commands.iter()
+    }
+
+    /// Runs `line` if it is a command, i.e. starts with `:`, and returns
+    /// `None` if it should be evaluated instead.
+    pub fn dispatch(&self, line: &str, interpreter: &Interpreter) -> Option<CommandOutput> {
+        let line = line.trim().strip_prefix(':')?;
+        let (name, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let output = match self.commands.iter().find(|c| c.name == name) {
+            Some(command) => (command.handler)(self, interpreter, args.trim()),
+            None => CommandOutput::Print(format!(
+                "Unknown command `:{}`, type `:help` for a list of commands",
+                name
+            )),
+        };
+        Some(output)
+    }
+}
+
+fn help(commands: &Commands, _: &Interpreter, _: &str) -> CommandOutput {
+    let width = commands.iter().map(|c| c.name.len()).max().unwrap_or(0);
+    let lines: Vec<_> = commands
+        .iter()
+        .map(|c| format!(":{:width$}  {}", c.name, c.help, width = width))
+        .collect();
+    CommandOutput::Print(lines.join("\n"))
+}
+
+fn examples(_: &Commands, _: &Interpreter, _: &str) -> CommandOutput {
+    let lines: Vec<_> = programs::examples()
+        .iter()
+        .map(|(name, src)| {
+            format!(
+                "// {}, `load_example({:?})`\n{}",
+                name,
+                name,
+                src.trim_end()
+            )
+        })
+        .collect();
+    CommandOutput::Print(lines.join("\n\n"))
+}
+
+fn unset(_: &Commands, interpreter: &Interpreter, args: &str) -> CommandOutput {
+    if args.is_empty() {
+        return CommandOutput::Print("Usage: `:unset <name>...`".into());
+    }
+    let lines: Vec<_> = args
+        .split_whitespace()
+        .map(
+            |name| match interpreter.environment().borrow_mut().remove(name) {
+                Some(_) => format!("Removed `{}`", name),
+                None => format!("`{}` is not bound", name),
+            },
+        )
+        .collect();
+    CommandOutput::Print(lines.join("\n"))
+}
+
+/// The options of `:set`, see [`Interpreter::set_transactional`] and
+/// [`Parser::with_strict`](crate::Parser::with_strict) for what
+/// `transactional` and `strict` do.
+fn set(_: &Commands, interpreter: &Interpreter, args: &str) -> CommandOutput {
+    let on_off = |on: bool| if on { "on" } else { "off" };
+    let output = match args.split_whitespace().collect::<Vec<_>>()[..] {
+        [] => format!(
+            "transactional  {}\nstrict         {}",
+            on_off(interpreter.is_transactional()),
+            on_off(interpreter.is_strict())
+        ),
+        ["transactional", value @ ("on" | "off")] => {
+            interpreter.set_transactional(value == "on");
+            let effect = match value {
+                "on" => "leaves no bindings behind",
+                _ => "keeps the bindings made before the error",
+            };
+            format!("transactional is {}, a line that fails {}", value, effect)
+        }
+        ["transactional", ..] => "Usage: `:set transactional on|off`".into(),
+        ["strict", value @ ("on" | "off")] => {
+            interpreter.set_strict(value == "on");
+            let effect = match value {
+                "on" => "warns about",
+                _ => "ignores",
+            };
+            format!("strict is {}, parsing {} unused values", value, effect)
+        }
+        ["strict", ..] => "Usage: `:set strict on|off`".into(),
+        [option, ..] => format!("Unknown option `{}`", option),
+    };
+    CommandOutput::Print(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser::Parser};
+
+    #[test]
+    fn test_dispatch() {
+        let commands = Commands::new();
+        let interpreter = Interpreter::new();
+        assert_eq!(commands.dispatch("let x = 1;", &interpreter), None);
+        assert_eq!(
+            commands.dispatch("  :clear ", &interpreter),
+            Some(CommandOutput::Clear)
+        );
+        assert_eq!(
+            commands.dispatch(":help", &interpreter),
+            Some(CommandOutput::Print(
+                ":help      List the available commands
+:clear     Clear the screen
+:examples  Show example programs to try
+:unset     Remove the given global bindings
+:set       Show or change an option, e.g. `:set transactional on`
+:version   Show the version of the interpreter"
+                    .into()
+            ))
+        );
+        assert_eq!(
+            commands.dispatch(":nope 1", &interpreter),
+            Some(CommandOutput::Print(
+                "Unknown command `:nope`, type `:help` for a list of commands".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_register() {
+        let mut commands = Commands::new();
+        commands.register("echo", "Print the arguments", |_, _, args| {
+            CommandOutput::Print(args.to_string())
+        });
+        let interpreter = Interpreter::new();
+        assert_eq!(
+            commands.dispatch(":echo  a b ", &interpreter),
+            Some(CommandOutput::Print("a b".into()))
+        );
+    }
+
+    #[test]
+    fn test_unset() {
+        let commands = Commands::new();
+        let interpreter = Interpreter::new();
+        let lexer = Lexer::new("let x = 1; let y = 2;");
+        interpreter
+            .eval(Parser::new(lexer).parse_program().0)
+            .unwrap();
+        assert_eq!(
+            commands.dispatch(":unset x z", &interpreter),
+            Some(CommandOutput::Print("Removed `x`\n`z` is not bound".into()))
+        );
+        let globals: Vec<_> = interpreter.globals().map(|global| global.0).collect();
+        assert_eq!(globals, vec!["y"]);
+    }
+
+    #[test]
+    fn test_version() {
+        let commands = Commands::new();
+        let interpreter = Interpreter::new();
+        let expected = format!("monkey {} ({})", crate::VERSION, crate::GIT_HASH);
+        assert_eq!(
+            commands.dispatch(":version", &interpreter),
+            Some(CommandOutput::Print(expected))
+        );
+        let lexer = Lexer::new("version()");
+        let result = interpreter
+            .eval(Parser::new(lexer).parse().program)
+            .unwrap();
+        assert_eq!(result.to_string(), crate::version());
+    }
+
+    #[test]
+    fn test_set() {
+        let commands = Commands::new();
+        let interpreter = Interpreter::new();
+        let print = |line| match commands.dispatch(line, &interpreter) {
+            Some(CommandOutput::Print(text)) => text,
+            output => panic!("unexpected {:?}", output),
+        };
+        assert_eq!(print(":set"), "transactional  off\nstrict         off");
+        assert_eq!(
+            print(":set transactional on"),
+            "transactional is on, a line that fails leaves no bindings behind"
+        );
+        assert!(interpreter.is_transactional());
+        assert_eq!(
+            print(":set transactional yes"),
+            "Usage: `:set transactional on|off`"
+        );
+        assert_eq!(
+            print(":set strict on"),
+            "strict is on, parsing warns about unused values"
+        );
+        assert!(interpreter.is_strict());
+        assert_eq!(print(":set"), "transactional  on\nstrict         on");
+        assert_eq!(print(":set strict"), "Usage: `:set strict on|off`");
+        assert_eq!(print(":set color on"), "Unknown option `color`");
+    }
+}
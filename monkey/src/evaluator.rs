@@ -1,74 +1,298 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    fmt,
+    rc::Rc,
+};
 
 use crate::{
-    ast::{Expression, Node, Program, Statement},
-    builtins::BUILTINS,
-    object::{Environment, Object},
+    ast::{
+        BlockStatement, Expression, Float, Identifier, Node, Pattern, Program, Statement, Target,
+    },
+    builtins::builtin,
+    generator::Generator,
+    interpreter::Interpreter,
+    object::{grapheme_object, graphemes, integer, Builtin, Environment, Object},
+    token::{keyword_in_other_case, Comment, Span},
 };
 
 use miette::{Result, Severity};
+use num_bigint::{BigInt, Sign};
+
+/// A function call in progress.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// The called expression as written at the call site, e.g. `add` or
+    /// `newAdder(2)`.
+    pub function: String,
+    pub arguments: Vec<Rc<Object>>,
+}
+
+/// Progress of the evaluation, reported to the observer.
+#[derive(Debug)]
+pub enum Event<'a> {
+    /// The statement is about to be evaluated.
+    Statement(&'a Statement),
+    /// A function was called and its frame pushed onto the call stack.
+    Call,
+    /// A function returned the value, its frame is still on the call stack.
+    Return(&'a Object),
+}
+
+type Observer = Box<dyn FnMut(&Event)>;
+
+/// Where `puts` and `print` write their text.
+pub(crate) type Output = Rc<RefCell<dyn FnMut(&str)>>;
+
+thread_local! {
+    static CALL_STACK: RefCell<Vec<Frame>> = const { RefCell::new(Vec::new()) };
+    static OBSERVER: RefCell<Option<Observer>> = const { RefCell::new(None) };
+    static OUTPUT: RefCell<Option<Output>> = const { RefCell::new(None) };
+    static THROWN: RefCell<Option<Rc<Object>>> = const { RefCell::new(None) };
+    static SESSION: RefCell<Option<Rc<RefCell<Environment>>>> = const { RefCell::new(None) };
+    static MAX_CALL_DEPTH: Cell<usize> = const { Cell::new(Interpreter::DEFAULT_MAX_CALL_DEPTH) };
+}
+
+/// The error raised by `throw`. Reports cannot hold an `Rc`, so the thrown
+/// value waits in `THROWN` until a `catch` takes it.
+#[derive(Debug)]
+struct Thrown(String);
+
+impl fmt::Display for Thrown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for Thrown {}
+
+impl miette::Diagnostic for Thrown {}
+
+/// The calls in progress, outermost first.
+pub(crate) fn call_stack() -> Vec<Frame> {
+    CALL_STACK.with_borrow(|stack| stack.clone())
+}
+
+/// Sets the global environment of the interpreter running the program,
+/// which `load_example` evaluates into, returning the previous one.
+pub(crate) fn set_session(
+    env: Option<Rc<RefCell<Environment>>>,
+) -> Option<Rc<RefCell<Environment>>> {
+    SESSION.replace(env)
+}
+
+pub(crate) fn session() -> Option<Rc<RefCell<Environment>>> {
+    SESSION.with_borrow(|session| session.clone())
+}
+
+/// Sets how many calls may be in progress at once, returning the previous
+/// limit.
+pub(crate) fn set_max_call_depth(depth: usize) -> usize {
+    MAX_CALL_DEPTH.replace(depth)
+}
+
+/// Installs `observer` to be notified of evaluation progress, returning the
+/// previous one.
+pub(crate) fn set_observer(observer: Option<Observer>) -> Option<Observer> {
+    OBSERVER.replace(observer)
+}
+
+/// Sends the text of `puts` and `print` to `output` instead of stdout,
+/// returning the previous destination.
+pub(crate) fn set_output(output: Option<Output>) -> Option<Output> {
+    OUTPUT.replace(output)
+}
+
+/// Writes `text` to the output, stdout unless one was set.
+pub(crate) fn write_output(text: &str) {
+    match OUTPUT.with_borrow(|output| output.clone()) {
+        Some(output) => (output.borrow_mut())(text),
+        None => {
+            print!("{}", text);
+            // `print` may leave the line unfinished.
+            let _ = std::io::Write::flush(&mut std::io::stdout());
+        }
+    }
+}
+
+fn notify(event: Event) {
+    // Taken out for the duration of the call, so evaluating from within the
+    // observer does not notify it again.
+    let Some(mut observer) = OBSERVER.take() else {
+        return;
+    };
+    observer(&event);
+    OBSERVER.with_borrow_mut(|slot| {
+        slot.get_or_insert(observer);
+    });
+}
 
 pub fn eval(node: Node, env: &Rc<RefCell<Environment>>) -> Result<Rc<Object>> {
     match node {
         Node::Program(program) => eval_program(&program, env),
-        Node::Statement(stmt) => eval_statement(&stmt, env),
+        Node::Statement(stmt) => eval_statement(&stmt, &[], env),
         Node::Expression(expr) => eval_expression(&expr, env),
     }
 }
 
-fn eval_program(program: &Program, env: &Rc<RefCell<Environment>>) -> Result<Rc<Object>> {
+pub(crate) fn eval_program(
+    program: &Program,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Rc<Object>> {
     let mut result = Rc::new(Object::Null);
-    for stmt in program.statements() {
-        result = eval_statement(stmt, env)?;
+    for (idx, stmt) in program.statements().iter().enumerate() {
+        result = eval_statement(stmt, program.comments(idx), env)?;
 
         // TODO return the inner of ReturnValue ???
-        if let Object::ReturnValue(_) = *result {
+        match *result {
+            Object::ReturnValue(_) => return Ok(result),
+            Object::Break(_) | Object::Continue => {
+                return Err(miette::miette!("`{}` outside of a loop", result))
+            }
+            _ => {}
+        };
+    }
+    Ok(result)
+}
+
+fn eval_block_statement(
+    block: &BlockStatement,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Rc<Object>> {
+    let mut result = Rc::new(Object::Null);
+    for (idx, stmt) in block.statements().iter().enumerate() {
+        result = eval_statement(stmt, block.comments(idx), env)?;
+
+        if let Object::ReturnValue(_) | Object::Break(_) | Object::Continue = *result {
             return Ok(result);
         };
     }
     Ok(result)
 }
 
-fn eval_statement(statement: &Statement, env: &Rc<RefCell<Environment>>) -> Result<Rc<Object>> {
+/// Evaluates `block` in a scope of its own, so that its `let`s end with it.
+fn eval_scoped_block(block: &BlockStatement, env: &Rc<RefCell<Environment>>) -> Result<Rc<Object>> {
+    eval_block_statement(block, &enclosed(env))
+}
+
+pub(crate) fn enclosed(env: &Rc<RefCell<Environment>>) -> Rc<RefCell<Environment>> {
+    Rc::new(RefCell::new(Environment::new_enclosed(Rc::clone(env))))
+}
+
+pub(crate) fn eval_statement(
+    statement: &Statement,
+    comments: &[Comment],
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Rc<Object>> {
+    notify(Event::Statement(statement));
     match statement {
-        Statement::Let { token, name, value } => {
+        Statement::Let {
+            token: _,
+            name,
+            value,
+        } => {
+            let val = match value {
+                Expression::FunctionLiteral {
+                    parameters,
+                    rest,
+                    body,
+                    generator,
+                } => eval_function_literal(
+                    parameters,
+                    rest,
+                    body,
+                    *generator,
+                    doc_comment(comments),
+                    env,
+                ),
+                _ => eval_expression(value, env)?,
+            };
+            define(env, name, val)?;
+            Ok(Rc::new(Object::Null))
+        }
+        Statement::Destructure {
+            token: _,
+            target,
+            value,
+        } => {
             let val = eval_expression(value, env)?;
-            let mut borrow_env = env.as_ref().borrow_mut();
-            borrow_env.set(name.into(), val);
+            destructure(target, val, env)?;
             Ok(Rc::new(Object::Null))
         }
-        Statement::Return { token, value } => {
+        Statement::Return { token: _, value } => {
             let val = eval_expression(value, env)?;
             Ok(Rc::new(Object::ReturnValue(val)))
         }
+        Statement::Throw { token: _, value } => {
+            let val = eval_expression(value, env)?;
+            let message = match val.as_ref() {
+                Object::Error { message, .. } => message.clone(),
+                val => format!("uncaught exception: {}", val.inspect()),
+            };
+            let report = miette::Report::new(Thrown(message));
+            THROWN.set(Some(val));
+            Err(report)
+        }
+        // A generator runs its `yield` statements itself, see
+        // `crate::generator`, so this one is part of an expression.
+        Statement::Yield { token, value: _ } => {
+            let Span { start, end } = token.span;
+            Err(miette::miette!(
+                labels = vec![miette::LabeledSpan::at(start..end + 1, "here")],
+                help = "Move the `yield` out of the expression into a statement of its own",
+                "`yield` inside an expression is not supported"
+            ))
+        }
+        Statement::Break { token: _, value } => {
+            let value = match value {
+                Some(value) => eval_expression(value, env)?,
+                None => Rc::new(Object::Null),
+            };
+            Ok(Rc::new(Object::Break(value)))
+        }
+        Statement::Continue { token: _ } => Ok(Rc::new(Object::Continue)),
         Statement::Expr(expr) => Ok(eval_expression(expr, env)?),
     }
 }
 
-fn eval_expression(expression: &Expression, env: &Rc<RefCell<Environment>>) -> Result<Rc<Object>> {
+pub(crate) fn eval_expression(
+    expression: &Expression,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Rc<Object>> {
     match expression {
         Expression::IntegerLiteral(i) => Ok(Rc::new(Object::Integer(*i))),
+        Expression::BigIntegerLiteral(i) => Ok(Rc::new(Object::BigInteger(i.clone()))),
+        Expression::FloatLiteral(x) => Ok(Rc::new(Object::Float(*x))),
         Expression::Boolean(b) => Ok(Rc::new(Object::Boolean(*b))),
+        Expression::Null => Ok(Rc::new(Object::Null)),
         Expression::Ident(identifier) => {
             let name = identifier.value();
             let env = env.as_ref().borrow();
-            let builtins = BUILTINS;
             match env.get(name) {
                 Some(val) => Ok(Rc::clone(&val)),
-                None => match builtins.get(name) {
-                    Some(builtin) => Ok(Rc::clone(builtin)),
-                    None => Err(miette::miette!("identifier not found: {}", name)),
-                },
+                None => builtin(name).ok_or_else(|| identifier_not_found(name)),
             }
         }
         Expression::Prefix {
-            token,
+            token: _,
             operator,
             right,
         } => {
             let right_obj = eval_expression(right, env)?;
             eval_prefix_expression(operator, &right_obj)
         }
+        Expression::Infix {
+            token: _,
+            operator,
+            left,
+            right,
+        } if operator == "??" => {
+            let left = eval_expression(left, env)?;
+            match left.as_ref() {
+                Object::Null => eval_expression(right, env),
+                _ => Ok(left),
+            }
+        }
         Expression::Infix {
             token,
             operator,
@@ -77,7 +301,14 @@ fn eval_expression(expression: &Expression, env: &Rc<RefCell<Environment>>) -> R
         } => {
             let left_obj = eval_expression(left, env)?;
             let right_obj = eval_expression(right, env)?;
-            eval_infix_expression(operator, &left_obj, &right_obj)
+            if let Some((name, method, negate)) = operator_method(operator, &left_obj) {
+                let result = call(name.into(), method, vec![left_obj, right_obj], token.span)?;
+                return Ok(match negate {
+                    true => Rc::new(Object::Boolean(!is_truthy(&result))),
+                    false => result,
+                });
+            }
+            eval_infix_expression(operator, &left_obj, &right_obj, token.span)
         }
         Expression::If {
             condition,
@@ -86,41 +317,466 @@ fn eval_expression(expression: &Expression, env: &Rc<RefCell<Environment>>) -> R
         } => {
             let condition = eval_expression(condition, env)?;
             match is_truthy(&condition) {
-                true => eval_program(consequence, env),
+                true => eval_scoped_block(consequence, env),
                 false => {
                     if let Some(alt) = alternative {
-                        eval_program(alt, env)
+                        eval_scoped_block(alt, env)
                     } else {
                         Ok(Rc::new(Object::Null))
                     }
                 }
             }
         }
-        Expression::FunctionLiteral { parameters, body } => Ok(Rc::new(Object::Function {
-            parameters: parameters.clone(),
-            body: body.clone(),
-            env: Rc::clone(env),
-        })),
+        Expression::While { condition, body } => eval_while_expression(condition, body, env),
+        Expression::DoWhile { body, condition } => loop {
+            let result = eval_scoped_block(body, env)?;
+            match result.as_ref() {
+                Object::ReturnValue(_) => return Ok(result),
+                Object::Break(value) => return Ok(Rc::clone(value)),
+                _ => {}
+            }
+            if !is_truthy(eval_expression(condition, env)?.as_ref()) {
+                return Ok(Rc::new(Object::Null));
+            }
+        },
+        Expression::Loop { body } => loop {
+            let result = eval_scoped_block(body, env)?;
+            match result.as_ref() {
+                Object::ReturnValue(_) => return Ok(result),
+                Object::Break(value) => return Ok(Rc::clone(value)),
+                _ => {}
+            }
+        },
+        Expression::For {
+            variable,
+            iterable,
+            body,
+        } => eval_for_expression(variable, iterable, body, env),
+        Expression::Try {
+            body,
+            error,
+            handler,
+        } => eval_try_expression(body, error, handler, env),
+        Expression::Match { value, arms } => eval_match_expression(value, arms, env),
+        Expression::Block(block) => eval_scoped_block(block, env),
+        Expression::FunctionLiteral {
+            parameters,
+            rest,
+            body,
+            generator,
+        } => Ok(eval_function_literal(
+            parameters, rest, body, *generator, None, env,
+        )),
         Expression::Call {
             function,
             arguments,
+            span,
         } => {
             let func = eval_expression(function, env)?;
             let args = eval_expressions(arguments, env)?;
-            apply_function(func, args)
+            call(function.to_string(), func, args, *span)
         }
         Expression::StringLiteral(s) => Ok(Rc::new(Object::String(s.into()))),
+        Expression::CharLiteral(c) => Ok(Rc::new(Object::Char(*c))),
+        Expression::BytesLiteral(b) => Ok(Rc::new(Object::Bytes(b.clone()))),
+        Expression::Spread(_) => Err(miette::miette!(
+            "`..` can only spread into array literals and call arguments"
+        )),
         Expression::ArrayLiteral(v) => {
             let elements = eval_expressions(v, env)?;
             Ok(Rc::new(Object::Array(elements)))
         }
-        Expression::IndexExpr { left, index } => {
+        Expression::Field {
+            left,
+            name,
+            optional,
+        } => {
+            let left = eval_expression(left, env)?;
+            match left.as_ref() {
+                Object::Null if *optional => Ok(left),
+                Object::Hash(map) => match map.get(&Object::String(name.value().into())) {
+                    Some(value) => Ok(Rc::clone(value)),
+                    None => Ok(Rc::new(Object::Null)),
+                },
+                _ => Err(miette::miette!(
+                    "cannot access `.{}` on {}, only hashes have fields",
+                    name,
+                    left.r#type()
+                )),
+            }
+        }
+        Expression::IndexExpr {
+            left,
+            index,
+            optional,
+        } => {
             let left = eval_expression(left, env)?;
+            if *optional && *left == Object::Null {
+                return Ok(left);
+            }
             let index = eval_expression(index, env)?;
             eval_index_expression(left, index)
         }
+        Expression::Slice { left, start, end } => {
+            let left = eval_expression(left, env)?;
+            let start = match start {
+                Some(start) => Some(eval_expression(start, env)?),
+                None => None,
+            };
+            let end = match end {
+                Some(end) => Some(eval_expression(end, env)?),
+                None => None,
+            };
+            eval_slice_expression(left, start, end)
+        }
         Expression::HashLiteral(v) => eval_hash_literal(v.clone(), env),
+        Expression::Assign {
+            token: _,
+            name,
+            value,
+        } => {
+            let val = eval_expression(value, env)?;
+            assign(env, name.value(), Rc::clone(&val))?;
+            Ok(val)
+        }
+        Expression::IndexAssign { target, value, .. } => {
+            let val = eval_expression(value, env)?;
+            assign_index(target, Rc::clone(&val), env)?;
+            Ok(val)
+        }
+    }
+}
+
+/// Rebinds the existing variable `name`, unless it is a frozen builtin.
+fn assign(env: &Rc<RefCell<Environment>>, name: &str, val: Rc<Object>) -> Result<()> {
+    if env.borrow().is_frozen(name) {
+        return Err(frozen_binding(name));
+    }
+    if env.borrow_mut().assign(name, val) {
+        Ok(())
+    } else {
+        Err(identifier_not_found(name))
+    }
+}
+
+/// Assigns `val` to `target`, a variable or an element or field of one, by
+/// rebinding the variable to a copy with the element replaced or inserted.
+fn assign_index(
+    target: &Expression,
+    val: Rc<Object>,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<()> {
+    match target {
+        Expression::Ident(name) => assign(env, name.value(), val),
+        Expression::IndexExpr { left, index, .. } => {
+            let container = eval_expression(left, env)?;
+            let index = eval_expression(index, env)?;
+            let updated = with_element(&container, index, val)?;
+            assign_index(left, Rc::new(updated), env)
+        }
+        Expression::Field { left, name, .. } => {
+            let container = eval_expression(left, env)?;
+            if !matches!(container.as_ref(), Object::Hash(_)) {
+                return Err(miette::miette!(
+                    "cannot assign to `.{}` on {}, only hashes have fields",
+                    name,
+                    container.r#type()
+                ));
+            }
+            let key = Rc::new(Object::String(name.value().into()));
+            let updated = with_element(&container, key, val)?;
+            assign_index(left, Rc::new(updated), env)
+        }
+        _ => Err(miette::miette!("cannot assign to {}", target)),
+    }
+}
+
+/// A copy of `container` with the element at `index` replaced by `val`, or
+/// for hashes inserted if missing.
+#[allow(clippy::mutable_key_type)]
+pub(crate) fn with_element(
+    container: &Rc<Object>,
+    index: Rc<Object>,
+    val: Rc<Object>,
+) -> Result<Object> {
+    match (container.as_ref(), index.as_ref()) {
+        (Object::Array(v), Object::Integer(idx)) => {
+            if *idx < 0 || *idx as usize >= v.len() {
+                return Err(miette::miette!(
+                    "index {} out of bounds for ARRAY of length {}",
+                    idx,
+                    v.len()
+                ));
+            }
+            let mut v = v.clone();
+            v[*idx as usize] = val;
+            Ok(Object::Array(v))
+        }
+        (Object::Array(_), _) => Err(miette::miette!(
+            "array index must be INTEGER, got {}",
+            index.r#type()
+        )),
+        (Object::Hash(map), _) => {
+            if !index.is_hashable() {
+                return Err(miette::miette!("unusable as hash key: {}", index.r#type()));
+            }
+            let mut map = map.clone();
+            map.insert(index, val);
+            Ok(Object::Hash(map))
+        }
+        _ => Err(miette::miette!(
+            "cannot assign to an index of {}",
+            container.r#type()
+        )),
+    }
+}
+
+/// Binds `name` in the innermost scope, unless that would hide a builtin of
+/// a frozen base layer.
+pub(crate) fn define(env: &Rc<RefCell<Environment>>, name: &str, val: Rc<Object>) -> Result<()> {
+    if env.borrow().shadows_frozen(name) {
+        return Err(frozen_binding(name));
+    }
+    env.borrow_mut().set(name.into(), val);
+    Ok(())
+}
+
+/// Binds the names of `target` to the matching parts of `val`.
+fn destructure(target: &Target, val: Rc<Object>, env: &Rc<RefCell<Environment>>) -> Result<()> {
+    match target {
+        Target::Array(names) => {
+            let Object::Array(elements) = val.as_ref() else {
+                return Err(miette::miette!(
+                    "cannot destructure {} as an array",
+                    val.r#type()
+                ));
+            };
+            for (idx, name) in names.iter().enumerate() {
+                let element = elements
+                    .get(idx)
+                    .cloned()
+                    .unwrap_or_else(|| Rc::new(Object::Null));
+                define(env, name.value(), element)?;
+            }
+        }
+        Target::Hash(entries) => {
+            let Object::Hash(map) = val.as_ref() else {
+                return Err(miette::miette!(
+                    "cannot destructure {} as a hash",
+                    val.r#type()
+                ));
+            };
+            for (key, name) in entries {
+                let value = map
+                    .get(&Object::String(key.clone()))
+                    .cloned()
+                    .unwrap_or_else(|| Rc::new(Object::Null));
+                define(env, name.value(), value)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn frozen_binding(name: &str) -> miette::Report {
+    miette::miette!(
+        help = "Builtins can only be redefined inside a function or block, pick another name",
+        "cannot redefine builtin `{}`",
+        name
+    )
+}
+
+fn identifier_not_found(name: &str) -> miette::Report {
+    match keyword_in_other_case(name) {
+        Some(keyword) => miette::miette!(
+            help = format!("Keywords are lowercase, did you mean `{}`?", keyword),
+            "identifier not found: {}",
+            name
+        ),
+        None => miette::miette!("identifier not found: {}", name),
+    }
+}
+
+fn eval_while_expression(
+    condition: &Expression,
+    body: &BlockStatement,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Rc<Object>> {
+    while is_truthy(eval_expression(condition, env)?.as_ref()) {
+        let result = eval_scoped_block(body, env)?;
+        match result.as_ref() {
+            Object::ReturnValue(_) => return Ok(result),
+            Object::Break(value) => return Ok(Rc::clone(value)),
+            _ => {}
+        }
+    }
+    Ok(Rc::new(Object::Null))
+}
+
+/// The items of an iterable, failing if producing one fails.
+pub(crate) type Items = Box<dyn Iterator<Item = Result<Rc<Object>>>>;
+
+/// The elements of an array, the characters of a string, the integers of a
+/// range or bytes or the values of a generator, as `for` and `..` spreading
+/// see them.
+fn iterate(obj: &Object) -> Option<Items> {
+    let items: Box<dyn Iterator<Item = Rc<Object>>> = match obj {
+        Object::Generator(generator) => {
+            let generator = generator.clone();
+            return Some(Box::new(std::iter::from_fn(move || {
+                generator.resume().transpose()
+            })));
+        }
+        Object::Array(v) => Box::new(v.clone().into_iter()),
+        Object::String(s) => {
+            let chars: Vec<_> = graphemes(s)
+                .into_iter()
+                .map(|c| Rc::new(grapheme_object(c)))
+                .collect();
+            Box::new(chars.into_iter())
+        }
+        Object::Range { start, end } => {
+            Box::new((*start..*end).map(|i| Rc::new(Object::Integer(i))))
+        }
+        Object::Bytes(b) => Box::new(
+            b.clone()
+                .into_iter()
+                .map(|byte| Rc::new(Object::Integer(byte.into()))),
+        ),
+        _ => return None,
+    };
+    Some(Box::new(items.map(Ok)))
+}
+
+/// The items a `for` loop over `iterable` goes through.
+pub(crate) fn for_items(iterable: &Object) -> Result<Items> {
+    iterate(iterable).ok_or_else(|| {
+        miette::miette!(
+            "`for` needs an ARRAY, STRING, BYTES, RANGE or GENERATOR to iterate, got {}",
+            iterable.r#type()
+        )
+    })
+}
+
+fn eval_for_expression(
+    variable: &Identifier,
+    iterable: &Expression,
+    body: &BlockStatement,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Rc<Object>> {
+    let iterable = eval_expression(iterable, env)?;
+    for item in for_items(&iterable)? {
+        // Each iteration gets its own binding, which closures can keep.
+        let scope = enclosed(env);
+        define(&scope, variable.value(), item?)?;
+        let result = eval_block_statement(body, &scope)?;
+        match result.as_ref() {
+            Object::ReturnValue(_) => return Ok(result),
+            Object::Break(value) => return Ok(Rc::clone(value)),
+            _ => {}
+        }
+    }
+    Ok(Rc::new(Object::Null))
+}
+
+fn eval_try_expression(
+    body: &BlockStatement,
+    error: &Identifier,
+    handler: &BlockStatement,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Rc<Object>> {
+    let report = match eval_scoped_block(body, env) {
+        Ok(result) => return Ok(result),
+        Err(report) => report,
+    };
+    let scope = enclosed(env);
+    define(&scope, error.value(), caught(report))?;
+    eval_block_statement(handler, &scope)
+}
+
+/// What `catch` binds for `report`: the value thrown, or the message of a
+/// runtime error.
+pub(crate) fn caught(report: miette::Report) -> Rc<Object> {
+    if report.downcast_ref::<Thrown>().is_some() {
+        return THROWN.take().unwrap_or_else(|| Rc::new(Object::Null));
+    }
+    Rc::new(Object::Error {
+        message: report.to_string(),
+        span: report_span(&report),
+    })
+}
+
+/// Labels a builtin's error with the span of the failing call, unless it
+/// already points somewhere or is a value thrown through it, e.g. by the
+/// generator `next` resumes, and points to its signature if there's no help.
+fn at_call(report: miette::Report, builtin: &Builtin, span: Span) -> miette::Report {
+    if report.labels().is_some() || report.downcast_ref::<Thrown>().is_some() {
+        return report;
+    }
+    let label = miette::LabeledSpan::at(span.start..span.end + 1, "here");
+    let help = match report.help() {
+        Some(help) => help.to_string(),
+        None => format!("Usage: {}", builtin.signature()),
+    };
+    let diagnostic = miette::MietteDiagnostic::new(report.to_string())
+        .with_label(label)
+        .with_help(help);
+    miette::Report::new(diagnostic)
+}
+
+/// The span of the first label of `report`, if it has any.
+fn report_span(report: &miette::Report) -> Option<Span> {
+    let label = report.labels()?.next()?;
+    Some(Span {
+        start: label.offset(),
+        end: label.offset() + label.len().max(1) - 1,
+    })
+}
+
+fn eval_match_expression(
+    value: &Expression,
+    arms: &[(Pattern, BlockStatement)],
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Rc<Object>> {
+    let value = eval_expression(value, env)?;
+    for (pattern, body) in arms {
+        let matches = match pattern {
+            Pattern::Wildcard => true,
+            Pattern::Literal(literal) => *eval_expression(literal, env)? == *value,
+        };
+        if matches {
+            return eval_scoped_block(body, env);
+        }
     }
+    Ok(Rc::new(Object::Null))
+}
+
+pub(crate) fn eval_function_literal(
+    parameters: &[Identifier],
+    rest: &Option<Identifier>,
+    body: &BlockStatement,
+    generator: bool,
+    doc: Option<String>,
+    env: &Rc<RefCell<Environment>>,
+) -> Rc<Object> {
+    Rc::new(Object::Function {
+        parameters: parameters.to_vec(),
+        rest: rest.clone(),
+        body: body.clone(),
+        env: Rc::clone(env),
+        doc,
+        generator,
+    })
+}
+
+/// Joins the `///` doc comment lines among `comments`, stripping the markers.
+pub(crate) fn doc_comment(comments: &[Comment]) -> Option<String> {
+    let lines: Vec<_> = comments
+        .iter()
+        .filter_map(|comment| comment.text.strip_prefix("///"))
+        .map(|line| line.strip_prefix(' ').unwrap_or(line))
+        .collect();
+    (!lines.is_empty()).then(|| lines.join("\n"))
 }
 
 fn eval_prefix_expression(operator: &str, right: &Object) -> Result<Rc<Object>> {
@@ -135,7 +791,12 @@ fn eval_prefix_expression(operator: &str, right: &Object) -> Result<Rc<Object>>
             Ok(Rc::new(Object::Boolean(res)))
         }
         "-" => match right {
-            Object::Integer(i) => Ok(Rc::new(Object::Integer(-i))),
+            Object::Integer(i) => Ok(Rc::new(match i.checked_neg() {
+                Some(i) => Object::Integer(i),
+                None => Object::BigInteger(-BigInt::from(*i)),
+            })),
+            Object::BigInteger(i) => Ok(Rc::new(integer(-i))),
+            Object::Float(x) => Ok(Rc::new(Object::Float(Float(-x.0)))),
             _ => Err(miette::miette!(
                 severity = Severity::Error,
                 //code = "expected::rparen",
@@ -159,7 +820,215 @@ fn eval_prefix_expression(operator: &str, right: &Object) -> Result<Rc<Object>>
     }
 }
 
-fn eval_infix_expression(operator: &str, left: &Object, right: &Object) -> Result<Rc<Object>> {
+/// Calls `func` as `function` on the call stack, up to the maximum depth.
+fn call(
+    function: String,
+    func: Rc<Object>,
+    args: Vec<Rc<Object>>,
+    span: Span,
+) -> Result<Rc<Object>> {
+    let max_depth = MAX_CALL_DEPTH.get();
+    if CALL_STACK.with_borrow(|stack| stack.len()) >= max_depth {
+        return Err(miette::miette!(
+            labels = vec![miette::LabeledSpan::at(span.start..span.end + 1, "here")],
+            help = format!("At most {} calls can be in progress at once", max_depth),
+            "maximum recursion depth exceeded"
+        ));
+    }
+    let frame = Frame {
+        function,
+        arguments: args.clone(),
+    };
+    CALL_STACK.with_borrow_mut(|stack| stack.push(frame));
+    notify(Event::Call);
+    let builtin = match func.as_ref() {
+        Object::Builtin(builtin) => Some(*builtin),
+        _ => None,
+    };
+    let result = apply_function(func, args).map_err(|report| match builtin {
+        Some(builtin) => at_call(report, &builtin, span),
+        None => report,
+    });
+    if let Ok(value) = &result {
+        notify(Event::Return(value));
+    }
+    CALL_STACK.with_borrow_mut(|stack| stack.pop());
+    result
+}
+
+/// The function a hash defines for `operator` under a key like `__add__`,
+/// with its name and whether its result is to be negated, which is the case
+/// for `!=` falling back to `__eq__`.
+fn operator_method(operator: &str, left: &Object) -> Option<(&'static str, Rc<Object>, bool)> {
+    let Object::Hash(hash) = left else {
+        return None;
+    };
+    let name = match operator {
+        "+" => "__add__",
+        "-" => "__sub__",
+        "*" => "__mul__",
+        "/" => "__div__",
+        "%" => "__mod__",
+        "<" => "__lt__",
+        ">" => "__gt__",
+        "==" => "__eq__",
+        "!=" => "__ne__",
+        _ => return None,
+    };
+    let method = |name: &str| {
+        hash.get(&Object::String(name.into()))
+            .filter(|value| matches!(value.as_ref(), Object::Function { .. } | Object::Builtin(_)))
+            .cloned()
+    };
+    match (method(name), operator) {
+        (Some(method), _) => Some((name, method, false)),
+        (None, "!=") => method("__eq__").map(|method| ("__eq__", method, true)),
+        (None, _) => None,
+    }
+}
+
+/// Evaluates `left operator right`, with `span` the operator's, to point
+/// errors at.
+fn eval_infix_expression(
+    operator: &str,
+    left: &Object,
+    right: &Object,
+    span: Span,
+) -> Result<Rc<Object>> {
+    // A big integer is never zero.
+    let is_zero = match right {
+        Object::Integer(i) => *i == 0,
+        Object::Float(x) => x.0 == 0.0,
+        _ => false,
+    };
+    if matches!(operator, "/" | "%") && is_zero {
+        return Err(miette::miette!(
+            labels = vec![miette::LabeledSpan::at(
+                span.start..span.end + 1,
+                "the divisor is zero"
+            )],
+            "{} by zero",
+            if operator == "/" {
+                "division"
+            } else {
+                "modulo"
+            }
+        ));
+    }
+
+    // Anything can be compared against null.
+    if matches!(left, Object::Null) || matches!(right, Object::Null) {
+        match operator {
+            "==" => return Ok(Rc::new(Object::Boolean(left == right))),
+            "!=" => return Ok(Rc::new(Object::Boolean(left != right))),
+            _ => {}
+        }
+    }
+
+    // A character can be appended to a string or prepended to one, and
+    // equals the string of just that character.
+    match (left, operator, right) {
+        (Object::String(l), "+", Object::Char(r)) => {
+            return Ok(Rc::new(Object::String(format!("{}{}", l, r))))
+        }
+        (Object::Char(l), "+", Object::String(r)) => {
+            return Ok(Rc::new(Object::String(format!("{}{}", l, r))))
+        }
+        (Object::Char(_), "==", Object::String(_)) | (Object::String(_), "==", Object::Char(_)) => {
+            return Ok(Rc::new(Object::Boolean(left == right)))
+        }
+        (Object::Char(_), "!=", Object::String(_)) | (Object::String(_), "!=", Object::Char(_)) => {
+            return Ok(Rc::new(Object::Boolean(left != right)))
+        }
+        _ => {}
+    }
+
+    // A string or an array times a count repeats it.
+    let negative = match right {
+        Object::Integer(count) => *count < 0,
+        Object::BigInteger(count) => count.sign() == Sign::Minus,
+        _ => false,
+    };
+    match (left, operator, right) {
+        (Object::String(_) | Object::Array(_), "*", Object::Integer(_) | Object::BigInteger(_))
+            if negative =>
+        {
+            return Err(miette::miette!(
+                labels = vec![miette::LabeledSpan::at(
+                    span.start..span.end + 1,
+                    "the count is negative"
+                )],
+                "cannot repeat {} a negative number of times, got {}",
+                left.r#type(),
+                right
+            ))
+        }
+        (Object::String(_) | Object::Array(_), "*", Object::Integer(_) | Object::BigInteger(_))
+            if repetition_len(left, right).is_none_or(|len| len > MAX_REPETITION_LEN) =>
+        {
+            return Err(miette::miette!(
+                labels = vec![miette::LabeledSpan::at(
+                    span.start..span.end + 1,
+                    "the count is too large"
+                )],
+                help = format!("Repetitions build at most {} elements", MAX_REPETITION_LEN),
+                "cannot repeat {} {} times",
+                left.r#type(),
+                right
+            ))
+        }
+        (Object::String(l), "*", Object::Integer(count)) => {
+            return Ok(Rc::new(Object::String(l.repeat(*count as usize))))
+        }
+        (Object::Array(l), "*", Object::Integer(count)) => {
+            return Ok(Rc::new(Object::Array(
+                l.iter()
+                    .cycle()
+                    .take(l.len() * *count as usize)
+                    .cloned()
+                    .collect(),
+            )))
+        }
+        _ => {}
+    }
+
+    // An integer combined with a float is promoted to a float.
+    if matches!(left, Object::Float(_)) || matches!(right, Object::Float(_)) {
+        if let (Some(l), Some(r)) = (left.to_f64(), right.to_f64()) {
+            let result = match operator {
+                "+" => Object::Float(Float(l + r)),
+                "-" => Object::Float(Float(l - r)),
+                "*" => Object::Float(Float(l * r)),
+                "/" => Object::Float(Float(l / r)),
+                "%" => Object::Float(Float(l % r)),
+                "<" => Object::Boolean(l < r),
+                ">" => Object::Boolean(l > r),
+                "==" => Object::Boolean(l == r),
+                "!=" => Object::Boolean(l != r),
+                _ => {
+                    return Err(miette::miette!(
+                        "unknown operator: {} {} {}",
+                        left.r#type(),
+                        operator,
+                        right.r#type(),
+                    ))
+                }
+            };
+            return Ok(Rc::new(result));
+        }
+    }
+
+    let is_string = |obj: &Object| matches!(obj, Object::String(_));
+    if operator == "+" && right.r#type() != left.r#type() && (is_string(left) || is_string(right)) {
+        return Err(miette::miette!(
+            help = "Convert the other operand with `str` first, as in `\"n = \" + str(n)`",
+            "type mismatch: {} {} {}",
+            left.r#type(),
+            operator,
+            right.r#type(),
+        ));
+    }
+
     if right.r#type() != left.r#type() {
         return Err(miette::miette!(
             severity = Severity::Error,
@@ -175,15 +1044,42 @@ fn eval_infix_expression(operator: &str, left: &Object, right: &Object) -> Resul
     }
 
     match (left, operator, right) {
-        (Object::Integer(l), "+", Object::Integer(r)) => Ok(Rc::new(Object::Integer(l + r))),
-        (Object::Integer(l), "-", Object::Integer(r)) => Ok(Rc::new(Object::Integer(l - r))),
-        (Object::Integer(l), "*", Object::Integer(r)) => Ok(Rc::new(Object::Integer(l * r))),
-        (Object::Integer(l), "/", Object::Integer(r)) => Ok(Rc::new(Object::Integer(l / r))),
+        (Object::Integer(l), "+" | "-" | "*" | "/" | "%", Object::Integer(r)) => {
+            let result = match operator {
+                "+" => l.checked_add(*r),
+                "-" => l.checked_sub(*r),
+                "*" => l.checked_mul(*r),
+                "/" => l.checked_div(*r),
+                _ => l.checked_rem(*r),
+            };
+            match result {
+                Some(i) => Ok(Rc::new(Object::Integer(i))),
+                None => eval_big_integer_infix(operator, BigInt::from(*l), BigInt::from(*r)),
+            }
+        }
+
+        (Object::Integer(l), "..", Object::Integer(r)) => {
+            Ok(Rc::new(Object::Range { start: *l, end: *r }))
+        }
 
         (Object::Integer(l), "<", Object::Integer(r)) => Ok(Rc::new(Object::Boolean(l < r))),
         (Object::Integer(l), ">", Object::Integer(r)) => Ok(Rc::new(Object::Boolean(l > r))),
         (Object::Integer(l), "==", Object::Integer(r)) => Ok(Rc::new(Object::Boolean(l == r))),
         (Object::Integer(l), "!=", Object::Integer(r)) => Ok(Rc::new(Object::Boolean(l != r))),
+        (
+            Object::Integer(_) | Object::BigInteger(_),
+            _,
+            Object::Integer(_) | Object::BigInteger(_),
+        ) => eval_big_integer_infix(
+            operator,
+            left.to_bigint().expect("an integer"),
+            right.to_bigint().expect("an integer"),
+        ),
+
+        (Object::Char(l), "<", Object::Char(r)) => Ok(Rc::new(Object::Boolean(l < r))),
+        (Object::Char(l), ">", Object::Char(r)) => Ok(Rc::new(Object::Boolean(l > r))),
+        (Object::Char(l), "==", Object::Char(r)) => Ok(Rc::new(Object::Boolean(l == r))),
+        (Object::Char(l), "!=", Object::Char(r)) => Ok(Rc::new(Object::Boolean(l != r))),
 
         (Object::Boolean(l), "==", Object::Boolean(r)) => Ok(Rc::new(Object::Boolean(l == r))),
         (Object::Boolean(l), "!=", Object::Boolean(r)) => Ok(Rc::new(Object::Boolean(l != r))),
@@ -191,6 +1087,29 @@ fn eval_infix_expression(operator: &str, left: &Object, right: &Object) -> Resul
         (Object::String(l), "+", Object::String(r)) => {
             Ok(Rc::new(Object::String(format!("{}{}", l, r))))
         }
+        (Object::Array(l), "+", Object::Array(r)) => {
+            Ok(Rc::new(Object::Array(l.iter().chain(r).cloned().collect())))
+        }
+        // The right hash wins for keys in both.
+        (Object::Hash(l), "+", Object::Hash(r)) => {
+            #[allow(clippy::mutable_key_type)]
+            let mut merged = l.clone();
+            merged.extend(r.iter().map(|(k, v)| (Rc::clone(k), Rc::clone(v))));
+            Ok(Rc::new(Object::Hash(merged)))
+        }
+
+        (
+            Object::String(_) | Object::Bytes(_) | Object::Array(_) | Object::Hash(_),
+            "==" | "!=",
+            _,
+        ) => {
+            let equal = structurally_equal(left, right);
+            Ok(Rc::new(Object::Boolean(if operator == "==" {
+                equal
+            } else {
+                !equal
+            })))
+        }
         _ => Err(miette::miette!(
             severity = Severity::Error,
             //code = "expected::rparen",
@@ -205,32 +1124,147 @@ fn eval_infix_expression(operator: &str, left: &Object, right: &Object) -> Resul
     }
 }
 
-fn eval_expressions(
-    expressions: &[Expression],
-    env: &Rc<RefCell<Environment>>,
-) -> Result<Vec<Rc<Object>>> {
-    let mut result = Vec::new();
-    for exp in expressions {
-        let evaluated = eval_expression(exp, env)?;
-        result.push(evaluated);
-    }
-    Ok(result)
+/// The longest string, in bytes, or array that repeating one may build, which
+/// keeps a typo in the count from exhausting the memory.
+const MAX_REPETITION_LEN: usize = 1 << 24;
+
+/// The length of `left` repeated `count` times, `None` if it overflows, which
+/// includes any big integer count.
+fn repetition_len(left: &Object, count: &Object) -> Option<usize> {
+    let Object::Integer(count) = count else {
+        return None;
+    };
+    let len = match left {
+        Object::String(s) => s.len(),
+        Object::Array(v) => v.len(),
+        _ => return None,
+    };
+    len.checked_mul(usize::try_from(*count).ok()?)
+}
+
+/// Operators on integers that don't fit an `isize`, or whose result doesn't.
+fn eval_big_integer_infix(operator: &str, left: BigInt, right: BigInt) -> Result<Rc<Object>> {
+    let result = match operator {
+        "+" => integer(left + right),
+        "-" => integer(left - right),
+        "*" => integer(left * right),
+        "/" => integer(left / right),
+        "%" => integer(left % right),
+        "<" => Object::Boolean(left < right),
+        ">" => Object::Boolean(left > right),
+        "==" => Object::Boolean(left == right),
+        "!=" => Object::Boolean(left != right),
+        ".." => {
+            return Err(miette::miette!(
+                "range bounds must be between {} and {}",
+                isize::MIN,
+                isize::MAX
+            ))
+        }
+        _ => {
+            return Err(miette::miette!(
+                "unknown operator: INTEGER {} INTEGER",
+                operator
+            ))
+        }
+    };
+    Ok(Rc::new(result))
+}
+
+/// Whether `left` and `right` hold equal values, comparing arrays element by
+/// element and hashes entry by entry. Functions, builtins and channels are
+/// only equal to themselves.
+pub(crate) fn structurally_equal(left: &Object, right: &Object) -> bool {
+    match (left, right) {
+        (Object::Float(_), _) | (_, Object::Float(_)) => {
+            left.to_f64().is_some_and(|l| right.to_f64() == Some(l))
+        }
+        (
+            Object::Integer(_)
+            | Object::BigInteger(_)
+            | Object::Boolean(_)
+            | Object::String(_)
+            | Object::Char(_)
+            | Object::Bytes(_)
+            | Object::Null,
+            _,
+        )
+        | (Object::Range { .. } | Object::Sender(_) | Object::Receiver(_), _) => left == right,
+        (Object::Array(l), Object::Array(r)) => {
+            l.len() == r.len() && l.iter().zip(r).all(|(l, r)| structurally_equal(l, r))
+        }
+        (Object::Hash(l), Object::Hash(r)) => {
+            l.len() == r.len()
+                && l.iter()
+                    .all(|(key, l)| r.get(key).is_some_and(|r| structurally_equal(l, r)))
+        }
+        _ => std::ptr::eq(left, right),
+    }
+}
+
+fn eval_expressions(
+    expressions: &[Expression],
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Vec<Rc<Object>>> {
+    let mut result = Vec::new();
+    for exp in expressions {
+        let Expression::Spread(value) = exp else {
+            result.push(eval_expression(exp, env)?);
+            continue;
+        };
+        let evaluated = eval_expression(value, env)?;
+        match iterate(&evaluated) {
+            Some(items) => {
+                for item in items {
+                    result.push(item?);
+                }
+            }
+            None => {
+                return Err(miette::miette!(
+                    "`..` needs an ARRAY, STRING, BYTES, RANGE or GENERATOR to spread, got {}",
+                    evaluated.r#type()
+                ))
+            }
+        }
+    }
+    Ok(result)
 }
 
 fn eval_index_expression(left: Rc<Object>, index: Rc<Object>) -> Result<Rc<Object>> {
     match (left.as_ref(), index.as_ref()) {
         (Object::Array(v), Object::Integer(idx)) => {
-            let max = (v.len() - 1) as isize;
-
-            if *idx < 0 || *idx > max {
+            match usize::try_from(*idx).ok().and_then(|i| v.get(i)) {
+                Some(value) => Ok(Rc::clone(value)),
+                None => Ok(Rc::new(Object::Null)),
+            }
+        }
+        (Object::Range { start, end }, Object::Integer(idx)) => {
+            // Past `isize::MAX` is past the end too
+            match start.checked_add(*idx) {
+                Some(i) if *idx >= 0 && i < *end => Ok(Rc::new(Object::Integer(i))),
+                _ => Ok(Rc::new(Object::Null)),
+            }
+        }
+        (Object::String(s), Object::Integer(idx)) => {
+            if *idx < 0 {
                 return Ok(Rc::new(Object::Null));
             }
-
-            Ok(Rc::clone(&v[*idx as usize]))
+            match graphemes(s).get(*idx as usize) {
+                Some(c) => Ok(Rc::new(grapheme_object(c))),
+                None => Ok(Rc::new(Object::Null)),
+            }
         }
+        (Object::Bytes(b), Object::Integer(idx)) => match usize::try_from(*idx) {
+            Ok(idx) if idx < b.len() => Ok(Rc::new(Object::Integer(b[idx].into()))),
+            _ => Ok(Rc::new(Object::Null)),
+        },
+        (
+            Object::Array(_) | Object::String(_) | Object::Bytes(_) | Object::Range { .. },
+            Object::BigInteger(_),
+        ) => Ok(Rc::new(Object::Null)),
         (Object::Hash(map), _) => {
             if !index.is_hashable() {
-                return Err(miette::miette!("unusable as hash key: {}", index.r#type()))
+                return Err(miette::miette!("unusable as hash key: {}", index.r#type()));
             }
 
             match map.get(&index) {
@@ -238,52 +1272,140 @@ fn eval_index_expression(left: Rc<Object>, index: Rc<Object>) -> Result<Rc<Objec
                 None => Ok(Rc::new(Object::Null)),
             }
         }
-        _ => Err(miette::miette!("Indexing only for arrays and maps")),
+        _ => Err(miette::miette!(
+            "Indexing only for arrays, strings, bytes, ranges and maps"
+        )),
     }
 }
 
-fn eval_hash_literal(v: Vec<(Expression, Expression)>, env: &Rc<RefCell<Environment>>) -> Result<Rc<Object>> {
-    //let pairs = HashMap::new();
-    let pairs: Result<HashMap<_,_>> = v.iter().map(|(key, val)| {
-        let key = eval_expression(key, env)?;
-        let value = eval_expression(val, env)?;
-        if key.is_hashable() {
-            Ok((key, value))
-        } else {
-            Err(miette::miette!("Type of {} cannot be used as a key", key.r#type()))
+pub(crate) fn eval_slice_expression(
+    left: Rc<Object>,
+    start: Option<Rc<Object>>,
+    end: Option<Rc<Object>>,
+) -> Result<Rc<Object>> {
+    let start = slice_index(start)?;
+    let end = slice_index(end)?;
+    match left.as_ref() {
+        Object::String(s) => {
+            let chars = graphemes(s);
+            let (start, end) = slice_bounds(chars.len(), start, end);
+            Ok(Rc::new(Object::String(chars[start..end].concat())))
+        }
+        Object::Array(v) => {
+            let (start, end) = slice_bounds(v.len(), start, end);
+            Ok(Rc::new(Object::Array(v[start..end].to_vec())))
+        }
+        Object::Bytes(b) => {
+            let (start, end) = slice_bounds(b.len(), start, end);
+            Ok(Rc::new(Object::Bytes(b[start..end].to_vec())))
         }
-    }).collect();
+        _ => Err(miette::miette!(
+            "Slicing only for arrays, strings and bytes"
+        )),
+    }
+}
+
+fn slice_index(index: Option<Rc<Object>>) -> Result<Option<isize>> {
+    match index.as_deref() {
+        Some(Object::Integer(i)) => Ok(Some(*i)),
+        // Clamped to the sequence anyway.
+        Some(Object::BigInteger(i)) if i.sign() == Sign::Minus => Ok(Some(isize::MIN)),
+        Some(Object::BigInteger(_)) => Ok(Some(isize::MAX)),
+        Some(other) => Err(miette::miette!(
+            "slice index must be INTEGER, got {}",
+            other.r#type()
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Clamps optional slice bounds into `0..=len`, with missing bounds meaning
+/// the start or end of the sequence respectively.
+fn slice_bounds(len: usize, start: Option<isize>, end: Option<isize>) -> (usize, usize) {
+    let clamp = |i: isize| i.clamp(0, len as isize) as usize;
+    let start = start.map_or(0, clamp);
+    let end = end.map_or(len, clamp);
+    (start, end.max(start))
+}
+
+fn eval_hash_literal(
+    v: Vec<(Expression, Expression)>,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Rc<Object>> {
+    //let pairs = HashMap::new();
+    let pairs: Result<HashMap<_, _>> = v
+        .iter()
+        .map(|(key, val)| {
+            let key = eval_expression(key, env)?;
+            let value = eval_expression(val, env)?;
+            if key.is_hashable() {
+                Ok((key, value))
+            } else {
+                Err(miette::miette!(
+                    "Type of {} cannot be used as a key",
+                    key.r#type()
+                ))
+            }
+        })
+        .collect();
 
     pairs.map(|pairs| Rc::new(Object::Hash(pairs)))
 }
 
-fn apply_function(func: Rc<Object>, args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
+pub(crate) fn apply_function(func: Rc<Object>, args: Vec<Rc<Object>>) -> Result<Rc<Object>> {
     match func.as_ref() {
         Object::Function {
             parameters,
+            rest,
             body,
             env,
+            doc: _,
+            generator,
         } => {
+            if args.len() < parameters.len() {
+                let want = match rest {
+                    Some(_) => format!("at least {}", parameters.len()),
+                    None => parameters.len().to_string(),
+                };
+                return Err(miette::miette!(
+                    "wrong number of arguments. got={}, want = {}",
+                    args.len(),
+                    want
+                ));
+            }
             let extended_env = {
                 let mut new_env = Environment::new_enclosed(Rc::clone(env));
-                for (param_idx, param) in parameters.iter().enumerate() {
-                    new_env.set(param.value().into(), Rc::clone(&args[param_idx]));
+                let mut args = args.into_iter();
+                for param in parameters {
+                    new_env.set(param.value().into(), args.next().unwrap());
+                }
+                if let Some(rest) = rest {
+                    new_env.set(rest.value().into(), Rc::new(Object::Array(args.collect())));
                 }
                 new_env
             };
             let extended_env = Rc::new(RefCell::new(extended_env));
-            let evaluated = eval_program(body, &extended_env)?;
+            if *generator {
+                return Ok(Rc::new(Object::Generator(Generator::new(
+                    body,
+                    extended_env,
+                ))));
+            }
+            let evaluated = eval_block_statement(body, &extended_env)?;
             match evaluated.as_ref() {
                 Object::ReturnValue(rc) => Ok(Rc::clone(rc)),
+                Object::Break(_) | Object::Continue => {
+                    Err(miette::miette!("`{}` outside of a loop", evaluated))
+                }
                 _ => Ok(evaluated),
             }
         }
-        Object::Builtin(func) => func(args),
+        Object::Builtin(builtin) => (builtin.func)(args),
         _ => Err(miette::miette!("not a function: {}", func.r#type())),
     }
 }
 
-fn is_truthy(obj: &Object) -> bool {
+pub(crate) fn is_truthy(obj: &Object) -> bool {
     match obj {
         Object::Null => false,
         Object::Boolean(b) => *b,
@@ -308,7 +1430,7 @@ mod tests {
         let lexer = Lexer::new(input);
         let mut parser = Parser::new(lexer);
         let environment = Rc::new(RefCell::new(Environment::new()));
-        eval(Node::Program(parser.parse_program()), &environment)
+        eval(Node::Program(parser.parse_program().0), &environment)
     }
 
     #[test]
@@ -421,6 +1543,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_null_literal() {
+        let tests = [
+            ("null", Object::Null),
+            ("let f = fn() { return null; }; f()", Object::Null),
+            ("null == null", Object::Boolean(true)),
+            ("[1][5] == null", Object::Boolean(true)),
+            ("1 == null", Object::Boolean(false)),
+            ("null != \"a\"", Object::Boolean(true)),
+            ("!null", Object::Boolean(true)),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap(), Rc::new(expected), "{}", input);
+        }
+
+        match test_eval("null + 1") {
+            Ok(_) => unreachable!(),
+            Err(e) => assert_eq!(e.to_string(), "type mismatch: NULL + INTEGER"),
+        }
+    }
+
     #[test]
     fn test_bang_operator() {
         assert_eq!(test_eval("!true").unwrap(), Rc::new(Object::Boolean(false)));
@@ -474,6 +1617,433 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_else_if_expression() {
+        let input = "let sign = fn(x) { if (x < 0) { -1 } else if (x == 0) { 0 } else { 1 } };";
+        let tests = [("sign(-5)", -1), ("sign(0)", 0), ("sign(3)", 1)];
+        for (call, expected) in tests {
+            assert_eq!(
+                test_eval(&format!("{} {}", input, call)).unwrap(),
+                Rc::new(Object::Integer(expected))
+            );
+        }
+        assert_eq!(
+            test_eval("if (false) { 1 } else if (false) { 2 }").unwrap(),
+            Rc::new(Object::Null)
+        );
+    }
+
+    #[test]
+    fn test_ranges() {
+        let tests = [
+            ("1..4", Object::Range { start: 1, end: 4 }),
+            ("let n = 3; 0..n + 1", Object::Range { start: 0, end: 4 }),
+            ("(2..5)[0]", Object::Integer(2)),
+            ("(2..5)[2]", Object::Integer(4)),
+            ("(2..5)[3]", Object::Null),
+            ("(2..5)[-1]", Object::Null),
+            ("len(2..5)", Object::Integer(3)),
+            ("len(5..2)", Object::Integer(0)),
+            (
+                "(-9223372036854775807..9223372036854775807)[0]",
+                Object::Integer(-9223372036854775807),
+            ),
+            (
+                "(1..9223372036854775807)[9223372036854775807]",
+                Object::Null,
+            ),
+            (
+                "len(-9223372036854775807..9223372036854775807)",
+                Object::BigInteger("18446744073709551614".parse().unwrap()),
+            ),
+            ("first(2..5)", Object::Integer(2)),
+            ("last(2..5)", Object::Integer(4)),
+            ("last(5..5)", Object::Null),
+            (
+                "rest(1..3)",
+                Object::Array(vec![Rc::new(Object::Integer(2))]),
+            ),
+            (
+                "push(1..3, 3)",
+                Object::Array(vec![
+                    Rc::new(Object::Integer(1)),
+                    Rc::new(Object::Integer(2)),
+                    Rc::new(Object::Integer(3)),
+                ]),
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap(), Rc::new(expected), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_identifier_not_found_hints_keywords() {
+        let e = test_eval("If (true) { 1 }").unwrap_err();
+        assert_eq!(e.to_string(), "identifier not found: If");
+        assert_eq!(
+            e.help().unwrap().to_string(),
+            "Keywords are lowercase, did you mean `if`?"
+        );
+        assert!(test_eval("foo").unwrap_err().help().is_none());
+    }
+
+    #[test]
+    fn test_for_loops() {
+        let tests = [
+            ("let sum = 0; for (i in 0..10) { sum += i; } sum", Object::Integer(45)),
+            ("let sum = 0; for (x in [1, 2, 3]) { sum += x; } sum", Object::Integer(6)),
+            (
+                r#"let s = ""; for (c in "héllo") { s = c + s; } s"#,
+                Object::String("olléh".into()),
+            ),
+            (
+                "let sum = 0; for (i in 0..10) { if (i == 2) { continue; } if (i == 5) { break; } sum += i; } sum",
+                Object::Integer(8),
+            ),
+            ("for (i in 0..3) { i }", Object::Null),
+            ("let f = fn() { for (i in 1..100) { if (i * i > 50) { return i; } } }; f()", Object::Integer(8)),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap(), Rc::new(expected), "{}", input);
+        }
+
+        match test_eval("for (x in 5) { x }") {
+            Ok(_) => unreachable!(),
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "`for` needs an ARRAY, STRING, BYTES, RANGE or GENERATOR to iterate, got INTEGER"
+            ),
+        }
+    }
+
+    #[test]
+    fn test_block_expressions() {
+        let tests = [
+            ("{ let tmp = 1; tmp + 2 }", Object::Integer(3)),
+            (
+                "let x = 1; let y = { let x = 10; x * 2 }; x + y",
+                Object::Integer(21),
+            ),
+            ("let x = 1; { x = 5; }; x", Object::Integer(5)),
+            ("{ 5 } + 1", Object::Integer(6)),
+            ("{ let x = 1; }", Object::Null),
+            (
+                r#"let f = fn(n) { { if (n > 2) { return "big"; } }; "small" }; f(5)"#,
+                Object::String("big".into()),
+            ),
+            ("loop { { break 7; } }", Object::Integer(7)),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap(), Rc::new(expected), "{}", input);
+        }
+
+        match test_eval("{ let tmp = 1; }; tmp") {
+            Ok(_) => unreachable!(),
+            Err(e) => assert_eq!(e.to_string(), "identifier not found: tmp"),
+        }
+    }
+
+    #[test]
+    fn test_block_scoping() {
+        let tests = [
+            ("let x = 1; if (true) { let x = 2; x }", Object::Integer(2)),
+            ("let x = 1; if (true) { let x = 2; } x", Object::Integer(1)),
+            ("let x = 1; if (true) { x = 2; } x", Object::Integer(2)),
+            (
+                "let x = 1; if (false) {} else { let x = 2; } x",
+                Object::Integer(1),
+            ),
+            (
+                "let x = 1; let i = 0; while (i < 3) { let x = i; i += 1; } x",
+                Object::Integer(1),
+            ),
+            ("let x = 1; for (x in [5]) { x } x", Object::Integer(1)),
+            (
+                "let x = 1; match 0 { _ => { let x = 2; } } x",
+                Object::Integer(1),
+            ),
+            (
+                "let e = 1; try { throw 2; } catch (e) { e } e",
+                Object::Integer(1),
+            ),
+            (
+                "let fs = []; for (i in 0..3) { fs = push(fs, fn() { i }); } fs[0]() + fs[2]()",
+                Object::Integer(2),
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap(), Rc::new(expected), "{}", input);
+        }
+
+        for input in [
+            "if (true) { let y = 1; } y",
+            "for (i in 0..2) {} i",
+            "try { throw 1; } catch (e) {} e",
+        ] {
+            assert!(
+                test_eval(input)
+                    .unwrap_err()
+                    .to_string()
+                    .starts_with("identifier not found"),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_generators() {
+        let tests = [
+            (
+                "let g = fn*() { yield 1; yield 2; }(); [next(g), next(g), next(g)]",
+                "[1, 2, null]",
+            ),
+            (
+                "let count = fn*(n) { let i = 0; while (i < n) { yield i; i += 1; } }; let s = 0; for (x in count(4)) { s += x; } s",
+                "6",
+            ),
+            (
+                "let nat = fn*() { let n = 0; loop { yield n; n += 1; } }; let g = nat(); next(g); next(g); next(g)",
+                "2",
+            ),
+            (
+                "let g = fn*(xs) { for (x in xs) { if (x == 2) { continue; } if (x == 4) { break; } yield x; } yield 0; }([1, 2, 3, 4, 5]); [next(g), next(g), next(g), next(g)]",
+                "[1, 3, 0, null]",
+            ),
+            (
+                "let g = fn*() { try { yield 1; throw 2; } catch (e) { yield e + 10; } }(); [next(g), next(g), next(g)]",
+                "[1, 12, null]",
+            ),
+            (
+                "let g = fn*() { yield 1; return 5; yield 2; }(); [next(g), next(g)]",
+                "[1, null]",
+            ),
+            (
+                "let g = fn*() { match 1 { 1 => { yield \"one\"; } _ => { yield \"other\"; } } }(); next(g)",
+                "one",
+            ),
+            // A value thrown by the generator reaches `catch` as it is.
+            (
+                "let g = fn*() { throw 9 }; try { next(g()) } catch (e) { type(e) }",
+                "INTEGER",
+            ),
+            (
+                "let g = fn*() { throw 9 }; let t = try { next(g()) } catch (e) { e }; let u = try { 1 / 0 } catch (e) { type(e) }; [t, u]",
+                "[9, ERROR]",
+            ),
+            ("fn*() { yield 1; }", "fn*() {\n    yield 1;\n}"),
+            ("fn*() { yield 1; }()", "generator"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+
+        let tests = [
+            (
+                "let g = fn*() { throw 1; }(); next(g)",
+                "uncaught exception: 1",
+            ),
+            (
+                "let g = fn*() { yield next(g); }(); next(g)",
+                "generator is already running",
+            ),
+            (
+                "let g = fn*() { let x = { yield 1; 2 }; }(); next(g)",
+                "`yield` inside an expression is not supported",
+            ),
+            (
+                "next(1)",
+                "argument to `next` must be GENERATOR, got INTEGER",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(
+                test_eval(input).unwrap_err().to_string(),
+                expected,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_try_catch() {
+        let tests = [
+            ("try { throw 42; 1 } catch (e) { e + 1 }", Object::Integer(43)),
+            ("try { 1 } catch (e) { 2 }", Object::Integer(1)),
+            (r#"try { [1][5] + 1 } catch (e) { str(e) }"#, Object::String("ERROR: type mismatch: NULL + INTEGER".into())),
+            (r#"try { undefined } catch (e) { is_error(e) }"#, Object::Boolean(true)),
+            (
+                "let f = fn(n) { if (n == 0) { throw [n]; } f(n - 1) }; try { 1 + f(3) } catch (e) { e[0] - 1 }",
+                Object::Integer(-1),
+            ),
+            (
+                "try { try { throw 1; } catch (e) { throw e + 1; } } catch (e) { e * 10 }",
+                Object::Integer(20),
+            ),
+            (
+                "let f = fn() { try { return 1; } catch (e) { 2 }; 3 }; f()",
+                Object::Integer(1),
+            ),
+            (
+                "let n = 0; for (i in 0..5) { try { if (i == 3) { break; } throw i; } catch (e) { n += e; } } n",
+                Object::Integer(3),
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap(), Rc::new(expected), "{}", input);
+        }
+
+        assert_eq!(
+            test_eval(r#"throw {"code": 1};"#).unwrap_err().to_string(),
+            r#"uncaught exception: {"code": 1}"#
+        );
+        assert!(call_stack().is_empty());
+    }
+
+    #[test]
+    fn test_frozen_builtins() {
+        let eval_global = |input: &str| {
+            let mut parser = Parser::new(Lexer::new(input));
+            let environment = Rc::new(RefCell::new(Environment::new_global()));
+            eval(Node::Program(parser.parse_program().0), &environment)
+        };
+        let errors = [
+            ("let len = 5;", "cannot redefine builtin `len`"),
+            ("len = 5;", "cannot redefine builtin `len`"),
+            (
+                "let f = fn() { push = 1; }; f()",
+                "cannot redefine builtin `push`",
+            ),
+        ];
+        for (input, expected) in errors {
+            assert_eq!(
+                eval_global(input).unwrap_err().to_string(),
+                expected,
+                "{}",
+                input
+            );
+        }
+        let tests = [
+            ("len([1, 2])", Object::Integer(2)),
+            ("let f = fn(len) { len + 1 }; f(1)", Object::Integer(2)),
+            (
+                "let x = 0; for (first in [5]) { x = first; } x + first([1])",
+                Object::Integer(6),
+            ),
+            (
+                "if (true) { let len = 1; len } + len([])",
+                Object::Integer(1),
+            ),
+            (
+                "let f = fn() { let len = 1; len = 2; len }; f() + len([])",
+                Object::Integer(2),
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(eval_global(input).unwrap(), Rc::new(expected), "{}", input);
+        }
+        assert_eq!(
+            test_eval("let len = 5; len").unwrap(),
+            Rc::new(Object::Integer(5))
+        );
+    }
+
+    #[test]
+    fn test_assert() {
+        assert_eq!(test_eval("assert(1 < 2)").unwrap(), Rc::new(Object::Null));
+        assert_eq!(
+            test_eval(r#"assert([], "arrays are truthy")"#).unwrap(),
+            Rc::new(Object::Null)
+        );
+
+        let report = test_eval("let x = 3;\nassert(x < 2)").unwrap_err();
+        assert_eq!(report.to_string(), "assertion failed");
+        let label = report.labels().unwrap().next().unwrap();
+        assert_eq!((label.offset(), label.len()), (11, 13));
+
+        let report = test_eval(r#"let f = fn(x) { assert(x > 0, "x must be positive") }; f(-1)"#);
+        assert_eq!(
+            report.unwrap_err().to_string(),
+            "assertion failed: x must be positive"
+        );
+        assert_eq!(
+            test_eval(r#"assert(false, 1)"#).unwrap_err().to_string(),
+            "argument to `assert` must be STRING, got INTEGER"
+        );
+    }
+
+    #[test]
+    fn test_error_objects() {
+        let error = |message: &str| Object::Error {
+            message: message.into(),
+            span: None,
+        };
+        let tests = [
+            (r#"error("bad input")"#, error("bad input")),
+            (r#"is_error(error("x"))"#, Object::Boolean(true)),
+            (r#"is_error("x")"#, Object::Boolean(false)),
+            (r#"str(error("x"))"#, Object::String("ERROR: x".into())),
+            (r#"try { throw error("x"); } catch (e) { e }"#, error("x")),
+            (
+                r#"try { len(1, 2) } catch (e) { e }"#,
+                Object::Error {
+                    message: "wrong number of arguments. got=2, want = 1".into(),
+                    span: Some(Span { start: 6, end: 14 }),
+                },
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap(), Rc::new(expected), "{}", input);
+        }
+        assert_eq!(
+            test_eval(r#"throw error("out of coffee");"#)
+                .unwrap_err()
+                .to_string(),
+            "out of coffee"
+        );
+        assert_eq!(
+            test_eval(r#"let e = error("x\"y"); e"#).unwrap().inspect(),
+            r#"error("x\"y")"#
+        );
+    }
+
+    #[test]
+    fn test_match_expression() {
+        let input = r#"
+let describe = fn(x) {
+    match x {
+        0 => "zero",
+        -1 => { let s = "minus"; s + " one" }
+        "x" => "ex",
+        true => "yes",
+        null => "nothing",
+        _ => "other",
+    }
+};"#;
+        let tests = [
+            ("describe(0)", "zero"),
+            ("describe(-1)", "minus one"),
+            (r#"describe("x")"#, "ex"),
+            ("describe(1 == 1)", "yes"),
+            ("describe([1][5])", "nothing"),
+            ("describe([1])", "other"),
+        ];
+        for (call, expected) in tests {
+            assert_eq!(
+                test_eval(&format!("{} {}", input, call)).unwrap(),
+                Rc::new(Object::String(expected.into())),
+                "{}",
+                call
+            );
+        }
+        assert_eq!(
+            test_eval("match 5 { 1 => 2 }").unwrap(),
+            Rc::new(Object::Null)
+        );
+    }
+
     #[test]
     fn test_return_statement() {
         let expected = Rc::new(Object::ReturnValue(Rc::new(Object::Integer(10))));
@@ -550,10 +2120,159 @@ if (10 > 1) {
         );
     }
 
+    #[test]
+    fn test_array_destructuring() {
+        let tests = [
+            ("let [a, b] = [1, 2]; a * 10 + b", "12"),
+            ("let [a, b, c] = [1]; [a, b, c]", "[1, null, null]"),
+            ("let [x] = [1, 2, 3]; x", "1"),
+            (
+                "let f = fn() { let [q, r] = [7 / 2, 7 - 7 / 2 * 2]; q + r }; f()",
+                "4",
+            ),
+            (
+                "let divmod = fn(a, b) { return a / b, a - a / b * b; }; let q, r = divmod(7, 2); [q, r]",
+                "[3, 1]",
+            ),
+            ("let f = fn() { return 1, 2, 3; }; f()", "[1, 2, 3]"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+        assert_eq!(
+            test_eval("let [a, b] = 5;").unwrap_err().to_string(),
+            "cannot destructure INTEGER as an array"
+        );
+    }
+
+    #[test]
+    fn test_multiple_return_values() {
+        let tests = [
+            (
+                "let f = fn(x) { if (x) { return x, 1; } return 0, 2; }; [f(5), f(false)]",
+                "[[5, 1], [0, 2]]",
+            ),
+            ("let f = fn() { return 1, [2, 3]; }; let x, y = f(); y", "[2, 3]"),
+            ("let f = fn() { return 1 + 1, 3; }; let a, b = f(); a * b", "6"),
+            ("let a, b = [1]; [a, b]", "[1, null]"),
+            ("let a, b = [1, 2, 3]; b", "2"),
+            ("let _, b = [1, 2]; b", "2"),
+            (
+                "let swap = fn(a, b) { return b, a; }; let x, y = swap(1, 2); let x, y = swap(x, y); [x, y]",
+                "[1, 2]",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+        assert_eq!(
+            test_eval("let a, b = 5;").unwrap_err().to_string(),
+            "cannot destructure INTEGER as an array"
+        );
+    }
+
+    #[test]
+    fn test_field_access() {
+        let tests = [
+            (
+                r#"let p = {"name": "Ada", "pets": [{"name": "Rex"}]}; p.pets[0].name"#,
+                "Rex",
+            ),
+            (r#"let p = {"name": "Ada"}; p.age"#, "null"),
+            (r#"let p = {"greet": fn(x) { x + 1 }}; p.greet(1)"#, "2"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+        assert_eq!(
+            test_eval("[1].len").unwrap_err().to_string(),
+            "cannot access `.len` on ARRAY, only hashes have fields"
+        );
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let tests = [
+            (r#"let h = {"a": 1}; h.b ?? 2"#, "2"),
+            (r#"let h = {"a": 1}; h.a ?? undefined"#, "1"),
+            ("false ?? 1", "false"),
+            (r#"let h = {}; h?.a?.b ?? h.c ?? "default""#, "default"),
+            (
+                "let n = 0; let f = fn() { n += 1; null }; f() ?? f() ?? n",
+                "2",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_optional_chaining() {
+        let tests = [
+            (r#"let h = {"a": {"b": [1, 2]}}; h?.a?.b?.[1]"#, "2"),
+            (r#"let h = {"a": null}; h.a?.b"#, "null"),
+            (r#"let h = {}; h.a?.[undefined]"#, "null"),
+            (r#"let h = {}; h?.a"#, "null"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+        assert_eq!(
+            test_eval(r#"let h = {}; h.a?.b.c"#)
+                .unwrap_err()
+                .to_string(),
+            "cannot access `.c` on NULL, only hashes have fields"
+        );
+        assert_eq!(
+            test_eval("1?.a").unwrap_err().to_string(),
+            "cannot access `.a` on INTEGER, only hashes have fields"
+        );
+    }
+
+    #[test]
+    fn test_spread() {
+        let tests = [
+            ("let a = [1, 2, 3]; [..a, 4, 5]", "[1, 2, 3, 4, 5]"),
+            ("[0, ..1..3, ..[], ..\"hé\"]", "[0, 1, 2, h, é]"),
+            (
+                "let add = fn(x, y, z) { x + y + z }; let args = [2, 3]; add(1, ..args)",
+                "6",
+            ),
+            ("let f = fn(..xs) { xs }; f(..[1, 2], ..[3])", "[1, 2, 3]"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+        assert_eq!(
+            test_eval("[..5]").unwrap_err().to_string(),
+            "`..` needs an ARRAY, STRING, BYTES, RANGE or GENERATOR to spread, got INTEGER"
+        );
+    }
+
+    #[test]
+    fn test_hash_destructuring() {
+        let tests = [
+            (r#"let {name, age} = {"name": "Ada", "age": 36}; age"#, "36"),
+            (
+                r#"let {name: n, age} = {"name": "Ada"}; [n, age]"#,
+                "[Ada, null]",
+            ),
+            (r#"let {x} = {1: 2}; x"#, "null"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+        assert_eq!(
+            test_eval("let {a} = [1];").unwrap_err().to_string(),
+            "cannot destructure ARRAY as a hash"
+        );
+    }
+
     #[test]
     fn test_function_object() {
         let input = "fn(x) { x + 2; };";
-        let mut body = Program::new();
+        let mut body = BlockStatement::new();
         body.push(Statement::Expr(Expression::Infix {
             token: Token::new(TokenKind::Plus, 10, 10),
             operator: "+".into(),
@@ -567,8 +2286,11 @@ if (10 > 1) {
             test_eval(input).unwrap(),
             Rc::new(Object::Function {
                 parameters: vec![Identifier::new("x".into())],
+                rest: None,
                 body,
                 env,
+                doc: None,
+                generator: false,
             })
         );
     }
@@ -601,6 +2323,35 @@ if (10 > 1) {
         );
     }
 
+    #[test]
+    fn test_rest_parameters() {
+        let tests = [
+            ("let f = fn(first, ..rest) { rest }; f(1, 2, 3)", "[2, 3]"),
+            ("let f = fn(first, ..rest) { rest }; f(1)", "[]"),
+            ("let f = fn(..all) { len(all) }; f(1, 2, 3)", "3"),
+            ("let f = fn(a, b, ..rest,) { a + b }; f(1, 2, 3)", "3"),
+            (
+                "help(fn(x, ..xs) { x })",
+                "fn(x, ..xs)\n\nNo documentation available",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+        assert_eq!(
+            test_eval("let f = fn(a, b, ..rest) { a }; f(1)")
+                .unwrap_err()
+                .to_string(),
+            "wrong number of arguments. got=1, want = at least 2"
+        );
+        assert_eq!(
+            test_eval("let f = fn(a, b) { a }; f(1)")
+                .unwrap_err()
+                .to_string(),
+            "wrong number of arguments. got=1, want = 2"
+        );
+    }
+
     #[test]
     fn test_closures() {
         let input = "
@@ -614,7 +2365,11 @@ let addTwo = newAdder(2);
 addTwo(2);
 ";
         assert_eq!(test_eval(input).unwrap(), Rc::new(Object::Integer(4)));
-    }
+        assert_eq!(
+            test_eval("let adder = |x| |y| x + y; adder(2)(3)").unwrap(),
+            Rc::new(Object::Integer(5))
+        );
+    }
 
     #[test]
     fn test_string_literal() {
@@ -623,6 +2378,12 @@ addTwo(2);
             test_eval(input).unwrap(),
             Rc::new(Object::String("Hello World!".into()))
         );
+
+        let input = r#""Hello\t\"World\"\n""#;
+        assert_eq!(
+            test_eval(input).unwrap(),
+            Rc::new(Object::String("Hello\t\"World\"\n".into()))
+        );
     }
 
     #[test]
@@ -632,6 +2393,176 @@ addTwo(2);
             test_eval(input).unwrap(),
             Rc::new(Object::String("Hello World!".into()))
         );
+
+        let e = test_eval(r#""n = " + 1"#).unwrap_err();
+        assert_eq!(e.to_string(), "type mismatch: STRING + INTEGER");
+        assert_eq!(
+            e.help().unwrap().to_string(),
+            "Convert the other operand with `str` first, as in `\"n = \" + str(n)`"
+        );
+        assert_eq!(
+            test_eval(r#""n = " + str(1.5)"#).unwrap().to_string(),
+            "n = 1.5"
+        );
+
+        // `str` gives the printed form of any value.
+        let tests = [
+            (r#""x = " + str(null)"#, "x = null"),
+            (r#"str("a") == "a""#, "true"),
+            (r#"str({"a": 1})"#, "{a: 1}"),
+            ("str(1..3)", "1..3"),
+            (r#""[" + str([1, "b"]) + "]""#, "[[1, b]]"),
+            (
+                "str(true) + str(99999999999999999999)",
+                "true99999999999999999999",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+        let e = test_eval(r#"1 + "a""#).unwrap_err();
+        assert_eq!(e.to_string(), "type mismatch: INTEGER + STRING");
+        assert!(e.help().is_some());
+        assert_eq!(
+            test_eval("str(1, 2, 3)").unwrap_err().to_string(),
+            "wrong number of arguments. got=3, want = 1 or 2"
+        );
+    }
+
+    #[test]
+    fn test_array_concatenation() {
+        let tests = [
+            ("[1, 2] + [3]", "[1, 2, 3]"),
+            ("[] + []", "[]"),
+            ("let a = [1]; a += [[2]]; a", "[1, [2]]"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+        assert_eq!(
+            test_eval("[1] + 2").unwrap_err().to_string(),
+            "type mismatch: ARRAY + INTEGER"
+        );
+    }
+
+    #[test]
+    fn test_hash_merge() {
+        let tests = [
+            (
+                r#"{"a": 1, "b": 2} + {"b": 3, "c": 4}"#,
+                r#"{a: 1, b: 3, c: 4}"#,
+            ),
+            (r#"{} + {"a": 1}"#, "{a: 1}"),
+            (
+                r#"let defaults = {"debug": false}; let config = defaults + {"debug": true}; [defaults["debug"], config["debug"]]"#,
+                "[false, true]",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+        assert_eq!(
+            test_eval(r#"{"a": 1} + [1]"#).unwrap_err().to_string(),
+            "type mismatch: HASH + ARRAY"
+        );
+    }
+
+    #[test]
+    fn test_operator_methods() {
+        let vector = r#"let vec = fn(x, y) {
+            {
+                "x": x,
+                "y": y,
+                "__add__": fn(a, b) { vec(a.x + b.x, a.y + b.y) },
+                "__mul__": fn(a, k) { vec(a.x * k, a.y * k) },
+                "__eq__": fn(a, b) { a.x == b.x },
+                "__lt__": fn(a, b) { a.x < b.x },
+            }
+        };"#;
+        let tests = [
+            ("let v = vec(1, 2) + vec(3, 4); [v.x, v.y]", "[4, 6]"),
+            ("let v = vec(1, 2) * 3; [v.x, v.y]", "[3, 6]"),
+            ("let v = vec(1, 2); v += vec(1, 1); v.y", "3"),
+            ("vec(1, 2) == vec(1, 5)", "true"),
+            ("vec(1, 2) != vec(1, 5)", "false"),
+            ("vec(1, 2) < vec(2, 0)", "true"),
+            (r#"let h = {"__add__": 1}; (h + {"a": 2}).a"#, "2"),
+        ];
+        for (input, expected) in tests {
+            let input = format!("{} {}", vector, input);
+            assert_eq!(
+                test_eval(&input).unwrap().to_string(),
+                expected,
+                "{}",
+                input
+            );
+        }
+
+        let input = format!("{} vec(1, 2) - vec(1, 1)", vector);
+        assert_eq!(
+            test_eval(&input).unwrap_err().to_string(),
+            "unknown operator: HASH - HASH"
+        );
+        // Low enough for the stack of a test thread in a debug build.
+        let depth = set_max_call_depth(100);
+        let input = r#"let h = {"__eq__": fn(a, b) { a == b }}; h == h"#;
+        assert_eq!(
+            test_eval(input).unwrap_err().to_string(),
+            "maximum recursion depth exceeded"
+        );
+        set_max_call_depth(depth);
+    }
+
+    #[test]
+    fn test_repetition() {
+        let tests = [
+            (r#""ab" * 3"#, r#"ababab"#),
+            (r#""ab" * 0"#, ""),
+            ("[0] * 5", "[0, 0, 0, 0, 0]"),
+            ("[1, 2] * 2", "[1, 2, 1, 2]"),
+            ("let a = [[]] * 2; a[0] = 1; a", "[1, []]"),
+            (r#"let s = "-"; s *= 4; s"#, "----"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+
+        let tests = [
+            (
+                r#""ab" * -1"#,
+                "cannot repeat STRING a negative number of times, got -1",
+            ),
+            (
+                "[0] * -2",
+                "cannot repeat ARRAY a negative number of times, got -2",
+            ),
+            (r#"3 * "ab""#, "type mismatch: INTEGER * STRING"),
+            (
+                "[0] * (-9223372036854775807 * 2)",
+                "cannot repeat ARRAY a negative number of times, got -18446744073709551614",
+            ),
+            (
+                r#""ab" * 9223372036854775807"#,
+                "cannot repeat STRING 9223372036854775807 times",
+            ),
+            (
+                "[1, 2] * 4611686018427387904",
+                "cannot repeat ARRAY 4611686018427387904 times",
+            ),
+            (
+                r#""a" * (9223372036854775807 * 2)"#,
+                "cannot repeat STRING 18446744073709551614 times",
+            ),
+            ("[0] * 16777217", "cannot repeat ARRAY 16777217 times"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(
+                test_eval(input).unwrap_err().to_string(),
+                expected,
+                "{}",
+                input
+            );
+        }
     }
 
     #[test]
@@ -666,6 +2597,560 @@ addTwo(2);
         };
     }
 
+    #[test]
+    fn test_int_and_str() {
+        let tests = [
+            (r#"int("42")"#, Object::Integer(42)),
+            (r#"int(" -17\n")"#, Object::Integer(-17)),
+            (r#"int("+0xff")"#, Object::Integer(255)),
+            (r#"int("-0b101")"#, Object::Integer(-5)),
+            (r#"int("ff", 16)"#, Object::Integer(255)),
+            (r#"int("Zz", 36)"#, Object::Integer(1295)),
+            (
+                r#"int("-9223372036854775808")"#,
+                Object::Integer(isize::MIN),
+            ),
+            ("int(7)", Object::Integer(7)),
+            ("int(3.9)", Object::Integer(3)),
+            (r#"int(float("3.9"))"#, Object::Integer(3)),
+            ("str(-42)", Object::String("-42".into())),
+            ("str([1, true])", Object::String("[1, true]".into())),
+            ("str(255, 16)", Object::String("ff".into())),
+            ("str(-5, 2)", Object::String("-101".into())),
+            ("str(0, 36)", Object::String("0".into())),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap(), Rc::new(expected), "{}", input);
+        }
+
+        let tests = [
+            (
+                r#"int("4 2")"#,
+                r#"cannot parse "4 2" as an integer in base 10"#,
+            ),
+            (
+                r#"int("1_000")"#,
+                r#"cannot parse "1_000" as an integer in base 10"#,
+            ),
+            (
+                r#"int("١٢")"#,
+                r#"cannot parse "١٢" as an integer in base 10"#,
+            ),
+            (
+                r#"int("0x")"#,
+                r#"cannot parse "0x" as an integer in base 10"#,
+            ),
+            (
+                r#"int("--1")"#,
+                r#"cannot parse "--1" as an integer in base 10"#,
+            ),
+            (
+                r#"int("0xff", 16)"#,
+                r#"cannot parse "0xff" as an integer in base 16"#,
+            ),
+            (
+                r#"int("1", 37)"#,
+                "base to `int` must be between 2 and 36, got 37",
+            ),
+            (
+                "int(true)",
+                "argument to `int` must be STRING, CHAR or FLOAT, got BOOLEAN",
+            ),
+            ("int('a', 16)", "`int` with a base needs a STRING, got CHAR"),
+            (
+                r#"str("a", 2)"#,
+                "`str` with a base needs an INTEGER, got STRING",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(
+                test_eval(input).unwrap_err().to_string(),
+                expected,
+                "{}",
+                input
+            );
+        }
+
+        let e = test_eval(r#"int("3.9")"#).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            r#"cannot parse "3.9" as an integer in base 10"#
+        );
+        assert_eq!(
+            e.help().unwrap().to_string(),
+            "`int` only parses whole numbers, `int(float(s))` rounds towards zero"
+        );
+        assert_eq!(
+            test_eval(r#"int("x")"#)
+                .unwrap_err()
+                .help()
+                .unwrap()
+                .to_string(),
+            "Usage: int(value, base?)"
+        );
+
+        // Only strings holding a finite float without a base point to `float`.
+        let help = |input| test_eval(input).unwrap_err().help().unwrap().to_string();
+        let float_help = "`int` only parses whole numbers, `int(float(s))` rounds towards zero";
+        assert_eq!(help(r#"int("3.0")"#), float_help);
+        assert_eq!(help(r#"int(" 1e3 ")"#), float_help);
+        assert_eq!(help(r#"int("-0.5")"#), float_help);
+        assert_eq!(help(r#"int("inf")"#), "Usage: int(value, base?)");
+        assert_eq!(help(r#"int("3.9", 10)"#), "Usage: int(value, base?)");
+        assert_eq!(help(r#"int("")"#), "Usage: int(value, base?)");
+        let tests = [
+            ("int(-0.5)", "0"),
+            ("int(1e20)", "100000000000000000000"),
+            (r#"int(float("-2.5e1"))"#, "-25"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        for input in [
+            "5 / 0",
+            "let x = 5; x /= 0;",
+            "(9223372036854775807 * 2) / (1 - 1)",
+        ] {
+            let err = test_eval(input).unwrap_err();
+            assert_eq!(err.to_string(), "division by zero", "{}", input);
+        }
+        let err = test_eval("1 + 10 / (2 - 2)").unwrap_err();
+        let label = err.labels().unwrap().next().unwrap();
+        assert_eq!((label.offset(), label.len()), (7, 1));
+        assert_eq!(
+            test_eval("try { 1 / 0 } catch (e) { str(e) }")
+                .unwrap()
+                .to_string(),
+            "ERROR: division by zero"
+        );
+    }
+
+    #[test]
+    fn test_modulo() {
+        let tests = [
+            ("7 % 3", "1"),
+            ("-7 % 3", "-1"),
+            ("7 % -3", "1"),
+            ("1 + 7 % 4 * 2", "7"),
+            ("(-9223372036854775807 - 1) % -1", "0"),
+            ("(9223372036854775807 * 2) % 10", "4"),
+            ("7.5 % 2", "1.5"),
+            ("-7 % 2.5", "-2.0"),
+            (r#"{"__mod__": fn(a, b) { b }} % 3"#, "3"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+
+        for input in ["5 % 0", "5.5 % 0.0", "(9223372036854775807 * 2) % 0"] {
+            let err = test_eval(input).unwrap_err();
+            assert_eq!(err.to_string(), "modulo by zero", "{}", input);
+        }
+        let err = test_eval("1 + 10 % (2 - 2)").unwrap_err();
+        let label = err.labels().unwrap().next().unwrap();
+        assert_eq!((label.offset(), label.len()), (7, 1));
+        assert_eq!(
+            test_eval("true % 2").unwrap_err().to_string(),
+            "type mismatch: BOOLEAN % INTEGER"
+        );
+    }
+
+    #[test]
+    fn test_floats() {
+        let tests = [
+            ("2.5", "2.5"),
+            ("-2.5", "-2.5"),
+            ("1 + 2.5", "3.5"),
+            ("2.5 * 2", "5.0"),
+            ("7 / 2", "3"),
+            ("7.0 / 2", "3.5"),
+            ("1 - 0.25", "0.75"),
+            ("0.1 + 0.2", "0.30000000000000004"),
+            ("1 == 1.0", "true"),
+            ("1.5 != 1", "true"),
+            ("1 < 1.5", "true"),
+            ("2.0 > 10", "false"),
+            ("[1, 2.0] == [1.0, 2]", "true"),
+            ("(9223372036854775807 + 1) * 1.0", "9.223372036854776e18"),
+            // Floats print in a form that reads back as the same float.
+            ("1e20", "1e20"),
+            ("2.5E-7 * 2", "5e-7"),
+            ("3e+2", "300.0"),
+            ("1e20 == 100000000000000000000.0", "true"),
+            ("9.223372036854776e18 == 2 * 4611686018427387904.0", "true"),
+            ("int(2.9)", "2"),
+            ("int(-2.9)", "-2"),
+            (r#"int(float("1e30"))"#, "1000000000000000019884624838656"),
+            ("float(3)", "3.0"),
+            (r#"float(" 1e3 ")"#, "1000.0"),
+            (r#"float("3.14")"#, "3.14"),
+            (r#"float("-Infinity") < 0"#, "true"),
+            (r#"float("  .5 ")"#, "0.5"),
+            (r#"float("-inf") < -1e308"#, "true"),
+            (r#"float("NaN") == float("nan")"#, "false"),
+            ("float(99999999999999999999)", "1e20"),
+            ("float(1.5)", "1.5"),
+            (r#"format("{:06}|{:4}", -2.5, 1.5)"#, "-002.5| 1.5"),
+            ("1..3", "1..3"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+
+        let errors = [
+            ("1.5 / 0", "division by zero"),
+            ("1 / 0.0", "division by zero"),
+            ("1.5..2", "unknown operator: FLOAT .. INTEGER"),
+            ("1.5 + true", "type mismatch: FLOAT + BOOLEAN"),
+            (r#"float("x")"#, r#"cannot parse "x" as a float"#),
+            (r#"float("1.2.3")"#, r#"cannot parse "1.2.3" as a float"#),
+            (r#"float("")"#, r#"cannot parse "" as a float"#),
+            (
+                "float([1])",
+                "argument to `float` must be INTEGER or STRING, got ARRAY",
+            ),
+            ("float(1, 2)", "wrong number of arguments. got=2, want = 1"),
+            (
+                "float(true)",
+                "argument to `float` must be INTEGER or STRING, got BOOLEAN",
+            ),
+            (r#"int(float("inf"))"#, "cannot convert inf to an integer"),
+        ];
+        for (input, expected) in errors {
+            assert_eq!(
+                test_eval(input).unwrap_err().to_string(),
+                expected,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_math() {
+        let tests = [
+            ("math.sin(0)", "0.0"),
+            ("math.cos(math.PI)", "-1.0"),
+            ("math.exp(1) == math.E", "true"),
+            ("math.log(math.E)", "1.0"),
+            ("math.log(1)", "0.0"),
+            ("math.floor(2.7)", "2"),
+            ("math.floor(-2.5)", "-3"),
+            ("math.ceil(2.1)", "3"),
+            ("math.ceil(-2.5)", "-2"),
+            ("math.round(2.5)", "3"),
+            ("math.round(-2.5)", "-3"),
+            ("math.round(2.4)", "2"),
+            ("math.floor(7)", "7"),
+            (
+                "math.round(100000000000000000000.0)",
+                "100000000000000000000",
+            ),
+            ("type(math.floor(1.5))", "INTEGER"),
+            (r#"let round = math["round"]; round(0.5)"#, "1"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+
+        let tests = [
+            (
+                "math.log(0)",
+                "`log` is only defined for positive numbers, got 0",
+            ),
+            (
+                r#"math.sin("1")"#,
+                "argument to `sin` must be INTEGER or FLOAT, got STRING",
+            ),
+            (
+                r#"math.round(float("nan"))"#,
+                "cannot convert NaN to an integer",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(
+                test_eval(input).unwrap_err().to_string(),
+                expected,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_random() {
+        let tests = [
+            ("-1 < random() < 1", "true"),
+            ("-3 < random_int(-2, 3) < 3", "true"),
+            ("random_int(5, 6)", "5"),
+            ("len(shuffle([1, 2, 3]))", "3"),
+            (
+                "let sum = 0; for (x in shuffle([1, 2, 3, 4])) { sum += x; } sum",
+                "10",
+            ),
+            ("shuffle([])", "[]"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+
+        let tests = [
+            (
+                "random_int(3, 3)",
+                "`random_int` needs `lo` below `hi`, got 3 and 3",
+            ),
+            (
+                "random_int(1.5, 3)",
+                "arguments to `random_int` must be INTEGER, got FLOAT and INTEGER",
+            ),
+            (
+                "random_int(0, 99999999999999999999)",
+                "99999999999999999999 is out of range for `random_int`, which supports -9223372036854775808 to 9223372036854775807",
+            ),
+            (
+                "random_int(-99999999999999999999, 0)",
+                "-99999999999999999999 is out of range for `random_int`, which supports -9223372036854775808 to 9223372036854775807",
+            ),
+            (
+                "shuffle(1..3)",
+                "argument to `shuffle` must be ARRAY, got RANGE",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(
+                test_eval(input).unwrap_err().to_string(),
+                expected,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_big_integers() {
+        let tests = [
+            (
+                "let fact = fn(n) { if (n < 2) { 1 } else { n * fact(n - 1) } }; fact(30)",
+                "265252859812191058636308480000000",
+            ),
+            ("9223372036854775807 + 1", "9223372036854775808"),
+            ("-9223372036854775807 - 2", "-9223372036854775809"),
+            ("-(-9223372036854775807 - 1)", "9223372036854775808"),
+            ("(-9223372036854775807 - 1) / -1", "9223372036854775808"),
+            ("(9223372036854775807 + 1) - 1", "9223372036854775807"),
+            ("(9223372036854775807 + 1) / 2 * 2", "9223372036854775808"),
+            (r#"int("99999999999999999999") / 3"#, "33333333333333333333"),
+            ("99999999999999999999999 - 1", "99999999999999999999998"),
+            ("-9223372036854775808", "-9223372036854775808"),
+            ("type(-9223372036854775808)", "INTEGER"),
+            (
+                "99999999999999999999 == int(\"99999999999999999999\")",
+                "true",
+            ),
+            ("0x10000000000000000", "18446744073709551616"),
+            (
+                "match (9223372036854775808) { 9223372036854775808 => 1, _ => 2 }",
+                "1",
+            ),
+            (
+                r#"int("-1" + str(2 * 9223372036854775807, 16), 16)"#,
+                "-36893488147419103230",
+            ),
+            ("str(9223372036854775807 * 4, 16)", "1fffffffffffffffc"),
+            ("9223372036854775807 * 3 > 9223372036854775807", "true"),
+            ("9223372036854775807 + 1 == 9223372036854775807 + 1", "true"),
+            ("9223372036854775807 + 1 - 1 == 9223372036854775807", "true"),
+            (
+                "let h = {}; h[9223372036854775807 * 2] = 1; h[2 * 9223372036854775807]",
+                "1",
+            ),
+            ("[1, 2][9223372036854775807 * 2]", "null"),
+            (
+                r#"format("{:025}", -9223372036854775807 * 2)"#,
+                "-000018446744073709551614",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+
+        assert_eq!(
+            test_eval("(9223372036854775807 + 1)..(9223372036854775807 + 2)")
+                .unwrap_err()
+                .to_string(),
+            "range bounds must be between -9223372036854775808 and 9223372036854775807"
+        );
+    }
+
+    #[test]
+    fn test_int_str_round_trip() {
+        // A xorshift generator, so that the values are arbitrary but the same
+        // on every run.
+        let mut state = 0x2545f4914f6cdd1d_u64;
+        let mut values = vec![0, 1, -1, isize::MAX, isize::MIN];
+        for _ in 0..200 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            // Shift by varying amounts to get small numbers too.
+            values.push((state as isize) >> (state % 64));
+        }
+        for value in values {
+            let sign = if value < 0 { "-" } else { "" };
+            let magnitude = value.unsigned_abs();
+            let digits = [
+                (2, format!("{}{:b}", sign, magnitude)),
+                (8, format!("{}{:o}", sign, magnitude)),
+                (10, value.to_string()),
+                (16, format!("{}{:x}", sign, magnitude)),
+            ];
+            for (base, expected) in digits {
+                let input = format!(r#"str(int("{}"), {})"#, value, base);
+                assert_eq!(
+                    test_eval(&input).unwrap(),
+                    Rc::new(Object::String(expected)),
+                    "{}",
+                    input
+                );
+            }
+            for base in [2, 7, 10, 16, 36] {
+                let input = format!(
+                    r#"let n = int("{}"); int(str(n, {}), {})"#,
+                    value, base, base
+                );
+                assert_eq!(
+                    test_eval(&input).unwrap(),
+                    Rc::new(Object::Integer(value)),
+                    "{}",
+                    input
+                );
+            }
+            let input = format!(r#"int(str(int("{}")))"#, value);
+            assert_eq!(
+                test_eval(&input).unwrap(),
+                Rc::new(Object::Integer(value)),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_format_padding() {
+        let tests = [
+            (r#"format("{} + {} = {}", 1, "two", [3])"#, "1 + two = [3]"),
+            (
+                r#"format("[{:>6}|{:<6}|{:^6}]", "ab", "ab", "ab")"#,
+                "[    ab|ab    |  ab  ]",
+            ),
+            (r#"format("[{:6}|{:6}]", "ab", 42)"#, "[ab    |    42]"),
+            (
+                r#"format("[{:*^7}|{:05}|{:05}]", "ab", 42, -42)"#,
+                "[**ab***|00042|-0042]",
+            ),
+            (r#"format("{:>3}|", "e\u{301}")"#, "  e\u{301}|"),
+            (r#"format("{{{}}}", 1)"#, "{1}"),
+            (r#"format("{1} {0} {1}", "a", "b")"#, "b a b"),
+            (r#"format("{} {0} {}", "a", "b")"#, "a a b"),
+            (r#"format("[{1:>4}|{0:04}]", 7, "x")"#, "[   x|0007]"),
+            (r#"format("{0}{0}{0}", "ab")"#, "ababab"),
+            (r#"format("{0:*^5}|{1:<3}|", 1, "x")"#, "**1**|x  |"),
+            (r#"format("{1} {}", 1, 2)"#, "2 1"),
+            (r#"format("{{0}} {0}", 1)"#, "{0} 1"),
+            (
+                r#"sprintf("[%5d|%-5d|%05d|%s|%3s]", 42, 42, -42, true, "x")"#,
+                "[   42|42   |-0042|true|  x]",
+            ),
+            (r#"sprintf("100%% %-04d|", 7)"#, "100% 7   |"),
+            (r#"sprintf("%6s|%s", 1.5, -1e20)"#, "   1.5|-1e20"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(
+                test_eval(input).unwrap(),
+                Rc::new(Object::String(expected.into())),
+                "{}",
+                input
+            );
+        }
+
+        let tests = [
+            (
+                r#"format("{} {}", 1)"#,
+                "format string has more placeholders than arguments",
+            ),
+            (
+                r#"format("{}", 1, 2)"#,
+                "format string has fewer placeholders than arguments",
+            ),
+            (
+                r#"format("{:x}", 1)"#,
+                "invalid placeholder `{:x}` in format string",
+            ),
+            (
+                r#"format("{a}", 1)"#,
+                "invalid placeholder `{a}` in format string",
+            ),
+            (
+                r#"format("{2}", 1, 2)"#,
+                "placeholder `{2}` refers to argument 2, but there are only 2",
+            ),
+            (
+                r#"format("{1}", 1, 2)"#,
+                "argument 0 is never used in the format string",
+            ),
+            (r#"format("}", 1)"#, "unmatched `}` in format string"),
+            (
+                r#"format("{0} {}", 1, 2)"#,
+                "argument 1 is never used in the format string",
+            ),
+            (
+                r#"format("{x}", 1)"#,
+                "invalid placeholder `{x}` in format string",
+            ),
+            (
+                r#"format("{-1}", 1)"#,
+                "invalid placeholder `{-1}` in format string",
+            ),
+            (
+                r#"format("{99999999999999999999999}", 1)"#,
+                "invalid placeholder `{99999999999999999999999}` in format string",
+            ),
+            (
+                r#"format(1)"#,
+                "first argument to `format` must be STRING, got INTEGER",
+            ),
+            (r#"sprintf("%d", "1")"#, "`%d` needs an INTEGER, got STRING"),
+            (r#"sprintf("%d", 1.5)"#, "`%d` needs an INTEGER, got FLOAT"),
+            (
+                r#"sprintf("%x", 1)"#,
+                "unknown conversion `%x` in format string",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(
+                test_eval(input).unwrap_err().to_string(),
+                expected,
+                "{}",
+                input
+            );
+        }
+
+        let help = |input| test_eval(input).unwrap_err().help().unwrap().to_string();
+        assert_eq!(
+            help(r#"sprintf("%x", 1)"#),
+            "Use `%d` for integers, `%s` for any value or `%%` for a percent sign"
+        );
+        assert_eq!(help(r#"format("}", 1)"#), "Use `}}` for a literal brace");
+        assert_eq!(
+            help(r#"sprintf("%d", 1.5)"#),
+            "Use `%s` for other values, including floats"
+        );
+    }
+
     #[test]
     fn test_array_literals() {
         assert_eq!(
@@ -714,9 +3199,13 @@ addTwo(2);
         );
         assert_eq!(test_eval("[1, 2, 3][3]").unwrap(), Rc::new(Object::Null));
         assert_eq!(test_eval("[1, 2, 3][-1]").unwrap(), Rc::new(Object::Null));
+        assert_eq!(test_eval("[][0]").unwrap(), Rc::new(Object::Null));
+        assert_eq!(test_eval("[][-1]").unwrap(), Rc::new(Object::Null));
+        assert_eq!(test_eval("[]?.[0]").unwrap(), Rc::new(Object::Null));
     }
 
     #[test]
+    #[allow(clippy::mutable_key_type)]
     fn test_hash_literals() {
         let input = r#"let two = "two";
 {
@@ -734,19 +3223,984 @@ addTwo(2);
         expected.insert(Object::Integer(4), Object::Integer(4));
         expected.insert(Object::Boolean(true), Object::Integer(5));
         expected.insert(Object::Boolean(false), Object::Integer(6));
-        let ex = expected.into_iter().map(|(key, val)| (Rc::new(key), Rc::new(val))).collect();
+        let ex = expected
+            .into_iter()
+            .map(|(key, val)| (Rc::new(key), Rc::new(val)))
+            .collect();
 
         assert_eq!(test_eval(input).unwrap(), Rc::new(Object::Hash(ex)));
     }
 
     #[test]
     fn test_hash_index_expressions() {
-        assert_eq!(test_eval(r#"{"foo": 5}["foo"]"#).unwrap(), Rc::new(Object::Integer(5)));
-        assert_eq!(test_eval(r#"{"foo": 5}["bar"]"#).unwrap(), Rc::new(Object::Null));
-        assert_eq!(test_eval(r#"let key = "foo"; {"foo": 5}[key]"#).unwrap(), Rc::new(Object::Integer(5)));
+        assert_eq!(
+            test_eval(r#"{"foo": 5}["foo"]"#).unwrap(),
+            Rc::new(Object::Integer(5))
+        );
+        assert_eq!(
+            test_eval(r#"{"foo": 5}["bar"]"#).unwrap(),
+            Rc::new(Object::Null)
+        );
+        assert_eq!(
+            test_eval(r#"let key = "foo"; {"foo": 5}[key]"#).unwrap(),
+            Rc::new(Object::Integer(5))
+        );
         assert_eq!(test_eval(r#"{}["foo"]"#).unwrap(), Rc::new(Object::Null));
-        assert_eq!(test_eval(r#"{5: 5}[5]"#).unwrap(), Rc::new(Object::Integer(5)));
-        assert_eq!(test_eval(r#"{true: 5}[true]"#).unwrap(), Rc::new(Object::Integer(5)));
-        assert_eq!(test_eval(r#"{false: 5}[false]"#).unwrap(), Rc::new(Object::Integer(5)));
+        assert_eq!(
+            test_eval(r#"{5: 5}[5]"#).unwrap(),
+            Rc::new(Object::Integer(5))
+        );
+        assert_eq!(
+            test_eval(r#"{true: 5}[true]"#).unwrap(),
+            Rc::new(Object::Integer(5))
+        );
+        assert_eq!(
+            test_eval(r#"{false: 5}[false]"#).unwrap(),
+            Rc::new(Object::Integer(5))
+        );
+    }
+
+    #[test]
+    fn test_assign_expressions() {
+        assert_eq!(
+            test_eval("let a = 5; a = 10; a;").unwrap(),
+            Rc::new(Object::Integer(10))
+        );
+        assert_eq!(
+            test_eval("let a = 5; a += 10; a;").unwrap(),
+            Rc::new(Object::Integer(15))
+        );
+        assert_eq!(
+            test_eval("let a = 5; a -= 10; a;").unwrap(),
+            Rc::new(Object::Integer(-5))
+        );
+        assert_eq!(
+            test_eval("let a = 5; a *= 2 + 1; a;").unwrap(),
+            Rc::new(Object::Integer(15))
+        );
+        assert_eq!(
+            test_eval("let a = 10; a /= 2; a;").unwrap(),
+            Rc::new(Object::Integer(5))
+        );
+        assert_eq!(
+            test_eval(r#"let s = "foo"; s += "bar"; s;"#).unwrap(),
+            Rc::new(Object::String("foobar".into()))
+        );
+        assert_eq!(
+            test_eval("let a = 1; let b = 2; a = b = 3; a + b;").unwrap(),
+            Rc::new(Object::Integer(6))
+        );
+        assert_eq!(
+            test_eval("let count = 0; let inc = fn() { count += 1; }; inc(); inc(); count;")
+                .unwrap(),
+            Rc::new(Object::Integer(2))
+        );
+
+        match test_eval("a = 5;") {
+            Ok(_) => unreachable!(),
+            Err(e) => assert_eq!(e.to_string(), "identifier not found: a"),
+        }
+    }
+
+    #[test]
+    fn test_while_loops() {
+        let tests = [
+            ("let i = 0; while (i < 5) { i += 1; } i", Object::Integer(5)),
+            ("let i = 0; while (i < 5) { i += 1; }", Object::Null),
+            (
+                "let i = 0; while (true) { if (i == 3) { break; } i += 1; } i",
+                Object::Integer(3),
+            ),
+            (
+                "let i = 0; let sum = 0; while (i < 5) { i += 1; if (i == 2) { continue; } sum += i; } sum",
+                Object::Integer(13),
+            ),
+            (
+                "let f = fn() { while (true) { return 7; } }; f()",
+                Object::Integer(7),
+            ),
+            (
+                "let n = 0; let i = 0; while (i < 3) { i += 1; let j = 0; while (true) { j += 1; if (j > i) { break; } n += 1; } } n",
+                Object::Integer(6),
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap(), Rc::new(expected), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_chained_comparisons() {
+        let tests = [
+            ("let x = 5; 0 < x < 10", "true"),
+            ("let x = 5; 0 < x < 3", "false"),
+            ("let x = 5; 0 > x < 10", "false"),
+            ("3 > 2 > 1 > 0", "true"),
+            ("1 < 2.5 < 3", "true"),
+            (
+                "let calls = 0; let f = fn() { calls += 1; 5 }; let r = [0 < f() < 10, 9 < f() < 10]; [r, calls]",
+                "[[true, false], 2]",
+            ),
+            ("let calls = 0; let f = fn() { calls += 1; 5 }; 9 < 0 < f(); calls", "0"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_do_while_loops() {
+        let tests = [
+            ("let i = 0; do { i += 1; } while (i < 5); i", Object::Integer(5)),
+            ("let i = 10; do { i += 1; } while (i < 5); i", Object::Integer(11)),
+            ("let i = 0; do { i += 1; } while (i < 5)", Object::Null),
+            (
+                "let i = 0; let n = 0; do { i += 1; if (i == 2) { continue; } n += 1; } while (i < 4); n",
+                Object::Integer(3),
+            ),
+            (
+                "let i = 0; do { if (i == 3) { break i * 10; } i += 1; } while (true)",
+                Object::Integer(30),
+            ),
+            (
+                "let f = fn() { do { return 7; } while (true); }; f()",
+                Object::Integer(7),
+            ),
+            (
+                "let g = fn*() { let i = 0; do { yield i; i += 1; } while (i < 2); }(); [next(g), next(g), next(g)]",
+                Object::Array(vec![
+                    Rc::new(Object::Integer(0)),
+                    Rc::new(Object::Integer(1)),
+                    Rc::new(Object::Null),
+                ]),
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap(), Rc::new(expected), "{}", input);
+        }
+        assert_eq!(
+            test_eval("do { let x = 1; } while (x < 1);")
+                .unwrap_err()
+                .to_string(),
+            "identifier not found: x"
+        );
+    }
+
+    #[test]
+    fn test_structural_equality() {
+        let tests = [
+            ("[1, 2] == [1, 2]", true),
+            ("[1, 2] != [1, 2]", false),
+            ("[1, 2] == [2, 1]", false),
+            ("[1, [2, [3]]] == [1, [2, [3]]]", true),
+            ("[1] == [1, 2]", false),
+            ("[1, true] == [1, 1]", false),
+            (r#""ab" == "a" + "b""#, true),
+            (r#"{"a": [1], 2: "b"} == {2: "b", "a": [1]}"#, true),
+            (r#"{"a": 1} == {"a": 2}"#, false),
+            (r#"{"a": 1} == {"b": 1}"#, false),
+            ("[1..3] == [1..3]", true),
+            ("let f = fn() { 1 }; [f] == [f]", true),
+            ("[fn() { 1 }] == [fn() { 1 }]", false),
+            ("[] == null", false),
+        ];
+        for (input, expected) in tests {
+            let result = test_eval(input).unwrap();
+            assert_eq!(*result, Object::Boolean(expected), "{}", input);
+        }
+        assert_eq!(
+            test_eval("[1] == 1").unwrap_err().to_string(),
+            "type mismatch: ARRAY == INTEGER"
+        );
+    }
+
+    #[test]
+    fn test_enumerate() {
+        let tests = [
+            (r#"enumerate(["a", "b"])"#, "[[0, a], [1, b]]"),
+            ("enumerate([])", "[]"),
+            ("enumerate(5..7)", "[[0, 5], [1, 6]]"),
+            (r#"enumerate("hé")"#, "[[0, h], [1, é]]"),
+            (
+                r#"enumerate({"b": 2, "a": 1, 0: true})"#,
+                "[[0, true], [a, 1], [b, 2]]",
+            ),
+            (
+                "let n = 0; for (pair in enumerate([5, 6])) { n += pair[0] * pair[1]; } n",
+                "6",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+        assert_eq!(
+            test_eval("enumerate(1)").unwrap_err().to_string(),
+            "argument to `enumerate` must be ARRAY, STRING, RANGE or HASH, got INTEGER"
+        );
+    }
+
+    #[test]
+    fn test_keys_and_values() {
+        let tests = [
+            (r#"keys({"b": 2, "a": 1, 0: true})"#, "[0, a, b]"),
+            (r#"values({"b": 2, "a": 1, 0: true})"#, "[true, 1, 2]"),
+            ("keys({})", "[]"),
+            (
+                r#"let h = {"x": 1, "y": 2}; let n = 0; for (k in keys(h)) { n += h[k]; } n"#,
+                "3",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+        assert_eq!(
+            test_eval("keys([1])").unwrap_err().to_string(),
+            "argument to `keys` must be HASH, got ARRAY"
+        );
+        assert_eq!(
+            test_eval("values(1)").unwrap_err().to_string(),
+            "argument to `values` must be HASH, got INTEGER"
+        );
+    }
+
+    #[test]
+    fn test_contains_and_has_key() {
+        let tests = [
+            ("contains([1, 2, 3], 2)", "true"),
+            ("contains([1, 2, 3], 4)", "false"),
+            ("contains([[1], [2]], [2])", "true"),
+            ("contains([1, 2], 2.0)", "true"),
+            ("contains(1..5, 4)", "true"),
+            ("contains(1..5, 5)", "false"),
+            (r#"contains(1..5, "a")"#, "false"),
+            (r#"contains("hello", "ell")"#, "true"),
+            (r#"contains("hello", 'z')"#, "false"),
+            (r#"has_key({"a": 1}, "a")"#, "true"),
+            (r#"has_key({"a": null}, "a")"#, "true"),
+            (r#"has_key({"a": 1}, 1)"#, "false"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+
+        let tests = [
+            (
+                r#"contains("123", 2)"#,
+                "`contains` can only find a STRING or CHAR in a STRING, got INTEGER",
+            ),
+            (
+                r#"contains({"a": 1}, "a")"#,
+                "argument to `contains` must be ARRAY, STRING or RANGE, got HASH",
+            ),
+            (
+                "has_key([1], 0)",
+                "argument to `has_key` must be HASH, got ARRAY",
+            ),
+            ("has_key({}, [1])", "unusable as hash key: ARRAY"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(
+                test_eval(input).unwrap_err().to_string(),
+                expected,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_delete() {
+        let tests = [
+            (r#"delete({"a": 1, "b": 2}, "a")"#, "{b: 2}"),
+            (r#"delete({"a": 1}, "b")"#, "{a: 1}"),
+            (r#"let h = {"a": 1}; delete(h, "a"); h"#, "{a: 1}"),
+            (r#"delete({1: 1, 2: 2}, 2)"#, "{1: 1}"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+
+        let tests = [
+            (r#"delete({"a": 1}, [1])"#, "unusable as hash key: ARRAY"),
+            (
+                "delete([1], 0)",
+                "argument to `delete` must be HASH, got ARRAY",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(
+                test_eval(input).unwrap_err().to_string(),
+                expected,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_insert_and_set_at() {
+        let tests = [
+            (r#"insert({"a": 1}, "b", 2)"#, "{a: 1, b: 2}"),
+            (r#"insert({"a": 1}, "a", 2)"#, "{a: 2}"),
+            (r#"let h = {"a": 1}; insert(h, "a", 2); h"#, "{a: 1}"),
+            ("set_at([1, 2, 3], 1, 5)", "[1, 5, 3]"),
+            ("let xs = [1, 2]; set_at(xs, 0, 5); xs", "[1, 2]"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+
+        let tests = [
+            ("insert({}, [1], 1)", "unusable as hash key: ARRAY"),
+            (
+                "insert([1], 0, 1)",
+                "argument to `insert` must be HASH, got ARRAY",
+            ),
+            (
+                "set_at([1], 1, 1)",
+                "index 1 out of bounds for ARRAY of length 1",
+            ),
+            (
+                r#"set_at([1], "a", 1)"#,
+                "array index must be INTEGER, got STRING",
+            ),
+            (
+                "set_at({}, 0, 1)",
+                "argument to `set_at` must be ARRAY, got HASH",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(
+                test_eval(input).unwrap_err().to_string(),
+                expected,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_type() {
+        let tests = [
+            ("type(1)", "INTEGER"),
+            ("type(1.5)", "FLOAT"),
+            (r#"type("a")"#, "STRING"),
+            ("type('a')", "CHAR"),
+            ("type(null)", "NULL"),
+            ("type([1])", "ARRAY"),
+            ("type({})", "HASH"),
+            ("type(1..2)", "RANGE"),
+            ("type(fn() {})", "FUNCTION"),
+            ("type(len)", "BUILTIN"),
+            (r#"type(error("a"))"#, "ERROR"),
+            (r#"type(type(1)) == "STRING""#, "true"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_slice_builtin() {
+        let tests = [
+            ("slice([1, 2, 3, 4], 1, 3)", "[2, 3]"),
+            ("slice([1, 2, 3, 4], 1)", "[2, 3, 4]"),
+            ("slice([1, 2, 3, 4], -2)", "[3, 4]"),
+            ("slice([1, 2, 3, 4], 0, -1)", "[1, 2, 3]"),
+            ("slice([1, 2, 3, 4], null, 2)", "[1, 2]"),
+            ("slice([1, 2, 3, 4], -10, 10)", "[1, 2, 3, 4]"),
+            ("slice([1, 2, 3, 4], 3, 1)", "[]"),
+            (r#"slice("héllo", 1, -1)"#, r#""éll""#),
+            (r#"slice("abc", -1)"#, r#""c""#),
+            (r#"slice(b"abc", 1)"#, r#"b"bc""#),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().inspect(), expected, "{}", input);
+        }
+
+        let tests = [
+            (
+                "slice({}, 0)",
+                "argument to `slice` must be ARRAY, STRING or BYTES, got HASH",
+            ),
+            (
+                r#"slice([1], "a")"#,
+                "slice index must be INTEGER, got STRING",
+            ),
+            (
+                "slice([1])",
+                "wrong number of arguments. got=1, want = 2 or 3",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(
+                test_eval(input).unwrap_err().to_string(),
+                expected,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_string_search() {
+        let tests = [
+            (r#"replace("a-b-c", "-", "+")"#, "a+b+c"),
+            (r#"replace("aaa", "aa", 'b')"#, "ba"),
+            (r#"replace("abc", "x", "y")"#, "abc"),
+            (r#"starts_with("hello", "he")"#, "true"),
+            (r#"starts_with("hello", 'e')"#, "false"),
+            (r#"ends_with("hello", "lo")"#, "true"),
+            (r#"ends_with("hello", "")"#, "true"),
+            (r#"index_of("hello", "l")"#, "2"),
+            (r#"index_of("hello", "x")"#, "-1"),
+            (r#"index_of("hello", "")"#, "0"),
+            ("index_of(\"e\u{301}llo\", 'l')", "1"),
+            ("index_of(\"e\u{301}\", 'e')", "-1"),
+            ("index_of([1, [2], 3], [2])", "1"),
+            ("index_of([1, 2], 3)", "-1"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+
+        let tests = [
+            (
+                r#"replace("abc", "", "-")"#,
+                "string replaced by `replace` must not be empty",
+            ),
+            (
+                r#"replace("abc", "a", 1)"#,
+                "replacement of `replace` must be STRING or CHAR, got INTEGER",
+            ),
+            (
+                r#"starts_with(1, "a")"#,
+                "argument to `starts_with` must be STRING, got INTEGER",
+            ),
+            (
+                r#"ends_with("a", [1])"#,
+                "`ends_with` can only find a STRING or CHAR in a STRING, got ARRAY",
+            ),
+            (
+                r#"index_of({}, 1)"#,
+                "argument to `index_of` must be ARRAY or STRING, got HASH",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(
+                test_eval(input).unwrap_err().to_string(),
+                expected,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_split_and_join() {
+        let tests = [
+            (r#"split("a,b,c", ",") == ["a", "b", "c"]"#, "true"),
+            (r#"split("a, b", ", ") == ["a", "b"]"#, "true"),
+            (r#"split(",a,", ',') == ["", "a", ""]"#, "true"),
+            (r#"split("", ",") == [""]"#, "true"),
+            (r#"join(["a", "b"], "-")"#, "a-b"),
+            (r#"join(['a', "b", 'c'], "")"#, "abc"),
+            (r#"len(join([], ", "))"#, "0"),
+            (r#"join(split("1 2 3", " "), '+')"#, "1+2+3"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+
+        let tests = [
+            (
+                r#"split("abc", "")"#,
+                "separator of `split` must not be empty",
+            ),
+            (
+                r#"split(1, ",")"#,
+                "argument to `split` must be STRING, got INTEGER",
+            ),
+            (
+                r#"split("a", 1)"#,
+                "separator of `split` must be STRING or CHAR, got INTEGER",
+            ),
+            (
+                r#"join("ab", ",")"#,
+                "argument to `join` must be ARRAY, got STRING",
+            ),
+            (
+                r#"join(["a"], null)"#,
+                "separator of `join` must be STRING or CHAR, got NULL",
+            ),
+            (
+                r#"join(["a", 1], ",")"#,
+                "elements joined by `join` must be STRING or CHAR, got INTEGER",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(
+                test_eval(input).unwrap_err().to_string(),
+                expected,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_locale_builtins() {
+        let tests = [
+            (r#"format_number(1234567)"#, "1,234,567"),
+            (r#"locale_compare("apple", "Banana")"#, "-1"),
+            (r#"locale_compare("b", "a", "de")"#, "1"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+        assert_eq!(
+            test_eval("format_number(1, 2)").unwrap_err().to_string(),
+            "locale argument to `format_number` must be STRING, got INTEGER"
+        );
+        let error = test_eval("format_number(1.5)").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "first argument to `format_number` must be INTEGER, got FLOAT"
+        );
+        assert_eq!(
+            error.help().unwrap().to_string(),
+            "Round floats first, as in `format_number(math.round(x))`"
+        );
+
+        struct German;
+        impl crate::locale::Locale for German {
+            fn compare(
+                &self,
+                a: &str,
+                b: &str,
+                _: Option<&str>,
+            ) -> std::result::Result<std::cmp::Ordering, String> {
+                Ok(a.cmp(b))
+            }
+
+            fn format_number(
+                &self,
+                n: isize,
+                locale: Option<&str>,
+            ) -> std::result::Result<String, String> {
+                match locale {
+                    Some("de") => Ok(n.to_string().replace("000", ".000")),
+                    _ => Err("unsupported locale".into()),
+                }
+            }
+        }
+        crate::locale::set_locale(German);
+        assert_eq!(
+            test_eval(r#"format_number(1000, "de")"#)
+                .unwrap()
+                .to_string(),
+            "1.000"
+        );
+        assert_eq!(
+            test_eval(r#"format_number(1000)"#).unwrap_err().to_string(),
+            "unsupported locale"
+        );
+        crate::locale::set_locale(crate::locale::FallbackLocale);
+    }
+
+    #[test]
+    fn test_index_assignment() {
+        let tests = [
+            ("let a = [1, 2, 3]; a[0] = 42; a", "[42, 2, 3]"),
+            ("let a = [1, 2, 3]; a[1] = 5", "5"),
+            ("let a = [[1, 2], [3]]; a[0][1] += 10; a", "[[1, 12], [3]]"),
+            ("let a = [1]; let b = a; a[0] = 2; [a, b]", "[[2], [1]]"),
+            (
+                "let a = [0, 0]; let f = fn(i) { a[i] = i + 1; }; f(0); f(1); a",
+                "[1, 2]",
+            ),
+            (
+                r#"let h = {"a": 1}; h["a"] = 2; h["b"] = 3; h"#,
+                "{a: 2, b: 3}",
+            ),
+            (r#"let h = {}; h.name = "monkey"; h.name"#, "monkey"),
+            (r#"let h = {"xs": [1]}; h.xs[0] += 1; h"#, "{xs: [2]}"),
+            (r#"let a = [{}]; a[0][true] = 1; a"#, "[{true: 1}]"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+
+        let errors = [
+            (
+                "let a = [1]; a[1] = 2",
+                "index 1 out of bounds for ARRAY of length 1",
+            ),
+            (
+                "let a = [1]; a[-1] = 2",
+                "index -1 out of bounds for ARRAY of length 1",
+            ),
+            (
+                "let a = [1]; a[true] = 2",
+                "array index must be INTEGER, got BOOLEAN",
+            ),
+            (
+                "let s = \"abc\"; s[0] = \"x\"",
+                "cannot assign to an index of STRING",
+            ),
+            ("b[0] = 1", "identifier not found: b"),
+            ("let h = {}; h[[1]] = 1", "unusable as hash key: ARRAY"),
+            (
+                "let a = [1]; a.x = 1",
+                "cannot assign to `.x` on ARRAY, only hashes have fields",
+            ),
+        ];
+        for (input, expected) in errors {
+            assert_eq!(
+                test_eval(input).unwrap_err().to_string(),
+                expected,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_break_values() {
+        let tests = [
+            (
+                "let i = 0; let found = loop { i += 1; if (i * i > 50) { break i; } }; found",
+                "8",
+            ),
+            ("loop { break; }", "null"),
+            (
+                "for (x in [1, 2, 3]) { if (x > 1) { break x * 10; } }",
+                "20",
+            ),
+            ("for (x in [1, 2, 3]) { x }", "null"),
+            (
+                "let i = 0; while (true) { i += 1; if (i == 3) { break [i]; } }",
+                "[3]",
+            ),
+            ("loop { for (x in 1..5) { break x; } break 0; }", "0"),
+            ("let f = fn() { loop { return 1; } }; f()", "1"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_function_display() {
+        assert_eq!(
+            test_eval("fn(x, y) { let z = x + y; if (z > 1) { z } else { 1 } }")
+                .unwrap()
+                .to_string(),
+            "fn(x, y) {
+    let z = (x + y);
+    if (z > 1) {
+        z
+    } else {
+        1
+    }
+}"
+        );
+        assert_eq!(test_eval("fn() {}").unwrap().to_string(), "fn() {}");
+    }
+
+    #[test]
+    fn test_string_index_expressions() {
+        assert_eq!(
+            test_eval(r#""hello"[1]"#).unwrap(),
+            Rc::new(Object::Char('e'))
+        );
+        assert_eq!(
+            test_eval(r#""héllo"[1]"#).unwrap(),
+            Rc::new(Object::Char('é'))
+        );
+        assert_eq!(test_eval(r#""hello"[5]"#).unwrap(), Rc::new(Object::Null));
+        assert_eq!(test_eval(r#""hello"[-1]"#).unwrap(), Rc::new(Object::Null));
+    }
+
+    #[test]
+    fn test_chars() {
+        let tests = [
+            ("'a'", Object::Char('a')),
+            (r"'\n'", Object::Char('\n')),
+            (r"'\u{e9}'", Object::Char('é')),
+            ("'a' < 'b'", Object::Boolean(true)),
+            ("'b' > 'é'", Object::Boolean(false)),
+            ("'a' == 'a'", Object::Boolean(true)),
+            ("'a' != 'A'", Object::Boolean(true)),
+            (r#""ab"[0] == 'a'"#, Object::Boolean(true)),
+            (r#""ab" + 'c'"#, Object::String("abc".into())),
+            (r#"'a' + "bc""#, Object::String("abc".into())),
+            ("int('a')", Object::Integer(97)),
+            ("char(233)", Object::Char('é')),
+            ("char(int('x') + 1)", Object::Char('y')),
+            ("str('x')", Object::String("x".into())),
+            (
+                r#"let n = 0; for (c in "a-b") { if (c == '-') { n += 1 } } n"#,
+                Object::Integer(1),
+            ),
+            (
+                "match ('b') { 'a' => 1, 'b' => 2, _ => 3 }",
+                Object::Integer(2),
+            ),
+            // A character equals the string of just that character.
+            (
+                "let h = {'a': 1, \"a\": 2}; len(keys(h))",
+                Object::Integer(1),
+            ),
+            ("let h = {'a': 1, \"a\": 2}; h['a']", Object::Integer(2)),
+            (r#"'a' == "a""#, Object::Boolean(true)),
+            (r#""a" != 'a'"#, Object::Boolean(false)),
+            (r#"'a' == "ab""#, Object::Boolean(false)),
+            (r#""abc"[0] == "a""#, Object::Boolean(true)),
+            (r#"contains(["a", "b"], "ab"[1])"#, Object::Boolean(true)),
+            (r#"index_of(["a", "b"], "ab"[1])"#, Object::Integer(1)),
+            (r#"{"a": 1}["abc"[0]]"#, Object::Integer(1)),
+            (r#"slice("ab", 0, 1) == "ab"[0]"#, Object::Boolean(true)),
+            (
+                r#"match ("abc"[0]) { "a" => 1, _ => 2 }"#,
+                Object::Integer(1),
+            ),
+            (r#"match ("a") { 'a' => 1, _ => 2 }"#, Object::Integer(1)),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap(), Rc::new(expected), "{}", input);
+        }
+
+        assert_eq!(
+            test_eval(r#"[enumerate("hé"), "ae\u{301}"[1], 'a', "a"]"#)
+                .unwrap()
+                .inspect(),
+            "[[[0, 'h'], [1, 'é']], \"e\u{301}\", 'a', \"a\"]"
+        );
+        let errors = [
+            (r#"'a' < "b""#, "type mismatch: CHAR < STRING"),
+            ("'a' + 'b'", "unknown operator: CHAR + CHAR"),
+            ("char(-1)", "-1 is not a valid code point"),
+            ("char(55296)", "55296 is not a valid code point"),
+            (
+                r#"char("a")"#,
+                "argument to `char` must be INTEGER, got STRING",
+            ),
+        ];
+        for (input, expected) in errors {
+            assert_eq!(
+                test_eval(input).unwrap_err().to_string(),
+                expected,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_bytes() {
+        let bytes = |b: &[u8]| Object::Bytes(b.to_vec());
+        let tests = [
+            (r#"b"GIF\x00\xff""#, bytes(b"GIF\x00\xff")),
+            (r#"len(b"h\u{e9}")"#, Object::Integer(3)),
+            (r#"b"abc"[1]"#, Object::Integer(98)),
+            (r#"b"abc"[3]"#, Object::Null),
+            (r#"b"abc"[-1]"#, Object::Null),
+            (r#"b"abc"[1:]"#, bytes(b"bc")),
+            (r#"bytes("hé")"#, bytes("hé".as_bytes())),
+            ("bytes([0, 255])", bytes(&[0, 255])),
+            (r#"decode(b"\xc3\xa9")"#, Object::String("é".into())),
+            (r#"bytes("ab") == b"ab""#, Object::Boolean(true)),
+            (r#"b"ab" != b"abc""#, Object::Boolean(true)),
+            (
+                r#"let n = 0; for (x in b"\x01\x02") { n += x } n"#,
+                Object::Integer(3),
+            ),
+            (r#"str(b"a\n\x7f")"#, Object::String(r#"b"a\n\x7f""#.into())),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap(), Rc::new(expected), "{}", input);
+        }
+
+        let errors = [
+            (
+                "bytes([1, 256])",
+                "bytes must be INTEGERs from 0 to 255, got 256",
+            ),
+            (
+                r#"bytes(["a"])"#,
+                r#"bytes must be INTEGERs from 0 to 255, got "a""#,
+            ),
+            (
+                "bytes(1)",
+                "argument to `bytes` must be STRING or ARRAY, got INTEGER",
+            ),
+            (r#"decode(b"\xff")"#, "bytes are not valid UTF-8"),
+            (
+                r#"decode("a")"#,
+                "argument to `decode` must be BYTES, got STRING",
+            ),
+        ];
+        for (input, expected) in errors {
+            assert_eq!(
+                test_eval(input).unwrap_err().to_string(),
+                expected,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_strings_count_graphemes() {
+        // "e" with a combining acute accent, a thumbs up with a skin tone
+        // modifier and a family joined from three emoji.
+        let s = r#"let s = "ae\u{301}👍🏽👨‍👩‍👧z"; "#;
+        let string = |s: &str| Object::String(s.into());
+        let tests = [
+            ("len(s)", Object::Integer(5)),
+            ("s[1]", string("e\u{301}")),
+            ("s[2]", string("👍🏽")),
+            ("s[3]", string("👨‍👩‍👧")),
+            ("s[5]", Object::Null),
+            ("s[1:3]", string("e\u{301}👍🏽")),
+            ("reverse(s)", string("z👨‍👩‍👧👍🏽e\u{301}a")),
+            ("len(chars(s))", Object::Integer(5)),
+            ("chars(s)[2]", string("👍🏽")),
+            ("let n = 0; for (c in s) { n += 1; } n", Object::Integer(5)),
+            (
+                "reverse([1, 2, 3])",
+                test_eval("[3, 2, 1]").unwrap().as_ref().clone(),
+            ),
+            (
+                "reverse(1..3)",
+                test_eval("[2, 1]").unwrap().as_ref().clone(),
+            ),
+        ];
+        for (input, expected) in tests {
+            let input = format!("{}{}", s, input);
+            assert_eq!(test_eval(&input).unwrap(), Rc::new(expected), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_string_slice_expressions() {
+        assert_eq!(
+            test_eval(r#""hello"[1:3]"#).unwrap(),
+            Rc::new(Object::String("el".into()))
+        );
+        assert_eq!(
+            test_eval(r#""héllo"[:2]"#).unwrap(),
+            Rc::new(Object::String("hé".into()))
+        );
+        assert_eq!(
+            test_eval(r#""hello"[3:]"#).unwrap(),
+            Rc::new(Object::String("lo".into()))
+        );
+        assert_eq!(
+            test_eval(r#""hello"[:]"#).unwrap(),
+            Rc::new(Object::String("hello".into()))
+        );
+        assert_eq!(
+            test_eval(r#""hello"[3:1]"#).unwrap(),
+            Rc::new(Object::String("".into()))
+        );
+        assert_eq!(
+            test_eval(r#""hello"[-5:99]"#).unwrap(),
+            Rc::new(Object::String("hello".into()))
+        );
+
+        match test_eval(r#""hello"[true:]"#) {
+            Ok(_) => unreachable!(),
+            Err(e) => assert_eq!(e.to_string(), "slice index must be INTEGER, got BOOLEAN"),
+        }
+    }
+
+    #[test]
+    fn test_help() {
+        let input = "
+/// Adds two numbers.
+///
+/// Works for strings, too.
+let add = fn(x, y) { x + y };
+help(add)";
+        assert_eq!(
+            test_eval(input).unwrap(),
+            Rc::new(Object::String(
+                "fn(x, y)\n\nAdds two numbers.\n\nWorks for strings, too.".into()
+            ))
+        );
+
+        let input = "
+// not a doc comment
+let add = fn(x, y) { x + y };
+help(add)";
+        assert_eq!(
+            test_eval(input).unwrap(),
+            Rc::new(Object::String(
+                "fn(x, y)\n\nNo documentation available".into()
+            ))
+        );
+
+        match test_eval("help(1)") {
+            Ok(_) => unreachable!(),
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "argument to `help` must be FUNCTION, got INTEGER"
+            ),
+        }
+    }
+
+    #[test]
+    fn test_builtin_identity() {
+        let tests = [
+            ("help(len)", Object::String("builtin len(value)".into())),
+            (
+                "str(int)",
+                Object::String("builtin int(value, base?)".into()),
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).unwrap(), Rc::new(expected), "{}", input);
+        }
+        assert_eq!(test_eval("len").unwrap().r#type(), "BUILTIN");
+        assert_eq!(
+            test_eval("let f = fn(x) { x }; f(push)").unwrap(),
+            test_eval("push").unwrap()
+        );
+        assert_ne!(test_eval("first").unwrap(), test_eval("last").unwrap());
+
+        let report = test_eval("push([1])").unwrap_err();
+        assert_eq!(
+            report.help().unwrap().to_string(),
+            "Usage: push(array, value)"
+        );
+    }
+
+    #[test]
+    fn test_array_slice_expressions() {
+        let array = |v: &[isize]| {
+            Rc::new(Object::Array(
+                v.iter().map(|i| Rc::new(Object::Integer(*i))).collect(),
+            ))
+        };
+        assert_eq!(test_eval("[1, 2, 3, 4][1:3]").unwrap(), array(&[2, 3]));
+        assert_eq!(test_eval("[1, 2, 3, 4][:2]").unwrap(), array(&[1, 2]));
+        assert_eq!(test_eval("[1, 2, 3, 4][2:]").unwrap(), array(&[3, 4]));
+        assert_eq!(test_eval("[1, 2, 3, 4][:]").unwrap(), array(&[1, 2, 3, 4]));
+        assert_eq!(test_eval("[1, 2, 3, 4][3:1]").unwrap(), array(&[]));
+        assert_eq!(
+            test_eval("[1, 2, 3, 4][-2:10]").unwrap(),
+            array(&[1, 2, 3, 4])
+        );
+        assert_eq!(
+            test_eval("let a = [1, 2, 3]; let b = a[1:]; a;").unwrap(),
+            array(&[1, 2, 3])
+        );
+
+        match test_eval("5[1:2]") {
+            Ok(_) => unreachable!(),
+            Err(e) => assert_eq!(e.to_string(), "Slicing only for arrays, strings and bytes"),
+        }
     }
 }
@@ -0,0 +1,308 @@
+//! Generators, the functions written `fn*`. Calling one gives a
+//! [`Generator`], which runs the body of the function up to each `yield` as
+//! `next` or a `for` loop asks for values.
+//!
+//! The evaluator recurses through the AST and can't stop halfway, so the
+//! statements containing a `yield` run on an explicit stack of frames instead,
+//! which keeps its place between values. All other statements and every
+//! expression are evaluated as usual, which is why `yield` has to be a
+//! statement of its own.
+
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use miette::Result;
+
+use crate::{
+    ast::{BlockStatement, Expression, Identifier, Pattern, Statement},
+    evaluator::{
+        caught, define, enclosed, eval_expression, eval_statement, for_items, is_truthy, Items,
+    },
+    object::{Environment, Object},
+};
+
+/// A suspended generator. Clones share their progress.
+#[derive(Clone)]
+pub struct Generator(Rc<RefCell<Vec<Frame>>>);
+
+/// What a frame does once its statements are done.
+enum Kind {
+    /// Nothing, the block ends.
+    Block,
+    /// Runs the statements again while the condition holds.
+    While(Expression),
+    Loop,
+    /// Runs the statements again for each remaining item, bound to the
+    /// variable.
+    For(Identifier, Items),
+    /// The statements are the body of a `try`, whose errors go to the
+    /// handler, bound to the name.
+    Try(Identifier, BlockStatement),
+}
+
+/// A block in progress, like the frame of a `while` loop with the statement of
+/// its body it is at.
+struct Frame {
+    kind: Kind,
+    block: Rc<BlockStatement>,
+    /// The index of the statement to run next.
+    next: usize,
+    /// The scope around the block.
+    outer: Rc<RefCell<Environment>>,
+    /// The scope of the block, a new one for each iteration of a loop.
+    scope: Rc<RefCell<Environment>>,
+}
+
+impl Generator {
+    /// A generator about to run `body` in `env`, which holds the arguments.
+    pub(crate) fn new(body: &BlockStatement, env: Rc<RefCell<Environment>>) -> Self {
+        let frame = Frame {
+            kind: Kind::Block,
+            block: Rc::new(body.clone()),
+            next: 0,
+            outer: Rc::clone(&env),
+            scope: env,
+        };
+        Self(Rc::new(RefCell::new(vec![frame])))
+    }
+
+    /// Runs the generator up to its next `yield` and gives the value, or
+    /// `None` once the body is done. An error ends the generator.
+    pub(crate) fn resume(&self) -> Result<Option<Rc<Object>>> {
+        let Ok(mut frames) = self.0.try_borrow_mut() else {
+            return Err(miette::miette!("generator is already running"));
+        };
+        let result = run(&mut frames);
+        if !matches!(result, Ok(Some(_))) {
+            frames.clear();
+        }
+        result
+    }
+}
+
+/// Generators are equal if they share their progress.
+impl PartialEq for Generator {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Generator {}
+
+impl fmt::Debug for Generator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Generator({} frames)", self.0.borrow().len())
+    }
+}
+
+fn run(frames: &mut Vec<Frame>) -> Result<Option<Rc<Object>>> {
+    loop {
+        let Some(frame) = frames.last_mut() else {
+            return Ok(None);
+        };
+        let block = Rc::clone(&frame.block);
+        let idx = frame.next;
+        let result = match block.statements().get(idx) {
+            Some(stmt) => {
+                frame.next += 1;
+                let scope = Rc::clone(&frame.scope);
+                step(frames, stmt, block.comments(idx), &scope)
+            }
+            None => finish(frames).map(|()| None),
+        };
+        match result {
+            Ok(Some(value)) => return Ok(Some(value)),
+            Ok(None) => {}
+            Err(report) => catch(frames, report)?,
+        }
+    }
+}
+
+/// Runs `stmt`, giving the value if it is a `yield`.
+fn step(
+    frames: &mut Vec<Frame>,
+    stmt: &Statement,
+    comments: &[crate::token::Comment],
+    scope: &Rc<RefCell<Environment>>,
+) -> Result<Option<Rc<Object>>> {
+    if !yields(stmt) {
+        let result = eval_statement(stmt, comments, scope)?;
+        match result.as_ref() {
+            Object::ReturnValue(_) => frames.clear(),
+            Object::Break(_) => leave_loop(frames, false),
+            Object::Continue => leave_loop(frames, true),
+            _ => {}
+        }
+        return Ok(None);
+    }
+    let Statement::Expr(expr) = stmt else {
+        let Statement::Yield { value, .. } = stmt else {
+            unreachable!("only `yield` and expressions yield");
+        };
+        return eval_expression(value, scope).map(Some);
+    };
+    match expr {
+        Expression::If {
+            condition,
+            consequence,
+            alternative,
+        } => {
+            let branch = match is_truthy(&*eval_expression(condition, scope)?) {
+                true => Some(consequence),
+                false => alternative.as_ref(),
+            };
+            if let Some(branch) = branch {
+                push(frames, Kind::Block, branch, scope, 0);
+            }
+        }
+        Expression::While { condition, body } => {
+            let kind = Kind::While(condition.as_ref().clone());
+            push(frames, kind, body, scope, body.len());
+        }
+        Expression::DoWhile { body, condition } => {
+            let kind = Kind::While(condition.as_ref().clone());
+            push(frames, kind, body, scope, 0);
+        }
+        Expression::Loop { body } => push(frames, Kind::Loop, body, scope, body.len()),
+        Expression::For {
+            variable,
+            iterable,
+            body,
+        } => {
+            let items = for_items(&*eval_expression(iterable, scope)?)?;
+            let kind = Kind::For(variable.clone(), items);
+            push(frames, kind, body, scope, body.len());
+        }
+        Expression::Try {
+            body,
+            error,
+            handler,
+        } => {
+            let kind = Kind::Try(error.clone(), handler.clone());
+            push(frames, kind, body, scope, 0);
+        }
+        Expression::Match { value, arms } => {
+            let value = eval_expression(value, scope)?;
+            for (pattern, body) in arms {
+                let matches = match pattern {
+                    Pattern::Wildcard => true,
+                    Pattern::Literal(literal) => *eval_expression(literal, scope)? == *value,
+                };
+                if matches {
+                    push(frames, Kind::Block, body, scope, 0);
+                    break;
+                }
+            }
+        }
+        Expression::Block(block) => push(frames, Kind::Block, block, scope, 0),
+        _ => unreachable!("only statements with blocks yield"),
+    }
+    Ok(None)
+}
+
+/// Starts running `block` inside `scope` at the statement `next`. Loops start
+/// past the end, so that [`finish`] decides whether to enter them at all.
+fn push(
+    frames: &mut Vec<Frame>,
+    kind: Kind,
+    block: &BlockStatement,
+    scope: &Rc<RefCell<Environment>>,
+    next: usize,
+) {
+    frames.push(Frame {
+        kind,
+        block: Rc::new(block.clone()),
+        next,
+        outer: Rc::clone(scope),
+        scope: enclosed(scope),
+    });
+}
+
+/// Ends an iteration of the innermost frame, whose statements are done.
+fn finish(frames: &mut Vec<Frame>) -> Result<()> {
+    let Some(frame) = frames.last_mut() else {
+        return Ok(());
+    };
+    let again = match &mut frame.kind {
+        Kind::Block | Kind::Try(..) => false,
+        Kind::Loop => true,
+        Kind::While(condition) => is_truthy(&*eval_expression(condition, &frame.outer)?),
+        Kind::For(variable, items) => match items.next().transpose()? {
+            Some(item) => {
+                let scope = enclosed(&frame.outer);
+                define(&scope, variable.value(), item)?;
+                frame.scope = scope;
+                frame.next = 0;
+                return Ok(());
+            }
+            None => false,
+        },
+    };
+    if again {
+        frame.scope = enclosed(&frame.outer);
+        frame.next = 0;
+    } else {
+        frames.pop();
+    }
+    Ok(())
+}
+
+/// Leaves the blocks up to the innermost loop, and the loop itself unless
+/// this is a `continue`.
+fn leave_loop(frames: &mut Vec<Frame>, next_iteration: bool) {
+    while let Some(frame) = frames.last_mut() {
+        if matches!(frame.kind, Kind::While(_) | Kind::Loop | Kind::For(..)) {
+            match next_iteration {
+                true => frame.next = frame.block.len(),
+                false => {
+                    frames.pop();
+                }
+            }
+            return;
+        }
+        frames.pop();
+    }
+}
+
+/// Unwinds to the innermost `try` and runs its handler with what `report`
+/// caught, or gives the error back without one.
+fn catch(frames: &mut Vec<Frame>, report: miette::Report) -> Result<()> {
+    while let Some(frame) = frames.pop() {
+        if let Kind::Try(error, handler) = frame.kind {
+            let scope = enclosed(&frame.outer);
+            define(&scope, error.value(), caught(report))?;
+            frames.push(Frame {
+                kind: Kind::Block,
+                block: Rc::new(handler),
+                next: 0,
+                outer: frame.outer,
+                scope,
+            });
+            return Ok(());
+        }
+    }
+    Err(report)
+}
+
+/// Whether `stmt` is a `yield` or has one in its blocks, so that it has to
+/// run on the frames.
+fn yields(stmt: &Statement) -> bool {
+    let any = |block: &BlockStatement| block.statements().iter().any(yields);
+    match stmt {
+        Statement::Yield { .. } => true,
+        Statement::Expr(Expression::If {
+            consequence,
+            alternative,
+            ..
+        }) => any(consequence) || alternative.as_ref().is_some_and(any),
+        Statement::Expr(
+            Expression::While { body, .. }
+            | Expression::DoWhile { body, .. }
+            | Expression::Loop { body }
+            | Expression::For { body, .. }
+            | Expression::Block(body),
+        ) => any(body),
+        Statement::Expr(Expression::Try { body, handler, .. }) => any(body) || any(handler),
+        Statement::Expr(Expression::Match { arms, .. }) => arms.iter().any(|(_, body)| any(body)),
+        _ => false,
+    }
+}
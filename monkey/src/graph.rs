@@ -0,0 +1,450 @@
+use crate::{
+    ast::{format_parameters, Expression, Program, Statement},
+    token::escape_bytes,
+};
+
+/// Collects the nodes and edges of a parse tree so it can be rendered in
+/// different graph description languages.
+struct Graph {
+    nodes: Vec<String>,
+    edges: Vec<(usize, usize, Option<&'static str>)>,
+}
+
+impl Graph {
+    fn from_program(program: &Program) -> Self {
+        let mut graph = Self {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+        };
+        graph.block(program.statements(), "Program");
+        graph
+    }
+
+    fn add_node(&mut self, label: impl Into<String>) -> usize {
+        self.nodes.push(label.into());
+        self.nodes.len() - 1
+    }
+
+    fn add_edge(&mut self, from: usize, to: usize, label: Option<&'static str>) {
+        self.edges.push((from, to, label));
+    }
+
+    fn block(&mut self, statements: &[Statement], label: &str) -> usize {
+        let id = self.add_node(label);
+        for stmt in statements {
+            let child = self.statement(stmt);
+            self.add_edge(id, child, None);
+        }
+        id
+    }
+
+    fn statement(&mut self, statement: &Statement) -> usize {
+        match statement {
+            Statement::Let { name, value, .. } => {
+                let id = self.add_node(format!("let {}", name));
+                let child = self.expression(value);
+                self.add_edge(id, child, Some("value"));
+                id
+            }
+            Statement::Destructure { target, value, .. } => {
+                let id = self.add_node(format!("let {}", target));
+                let child = self.expression(value);
+                self.add_edge(id, child, Some("value"));
+                id
+            }
+            Statement::Throw { value, .. } => {
+                let id = self.add_node("throw");
+                let child = self.expression(value);
+                self.add_edge(id, child, Some("value"));
+                id
+            }
+            Statement::Yield { value, .. } => {
+                let id = self.add_node("yield");
+                let child = self.expression(value);
+                self.add_edge(id, child, Some("value"));
+                id
+            }
+            Statement::Return { value, .. } => {
+                let id = self.add_node("return");
+                let child = self.expression(value);
+                self.add_edge(id, child, Some("value"));
+                id
+            }
+            Statement::Break { value, .. } => {
+                let id = self.add_node("break");
+                if let Some(value) = value {
+                    let child = self.expression(value);
+                    self.add_edge(id, child, Some("value"));
+                }
+                id
+            }
+            Statement::Continue { .. } => self.add_node("continue"),
+            Statement::Expr(expr) => self.expression(expr),
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) -> usize {
+        match expression {
+            Expression::Ident(ident) => self.add_node(ident.value()),
+            Expression::IntegerLiteral(i) => self.add_node(i.to_string()),
+            Expression::BigIntegerLiteral(i) => self.add_node(i.to_string()),
+            Expression::FloatLiteral(x) => self.add_node(x.to_string()),
+            Expression::Boolean(b) => self.add_node(b.to_string()),
+            Expression::Null => self.add_node("null"),
+            Expression::StringLiteral(s) => self.add_node(format!("\"{}\"", s)),
+            Expression::CharLiteral(c) => self.add_node(format!("'{}'", c)),
+            Expression::BytesLiteral(b) => self.add_node(format!("b\"{}\"", escape_bytes(b))),
+            Expression::Prefix {
+                operator, right, ..
+            } => {
+                let id = self.add_node(operator.as_str());
+                let child = self.expression(right);
+                self.add_edge(id, child, Some("right"));
+                id
+            }
+            Expression::Infix {
+                operator,
+                left,
+                right,
+                ..
+            } => {
+                let id = self.add_node(operator.as_str());
+                let left = self.expression(left);
+                self.add_edge(id, left, Some("left"));
+                let right = self.expression(right);
+                self.add_edge(id, right, Some("right"));
+                id
+            }
+            Expression::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                let id = self.add_node("if");
+                let child = self.expression(condition);
+                self.add_edge(id, child, Some("condition"));
+                let child = self.block(consequence.statements(), "Block");
+                self.add_edge(id, child, Some("consequence"));
+                if let Some(alt) = alternative {
+                    let child = self.block(alt.statements(), "Block");
+                    self.add_edge(id, child, Some("alternative"));
+                }
+                id
+            }
+            Expression::While { condition, body } => {
+                let id = self.add_node("while");
+                let child = self.expression(condition);
+                self.add_edge(id, child, Some("condition"));
+                let child = self.block(body.statements(), "Block");
+                self.add_edge(id, child, Some("body"));
+                id
+            }
+            Expression::DoWhile { body, condition } => {
+                let id = self.add_node("do while");
+                let child = self.block(body.statements(), "Block");
+                self.add_edge(id, child, Some("body"));
+                let child = self.expression(condition);
+                self.add_edge(id, child, Some("condition"));
+                id
+            }
+            Expression::Loop { body } => {
+                let id = self.add_node("loop");
+                let child = self.block(body.statements(), "Block");
+                self.add_edge(id, child, Some("body"));
+                id
+            }
+            Expression::For {
+                variable,
+                iterable,
+                body,
+            } => {
+                let id = self.add_node(format!("for {} in", variable));
+                let child = self.expression(iterable);
+                self.add_edge(id, child, Some("iterable"));
+                let child = self.block(body.statements(), "Block");
+                self.add_edge(id, child, Some("body"));
+                id
+            }
+            Expression::Try {
+                body,
+                error,
+                handler,
+            } => {
+                let id = self.add_node("try");
+                let child = self.block(body.statements(), "Block");
+                self.add_edge(id, child, Some("body"));
+                let child = self.block(handler.statements(), &format!("catch ({})", error));
+                self.add_edge(id, child, Some("handler"));
+                id
+            }
+            Expression::Match { value, arms } => {
+                let id = self.add_node("match");
+                let child = self.expression(value);
+                self.add_edge(id, child, Some("value"));
+                for (pattern, body) in arms {
+                    let child = self.block(body.statements(), &format!("{} =>", pattern));
+                    self.add_edge(id, child, Some("arm"));
+                }
+                id
+            }
+            Expression::Block(block) => self.block(block.statements(), "Block"),
+            Expression::FunctionLiteral {
+                parameters,
+                rest,
+                body,
+                generator,
+            } => {
+                let id = self.add_node(format!(
+                    "fn{}({})",
+                    if *generator { "*" } else { "" },
+                    format_parameters(parameters, rest.as_ref())
+                ));
+                let child = self.block(body.statements(), "Block");
+                self.add_edge(id, child, Some("body"));
+                id
+            }
+            Expression::Call {
+                function,
+                arguments,
+                ..
+            } => {
+                let id = self.add_node("call");
+                let child = self.expression(function);
+                self.add_edge(id, child, Some("function"));
+                for arg in arguments {
+                    let child = self.expression(arg);
+                    self.add_edge(id, child, Some("arg"));
+                }
+                id
+            }
+            Expression::Spread(value) => {
+                let id = self.add_node("..");
+                let child = self.expression(value);
+                self.add_edge(id, child, Some("value"));
+                id
+            }
+            Expression::ArrayLiteral(elements) => {
+                let id = self.add_node("[]");
+                for element in elements {
+                    let child = self.expression(element);
+                    self.add_edge(id, child, None);
+                }
+                id
+            }
+            Expression::Field {
+                left,
+                name,
+                optional,
+            } => {
+                let access = if *optional { "?." } else { "." };
+                let id = self.add_node(format!("{}{}", access, name));
+                let child = self.expression(left);
+                self.add_edge(id, child, Some("left"));
+                id
+            }
+            Expression::IndexExpr {
+                left,
+                index,
+                optional,
+            } => {
+                let id = self.add_node(if *optional { "?.index" } else { "index" });
+                let child = self.expression(left);
+                self.add_edge(id, child, Some("left"));
+                let child = self.expression(index);
+                self.add_edge(id, child, Some("index"));
+                id
+            }
+            Expression::Slice { left, start, end } => {
+                let id = self.add_node("slice");
+                let child = self.expression(left);
+                self.add_edge(id, child, Some("left"));
+                if let Some(start) = start {
+                    let child = self.expression(start);
+                    self.add_edge(id, child, Some("start"));
+                }
+                if let Some(end) = end {
+                    let child = self.expression(end);
+                    self.add_edge(id, child, Some("end"));
+                }
+                id
+            }
+            Expression::HashLiteral(pairs) => {
+                let id = self.add_node("{}");
+                for (key, value) in pairs {
+                    let pair = self.add_node(":");
+                    self.add_edge(id, pair, None);
+                    let child = self.expression(key);
+                    self.add_edge(pair, child, Some("key"));
+                    let child = self.expression(value);
+                    self.add_edge(pair, child, Some("value"));
+                }
+                id
+            }
+            Expression::Assign { name, value, .. } => {
+                let id = self.add_node(format!("{} =", name));
+                let child = self.expression(value);
+                self.add_edge(id, child, Some("value"));
+                id
+            }
+            Expression::IndexAssign { target, value, .. } => {
+                let id = self.add_node("=");
+                let child = self.expression(target);
+                self.add_edge(id, child, Some("target"));
+                let child = self.expression(value);
+                self.add_edge(id, child, Some("value"));
+                id
+            }
+        }
+    }
+}
+
+/// Renders the parse tree of `program` as a Graphviz DOT digraph.
+pub fn to_dot(program: &Program) -> String {
+    let graph = Graph::from_program(program);
+    let mut out = String::from("digraph ast {\n    node [shape=box];\n");
+    for (id, label) in graph.nodes.iter().enumerate() {
+        out.push_str(&format!("    n{} [label=\"{}\"];\n", id, escape_dot(label)));
+    }
+    for (from, to, label) in &graph.edges {
+        match label {
+            Some(label) => out.push_str(&format!(
+                "    n{} -> n{} [label=\"{}\"];\n",
+                from, to, label
+            )),
+            None => out.push_str(&format!("    n{} -> n{};\n", from, to)),
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Renders the parse tree of `program` as a Mermaid flowchart.
+pub fn to_mermaid(program: &Program) -> String {
+    let graph = Graph::from_program(program);
+    let mut out = String::from("flowchart TD\n");
+    for (id, label) in graph.nodes.iter().enumerate() {
+        out.push_str(&format!("    n{}[\"{}\"]\n", id, escape_mermaid(label)));
+    }
+    for (from, to, label) in &graph.edges {
+        match label {
+            Some(label) => out.push_str(&format!("    n{} -->|{}| n{}\n", from, label, to)),
+            None => out.push_str(&format!("    n{} --> n{}\n", from, to)),
+        }
+    }
+    out
+}
+
+/// Renders the parse tree of `program` as nested JSON objects, each with a
+/// `label`, the `edge` label connecting it to its parent (or `null`) and its
+/// `children`.
+pub fn to_json(program: &Program) -> String {
+    let graph = Graph::from_program(program);
+    let mut children = vec![Vec::new(); graph.nodes.len()];
+    for (from, to, label) in &graph.edges {
+        children[*from].push((*to, *label));
+    }
+    json_node(&graph, &children, 0, None)
+}
+
+fn json_node(
+    graph: &Graph,
+    children: &[Vec<(usize, Option<&'static str>)>],
+    id: usize,
+    edge: Option<&str>,
+) -> String {
+    let edge = match edge {
+        Some(edge) => format!("\"{}\"", escape_json(edge)),
+        None => "null".into(),
+    };
+    let nodes: Vec<_> = children[id]
+        .iter()
+        .map(|(child, label)| json_node(graph, children, *child, *label))
+        .collect();
+    format!(
+        "{{\"label\":\"{}\",\"edge\":{},\"children\":[{}]}}",
+        escape_json(&graph.nodes[id]),
+        edge,
+        nodes.join(",")
+    )
+}
+
+fn escape_dot(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_mermaid(label: &str) -> String {
+    label.replace('"', "#quot;")
+}
+
+fn escape_json(label: &str) -> String {
+    let mut escaped = String::new();
+    for c in label.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser::Parser};
+
+    fn program_from_input(input: &str) -> Program {
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        parser.parse_program().0
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let program = program_from_input("let x = 1 + 2;");
+        assert_eq!(
+            to_dot(&program),
+            r#"digraph ast {
+    node [shape=box];
+    n0 [label="Program"];
+    n1 [label="let x"];
+    n2 [label="+"];
+    n3 [label="1"];
+    n4 [label="2"];
+    n2 -> n3 [label="left"];
+    n2 -> n4 [label="right"];
+    n1 -> n2 [label="value"];
+    n0 -> n1;
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        let program = program_from_input(r#"let x = -"a";"#);
+        assert_eq!(
+            to_json(&program),
+            r#"{"label":"Program","edge":null,"children":[{"label":"let x","edge":null,"children":[{"label":"-","edge":"value","children":[{"label":"\"a\"","edge":"right","children":[]}]}]}]}"#
+        );
+    }
+
+    #[test]
+    fn test_to_mermaid() {
+        let program = program_from_input(r#"f("a")"#);
+        assert_eq!(
+            to_mermaid(&program),
+            r##"flowchart TD
+    n0["Program"]
+    n1["call"]
+    n2["f"]
+    n3["#quot;a#quot;"]
+    n1 -->|function| n2
+    n1 -->|arg| n3
+    n0 --> n1
+"##
+        );
+    }
+}
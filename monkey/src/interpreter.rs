@@ -0,0 +1,596 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+};
+
+use miette::Result;
+
+use crate::{
+    ast::{Expression, Program, Statement},
+    evaluator::{self, Event, Frame, Output},
+    object::{Environment, Object},
+    random,
+    scheduler::Scheduler,
+    script::CompiledScript,
+};
+
+/// A global binding as `(name, type, display, inspect)`, where `display` is
+/// how `puts` prints the value and `inspect` how it would be written in source.
+pub type Binding = (String, String, String, String);
+
+type Observer = Rc<RefCell<dyn FnMut(&Interpreter, &Event)>>;
+
+/// The functions a script bound, by name.
+type ScriptFunctions = Vec<(String, Rc<Object>)>;
+
+/// The global bindings at some point, see [`Interpreter::snapshot`].
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    store: HashMap<String, Rc<Object>>,
+}
+
+/// Evaluates programs in a persistent global environment and exposes its state
+/// for frontends, which can render it live from an observer.
+///
+/// Statements take effect one by one, so when a statement fails, the bindings
+/// made by the statements before it are kept. In transactional mode a failing
+/// program leaves the globals as they were before it instead.
+pub struct Interpreter {
+    env: Rc<RefCell<Environment>>,
+    observer: Option<Observer>,
+    output: RefCell<Option<Output>>,
+    transactional: Cell<bool>,
+    /// Whether frontends parse in strict mode, see
+    /// [`Parser::with_strict`](crate::Parser::with_strict).
+    strict: Cell<bool>,
+    max_call_depth: Cell<usize>,
+    /// Where the sequence of random numbers continues.
+    random: Cell<u64>,
+    /// The functions each script loaded with [`Interpreter::reload_script`]
+    /// bound last time.
+    scripts: RefCell<HashMap<String, ScriptFunctions>>,
+    scheduler: RefCell<Scheduler>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    /// How many calls may be in progress at once unless changed with
+    /// [`Interpreter::set_max_call_depth`]. A call takes about 5 KiB of stack
+    /// in release builds, so wasm with its 1 MiB stack gets 150 and native
+    /// hosts, whose main thread usually has 8 MiB, get 1000. Debug builds take
+    /// several times more per call and need a thread with a larger stack.
+    #[cfg(target_arch = "wasm32")]
+    pub const DEFAULT_MAX_CALL_DEPTH: usize = 150;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub const DEFAULT_MAX_CALL_DEPTH: usize = 1000;
+
+    /// An interpreter whose globals sit on top of a frozen layer of
+    /// builtins, see [`Environment::new_global`].
+    pub fn new() -> Self {
+        Self::with_environment(Rc::new(RefCell::new(Environment::new_global())))
+    }
+
+    pub fn with_environment(env: Rc<RefCell<Environment>>) -> Self {
+        Self {
+            env,
+            observer: None,
+            output: RefCell::new(None),
+            transactional: Cell::new(false),
+            strict: Cell::new(false),
+            max_call_depth: Cell::new(Self::DEFAULT_MAX_CALL_DEPTH),
+            random: Cell::new(random::clock_seed()),
+            scripts: RefCell::new(HashMap::new()),
+            scheduler: RefCell::new(Scheduler::default()),
+        }
+    }
+
+    pub fn environment(&self) -> &Rc<RefCell<Environment>> {
+        &self.env
+    }
+
+    /// Calls `observer` before every statement and around every function call
+    /// during [`Interpreter::eval`]. The interpreter passed to it can be used
+    /// to inspect the globals and the call stack.
+    pub fn set_observer(&mut self, observer: impl FnMut(&Interpreter, &Event) + 'static) {
+        self.observer = Some(Rc::new(RefCell::new(observer)));
+    }
+
+    /// Sends the text `puts` and `print` write to `output`, e.g. a terminal
+    /// in the browser, instead of stdout.
+    pub fn set_output(&self, output: impl FnMut(&str) + 'static) {
+        *self.output.borrow_mut() = Some(Rc::new(RefCell::new(output)));
+    }
+
+    pub fn is_transactional(&self) -> bool {
+        self.transactional.get()
+    }
+
+    /// Whether [`Interpreter::eval`] restores the globals when the program
+    /// fails.
+    pub fn set_transactional(&self, transactional: bool) {
+        self.transactional.set(transactional);
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.strict.get()
+    }
+
+    /// Whether the programs given to [`Interpreter::eval`] by a REPL should be
+    /// parsed with [`Parser::with_strict`](crate::Parser::with_strict), set
+    /// with `:set strict on`.
+    pub fn set_strict(&self, strict: bool) {
+        self.strict.set(strict);
+    }
+
+    pub fn max_call_depth(&self) -> usize {
+        self.max_call_depth.get()
+    }
+
+    /// How many function calls may be in progress at once before evaluation
+    /// fails with "maximum recursion depth exceeded". Hosts with a larger
+    /// stack than wasm, or running on a thread with one, can raise it from
+    /// [`Interpreter::DEFAULT_MAX_CALL_DEPTH`].
+    pub fn set_max_call_depth(&self, depth: usize) {
+        self.max_call_depth.set(depth);
+    }
+
+    /// Restarts the numbers of `random`, `random_int` and `shuffle`, which are
+    /// the same for the same seed, e.g. to make tests repeatable. Unless set,
+    /// the seed comes from the clock.
+    pub fn set_random_seed(&self, seed: u64) {
+        self.random.set(seed);
+    }
+
+    /// Captures the global bindings, to be brought back with
+    /// [`Interpreter::restore`]. Values are shared, not copied, which is
+    /// enough as evaluation never mutates a value in place.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            store: self.env.borrow().store.clone(),
+        }
+    }
+
+    /// Resets the global bindings to `snapshot`. Closures defined since keep
+    /// working, as they share the global environment.
+    pub fn restore(&self, snapshot: Snapshot) {
+        self.env.borrow_mut().store = snapshot.store;
+    }
+
+    pub fn eval(&self, program: Program) -> Result<Rc<Object>> {
+        self.eval_program(&program)
+    }
+
+    /// Evaluates a script parsed beforehand, which can be evaluated again
+    /// later or by other interpreters.
+    pub fn eval_script(&self, script: &CompiledScript) -> Result<Rc<Object>> {
+        self.eval_program(script.program())
+    }
+
+    fn eval_program(&self, program: &Program) -> Result<Rc<Object>> {
+        let snapshot = self.transactional.get().then(|| self.snapshot());
+        let hook = self.observer.as_ref().map(|observer| {
+            let observer = Rc::clone(observer);
+            let interpreter = Interpreter::with_environment(Rc::clone(&self.env));
+            Box::new(move |event: &Event| (observer.borrow_mut())(&interpreter, event))
+                as Box<dyn FnMut(&Event)>
+        });
+        let previous = evaluator::set_observer(hook);
+        let max_call_depth = evaluator::set_max_call_depth(self.max_call_depth.get());
+        let session = evaluator::set_session(Some(Rc::clone(&self.env)));
+        let output = evaluator::set_output(self.output.borrow().clone());
+        let random = random::set_state(self.random.get());
+        let result = evaluator::eval_program(program, &self.env);
+        self.random.set(random::set_state(random));
+        evaluator::set_output(output);
+        evaluator::set_session(session);
+        evaluator::set_max_call_depth(max_call_depth);
+        evaluator::set_observer(previous);
+        self.scheduler.borrow_mut().collect();
+        if let (Err(_), Some(snapshot)) = (&result, snapshot) {
+            self.restore(snapshot);
+        }
+        result
+    }
+
+    /// Binds the top-level functions, i.e. `let name = fn(..) {..}`, of the
+    /// script called `name`, replacing the ones its previous version bound and
+    /// removing those it no longer defines. Other statements are not run, so
+    /// reloading has no side effects. If the script has errors, the globals
+    /// are left as they were.
+    pub fn reload_script(
+        &self,
+        name: &str,
+        src: &str,
+    ) -> std::result::Result<(), Vec<miette::Report>> {
+        let script = CompiledScript::compile(src)?;
+        let program = script.program();
+        let mut functions = Vec::new();
+        let mut errors = Vec::new();
+        for (idx, stmt) in program.statements().iter().enumerate() {
+            let Statement::Let {
+                name,
+                value:
+                    Expression::FunctionLiteral {
+                        parameters,
+                        rest,
+                        body,
+                        generator,
+                    },
+                ..
+            } = stmt
+            else {
+                continue;
+            };
+            if self.env.borrow().shadows_frozen(name) {
+                errors.push(evaluator::frozen_binding(name));
+                continue;
+            }
+            let doc = evaluator::doc_comment(program.comments(idx));
+            let function = evaluator::eval_function_literal(
+                parameters, rest, body, *generator, doc, &self.env,
+            );
+            functions.push((name.clone(), function));
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let previous = self
+            .scripts
+            .borrow_mut()
+            .insert(name.to_string(), functions.clone())
+            .unwrap_or_default();
+        let mut env = self.env.borrow_mut();
+        for (name, function) in previous {
+            // Keep bindings that were redefined since the last load.
+            if env
+                .store
+                .get(&name)
+                .is_some_and(|bound| Rc::ptr_eq(bound, &function))
+            {
+                env.store.remove(&name);
+            }
+        }
+        for (name, function) in functions {
+            env.set(name, function);
+        }
+        Ok(())
+    }
+
+    /// Advances the clock of `set_timeout` by `elapsed_ms` milliseconds and
+    /// runs the callbacks that are due, including all deferred ones, in the
+    /// order they are due. Callbacks they schedule run on a later call at the
+    /// earliest. Returns the errors of the callbacks that failed.
+    ///
+    /// Hosts with a frame or event loop call this on every iteration with the
+    /// time since the previous one.
+    pub fn run_due_callbacks(&self, elapsed_ms: u64) -> Vec<miette::Report> {
+        let due = self.scheduler.borrow_mut().advance(elapsed_ms);
+        let max_call_depth = evaluator::set_max_call_depth(self.max_call_depth.get());
+        let output = evaluator::set_output(self.output.borrow().clone());
+        let random = random::set_state(self.random.get());
+        let errors = due
+            .into_iter()
+            .filter_map(|callback| evaluator::apply_function(callback, Vec::new()).err())
+            .collect();
+        self.random.set(random::set_state(random));
+        evaluator::set_output(output);
+        evaluator::set_max_call_depth(max_call_depth);
+        self.scheduler.borrow_mut().collect();
+        errors
+    }
+
+    /// The number of callbacks waiting for [`Interpreter::run_due_callbacks`].
+    pub fn pending_callbacks(&self) -> usize {
+        self.scheduler.borrow().len()
+    }
+
+    /// The bindings of the global environment, sorted by name.
+    pub fn globals(&self) -> impl Iterator<Item = Binding> {
+        let mut bindings: Vec<_> = self
+            .env
+            .borrow()
+            .store
+            .iter()
+            .map(|(name, val)| (name.clone(), val.r#type(), val.to_string(), val.inspect()))
+            .collect();
+        bindings.sort();
+        bindings.into_iter()
+    }
+
+    /// The function calls in progress, outermost first. Only non-empty while
+    /// evaluating, i.e. from within the observer.
+    pub fn call_stack(&self) -> Vec<Frame> {
+        evaluator::call_stack()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, object::Channel, parser::Parser};
+
+    fn parse(input: &str) -> Program {
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        parser.parse_program().0
+    }
+
+    #[test]
+    fn test_globals() {
+        let interpreter = Interpreter::new();
+        interpreter
+            .eval(parse(r#"let s = "a\"b"; let a = [1, "x"]; let n = 2;"#))
+            .unwrap();
+        let globals: Vec<_> = interpreter.globals().collect();
+        assert_eq!(
+            globals,
+            vec![
+                (
+                    "a".into(),
+                    "ARRAY".into(),
+                    "[1, x]".into(),
+                    r#"[1, "x"]"#.into()
+                ),
+                ("n".into(), "INTEGER".into(), "2".into(), "2".into()),
+                (
+                    "s".into(),
+                    "STRING".into(),
+                    "a\"b".into(),
+                    r#""a\"b""#.into()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_output() {
+        let interpreter = Interpreter::new();
+        let written = Rc::new(RefCell::new(String::new()));
+        interpreter.set_output({
+            let written = Rc::clone(&written);
+            move |text| written.borrow_mut().push_str(text)
+        });
+        interpreter
+            .eval(parse(r#"puts(1, "a"); print("b", [2]); print(); puts(3);"#))
+            .unwrap();
+        assert_eq!(*written.borrow(), "1\na\nb [2]3\n");
+
+        interpreter
+            .eval(parse(r#"set_timeout(fn() { print("later") }, 5);"#))
+            .unwrap();
+        interpreter.run_due_callbacks(5);
+        assert_eq!(*written.borrow(), "1\na\nb [2]3\nlater");
+    }
+
+    #[test]
+    fn test_random_seed() {
+        let run = |seed| {
+            let interpreter = Interpreter::new();
+            interpreter.set_random_seed(seed);
+            let program = "[random(), random_int(0, 100), shuffle([1, 2, 3, 4, 5])]";
+            let first = interpreter.eval(parse(program)).unwrap().to_string();
+            let second = interpreter.eval(parse(program)).unwrap().to_string();
+            (first, second)
+        };
+        let (first, second) = run(7);
+        assert_eq!(run(7), (first.clone(), second.clone()));
+        assert_ne!(first, second);
+        assert_ne!(run(8).0, first);
+    }
+
+    #[test]
+    fn test_transactional() {
+        let interpreter = Interpreter::new();
+        let names = |interpreter: &Interpreter| -> Vec<_> {
+            interpreter.globals().map(|global| global.0).collect()
+        };
+
+        assert!(interpreter.eval(parse("let a = 1; let b = c;")).is_err());
+        assert_eq!(names(&interpreter), vec!["a"]);
+
+        interpreter.set_transactional(true);
+        assert!(interpreter
+            .eval(parse("let d = 1; a = 2; let e = f;"))
+            .is_err());
+        assert_eq!(names(&interpreter), vec!["a"]);
+        assert_eq!(
+            interpreter.environment().borrow().get("a"),
+            Some(Rc::new(Object::Integer(1)))
+        );
+
+        interpreter.eval(parse("let g = fn() { a };")).unwrap();
+        let snapshot = interpreter.snapshot();
+        interpreter.eval(parse("a = 3; let h = 4;")).unwrap();
+        interpreter.restore(snapshot);
+        assert_eq!(names(&interpreter), vec!["a", "g"]);
+        assert_eq!(
+            interpreter.eval(parse("g()")).unwrap(),
+            Rc::new(Object::Integer(1))
+        );
+    }
+
+    #[test]
+    fn test_reload_script() {
+        let interpreter = Interpreter::new();
+        let v1 = "
+/// Doubles `x`.
+let double = fn(x) { x * 2 };
+let unused = fn() { 0 };
+let x = puts(\"not run\");";
+        interpreter.reload_script("lib", v1).unwrap();
+        interpreter
+            .eval(parse("let twice = fn(x) { double(x) }; let unused = 1;"))
+            .unwrap();
+        assert_eq!(
+            interpreter.eval(parse("twice(5)")).unwrap(),
+            Rc::new(Object::Integer(10))
+        );
+        assert_eq!(
+            interpreter.eval(parse("help(double)")).unwrap(),
+            Rc::new(Object::String("fn(x)\n\nDoubles `x`.".into()))
+        );
+
+        let v2 = "let double = fn(x) { x + x + 1 };";
+        interpreter.reload_script("lib", v2).unwrap();
+        assert_eq!(
+            interpreter.eval(parse("twice(5)")).unwrap(),
+            Rc::new(Object::Integer(11))
+        );
+        let globals: Vec<_> = interpreter.globals().map(|global| global.0).collect();
+        assert_eq!(globals, vec!["double", "twice", "unused"]);
+
+        interpreter.reload_script("lib", "").unwrap();
+        assert!(interpreter.eval(parse("twice(5)")).is_err());
+
+        interpreter.reload_script("lib", v2).unwrap();
+        let errors = interpreter.reload_script("lib", "let double = fn(x) { x }; let = 1;");
+        assert_eq!(errors.unwrap_err().len(), 1);
+        let errors =
+            interpreter.reload_script("lib", "let double = fn(x) { x }; let len = fn() { 0 };");
+        assert_eq!(
+            errors.unwrap_err()[0].to_string(),
+            "cannot redefine builtin `len`"
+        );
+        assert_eq!(
+            interpreter.eval(parse("twice(5)")).unwrap(),
+            Rc::new(Object::Integer(11))
+        );
+    }
+
+    #[test]
+    fn test_max_call_depth() {
+        let interpreter = Interpreter::new();
+        interpreter.set_max_call_depth(20);
+        let input = "let f = fn(n) { if (n == 0) { 0 } else { 1 + f(n - 1) } };";
+        interpreter.eval(parse(input)).unwrap();
+        assert_eq!(interpreter.eval(parse("f(19)")).unwrap().to_string(), "19");
+
+        let err = interpreter.eval(parse("f(20)")).unwrap_err();
+        assert_eq!(err.to_string(), "maximum recursion depth exceeded");
+        assert_eq!(
+            err.help().unwrap().to_string(),
+            "At most 20 calls can be in progress at once"
+        );
+        // The label points at the call that went too deep, `f(n - 1)`.
+        let label = err.labels().unwrap().next().unwrap();
+        assert_eq!((label.offset(), label.len()), (45, 8));
+        assert!(interpreter.call_stack().is_empty());
+
+        // An error does not leave calls behind on the stack.
+        assert_eq!(interpreter.eval(parse("f(19)")).unwrap().to_string(), "19");
+    }
+
+    #[test]
+    fn test_run_due_callbacks() {
+        let interpreter = Interpreter::new();
+        let log = |interpreter: &Interpreter| interpreter.eval(parse("log")).unwrap().to_string();
+        interpreter
+            .eval(parse(
+                "let log = [];
+                let note = fn(x) { fn() { log = push(log, x); } };
+                set_timeout(note(\"late\"), 20);
+                set_timeout(note(\"soon\"), 10);
+                defer(note(\"deferred\"));
+                set_timeout(fn() { defer(note(\"again\")); undefined }, 10);",
+            ))
+            .unwrap();
+        assert_eq!(interpreter.pending_callbacks(), 4);
+        assert_eq!(log(&interpreter), "[]");
+
+        assert!(interpreter.run_due_callbacks(0).is_empty());
+        assert_eq!(log(&interpreter), "[deferred]");
+
+        let errors = interpreter.run_due_callbacks(15);
+        assert_eq!(errors[0].to_string(), "identifier not found: undefined");
+        assert_eq!(log(&interpreter), "[deferred, soon]");
+        assert_eq!(interpreter.pending_callbacks(), 2);
+
+        assert!(interpreter.run_due_callbacks(5).is_empty());
+        assert_eq!(log(&interpreter), "[deferred, soon, again, late]");
+        assert_eq!(interpreter.pending_callbacks(), 0);
+
+        let error = interpreter.eval(parse("set_timeout(1, 2)")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "first argument to `set_timeout` must be FUNCTION, got INTEGER"
+        );
+    }
+
+    #[test]
+    fn test_channels() {
+        let interpreter = Interpreter::new();
+        let result = interpreter.eval(parse(
+            "let [tx, rx] = channel(); send(tx, 1); send(tx, [2]); [receive(rx), receive(rx), receive(rx)]",
+        ));
+        assert_eq!(result.unwrap().to_string(), "[1, [2], null]");
+
+        let channel = Channel::new();
+        let (producer, consumer) = (Interpreter::new(), Interpreter::new());
+        producer
+            .environment()
+            .borrow_mut()
+            .set("out".into(), channel.sender());
+        consumer
+            .environment()
+            .borrow_mut()
+            .set("inbox".into(), channel.receiver());
+        producer
+            .eval(parse("send(out, {\"n\": 1}); send(out, fn(x) { x * 2 })"))
+            .unwrap();
+        assert_eq!(channel.len(), 2);
+        let result = consumer.eval(parse("let h = receive(inbox); receive(inbox)(h[\"n\"])"));
+        assert_eq!(result.unwrap().to_string(), "2");
+        assert!(channel.is_empty());
+
+        channel.send(Rc::new(Object::Integer(3)));
+        let result = consumer.eval(parse("[inbox, receive(inbox)]"));
+        assert_eq!(result.unwrap().to_string(), "[receiver(0 queued), 3]");
+        let error = consumer.eval(parse("send(inbox, 1)")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "first argument to `send` must be SENDER, got RECEIVER"
+        );
+    }
+
+    #[test]
+    fn test_call_stack_in_observer() {
+        let stacks = Rc::new(RefCell::new(Vec::new()));
+        let mut interpreter = Interpreter::new();
+        interpreter.set_observer({
+            let stacks = Rc::clone(&stacks);
+            move |interpreter, event| {
+                if let Event::Return(value) = event {
+                    let frames: Vec<_> = interpreter
+                        .call_stack()
+                        .iter()
+                        .map(|frame| format!("{}({})", frame.function, frame.arguments[0]))
+                        .collect();
+                    let globals: Vec<_> = interpreter.globals().map(|global| global.0).collect();
+                    stacks.borrow_mut().push(format!(
+                        "{} -> {} with {}",
+                        frames.join(" > "),
+                        value,
+                        globals.join(", ")
+                    ));
+                }
+            }
+        });
+        interpreter
+            .eval(parse(
+                "let f = fn(n) { if (n > 0) { f(n - 1) } else { 0 } }; let x = f(1);",
+            ))
+            .unwrap();
+        assert_eq!(
+            *stacks.borrow(),
+            vec!["f(1) > f(0) -> 0 with f", "f(1) -> 0 with f"]
+        );
+        assert!(interpreter.call_stack().is_empty());
+    }
+}
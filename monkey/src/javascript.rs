@@ -0,0 +1,1635 @@
+//! Transpiles a Monkey program into readable JavaScript.
+//!
+//! Monkey semantics that JavaScript lacks (truthiness, integer division,
+//! `null` for missing indices, the builtins) are provided by a small runtime
+//! shim emitted at the top of the output, followed by the prelude and the
+//! examples. Hashes become `Map`s. An `if` used
+//! as a value becomes a conditional expression, or an immediately invoked
+//! arrow function when a branch holds more than one statement, in which case
+//! a `return` inside that branch only leaves the `if`.
+
+use std::collections::HashSet;
+
+use crate::{
+    ast::{BlockStatement, Expression, Pattern, Program, Statement, Target},
+    lexer::Lexer,
+    parser::Parser,
+    programs,
+};
+
+const RUNTIME: &str = concat!(
+    r#"var $broken = null;
+
+function $truthy(value) {
+    return value !== null && value !== false;
+}
+
+// Floats with an integral value can't be told from integers, so `4.0 / 8`
+// is `0` like `4 / 8`.
+function $div(left, right) {
+    if (right === 0) {
+        throw new $Error("division by zero");
+    }
+    return Number.isInteger(left) && Number.isInteger(right) ? Math.trunc(left / right) : left / right;
+}
+
+function $mod(left, right) {
+    if (right === 0) {
+        throw new $Error("modulo by zero");
+    }
+    return left % right;
+}
+
+// `+` also concatenates arrays and merges hashes.
+function $add(left, right) {
+    if (Array.isArray(left) && Array.isArray(right)) {
+        return left.concat(right);
+    }
+    if (left instanceof Map && right instanceof Map) {
+        return new Map([...left, ...right]);
+    }
+    return left + right;
+}
+
+// `*` also repeats strings and arrays.
+function $mul(left, right) {
+    if (typeof left !== "string" && !Array.isArray(left)) {
+        return left * right;
+    }
+    const type = Array.isArray(left) ? "ARRAY" : "STRING";
+    if (right < 0) {
+        throw new $Error("cannot repeat " + type + " a negative number of times, got " + right);
+    }
+    // As long as repetitions may be in Monkey, counting UTF-16 units instead
+    // of bytes.
+    if (left.length * right > 16777216) {
+        throw new $Error("cannot repeat " + type + " " + right + " times");
+    }
+    return typeof left === "string" ? left.repeat(right) : [].concat(...Array(right).fill(left));
+}
+
+class $Range {
+    constructor(start, end) {
+        this.start = start;
+        this.end = end;
+    }
+
+    get length() {
+        return Math.max(this.end - this.start, 0);
+    }
+
+    *[Symbol.iterator]() {
+        for (let i = this.start; i < this.end; i++) {
+            yield i;
+        }
+    }
+
+    toString() {
+        return this.start + ".." + this.end;
+    }
+}
+
+class $Char {
+    constructor(code) {
+        this.code = code;
+    }
+
+    // A number for `<` and `>`, else the character, e.g. for `+` with a string.
+    [Symbol.toPrimitive](hint) {
+        return hint === "number" ? this.code : String.fromCodePoint(this.code);
+    }
+}
+
+// Characters are interned, so that `===` and `Map` keys compare them by value.
+var $chars = new Map();
+
+function $char(code) {
+    if (!$chars.has(code)) {
+        $chars.set(code, new $Char(code));
+    }
+    return $chars.get(code);
+}
+
+class $Error extends Error {
+    toString() {
+        return "ERROR: " + this.message;
+    }
+}
+
+class $Sender {
+    constructor(queue) {
+        this.queue = queue;
+    }
+
+    toString() {
+        return "sender(" + this.queue.length + " queued)";
+    }
+}
+
+class $Receiver {
+    constructor(queue) {
+        this.queue = queue;
+    }
+
+    toString() {
+        return "receiver(" + this.queue.length + " queued)";
+    }
+}
+
+function $caught(error) {
+    return error instanceof Error && !(error instanceof $Error) ? new $Error(error.message) : error;
+}
+
+function $graphemes(string) {
+    return Array.from(new Intl.Segmenter().segment(string), (s) => s.segment);
+}
+
+// A grapheme of a single code point is a character, others stay strings.
+function $element(grapheme) {
+    const code = grapheme.codePointAt(0);
+    return String.fromCodePoint(code) === grapheme ? $char(code) : grapheme;
+}
+
+function $iter(value) {
+    return typeof value === "string" ? $graphemes(value).map($element) : value;
+}
+
+// `==` compares arrays and hashes by their contents.
+function $equal(left, right) {
+    if (Array.isArray(left) && Array.isArray(right)) {
+        return left.length === right.length && left.every((l, i) => $equal(l, right[i]));
+    }
+    if (left instanceof Map && right instanceof Map) {
+        return left.size === right.size
+            && [...left].every(([key, l]) => {
+                key = $key(right, key);
+                return right.has(key) && $equal(l, right.get(key));
+            });
+    }
+    if (left instanceof $Range && right instanceof $Range) {
+        return left.start === right.start && left.end === right.end;
+    }
+    if (left instanceof Uint8Array && right instanceof Uint8Array) {
+        return left.length === right.length && left.every((l, i) => l === right[i]);
+    }
+    // A character equals the string of just that character.
+    return $text(left) === $text(right);
+}
+
+function $text(value) {
+    return value instanceof $Char ? String(value) : value;
+}
+
+// The key `hash` stores `key` under, as a character and the string of just
+// that character are the same key.
+function $key(hash, key) {
+    const other = key instanceof $Char ? String(key) : typeof key === "string" && key !== "" ? $element(key) : key;
+    return !hash.has(key) && hash.has(other) ? other : key;
+}
+
+function $index(left, index) {
+    if (left instanceof Map) {
+        return left.has($key(left, index)) ? left.get($key(left, index)) : null;
+    }
+    if (left instanceof $Range) {
+        return index >= 0 && index < left.length ? left.start + index : null;
+    }
+    const value = typeof left === "string" ? $graphemes(left)[index] : left[index];
+    if (value === undefined) {
+        return null;
+    }
+    return typeof left === "string" ? $element(value) : value;
+}
+
+function $optional(left, index) {
+    return left === null ? null : $index(left, index());
+}
+
+function $set(left, index, value) {
+    if (left instanceof Map) {
+        if (!(["number", "string", "boolean"].includes(typeof index) || index instanceof $Char)) {
+            throw new $Error("unusable as hash key: " + $type(index));
+        }
+        return new Map(left).set($key(left, index), value);
+    }
+    if (!Array.isArray(left)) {
+        throw new $Error("only elements of arrays and hashes can be assigned to");
+    }
+    if (index < 0 || index >= left.length) {
+        throw new $Error("index " + index + " out of bounds for ARRAY of length " + left.length);
+    }
+    const copy = [...left];
+    copy[index] = value;
+    return copy;
+}
+
+// The name `type` gives, where numbers without a fractional part count as
+// integers and builtins as functions.
+function $type(value) {
+    if (value === null) {
+        return "NULL";
+    }
+    if (typeof value === "number") {
+        return Number.isInteger(value) ? "INTEGER" : "FLOAT";
+    }
+    if (typeof value === "function") {
+        return "FUNCTION";
+    }
+    const types = [
+        [Array, "ARRAY"], [Map, "HASH"], [Uint8Array, "BYTES"], [$Char, "CHAR"],
+        [$Range, "RANGE"], [$Error, "ERROR"], [$Sender, "SENDER"], [$Receiver, "RECEIVER"],
+    ];
+    const found = types.find(([constructor]) => value instanceof constructor);
+    if (found !== undefined) {
+        return found[1];
+    }
+    if (is_generator(value)) {
+        return "GENERATOR";
+    }
+    return { string: "STRING", boolean: "BOOLEAN" }[typeof value];
+}
+
+function $fields(map, keys) {
+    return keys.map((key) => $index(map, key));
+}
+
+function $slice(left, start, end) {
+    const sliced = (typeof left === "string" ? $graphemes(left) : left).slice(
+        Math.max(start ?? 0, 0),
+        end === null ? undefined : Math.max(end, 0)
+    );
+    return typeof left === "string" ? sliced.join("") : sliced;
+}
+
+function slice(value, start, end = null) {
+    const len = typeof value === "string" ? $graphemes(value).length : value.length;
+    const bound = (index) => (index !== null && index < 0 ? len + index : index);
+    return $slice(value, bound(start), bound(end));
+}
+
+function $inspect(value) {
+    if (value === null) {
+        return "null";
+    }
+    if (Array.isArray(value)) {
+        return "[" + value.map($inspect).join(", ") + "]";
+    }
+    if (value instanceof Map) {
+        const pairs = $sortedPairs(value).map(([k, v]) => $inspect(k) + ": " + $inspect(v));
+        return "{" + pairs.join(", ") + "}";
+    }
+    if (value instanceof Uint8Array) {
+        const escapes = { 9: "\\t", 10: "\\n", 34: "\\\"", 92: "\\\\" };
+        const printable = (byte) => byte >= 32 && byte <= 126;
+        const escaped = Array.from(value, (byte) =>
+            escapes[byte] ?? (printable(byte)
+                ? String.fromCharCode(byte)
+                : "\\x" + byte.toString(16).padStart(2, "0"))
+        );
+        return "b\"" + escaped.join("") + "\"";
+    }
+    return String(value);
+}
+
+// Integer keys first, then booleans, then characters, then strings, as the
+// interpreter does.
+function $sortedPairs(map) {
+    const rank = (key) =>
+        ["number", "boolean", "char", "string"].indexOf(key instanceof $Char ? "char" : typeof key);
+    return [...map].sort(([a], [b]) => rank(a) - rank(b) || (a < b ? -1 : a > b ? 1 : 0));
+}
+
+function len(value) {
+    return typeof value === "string" ? $graphemes(value).length : value.length;
+}
+
+function first(array) {
+    const items = [...array];
+    return items.length > 0 ? items[0] : null;
+}
+
+function last(array) {
+    const items = [...array];
+    return items.length > 0 ? items[items.length - 1] : null;
+}
+
+function rest(array) {
+    const items = [...array];
+    return items.length > 0 ? items.slice(1) : null;
+}
+
+function push(array, value) {
+    return [...array, value];
+}
+
+function reverse(value) {
+    const reversed = [...$iter(value)].reverse();
+    return typeof value === "string" ? reversed.join("") : reversed;
+}
+
+function chars(string) {
+    return [...$iter(string)];
+}
+
+function keys(hash) {
+    return $sortedPairs(hash).map(([key]) => key);
+}
+
+function values(hash) {
+    return $sortedPairs(hash).map(([, value]) => value);
+}
+
+function contains(collection, value) {
+    if (typeof collection === "string") {
+        return collection.includes(String(value));
+    }
+    if (collection instanceof $Range) {
+        return Number.isInteger(value) && value >= collection.start && value < collection.end;
+    }
+    return collection.some((element) => $equal(element, value));
+}
+
+function has_key(hash, key) {
+    return hash.has($key(hash, key));
+}
+
+function $delete(hash, key) {
+    const copy = new Map(hash);
+    copy.delete($key(hash, key));
+    return copy;
+}
+
+function insert(hash, key, value) {
+    if (!(hash instanceof Map)) {
+        throw new $Error("argument to `insert` must be HASH");
+    }
+    return $set(hash, key, value);
+}
+
+function set_at(array, index, value) {
+    if (!Array.isArray(array)) {
+        throw new $Error("argument to `set_at` must be ARRAY");
+    }
+    return $set(array, index, value);
+}
+
+function replace(string, from, to) {
+    if (String(from) === "") {
+        throw new $Error("string replaced by `replace` must not be empty");
+    }
+    return string.replaceAll(String(from), String(to));
+}
+
+function starts_with(string, prefix) {
+    return string.startsWith(String(prefix));
+}
+
+function ends_with(string, suffix) {
+    return string.endsWith(String(suffix));
+}
+
+function index_of(collection, value) {
+    if (Array.isArray(collection)) {
+        return collection.findIndex((element) => $equal(element, value));
+    }
+    const part = String(value);
+    if (part === "") {
+        return 0;
+    }
+    // Matches only count if they start and end between characters.
+    const bounds = [0];
+    $graphemes(collection).forEach((grapheme) => bounds.push(bounds[bounds.length - 1] + grapheme.length));
+    return bounds.findIndex((start) =>
+        collection.startsWith(part, start) && bounds.includes(start + part.length));
+}
+
+function split(string, separator) {
+    if (String(separator) === "") {
+        throw new $Error("separator of `split` must not be empty");
+    }
+    return string.split(String(separator));
+}
+
+function join(array, separator) {
+    return array.map(String).join(String(separator));
+}
+
+function enumerate(value) {
+    if (value instanceof Map) {
+        return $sortedPairs(value);
+    }
+    return [...$iter(value)].map((element, i) => [i, element]);
+}
+
+function error(message) {
+    return new $Error(message);
+}
+
+function set_timeout(callback, ms) {
+    setTimeout(callback, ms);
+    return null;
+}
+
+function defer(callback) {
+    setTimeout(callback, 0);
+    return null;
+}
+
+function channel() {
+    const queue = [];
+    return [new $Sender(queue), new $Receiver(queue)];
+}
+
+function send(sender, value) {
+    sender.queue.push(value);
+    return null;
+}
+
+function receive(receiver) {
+    return receiver.queue.length > 0 ? receiver.queue.shift() : null;
+}
+
+function next(generator) {
+    const result = generator.next();
+    return result.done ? null : result.value;
+}
+
+function assert(condition, message) {
+    if (condition === null || condition === false) {
+        throw new $Error(message === undefined ? "assertion failed" : "assertion failed: " + message);
+    }
+    return null;
+}
+
+function is_error(value) {
+    return value instanceof $Error;
+}
+
+function type(value) {
+    return $type(value);
+}
+
+function is_generator(value) {
+    return Object.prototype.toString.call(value) === "[object Generator]";
+}
+
+function int(value, base) {
+    if (typeof value === "number" && base === undefined) {
+        if (!Number.isFinite(value)) {
+            throw new Error("cannot convert " + value + " to an integer");
+        }
+        return Math.trunc(value);
+    }
+    if (value instanceof $Char && base === undefined) {
+        return value.code;
+    }
+    if (typeof value !== "string") {
+        throw new Error("argument to `int` must be STRING, CHAR or FLOAT");
+    }
+    const prefixes = { "0x": 16, "0o": 8, "0b": 2 };
+    let text = value.trim();
+    const negative = text.startsWith("-");
+    if (negative || text.startsWith("+")) {
+        text = text.slice(1);
+    }
+    let radix = base ?? 10;
+    if (base === undefined && text.slice(0, 2) in prefixes) {
+        radix = prefixes[text.slice(0, 2)];
+        text = text.slice(2);
+    }
+    const digits = "0123456789abcdefghijklmnopqrstuvwxyz".slice(0, radix);
+    if (text === "" || ![...text.toLowerCase()].every((c) => digits.includes(c))) {
+        throw new Error(
+            "cannot parse " + JSON.stringify(value) + " as an integer in base " + (base ?? 10)
+        );
+    }
+    const magnitude = parseInt(text, radix);
+    return negative ? -magnitude : magnitude;
+}
+
+function float(value) {
+    if (typeof value === "number") {
+        return value;
+    }
+    if (typeof value !== "string") {
+        throw new Error("argument to `float` must be INTEGER or STRING");
+    }
+    const text = value.trim();
+    // Infinities and NaN are spelled the way Rust parses them.
+    const special = { inf: Infinity, infinity: Infinity, nan: NaN };
+    const name = text.replace(/^[+-]/, "").toLowerCase();
+    if (name in special) {
+        return text.startsWith("-") ? -special[name] : special[name];
+    }
+    if (!/^[+-]?(\d+\.?\d*|\.\d+)(e[+-]?\d+)?$/i.test(text)) {
+        throw new Error("cannot parse " + JSON.stringify(value) + " as a float");
+    }
+    return Number(text);
+}
+
+var math = new Map([
+    ["sin", Math.sin],
+    ["cos", Math.cos],
+    ["exp", Math.exp],
+    ["log", (x) => {
+        if (!(x > 0)) {
+            throw new $Error("`log` is only defined for positive numbers, got " + x);
+        }
+        return Math.log(x);
+    }],
+    ["floor", Math.floor],
+    ["ceil", Math.ceil],
+    // Half-way cases away from zero, where `Math.round` goes up.
+    ["round", (x) => Math.sign(x) * Math.round(Math.abs(x))],
+    ["PI", Math.PI],
+    ["E", Math.E],
+]);
+
+function random() {
+    return Math.random();
+}
+
+function random_int(lo, hi) {
+    if (lo >= hi) {
+        throw new $Error("`random_int` needs `lo` below `hi`, got " + lo + " and " + hi);
+    }
+    return lo + Math.floor(Math.random() * (hi - lo));
+}
+
+function shuffle(array) {
+    const copy = [...array];
+    for (let i = copy.length - 1; i > 0; i--) {
+        const j = Math.floor(Math.random() * (i + 1));
+        [copy[i], copy[j]] = [copy[j], copy[i]];
+    }
+    return copy;
+}
+
+function char(code) {
+    if (code instanceof $Char) {
+        return code;
+    }
+    if (!Number.isInteger(code) || code < 0 || code > 0x10ffff || (code >= 0xd800 && code < 0xe000)) {
+        throw new Error(code + " is not a valid code point");
+    }
+    return $char(code);
+}
+
+function bytes(value) {
+    if (typeof value === "string") {
+        return new TextEncoder().encode(value);
+    }
+    if (value instanceof Uint8Array) {
+        return value;
+    }
+    if (!Array.isArray(value)) {
+        throw new Error("argument to `bytes` must be STRING or ARRAY");
+    }
+    const invalid = value.find((byte) => !Number.isInteger(byte) || byte < 0 || byte > 255);
+    if (invalid !== undefined) {
+        throw new Error("bytes must be INTEGERs from 0 to 255, got " + $inspect(invalid));
+    }
+    return new Uint8Array(value);
+}
+
+function decode(bytes) {
+    try {
+        return new TextDecoder("utf-8", { fatal: true }).decode(bytes);
+    } catch {
+        throw new Error("bytes are not valid UTF-8");
+    }
+}
+
+function str(value, base) {
+    return base === undefined ? $inspect(value) : value.toString(base);
+}
+
+function $pad(value, { fill, align, zero, width }) {
+    const text = $inspect(value);
+    const isInteger = typeof value === "number";
+    if (zero && isInteger) {
+        const sign = text.startsWith("-") ? "-" : "";
+        const digits = text.slice(sign.length);
+        return sign + $pad(digits, { fill: "0", align: ">", width: width - sign.length });
+    }
+    align = align ?? (isInteger ? ">" : "<");
+    fill = fill ?? (zero ? "0" : " ");
+    const missing = Math.max(width - $graphemes(text).length, 0);
+    const left = align === ">" ? missing : align === "^" ? Math.floor(missing / 2) : 0;
+    return fill.repeat(left) + text + fill.repeat(missing - left);
+}
+
+function format(template, ...values) {
+    const used = values.map(() => false);
+    let next = 0;
+    let positional = false;
+    const out = template.replace(/\{\{|\}\}|\{([^}]*)\}|\{|\}/g, (match, spec) => {
+        if (match === "{{" || match === "}}") {
+            return match[0];
+        }
+        if (match === "{") {
+            throw new Error("unterminated placeholder in format string");
+        }
+        if (match === "}") {
+            throw new Error("unmatched `}` in format string");
+        }
+        const parsed = /^(\d*)(?::(?:(.)?([<^>]))?(0)?(\d*))?$/u.exec(spec);
+        if (parsed === null) {
+            throw new Error("invalid placeholder `{" + spec + "}` in format string");
+        }
+        const [, position, fill, align, zero, width] = parsed;
+        const index = position === "" ? next++ : Number(position);
+        positional ||= position !== "";
+        if (index >= values.length) {
+            throw new Error(position === ""
+                ? "format string has more placeholders than arguments"
+                : "placeholder `{" + spec + "}` refers to argument " + index + ", but there are only " + values.length);
+        }
+        used[index] = true;
+        return $pad(values[index], { fill, align, zero: zero !== undefined, width: Number(width) });
+    });
+    const unused = used.indexOf(false);
+    if (unused !== -1) {
+        throw new Error(positional
+            ? "argument " + unused + " is never used in the format string"
+            : "format string has fewer placeholders than arguments");
+    }
+    return out;
+}
+
+function sprintf(template, ...values) {
+    const out = template.replace(/%(-)?(0)?(\d*)(.?)/gu, (match, left, zero, width, conversion) => {
+        if (match === "%%") {
+            return "%";
+        }
+        if (conversion !== "d" && conversion !== "s") {
+            throw new Error(
+                conversion === ""
+                    ? "incomplete `%` directive in format string"
+                    : "unknown conversion `%" + conversion + "` in format string"
+            );
+        }
+        if (values.length === 0) {
+            throw new Error("format string has more placeholders than arguments");
+        }
+        const value = values.shift();
+        if (conversion === "d" && typeof value !== "number") {
+            throw new Error("`%d` needs an INTEGER");
+        }
+        return $pad(value, {
+            align: left ? "<" : ">",
+            zero: zero !== undefined && !left,
+            width: Number(width),
+        });
+    });
+    if (values.length > 0) {
+        throw new Error("format string has fewer placeholders than arguments");
+    }
+    return out;
+}
+
+function $void(value) {
+    return null;
+}
+
+function puts(...values) {
+    values.forEach((value) => console.log($inspect(value)));
+    return null;
+}
+
+// Without a newline only where there is a stdout, browsers always start one.
+function print(...values) {
+    const text = values.map($inspect).join(" ");
+    if (typeof process !== "undefined") {
+        process.stdout.write(text);
+    } else {
+        console.log(text);
+    }
+    return null;
+}
+
+function help(func) {
+    return String(func);
+}
+
+function locale_compare(a, b, locale) {
+    return Math.sign(a.localeCompare(b, locale));
+}
+
+function format_number(number, locale) {
+    return new Intl.NumberFormat(locale).format(number);
+}
+
+function examples() {
+    return [...$examples.keys()];
+}
+
+// `load_example(name)` becomes `eval($example(name))`, binding the names of the
+// example where it is called.
+function $example(name) {
+    if (!$examples.has(name)) {
+        throw new $Error("unknown example `" + name + "`, see `examples()` for the available ones");
+    }
+    return $examples.get(name);
+}
+
+function load_example() {
+    throw new $Error("`load_example` can only be called directly");
+}
+
+function version() {
+    return ""#,
+    env!("CARGO_PKG_VERSION"),
+    " (",
+    env!("MONKEY_GIT_HASH"),
+    r#")";
+}
+"#
+);
+
+/// Whether the value of the last statement in a block is returned.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Discard,
+    Tail,
+}
+
+/// Renders `program` as JavaScript, preceded by the runtime shim.
+pub fn to_javascript(program: &Program) -> String {
+    let mut out = String::from(RUNTIME);
+    out.push('\n');
+    out.push_str(&library());
+    out.push_str(&top_level(program));
+    out
+}
+
+/// The prelude and the examples for `load_example`, which follow the runtime
+/// in every transpiled program.
+fn library() -> String {
+    let mut out = top_level(&programs::prelude());
+    let examples: Vec<_> = programs::examples()
+        .iter()
+        .map(|(name, src)| {
+            let program = Parser::new(Lexer::new(src)).parse().program;
+            format!("    [{}, {}]", quote(name), quote(&top_level(&program)))
+        })
+        .collect();
+    out.push_str(&format!(
+        "var $examples = new Map([\n{}\n]);\n",
+        examples.join(",\n")
+    ));
+    out
+}
+
+fn top_level(program: &Program) -> String {
+    let mut out = String::new();
+    for (idx, stmt) in program.statements().iter().enumerate() {
+        for comment in program.comments(idx) {
+            out.push_str(&comment.text);
+            out.push('\n');
+        }
+        let stmt = match stmt {
+            // There is nothing to return from at the top level.
+            Statement::Return { value, .. } => format!("{};", expression(value)),
+            stmt => statement(stmt, Mode::Discard),
+        };
+        out.push_str(&stmt);
+        out.push('\n');
+    }
+    for comment in program.trailing_comments() {
+        out.push_str(&comment.text);
+        out.push('\n');
+    }
+    out
+}
+
+fn block(block: &BlockStatement, mode: Mode) -> String {
+    render_block(block, mode, false)
+}
+
+/// Like [`block`] for the body of an `if`, a loop, a `match` arm or a `try`,
+/// whose bindings become block scoped `let`s.
+fn scoped_block(block: &BlockStatement, mode: Mode) -> String {
+    render_block(block, mode, true)
+}
+
+fn render_block(block: &BlockStatement, mode: Mode, scoped: bool) -> String {
+    let last = block.len().saturating_sub(1);
+    let mut lines = Vec::new();
+    let mut declared = HashSet::new();
+    for (idx, stmt) in block.statements().iter().enumerate() {
+        lines.extend(
+            block
+                .comments(idx)
+                .iter()
+                .map(|comment| comment.text.clone()),
+        );
+        let js = match idx == last {
+            true => statement(stmt, mode),
+            false => statement(stmt, Mode::Discard),
+        };
+        let js = match scoped {
+            true => declare_scoped(stmt, js, &mut declared),
+            false => js,
+        };
+        lines.extend(js.lines().map(String::from));
+    }
+    lines.extend(
+        block
+            .trailing_comments()
+            .iter()
+            .map(|comment| comment.text.clone()),
+    );
+    if lines.is_empty() {
+        return "{}".into();
+    }
+    let mut out = String::from("{\n");
+    for line in lines {
+        out.push_str(&format!("    {}\n", line));
+    }
+    out.push('}');
+    out
+}
+
+/// Turns the `var` of `js`, the translation of `stmt`, into a `let` for the
+/// names bound by `stmt` that aren't in `declared` yet. JavaScript forbids
+/// declaring a name twice in a block, so names already there are assigned.
+fn declare_scoped(stmt: &Statement, js: String, declared: &mut HashSet<String>) -> String {
+    let names: Vec<_> = match stmt {
+        Statement::Let { name, .. } => vec![name.as_str()],
+        Statement::Destructure {
+            target: Target::Array(names),
+            ..
+        } => names.iter().map(|name| name.value()).collect(),
+        Statement::Destructure {
+            target: Target::Hash(entries),
+            ..
+        } => entries.iter().map(|(_, name)| name.value()).collect(),
+        _ => return js,
+    };
+    let Some(binding) = js.strip_prefix("var ") else {
+        return js;
+    };
+    let new: Vec<_> = names
+        .iter()
+        .copied()
+        .filter(|name| declared.insert(name.to_string()))
+        .collect();
+    match new.len() {
+        0 => binding.into(),
+        len if len == names.len() => format!("let {}", binding),
+        _ => format!("let {};\n{}", new.join(", "), binding),
+    }
+}
+
+/// The binding pattern for `target`, defaulting missing parts to `null`.
+fn destructure_target(target: &Target) -> String {
+    match target {
+        Target::Array(names) => {
+            let names: Vec<_> = names
+                .iter()
+                .map(|name| format!("{} = null", name))
+                .collect();
+            format!("[{}]", names.join(", "))
+        }
+        Target::Hash(entries) => {
+            let names: Vec<_> = entries
+                .iter()
+                .map(|(_, name)| format!("{} = null", name))
+                .collect();
+            format!("[{}]", names.join(", "))
+        }
+    }
+}
+
+/// Completes the translation `stmt` of a loop with `body`. A `break value`
+/// leaves the value in `$broken`, which a loop in tail position returns and
+/// any other loop resets, so that it never leaks into an enclosing loop.
+fn finish_loop(stmt: String, body: &BlockStatement, mode: Mode) -> String {
+    match (mode, breaks_with_value(body)) {
+        (Mode::Tail, true) => format!("var $broken = null;\n{}\nreturn $broken;", stmt),
+        (Mode::Tail, false) => format!("{}\nreturn null;", stmt),
+        (Mode::Discard, true) => format!("{}\n$broken = null;", stmt),
+        (Mode::Discard, false) => stmt,
+    }
+}
+
+/// Whether `block` has a `break value` that leaves the loop it belongs to.
+fn breaks_with_value(block: &BlockStatement) -> bool {
+    block.statements().iter().any(|stmt| match stmt {
+        Statement::Break { value, .. } => value.is_some(),
+        Statement::Expr(Expression::If {
+            consequence,
+            alternative,
+            ..
+        }) => breaks_with_value(consequence) || alternative.as_ref().is_some_and(breaks_with_value),
+        Statement::Expr(Expression::Try { body, handler, .. }) => {
+            breaks_with_value(body) || breaks_with_value(handler)
+        }
+        Statement::Expr(Expression::Match { arms, .. }) => {
+            arms.iter().any(|(_, body)| breaks_with_value(body))
+        }
+        Statement::Expr(Expression::Block(body)) => breaks_with_value(body),
+        _ => false,
+    })
+}
+
+fn statement(stmt: &Statement, mode: Mode) -> String {
+    match stmt {
+        Statement::Let { name, value, .. } => {
+            let stmt = format!("var {} = {};", name, expression(value));
+            match mode {
+                Mode::Tail => format!("{}\nreturn null;", stmt),
+                Mode::Discard => stmt,
+            }
+        }
+        Statement::Destructure { target, value, .. } => {
+            let value = match target {
+                Target::Array(_) => expression(value),
+                Target::Hash(entries) => {
+                    let keys: Vec<_> = entries
+                        .iter()
+                        .map(|(key, _)| format!("{:?}", key))
+                        .collect();
+                    format!("$fields({}, [{}])", expression(value), keys.join(", "))
+                }
+            };
+            let stmt = format!("var {} = {};", destructure_target(target), value);
+            match mode {
+                Mode::Tail => format!("{}\nreturn null;", stmt),
+                Mode::Discard => stmt,
+            }
+        }
+        Statement::Return { value, .. } => format!("return {};", expression(value)),
+        Statement::Throw { value, .. } => format!("throw {};", expression(value)),
+        Statement::Yield { value, .. } => format!("yield {};", expression(value)),
+        Statement::Break { value: None, .. } => "break;".into(),
+        Statement::Break {
+            value: Some(value), ..
+        } => format!("$broken = {};\nbreak;", expression(value)),
+        Statement::Continue { .. } => "continue;".into(),
+        Statement::Expr(Expression::While { condition, body }) => {
+            let stmt = format!(
+                "while ($truthy({})) {}",
+                expression(condition),
+                scoped_block(body, Mode::Discard)
+            );
+            finish_loop(stmt, body, mode)
+        }
+        Statement::Expr(Expression::DoWhile { body, condition }) => {
+            let stmt = format!(
+                "do {} while ($truthy({}));",
+                scoped_block(body, Mode::Discard),
+                expression(condition)
+            );
+            finish_loop(stmt, body, mode)
+        }
+        Statement::Expr(Expression::Loop { body }) => {
+            let stmt = format!("while (true) {}", scoped_block(body, Mode::Discard));
+            finish_loop(stmt, body, mode)
+        }
+        Statement::Expr(Expression::For {
+            variable,
+            iterable,
+            body,
+        }) => {
+            let stmt = format!(
+                "for (let {} of $iter({})) {}",
+                variable,
+                expression(iterable),
+                scoped_block(body, Mode::Discard)
+            );
+            finish_loop(stmt, body, mode)
+        }
+        Statement::Expr(Expression::Try {
+            body,
+            error,
+            handler,
+        }) => {
+            let handler = scoped_block(handler, mode);
+            let handler = match &handler[1..handler.len() - 1] {
+                "" => "\n",
+                statements => statements,
+            };
+            format!(
+                "try {} catch ($error) {{\n    let {} = $caught($error);{}}}",
+                scoped_block(body, mode),
+                error,
+                handler
+            )
+        }
+        Statement::Expr(Expression::If {
+            condition,
+            consequence,
+            alternative,
+        }) => {
+            let mut out = format!(
+                "if ($truthy({})) {}",
+                expression(condition),
+                scoped_block(consequence, mode)
+            );
+            match (alternative, mode) {
+                (Some(alt), _) => out.push_str(&format!(" else {}", scoped_block(alt, mode))),
+                (None, Mode::Tail) => out.push_str(" else {\n    return null;\n}"),
+                (None, Mode::Discard) => {}
+            }
+            out
+        }
+        Statement::Expr(Expression::Block(body)) => scoped_block(body, mode),
+        Statement::Expr(Expression::IndexAssign { target, value, .. }) if mode == Mode::Discard => {
+            format!("{};", index_assignment(target, expression(value)))
+        }
+        Statement::Expr(expr) => match mode {
+            Mode::Tail => format!("return {};", expression(expr)),
+            Mode::Discard => format!("{};", expression(expr)),
+        },
+    }
+}
+
+fn expression(expr: &Expression) -> String {
+    match expr {
+        // `void` and `delete` are operators in JavaScript, so the builtins are
+        // renamed.
+        Expression::Ident(ident) if matches!(ident.value(), "void" | "delete") => {
+            format!("${}", ident)
+        }
+        Expression::Ident(ident) => ident.to_string(),
+        Expression::IntegerLiteral(i) => i.to_string(),
+        Expression::BigIntegerLiteral(i) => i.to_string(),
+        Expression::FloatLiteral(x) => x.to_string(),
+        Expression::Boolean(b) => b.to_string(),
+        Expression::Null => "null".into(),
+        Expression::StringLiteral(s) => quote(s),
+        Expression::CharLiteral(c) => format!("$char({})", *c as u32),
+        Expression::BytesLiteral(b) => {
+            let bytes: Vec<_> = b.iter().map(|byte| byte.to_string()).collect();
+            format!("new Uint8Array([{}])", bytes.join(", "))
+        }
+        Expression::Prefix {
+            operator, right, ..
+        } => match operator.as_str() {
+            "!" => format!("!$truthy({})", expression(right)),
+            _ => format!("({}{})", operator, expression(right)),
+        },
+        Expression::Infix {
+            operator,
+            left,
+            right,
+            ..
+        } => match operator.as_str() {
+            "/" => format!("$div({}, {})", expression(left), expression(right)),
+            "%" => format!("$mod({}, {})", expression(left), expression(right)),
+            "+" if !is_scalar(left) && !is_scalar(right) => {
+                format!("$add({}, {})", expression(left), expression(right))
+            }
+            "*" if !matches!(
+                left.as_ref(),
+                Expression::IntegerLiteral(_)
+                    | Expression::BigIntegerLiteral(_)
+                    | Expression::FloatLiteral(_)
+            ) =>
+            {
+                format!("$mul({}, {})", expression(left), expression(right))
+            }
+            // Strings and characters can equal each other.
+            "==" | "!="
+                if (!is_scalar(left) && !is_scalar(right)) || is_text(left) || is_text(right) =>
+            {
+                let negation = if operator == "!=" { "!" } else { "" };
+                format!(
+                    "{}$equal({}, {})",
+                    negation,
+                    expression(left),
+                    expression(right)
+                )
+            }
+            "==" => format!("({} === {})", expression(left), expression(right)),
+            "!=" => format!("({} !== {})", expression(left), expression(right)),
+            ".." => format!("new $Range({}, {})", expression(left), expression(right)),
+            _ => format!("({} {} {})", expression(left), operator, expression(right)),
+        },
+        Expression::If {
+            condition,
+            consequence,
+            alternative,
+        } => {
+            let single = |branch: &BlockStatement| match branch.statements() {
+                [Statement::Expr(expr)] => Some(expression(expr)),
+                [] => Some("null".into()),
+                _ => None,
+            };
+            let alt = match alternative {
+                Some(alt) => single(alt),
+                None => Some("null".into()),
+            };
+            match (single(consequence), alt) {
+                (Some(cons), Some(alt)) => {
+                    format!("($truthy({}) ? {} : {})", expression(condition), cons, alt)
+                }
+                _ => {
+                    let stmt = statement(&Statement::Expr(expr.clone()), Mode::Tail);
+                    let body = stmt.replace('\n', "\n    ");
+                    format!("(() => {{\n    {}\n}})()", body)
+                }
+            }
+        }
+        Expression::Match { value, arms } => {
+            let mut body = String::new();
+            for (pattern, arm) in arms {
+                let arm = scoped_block(arm, Mode::Tail);
+                match pattern {
+                    Pattern::Literal(literal) if is_text(literal) => body.push_str(&format!(
+                        "if ($equal($match, {})) {}\n",
+                        expression(literal),
+                        arm
+                    )),
+                    Pattern::Literal(literal) => body.push_str(&format!(
+                        "if ($match === {}) {}\n",
+                        expression(literal),
+                        arm
+                    )),
+                    Pattern::Wildcard => body.push_str(&format!("{}\n", arm)),
+                }
+            }
+            body.push_str("return null;");
+            format!(
+                "(($match) => {{\n    {}\n}})({})",
+                body.replace('\n', "\n    "),
+                expression(value)
+            )
+        }
+        // A function keeps the `var`s of the block from leaking out.
+        Expression::Block(body) => format!("(() => {})()", block(body, Mode::Tail)),
+        Expression::While { .. }
+        | Expression::DoWhile { .. }
+        | Expression::Loop { .. }
+        | Expression::For { .. }
+        | Expression::Try { .. } => {
+            let stmt = statement(&Statement::Expr(expr.clone()), Mode::Tail);
+            let body = stmt.replace('\n', "\n    ");
+            format!("(() => {{\n    {}\n}})()", body)
+        }
+        Expression::FunctionLiteral {
+            parameters,
+            rest,
+            body,
+            generator,
+        } => {
+            let params: Vec<_> = parameters
+                .iter()
+                .map(|param| param.to_string())
+                .chain(rest.iter().map(|rest| format!("...{}", rest)))
+                .collect();
+            match generator {
+                true => format!(
+                    "(function* ({}) {})",
+                    params.join(", "),
+                    block(body, Mode::Discard)
+                ),
+                false => format!("(({}) => {})", params.join(", "), block(body, Mode::Tail)),
+            }
+        }
+        Expression::Call {
+            function,
+            arguments,
+            ..
+        } if matches!(function.as_ref(), Expression::Ident(ident) if ident.value() == "load_example")
+            && arguments.len() == 1 =>
+        {
+            format!("eval($example({}))", expression(&arguments[0]))
+        }
+        Expression::Call {
+            function,
+            arguments,
+            ..
+        } => {
+            let args: Vec<_> = arguments.iter().map(expression).collect();
+            format!("{}({})", expression(function), args.join(", "))
+        }
+        Expression::Spread(value) => format!("...$iter({})", expression(value)),
+        Expression::ArrayLiteral(elements) => {
+            let elements: Vec<_> = elements.iter().map(expression).collect();
+            format!("[{}]", elements.join(", "))
+        }
+        Expression::IndexExpr {
+            left,
+            index,
+            optional: false,
+        } => {
+            format!("$index({}, {})", expression(left), expression(index))
+        }
+        Expression::IndexExpr {
+            left,
+            index,
+            optional: true,
+        } => {
+            format!(
+                "$optional({}, () => {})",
+                expression(left),
+                expression(index)
+            )
+        }
+        Expression::Field {
+            left,
+            name,
+            optional: false,
+        } => {
+            format!("$index({}, {:?})", expression(left), name.value())
+        }
+        Expression::Field {
+            left,
+            name,
+            optional: true,
+        } => {
+            format!("$optional({}, () => {:?})", expression(left), name.value())
+        }
+        Expression::Slice { left, start, end } => {
+            let bound = |bound: &Option<Box<Expression>>| match bound {
+                Some(bound) => expression(bound),
+                None => "null".into(),
+            };
+            format!(
+                "$slice({}, {}, {})",
+                expression(left),
+                bound(start),
+                bound(end)
+            )
+        }
+        Expression::HashLiteral(pairs) => {
+            let pairs: Vec<_> = pairs
+                .iter()
+                .map(|(key, val)| format!("[{}, {}]", expression(key), expression(val)))
+                .collect();
+            format!("new Map([{}])", pairs.join(", "))
+        }
+        Expression::Assign { name, value, .. } => format!("({} = {})", name, expression(value)),
+        Expression::IndexAssign { target, value, .. } => {
+            format!(
+                "(($value) => {{\n    {};\n    return $value;\n}})({})",
+                index_assignment(target, "$value".into()),
+                expression(value)
+            )
+        }
+    }
+}
+
+/// Rebinds the variable of `target`, possibly nested like `a[i].name`, to a
+/// copy with the element replaced by `value`, as Monkey arrays and hashes are
+/// values.
+fn index_assignment(target: &Expression, value: String) -> String {
+    match target {
+        Expression::IndexExpr { left, index, .. } => index_assignment(
+            left,
+            format!(
+                "$set({}, {}, {})",
+                expression(left),
+                expression(index),
+                value
+            ),
+        ),
+        Expression::Field { left, name, .. } => index_assignment(
+            left,
+            format!("$set({}, {:?}, {})", expression(left), name.value(), value),
+        ),
+        target => format!("{} = {}", expression(target), value),
+    }
+}
+
+/// Whether `expr` is a literal for which `===` gives the same result as the
+/// structural `==` of Monkey.
+fn is_scalar(expr: &Expression) -> bool {
+    matches!(
+        expr,
+        Expression::IntegerLiteral(_)
+            | Expression::BigIntegerLiteral(_)
+            | Expression::FloatLiteral(_)
+            | Expression::Boolean(_)
+            | Expression::Null
+            | Expression::StringLiteral(_)
+            | Expression::CharLiteral(_)
+    )
+}
+
+fn is_text(expr: &Expression) -> bool {
+    matches!(
+        expr,
+        Expression::StringLiteral(_) | Expression::CharLiteral(_)
+    )
+}
+
+fn quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser::Parser};
+
+    fn transpile(input: &str) -> String {
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().0;
+        to_javascript(&program)
+            .strip_prefix(&format!("{}\n{}", RUNTIME, library()))
+            .unwrap()
+            .trim()
+            .to_string()
+    }
+
+    #[test]
+    fn test_modulo() {
+        assert_eq!(transpile("puts(x % 2);"), "puts($mod(x, 2));");
+    }
+
+    #[test]
+    fn test_closures() {
+        let input = "
+let newAdder = fn(x) { fn(y) { x + y } };
+let addTwo = newAdder(2);
+puts(addTwo(3) / 2);";
+        assert_eq!(
+            transpile(input),
+            "var newAdder = ((x) => {
+    return ((y) => {
+        return $add(x, y);
+    });
+});
+var addTwo = newAdder(2);
+puts($div(addTwo(3), 2));"
+        );
+    }
+
+    #[test]
+    fn test_if_expressions() {
+        assert_eq!(
+            transpile("let x = if (a == 1) { \"one\" } else { 2 };"),
+            "var x = ($truthy((a === 1)) ? \"one\" : 2);"
+        );
+        assert_eq!(
+            transpile("let f = fn(n) { if (n < 1) { return 0; } n }"),
+            "var f = ((n) => {
+    if ($truthy((n < 1))) {
+        return 0;
+    }
+    return n;
+});"
+        );
+        assert_eq!(
+            transpile("let f = fn(n) { if (!n) { let y = 1; y } }"),
+            "var f = ((n) => {
+    if ($truthy(!$truthy(n))) {
+        let y = 1;
+        return y;
+    } else {
+        return null;
+    }
+});"
+        );
+    }
+
+    #[test]
+    fn test_while_loops() {
+        assert_eq!(
+            transpile("let i = 0; while (i < 3) { i += 1; if (i == 2) { continue; } break; }"),
+            "var i = 0;
+while ($truthy((i < 3))) {
+    (i = (i + 1));
+    if ($truthy((i === 2))) {
+        continue;
+    }
+    break;
+}"
+        );
+        assert_eq!(
+            transpile("let f = fn() { while (false) {} }"),
+            "var f = (() => {
+    while ($truthy(false)) {}
+    return null;
+});"
+        );
+    }
+
+    #[test]
+    fn test_do_while_loops() {
+        assert_eq!(
+            transpile("do { i += 1; } while (i < 3);"),
+            "do {
+    (i = (i + 1));
+} while ($truthy((i < 3)));"
+        );
+    }
+
+    #[test]
+    fn test_equality() {
+        assert_eq!(
+            transpile("a == [1]; a != b; a == 1;"),
+            "$equal(a, [1]);\n!$equal(a, b);\n(a === 1);"
+        );
+        assert_eq!(transpile("c == 'é';"), "$equal(c, $char(233));");
+        assert_eq!(transpile("s != \"a\";"), "!$equal(s, \"a\");");
+    }
+
+    #[test]
+    fn test_repetition() {
+        assert_eq!(
+            transpile("\"ab\" * n; 2 * x;"),
+            "$mul(\"ab\", n);\n(2 * x);"
+        );
+    }
+
+    #[test]
+    fn test_array_concatenation() {
+        assert_eq!(transpile("a + [1]; a + 1;"), "$add(a, [1]);\n(a + 1);");
+    }
+
+    #[test]
+    fn test_index_assignment() {
+        assert_eq!(
+            transpile("a[i][0] = 1; h.k = 3; let x = a[0] = 2;"),
+            "a = $set(a, i, $set($index(a, i), 0, 1));
+h = $set(h, \"k\", 3);
+var x = (($value) => {
+    a = $set(a, 0, $value);
+    return $value;
+})(2);"
+        );
+    }
+
+    #[test]
+    fn test_break_values() {
+        assert_eq!(
+            transpile("let f = fn() { loop { if (x) { break 1; } } }; loop { break; }"),
+            "var f = (() => {
+    var $broken = null;
+    while (true) {
+        if ($truthy(x)) {
+            $broken = 1;
+            break;
+        }
+    }
+    return $broken;
+});
+while (true) {
+    break;
+}"
+        );
+        assert_eq!(
+            transpile("for (x in xs) { break x; }"),
+            "for (let x of $iter(xs)) {
+    $broken = x;
+    break;
+}
+$broken = null;"
+        );
+    }
+
+    #[test]
+    fn test_for_loops() {
+        assert_eq!(
+            transpile("for (i in 0..n + 1) { puts((1..3)[i]); }"),
+            "for (let i of $iter(new $Range(0, (n + 1)))) {
+    puts($index(new $Range(1, 3), i));
+}"
+        );
+        assert_eq!(
+            transpile("let f = fn(xs) { for (x in xs) { x } }"),
+            "var f = ((xs) => {
+    for (let x of $iter(xs)) {
+        x;
+    }
+    return null;
+});"
+        );
+    }
+
+    #[test]
+    fn test_spread() {
+        assert_eq!(
+            transpile("f(..[..a, 1]);"),
+            "f(...$iter([...$iter(a), 1]));"
+        );
+    }
+
+    #[test]
+    fn test_destructuring() {
+        assert_eq!(
+            transpile("let [a, b] = [1];"),
+            "var [a = null, b = null] = [1];"
+        );
+        assert_eq!(
+            transpile("let {name, age: a} = person;"),
+            "var [name = null, a = null] = $fields(person, [\"name\", \"age\"]);"
+        );
+    }
+
+    #[test]
+    fn test_try_catch() {
+        assert_eq!(
+            transpile("let x = try { throw 1; } catch (e) { e + 1 };"),
+            "var x = (() => {
+    try {
+        throw 1;
+    } catch ($error) {
+        let e = $caught($error);
+        return (e + 1);
+    }
+})();"
+        );
+        assert_eq!(
+            transpile("try { f() } catch (e) {}"),
+            "try {
+    f();
+} catch ($error) {
+    let e = $caught($error);
+}"
+        );
+    }
+
+    #[test]
+    fn test_block_scoping() {
+        assert_eq!(
+            transpile("if (x) { let y = 1; let y = y + 1; let [y, z] = [y]; }"),
+            "if ($truthy(x)) {
+    let y = 1;
+    y = (y + 1);
+    let z;
+    [y = null, z = null] = [y];
+}"
+        );
+        assert_eq!(
+            transpile("let y = { let t = 2; t * t };"),
+            "var y = (() => {
+    var t = 2;
+    return $mul(t, t);
+})();"
+        );
+    }
+
+    #[test]
+    fn test_generators() {
+        assert_eq!(
+            transpile("let g = fn*(n) { yield n; };"),
+            "var g = (function* (n) {
+    yield n;
+});"
+        );
+    }
+
+    #[test]
+    fn test_match_expressions() {
+        assert_eq!(
+            transpile(r#"let s = match x { 1 => "one", "a" => 2, _ => { puts(x); "many" } };"#),
+            r#"var s = (($match) => {
+    if ($match === 1) {
+        return "one";
+    }
+    if ($equal($match, "a")) {
+        return 2;
+    }
+    {
+        puts(x);
+        return "many";
+    }
+    return null;
+})(x);"#
+        );
+    }
+
+    #[test]
+    fn test_comments() {
+        assert_eq!(
+            transpile("/// Doubles x.\nlet double = fn(x) {\n// twice\nx * 2 };\n// done"),
+            "/// Doubles x.
+var double = ((x) => {
+    // twice
+    return $mul(x, 2);
+});
+// done"
+        );
+    }
+
+    #[test]
+    fn test_collections() {
+        assert_eq!(
+            transpile(r#"let h = {"a\n": [1, 2][0], 2: true}; h["a\n"]; "héllo"[1:];"#),
+            r#"var h = new Map([["a\n", $index([1, 2], 0)], [2, true]]);
+$index(h, "a\n");
+$slice("héllo", 1, null);"#
+        );
+        assert_eq!(transpile(r#"delete(h, "a");"#), r#"$delete(h, "a");"#);
+    }
+}
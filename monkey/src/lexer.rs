@@ -1,26 +1,57 @@
-use crate::token::{Span, Token, TokenKind};
+use std::{cell::OnceCell, sync::Arc};
+
+use crate::token::{Comment, Span, Token, TokenKind};
 
 pub struct Lexer<'a> {
     input: &'a str,
+    /// A shared copy of `input` for diagnostics, made on the first error.
+    source: OnceCell<Arc<str>>,
+    /// The byte offset of `ch`, so that spans can label the source directly.
     position: usize,
     read_position: usize,
     ch: Option<char>,
+    errors: Vec<miette::Report>,
+    comments: Vec<Comment>,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
         let mut lexer = Self {
             input,
+            source: OnceCell::new(),
             position: 0,
             read_position: 0,
             ch: None,
+            errors: Vec::new(),
+            comments: Vec::new(),
         };
         lexer.read_char();
         lexer
     }
 
-    pub fn source_code(&self) -> &str {
-        self.input
+    /// The input to attach to diagnostics, copied only once however many
+    /// errors there are.
+    pub fn source_code(&self) -> Arc<str> {
+        Arc::clone(self.source.get_or_init(|| Arc::from(self.input)))
+    }
+
+    /// Returns the errors encountered while lexing so far, e.g. invalid
+    /// escape sequences in string literals.
+    pub fn take_errors(&mut self) -> Vec<miette::Report> {
+        std::mem::take(&mut self.errors)
+    }
+
+    fn error(&mut self, span: Span, help: &str, message: String) {
+        self.errors.push(
+            miette::miette!(
+                severity = miette::Severity::Error,
+                labels = vec![miette::LabeledSpan::at(span.start..span.end + 1, "here")],
+                help = help,
+                "{}",
+                message
+            )
+            .with_source_code(self.source_code()),
+        );
     }
 
     fn skip_whitespace(&mut self) {
@@ -29,24 +60,59 @@ impl<'a> Lexer<'a> {
         }
     }
 
-    fn read_char(&mut self) {
-        let input_len = self.input.chars().count();
-        if self.read_position >= input_len {
-            self.ch = None;
-        } else {
-            self.ch = self.input.chars().nth(self.read_position);
+    /// Skips whitespace and collects comments until the start of the next token.
+    fn skip_trivia(&mut self) {
+        loop {
+            self.skip_whitespace();
+            if self.ch == Some('/') && self.peek_char() == Some('/') {
+                self.read_comment();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn read_comment(&mut self) {
+        let start = self.position;
+        let mut text = String::new();
+        while let Some(c) = self.ch.filter(|c| *c != '\n') {
+            text.push(c);
+            self.read_char();
         }
+        self.comments.push(Comment {
+            text,
+            span: Span {
+                start,
+                end: self.position - 1,
+            },
+        });
+    }
+
+    fn read_char(&mut self) {
         self.position = self.read_position;
-        self.read_position += 1;
+        self.ch = self.peek_char();
+        self.read_position += self.ch.map_or(1, char::len_utf8);
     }
 
     fn peek_char(&self) -> Option<char> {
-        self.input.chars().nth(self.read_position)
+        self.input.get(self.read_position..)?.chars().next()
+    }
+
+    /// The offset of the last byte of `ch`, the inclusive end of a span
+    /// ending with it.
+    fn char_end(&self) -> usize {
+        self.position + self.ch.map_or(1, char::len_utf8) - 1
     }
 
     pub fn next_token(&mut self) -> Token {
-        self.skip_whitespace();
+        self.skip_trivia();
+        let comments = std::mem::take(&mut self.comments);
+        let mut token = self.read_token();
+        token.comments = comments;
+        token
+    }
 
+    fn read_token(&mut self) -> Token {
         let token = match self.ch {
             Some('=') if self.peek_char() == Some('=') => {
                 let start = self.position;
@@ -54,8 +120,26 @@ impl<'a> Lexer<'a> {
                 let end = self.position;
                 Token::new(TokenKind::Equal, start, end)
             }
+            Some('=') if self.peek_char() == Some('>') => {
+                let start = self.position;
+                self.read_char();
+                let end = self.position;
+                Token::new(TokenKind::FatArrow, start, end)
+            }
             Some('=') => Token::new(TokenKind::Assign, self.position, self.position),
+            Some('+') if self.peek_char() == Some('=') => {
+                let start = self.position;
+                self.read_char();
+                let end = self.position;
+                Token::new(TokenKind::PlusAssign, start, end)
+            }
             Some('+') => Token::new(TokenKind::Plus, self.position, self.position),
+            Some('-') if self.peek_char() == Some('=') => {
+                let start = self.position;
+                self.read_char();
+                let end = self.position;
+                Token::new(TokenKind::MinusAssign, start, end)
+            }
             Some('-') => Token::new(TokenKind::Minus, self.position, self.position),
             Some('!') if self.peek_char() == Some('=') => {
                 let start = self.position;
@@ -64,8 +148,21 @@ impl<'a> Lexer<'a> {
                 Token::new(TokenKind::NotEqual, start, end)
             }
             Some('!') => Token::new(TokenKind::Bang, self.position, self.position),
+            Some('/') if self.peek_char() == Some('=') => {
+                let start = self.position;
+                self.read_char();
+                let end = self.position;
+                Token::new(TokenKind::SlashAssign, start, end)
+            }
             Some('/') => Token::new(TokenKind::Slash, self.position, self.position),
+            Some('*') if self.peek_char() == Some('=') => {
+                let start = self.position;
+                self.read_char();
+                let end = self.position;
+                Token::new(TokenKind::AsteriskAssign, start, end)
+            }
             Some('*') => Token::new(TokenKind::Asterisk, self.position, self.position),
+            Some('%') => Token::new(TokenKind::Percent, self.position, self.position),
             Some('<') => Token::new(TokenKind::LessThan, self.position, self.position),
             Some('>') => Token::new(TokenKind::GreaterThan, self.position, self.position),
             Some(';') => Token::new(TokenKind::Semicolon, self.position, self.position),
@@ -77,25 +174,62 @@ impl<'a> Lexer<'a> {
             Some('[') => Token::new(TokenKind::LBracket, self.position, self.position),
             Some(']') => Token::new(TokenKind::RBracket, self.position, self.position),
             Some(':') => Token::new(TokenKind::Colon, self.position, self.position),
+            Some('.') if self.peek_char() == Some('.') => {
+                let start = self.position;
+                self.read_char();
+                let end = self.position;
+                Token::new(TokenKind::DotDot, start, end)
+            }
+            Some('.') => Token::new(TokenKind::Dot, self.position, self.position),
+            Some('?') if self.peek_char() == Some('.') => {
+                let start = self.position;
+                self.read_char();
+                let end = self.position;
+                Token::new(TokenKind::QuestionDot, start, end)
+            }
+            Some('?') if self.peek_char() == Some('?') => {
+                let start = self.position;
+                self.read_char();
+                let end = self.position;
+                Token::new(TokenKind::Coalesce, start, end)
+            }
+            Some('|') if self.peek_char() == Some('>') => {
+                let start = self.position;
+                self.read_char();
+                let end = self.position;
+                Token::new(TokenKind::Pipe, start, end)
+            }
+            Some('|') => Token::new(TokenKind::Bar, self.position, self.position),
             Some('"') => {
                 let (literal, span) = self.read_string();
                 let token_kind = TokenKind::String(literal);
                 Token::new(token_kind, span.start, span.end)
             }
-            Some(c) if is_letter(c) => {
+            Some('\'') => {
+                let (literal, span) = self.read_char_literal();
+                Token::new(TokenKind::Char(literal), span.start, span.end)
+            }
+            Some('b') if self.peek_char() == Some('"') => {
+                let (literal, span) = self.read_bytes();
+                Token::new(TokenKind::Bytes(literal), span.start, span.end)
+            }
+            Some(c) if is_identifier_start(c) => {
                 let (ident, span) = self.read_identfier();
                 let token_kind = TokenKind::Ident(ident).lookup_ident();
                 return Token::new(token_kind, span.start, span.end);
             }
             Some(c) if is_digit(c) => {
                 let (number, span) = self.read_number();
-                let token_kind = TokenKind::Int(number);
-                return Token {
-                    kind: token_kind,
-                    span,
+                let is_float = !matches!(number.get(..2), Some("0x" | "0o" | "0b"))
+                    && number.contains(['.', 'e', 'E']);
+                let token_kind = if is_float {
+                    TokenKind::Float(number)
+                } else {
+                    TokenKind::Int(number)
                 };
+                return Token::new(token_kind, span.start, span.end);
             }
-            Some(_) => Token::new(TokenKind::Illegal, self.position, self.position),
+            Some(c) => Token::new(TokenKind::Illegal(c), self.position, self.char_end()),
             None => Token::new(TokenKind::Eof, self.position, self.position),
         };
 
@@ -106,11 +240,13 @@ impl<'a> Lexer<'a> {
 
     fn read_identfier(&mut self) -> (String, Span) {
         let current_position = self.position;
-        while self.ch.is_some_and(is_letter) {
+        let mut literal = String::new();
+        while let Some(c) = self.ch.filter(|c| is_identifier_continue(*c)) {
+            literal.push(c);
             self.read_char();
         }
         (
-            self.input[current_position..self.position].to_string(),
+            literal,
             Span {
                 start: current_position,
                 end: self.position - 1,
@@ -120,11 +256,49 @@ impl<'a> Lexer<'a> {
 
     fn read_number(&mut self) -> (String, Span) {
         let current_position = self.position;
-        while self.ch.is_some_and(is_digit) {
+        let mut literal = String::new();
+        // After a radix prefix like `0x`, all letters and digits belong to the
+        // literal, so that the parser can point at invalid digits.
+        let has_prefix = self.ch == Some('0') && matches!(self.peek_char(), Some('x' | 'o' | 'b'));
+        let is_part: fn(char) -> bool = if has_prefix {
+            literal.push('0');
+            self.read_char();
+            |c| c.is_ascii_alphanumeric()
+        } else {
+            is_digit
+        };
+        while let Some(c) = self.ch.filter(|c| is_part(*c)) {
+            literal.push(c);
             self.read_char();
         }
+        // A fraction makes it a float, while `1..2` stays a range.
+        if !has_prefix && self.ch == Some('.') && self.peek_char().is_some_and(is_digit) {
+            literal.push('.');
+            self.read_char();
+            while let Some(c) = self.ch.filter(|c| is_digit(*c)) {
+                literal.push(c);
+                self.read_char();
+            }
+        }
+        // So does an exponent, as in `1e20` or `2.5e-7`, the form floats
+        // too large or small for plain digits print in.
+        if !has_prefix && matches!(self.ch, Some('e' | 'E')) {
+            let rest = self.input.get(self.read_position..).unwrap_or_default();
+            let sign_len = usize::from(rest.starts_with(['+', '-']));
+            if rest[sign_len..].starts_with(is_digit) {
+                // The `e` and the sign, if any.
+                for _ in 0..=sign_len {
+                    literal.extend(self.ch);
+                    self.read_char();
+                }
+                while let Some(c) = self.ch.filter(|c| is_digit(*c)) {
+                    literal.push(c);
+                    self.read_char();
+                }
+            }
+        }
         (
-            self.input[current_position..self.position].to_string(),
+            literal,
             Span {
                 start: current_position,
                 end: self.position - 1,
@@ -133,25 +307,253 @@ impl<'a> Lexer<'a> {
     }
 
     fn read_string(&mut self) -> (String, Span) {
-        let current_position = self.position + 1;
+        let start = self.position;
+        let mut literal = String::new();
         loop {
             self.read_char();
-            if self.ch.is_some_and(|c| c == '"') {
-                break;
+            match self.ch {
+                None | Some('"') => break,
+                Some('\\') => {
+                    if !self.read_escape(&mut literal) {
+                        break;
+                    }
+                }
+                Some(c) => literal.push(c),
             }
         }
         (
-            self.input[current_position..self.position].to_string(),
+            literal,
             Span {
-                start: current_position - 1,
+                start,
                 end: self.position,
             },
         )
     }
+
+    /// Reads a literal like `b"GIF\x00"`, leaving the lexer on the closing
+    /// quote. Besides the escapes of strings, `\xNN` stands for any byte,
+    /// and other characters are encoded as UTF-8.
+    fn read_bytes(&mut self) -> (Vec<u8>, Span) {
+        let start = self.position;
+        self.read_char();
+        let mut literal = Vec::new();
+        loop {
+            self.read_char();
+            match self.ch {
+                None | Some('"') => break,
+                Some('\\') if self.peek_char() == Some('x') => {
+                    let escape_start = self.position;
+                    self.read_char();
+                    let mut digits = String::new();
+                    while let Some(c) = self.peek_char().filter(char::is_ascii_hexdigit) {
+                        if digits.len() == 2 {
+                            break;
+                        }
+                        digits.push(c);
+                        self.read_char();
+                    }
+                    match u8::from_str_radix(&digits, 16) {
+                        Ok(byte) if digits.len() == 2 => literal.push(byte),
+                        _ => {
+                            let span = Span {
+                                start: escape_start,
+                                end: self.char_end(),
+                            };
+                            self.error(
+                                span,
+                                "Use `\\x` with exactly 2 hex digits, e.g. `\\x0a`",
+                                "Invalid byte escape".into(),
+                            );
+                        }
+                    }
+                }
+                Some('\\') => {
+                    let mut escaped = String::new();
+                    let more = self.read_escape(&mut escaped);
+                    literal.extend(escaped.bytes());
+                    if !more {
+                        break;
+                    }
+                }
+                Some(c) => literal.extend(c.encode_utf8(&mut [0; 4]).bytes()),
+            }
+        }
+        (
+            literal,
+            Span {
+                start,
+                end: self.position,
+            },
+        )
+    }
+
+    /// Reads a literal like `'a'` or `'\n'`, leaving the lexer on the closing
+    /// quote. Anything but exactly one character between the quotes is an
+    /// error.
+    fn read_char_literal(&mut self) -> (char, Span) {
+        let start = self.position;
+        let mut literal = String::new();
+        loop {
+            self.read_char();
+            match self.ch {
+                None | Some('\'' | '\n') => break,
+                Some('\\') => {
+                    if !self.read_escape(&mut literal) {
+                        break;
+                    }
+                }
+                Some(c) => literal.push(c),
+            }
+        }
+        let span = Span {
+            start,
+            end: self.position,
+        };
+
+        let mut chars = literal.chars();
+        match (chars.next(), chars.next()) {
+            _ if self.ch != Some('\'') => self.error(
+                span,
+                "Close the character with `'`",
+                "Unterminated character literal".into(),
+            ),
+            (Some(_), None) => {}
+            (None, _) => self.error(
+                span,
+                "Write a character between the quotes, e.g. `'a'`",
+                "Empty character literal".into(),
+            ),
+            (Some(_), Some(_)) => self.error(
+                span,
+                "Use double quotes for strings, e.g. `\"ab\"`",
+                "A character literal holds exactly one character".into(),
+            ),
+        }
+        // The parser goes on with the first character after an error.
+        (
+            literal
+                .chars()
+                .next()
+                .unwrap_or(char::REPLACEMENT_CHARACTER),
+            span,
+        )
+    }
+
+    /// Reads the escape sequence started by the backslash the lexer is on into
+    /// `literal`, leaving the lexer on its last character. Returns false at
+    /// the end of input.
+    fn read_escape(&mut self, literal: &mut String) -> bool {
+        let escape_start = self.position;
+        self.read_char();
+        match self.ch {
+            Some('n') => literal.push('\n'),
+            Some('t') => literal.push('\t'),
+            Some('\\') => literal.push('\\'),
+            Some('"') => literal.push('"'),
+            Some('\'') => literal.push('\''),
+            Some('u') => match self.read_unicode_escape() {
+                Some(c) => literal.push(c),
+                None => {
+                    let span = Span {
+                        start: escape_start,
+                        end: self.char_end(),
+                    };
+                    self.error(
+                        span,
+                        "Use `\\u{...}` with 1 to 6 hex digits of a valid code point",
+                        "Invalid unicode escape".into(),
+                    );
+                }
+            },
+            Some(c) => {
+                let span = Span {
+                    start: escape_start,
+                    end: self.char_end(),
+                };
+                self.error(
+                    span,
+                    "Valid escapes are `\\n`, `\\t`, `\\\\`, `\\\"`, `\\'` and `\\u{...}`",
+                    format!("Invalid escape sequence `\\{}`", c),
+                );
+                literal.push('\\');
+                literal.push(c);
+            }
+            None => return false,
+        }
+        true
+    }
+
+    /// Reads the `{...}` part of a `\u{...}` escape, leaving the lexer on the
+    /// closing brace.
+    fn read_unicode_escape(&mut self) -> Option<char> {
+        if self.peek_char() != Some('{') {
+            return None;
+        }
+        self.read_char();
+
+        let mut digits = String::new();
+        while let Some(c) = self.peek_char().filter(char::is_ascii_hexdigit) {
+            digits.push(c);
+            self.read_char();
+        }
+
+        if self.peek_char() != Some('}') || digits.len() > 6 {
+            return None;
+        }
+        self.read_char();
+
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    /// Yields tokens until the end of input, without the final `Eof` token.
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = self.next_token();
+        (token.kind != TokenKind::Eof).then_some(token)
+    }
+}
+
+/// Lexes `input` and returns every token whose span does not slice back to
+/// exactly its lexeme, together with the text the span actually covers.
+pub fn verify_spans(input: &str) -> Vec<(Token, String)> {
+    Lexer::new(input)
+        .filter_map(|token| {
+            let Span { start, end } = token.span;
+            // An unterminated string ends past the input.
+            let end = (end + 1).min(input.len());
+            let text = input.get(start..end).unwrap_or_default().to_string();
+            let matches = match &token.kind {
+                // Escapes can be spelled in several ways, so check that the
+                // covered text lexes back to the same string instead.
+                TokenKind::String(_) => {
+                    text.ends_with('"') && Lexer::new(&text).next_token().kind == token.kind
+                }
+                TokenKind::Bytes(_) => {
+                    text.ends_with('"') && Lexer::new(&text).next_token().kind == token.kind
+                }
+                TokenKind::Char(_) => {
+                    text.ends_with('\'') && Lexer::new(&text).next_token().kind == token.kind
+                }
+                kind => text == kind.to_string(),
+            };
+            (!matches).then_some((token, text))
+        })
+        .collect()
+}
+
+/// Identifiers follow the Unicode rules also used by Rust, e.g. `café` or
+/// `x1`, plus a leading `_`.
+fn is_identifier_start(character: char) -> bool {
+    unicode_ident::is_xid_start(character) || character == '_'
 }
 
-fn is_letter(character: char) -> bool {
-    character.is_ascii_alphabetic() || character == '_'
+fn is_identifier_continue(character: char) -> bool {
+    unicode_ident::is_xid_continue(character)
 }
 
 fn is_digit(character: char) -> bool {
@@ -384,19 +786,392 @@ if (5 < 10) {
             Token::new(TokenKind::String("foo bar".into()), 201, 209)
         );
         // [1, 2];
-        assert_eq!(lexer.next_token(), Token::new(TokenKind::LBracket, 211, 211));
-        assert_eq!(lexer.next_token(), Token::new(TokenKind::Int("1".into()), 212, 212));
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::LBracket, 211, 211)
+        );
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::Int("1".into()), 212, 212)
+        );
         assert_eq!(lexer.next_token(), Token::new(TokenKind::Comma, 213, 213));
-        assert_eq!(lexer.next_token(), Token::new(TokenKind::Int("2".into()), 215, 215));
-        assert_eq!(lexer.next_token(), Token::new(TokenKind::RBracket, 216, 216));
-        assert_eq!(lexer.next_token(), Token::new(TokenKind::Semicolon, 217, 217));
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::Int("2".into()), 215, 215)
+        );
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::RBracket, 216, 216)
+        );
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::Semicolon, 217, 217)
+        );
         // {"foo": "bar"}
         assert_eq!(lexer.next_token(), Token::new(TokenKind::LBrace, 219, 219));
-        assert_eq!(lexer.next_token(), Token::new(TokenKind::String("foo".into()), 220, 224));
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::String("foo".into()), 220, 224)
+        );
         assert_eq!(lexer.next_token(), Token::new(TokenKind::Colon, 225, 225));
-        assert_eq!(lexer.next_token(), Token::new(TokenKind::String("bar".into()), 227, 231));
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::String("bar".into()), 227, 231)
+        );
         assert_eq!(lexer.next_token(), Token::new(TokenKind::RBrace, 232, 232));
         //
         assert_eq!(lexer.next_token(), Token::new(TokenKind::Eof, 234, 234));
     }
+
+    #[test]
+    fn test_compound_assignment_tokens() {
+        let mut lexer = Lexer::new("x += 1 -= *= /=");
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::Ident("x".into()), 0, 0)
+        );
+        assert_eq!(lexer.next_token(), Token::new(TokenKind::PlusAssign, 2, 3));
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::Int("1".into()), 5, 5)
+        );
+        assert_eq!(lexer.next_token(), Token::new(TokenKind::MinusAssign, 7, 8));
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::AsteriskAssign, 10, 11)
+        );
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::SlashAssign, 13, 14)
+        );
+        assert_eq!(lexer.next_token(), Token::new(TokenKind::Eof, 15, 15));
+    }
+
+    #[test]
+    fn test_token_spans_slice_back_to_lexemes() {
+        let input = r#"let add = fn(x, y) { x + y; };
+!-/*5; 5 < 10 > 5; 10 == 10; 10 != 9;
+x += 1; x -= 1; x *= 2; x /= 2;
+if (true) { return false; } else { "foo bar" }
+[1, 2][0]; {"foo": "bar"}
+"#;
+        assert_eq!(verify_spans(input), vec![]);
+    }
+
+    #[test]
+    fn test_unicode_identifiers() {
+        let input = "let café = 1; _x2 + Ünïcödé";
+        let kinds: Vec<_> = Lexer::new(input).map(|t| t.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Let,
+                TokenKind::Ident("café".into()),
+                TokenKind::Assign,
+                TokenKind::Int("1".into()),
+                TokenKind::Semicolon,
+                TokenKind::Ident("_x2".into()),
+                TokenKind::Plus,
+                TokenKind::Ident("Ünïcödé".into()),
+            ]
+        );
+        assert_eq!(verify_spans(input), vec![]);
+        assert_eq!(verify_spans(r#""é" ö @ "\u{e9}""#), vec![]);
+    }
+
+    #[test]
+    fn test_spans_count_bytes() {
+        let mut lexer = Lexer::new("é + ü§");
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::Ident("é".into()), 0, 1)
+        );
+        assert_eq!(lexer.next_token(), Token::new(TokenKind::Plus, 3, 3));
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::Ident("ü".into()), 5, 6)
+        );
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::Illegal('§'), 7, 8)
+        );
+    }
+
+    #[test]
+    fn test_verify_spans_reports_unterminated_string() {
+        let mismatches = verify_spans(r#"let x = "abc"#);
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].1, r#""abc"#);
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let input = r#""a\nb" "\t\\" "say \"hi\"" "\u{1F600}\u{e9}""#;
+        let mut lexer = Lexer::new(input);
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::String("a\nb".into()), 0, 5)
+        );
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::String("\t\\".into()), 7, 12)
+        );
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::String("say \"hi\"".into()), 14, 25)
+        );
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::String("\u{1F600}\u{e9}".into()), 27, 43)
+        );
+        assert_eq!(lexer.next_token(), Token::new(TokenKind::Eof, 44, 44));
+        assert!(lexer.take_errors().is_empty());
+        assert_eq!(verify_spans(input), vec![]);
+    }
+
+    #[test]
+    fn test_percent() {
+        let mut lexer = Lexer::new("7 % 2");
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::Int("7".into()), 0, 0)
+        );
+        assert_eq!(lexer.next_token(), Token::new(TokenKind::Percent, 2, 2));
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::Int("2".into()), 4, 4)
+        );
+    }
+
+    #[test]
+    fn test_pipe() {
+        let mut lexer = Lexer::new("x |> f |y");
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::Ident("x".into()), 0, 0)
+        );
+        assert_eq!(lexer.next_token(), Token::new(TokenKind::Pipe, 2, 3));
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::Ident("f".into()), 5, 5)
+        );
+        assert_eq!(lexer.next_token(), Token::new(TokenKind::Bar, 7, 7));
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::Ident("y".into()), 8, 8)
+        );
+    }
+
+    #[test]
+    fn test_question_dot() {
+        let mut lexer = Lexer::new("a?.b ?? ?");
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::Ident("a".into()), 0, 0)
+        );
+        assert_eq!(lexer.next_token(), Token::new(TokenKind::QuestionDot, 1, 2));
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::Ident("b".into()), 3, 3)
+        );
+        assert_eq!(lexer.next_token(), Token::new(TokenKind::Coalesce, 5, 6));
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::Illegal('?'), 8, 8)
+        );
+    }
+
+    #[test]
+    fn test_source_code_is_shared() {
+        let lexer = Lexer::new("let x = 1;");
+        let source = lexer.source_code();
+        assert_eq!(&*source, "let x = 1;");
+        assert!(Arc::ptr_eq(&source, &lexer.source_code()));
+    }
+
+    #[test]
+    fn test_prefixed_numbers() {
+        let input = "0xFF+0o7 0b1021 0x";
+        let mut lexer = Lexer::new(input);
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::Int("0xFF".into()), 0, 3)
+        );
+        assert_eq!(lexer.next_token(), Token::new(TokenKind::Plus, 4, 4));
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::Int("0o7".into()), 5, 7)
+        );
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::Int("0b1021".into()), 9, 14)
+        );
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::Int("0x".into()), 16, 17)
+        );
+        assert_eq!(lexer.next_token(), Token::new(TokenKind::Eof, 18, 18));
+        assert_eq!(verify_spans(input), vec![]);
+    }
+
+    #[test]
+    fn test_invalid_string_escapes() {
+        let mut lexer = Lexer::new(r#""\q" "\u{110000}" "\u41""#);
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::String("\\q".into()), 0, 3)
+        );
+        lexer.next_token();
+        lexer.next_token();
+        let errors: Vec<_> = lexer.take_errors().iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "Invalid escape sequence `\\q`",
+                "Invalid unicode escape",
+                "Invalid unicode escape",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_float_literals() {
+        let input = "2.5 1..2 0x1.5 7. x.0 1e20 2.5E-7 3e+2 4e 5e- 0xe";
+        let kinds: Vec<_> = Lexer::new(input).map(|t| t.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Float("2.5".into()),
+                TokenKind::Int("1".into()),
+                TokenKind::DotDot,
+                TokenKind::Int("2".into()),
+                TokenKind::Int("0x1".into()),
+                TokenKind::Dot,
+                TokenKind::Int("5".into()),
+                TokenKind::Int("7".into()),
+                TokenKind::Dot,
+                TokenKind::Ident("x".into()),
+                TokenKind::Dot,
+                TokenKind::Int("0".into()),
+                TokenKind::Float("1e20".into()),
+                TokenKind::Float("2.5E-7".into()),
+                TokenKind::Float("3e+2".into()),
+                TokenKind::Int("4".into()),
+                TokenKind::Ident("e".into()),
+                TokenKind::Int("5".into()),
+                TokenKind::Ident("e".into()),
+                TokenKind::Minus,
+                TokenKind::Int("0xe".into()),
+            ]
+        );
+        assert_eq!(verify_spans(input), vec![]);
+    }
+
+    #[test]
+    fn test_char_literals() {
+        let input = r#"'a' 'é' '\n' '\'' '\u{1F600}' '"' '\\'"#;
+        let mut lexer = Lexer::new(input);
+        let kinds: Vec<_> = lexer.by_ref().map(|t| t.kind).collect();
+        assert!(lexer.take_errors().is_empty());
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Char('a'),
+                TokenKind::Char('é'),
+                TokenKind::Char('\n'),
+                TokenKind::Char('\''),
+                TokenKind::Char('\u{1F600}'),
+                TokenKind::Char('"'),
+                TokenKind::Char('\\'),
+            ]
+        );
+        let mut lexer = Lexer::new(input);
+        lexer.next_token();
+        assert_eq!(lexer.next_token(), Token::new(TokenKind::Char('é'), 4, 7));
+        assert_eq!(verify_spans(input), vec![]);
+    }
+
+    #[test]
+    fn test_byte_strings() {
+        let input = r#"b"GIF\x00\xFF\n" b"é" b "x""#;
+        let mut lexer = Lexer::new(input);
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::Bytes(b"GIF\x00\xff\n".to_vec()), 0, 15)
+        );
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::Bytes("é".as_bytes().to_vec()), 17, 21)
+        );
+        assert_eq!(lexer.next_token().kind, TokenKind::Ident("b".into()));
+        assert!(lexer.take_errors().is_empty());
+        assert_eq!(verify_spans(input), vec![]);
+
+        let mut lexer = Lexer::new(r#"b"\x4" b"\xzz""#);
+        lexer.next_token();
+        lexer.next_token();
+        let errors: Vec<_> = lexer.take_errors().iter().map(|e| e.to_string()).collect();
+        assert_eq!(errors, vec!["Invalid byte escape", "Invalid byte escape"]);
+    }
+
+    #[test]
+    fn test_invalid_char_literals() {
+        let mut lexer = Lexer::new("'' 'ab' 'c\nx");
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::Char('\u{FFFD}'), 0, 1)
+        );
+        assert_eq!(lexer.next_token(), Token::new(TokenKind::Char('a'), 3, 6));
+        assert_eq!(lexer.next_token(), Token::new(TokenKind::Char('c'), 8, 10));
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::Ident("x".into()), 11, 11)
+        );
+        let errors: Vec<_> = lexer.take_errors().iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "Empty character literal",
+                "A character literal holds exactly one character",
+                "Unterminated character literal",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_comments() {
+        let input = "// first
+let x = 5; // trailing
+// last
+";
+        let mut lexer = Lexer::new(input);
+        let token = lexer.next_token();
+        assert_eq!(token.kind, TokenKind::Let);
+        assert_eq!(
+            token.comments,
+            vec![Comment {
+                text: "// first".into(),
+                span: Span { start: 0, end: 7 },
+            }]
+        );
+        lexer.next_token();
+        lexer.next_token();
+        lexer.next_token();
+        assert_eq!(lexer.next_token(), Token::new(TokenKind::Semicolon, 18, 18));
+        let token = lexer.next_token();
+        assert_eq!(token.kind, TokenKind::Eof);
+        assert_eq!(
+            token.comments,
+            vec![
+                Comment {
+                    text: "// trailing".into(),
+                    span: Span { start: 20, end: 30 },
+                },
+                Comment {
+                    text: "// last".into(),
+                    span: Span { start: 32, end: 38 },
+                },
+            ]
+        );
+    }
 }
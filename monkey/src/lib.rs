@@ -1,13 +1,46 @@
 mod ast;
 mod builtins;
+mod commands;
 mod evaluator;
+mod generator;
+mod graph;
+mod interpreter;
+mod javascript;
 mod lexer;
+mod locale;
 mod object;
 mod parser;
+mod programs;
+mod random;
+mod repl_core;
+mod scheduler;
+mod script;
 mod token;
+mod wat;
 
-pub use lexer::Lexer;
-pub use evaluator::eval;
-pub use ast::Node;
-pub use object::Environment;
-pub use parser::Parser;
+pub use ast::{Node, Program, Statement};
+pub use commands::{Command, CommandOutput, Commands, Handler};
+pub use evaluator::{eval, Event, Frame};
+pub use graph::{to_dot, to_json, to_mermaid};
+pub use interpreter::{Binding, Interpreter, Snapshot};
+pub use javascript::to_javascript;
+pub use lexer::{verify_spans, Lexer};
+pub use locale::{set_locale, FallbackLocale, Locale};
+pub use object::{Channel, Environment};
+pub use parser::{ParseOutcome, Parser};
+pub use repl_core::{LineOutput, ReplCore};
+pub use script::CompiledScript;
+pub use token::{Span, Token, TokenKind};
+pub use wat::to_wat;
+
+/// The version of the crate.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The abbreviated hash of the commit the crate was built from, or `unknown`.
+pub const GIT_HASH: &str = env!("MONKEY_GIT_HASH");
+
+/// The version and commit of this build, e.g. `0.1.0 (4ebe2b1)`, for bug
+/// reports.
+pub fn version() -> String {
+    format!("{} ({})", VERSION, GIT_HASH)
+}
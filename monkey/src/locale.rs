@@ -0,0 +1,80 @@
+//! Locale-aware string comparison and number formatting for the
+//! `locale_compare` and `format_number` builtins.
+//!
+//! Without a locale database, a fallback that ignores the locale is used.
+//! Hosts that have one, like browsers with `Intl`, install it with
+//! [`set_locale`].
+
+use std::{cell::RefCell, cmp::Ordering, rc::Rc};
+
+/// Compares and formats according to a locale, given as a BCP 47 language
+/// tag like `de-DE`, or the host's default locale for `None`. Errors are
+/// messages, e.g. for an invalid tag.
+pub trait Locale {
+    /// Compares `a` and `b` in the order a dictionary for `locale` would list
+    /// them.
+    fn compare(&self, a: &str, b: &str, locale: Option<&str>) -> Result<Ordering, String>;
+
+    /// Formats `n` with the digit grouping of `locale`, like `1,234,567`.
+    fn format_number(&self, n: isize, locale: Option<&str>) -> Result<String, String>;
+}
+
+/// Ignores the locale, comparing case-insensitively and grouping digits in
+/// threes with commas.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FallbackLocale;
+
+impl Locale for FallbackLocale {
+    fn compare(&self, a: &str, b: &str, _: Option<&str>) -> Result<Ordering, String> {
+        Ok(a.to_lowercase()
+            .cmp(&b.to_lowercase())
+            .then_with(|| a.cmp(b)))
+    }
+
+    fn format_number(&self, n: isize, _: Option<&str>) -> Result<String, String> {
+        let digits = n.unsigned_abs().to_string();
+        let mut out = String::new();
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                out.push(',');
+            }
+            out.push(digit);
+        }
+        Ok(if n < 0 { format!("-{}", out) } else { out })
+    }
+}
+
+thread_local! {
+    static LOCALE: RefCell<Rc<dyn Locale>> = RefCell::new(Rc::new(FallbackLocale));
+}
+
+/// Makes the builtins of all interpreters on this thread use `locale`.
+pub fn set_locale(locale: impl Locale + 'static) {
+    LOCALE.set(Rc::new(locale));
+}
+
+pub(crate) fn locale() -> Rc<dyn Locale> {
+    LOCALE.with_borrow(Rc::clone)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fallback() {
+        let locale = FallbackLocale;
+        let tests = [
+            (0, "0"),
+            (999, "999"),
+            (1000, "1,000"),
+            (-1234567, "-1,234,567"),
+        ];
+        for (n, expected) in tests {
+            assert_eq!(locale.format_number(n, None).unwrap(), expected);
+        }
+        assert_eq!(locale.compare("apple", "Banana", None), Ok(Ordering::Less));
+        assert_eq!(locale.compare("B", "b", Some("de")), Ok(Ordering::Less));
+        assert_eq!(locale.compare("a", "a", None), Ok(Ordering::Equal));
+    }
+}
@@ -1,51 +1,297 @@
 use core::fmt;
 use miette::Result;
-use std::{cell::RefCell, collections::HashMap, hash, rc::Rc};
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    hash,
+    rc::Rc,
+};
+use unicode_segmentation::UnicodeSegmentation;
 
-use crate::ast::{BlockStatement, Identifier};
+use crate::{
+    ast::{format_parameters, BlockStatement, Float, Identifier},
+    generator::Generator,
+    programs::base_bindings,
+    token::{escape, escape_bytes, escape_char, Span},
+};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub enum Object {
     Integer(isize),
+    /// An integer beyond the range of [`Object::Integer`], which arithmetic
+    /// switches to instead of overflowing. Integers that fit are never stored
+    /// as one, see [`integer`], so that equal integers compare equal.
+    BigInteger(BigInt),
+    Float(Float),
     Boolean(bool),
     Null,
     ReturnValue(Rc<Object>),
+    /// Unwinds to the nearest enclosing loop, which stops and evaluates to
+    /// the value.
+    Break(Rc<Object>),
+    /// Unwinds to the nearest enclosing loop, which starts its next iteration.
+    Continue,
     Function {
         parameters: Vec<Identifier>,
+        rest: Option<Identifier>,
         body: BlockStatement,
         env: Rc<RefCell<Environment>>,
+        /// Documentation from `///` comments preceding the `let` binding.
+        doc: Option<String>,
+        /// Whether it was written `fn*`, so that calling it gives a
+        /// [`Object::Generator`].
+        generator: bool,
     },
     String(String),
-    Builtin(fn(Vec<Rc<Object>>) -> Result<Rc<Object>>),
+    /// A single Unicode code point, written `'a'`.
+    Char(char),
+    /// Binary data, written `b"..."`.
+    Bytes(Vec<u8>),
+    Builtin(Builtin),
     Array(Vec<Rc<Object>>),
-    Hash(HashMap<Rc<Object>, Rc<Object>>)
+    Hash(HashMap<Rc<Object>, Rc<Object>>),
+    /// The integers from `start` up to, but excluding, `end`.
+    Range {
+        start: isize,
+        end: isize,
+    },
+    /// An error as a value, created with `error` or caught from a runtime
+    /// error.
+    Error {
+        message: String,
+        /// Where in the source the error occurred, if known.
+        span: Option<Span>,
+    },
+    /// The end of a channel that values are sent into.
+    Sender(Channel),
+    /// The end of a channel that values are received from.
+    Receiver(Channel),
+    /// The suspended body of a `fn*` function, giving a value for each
+    /// `yield`.
+    Generator(Generator),
+}
+
+/// Compares like a derived implementation, except that a [`Object::Char`]
+/// equals the [`Object::String`] of just that character, so that an element
+/// of a string matches a string literal.
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Object::Integer(a), Object::Integer(b)) => a == b,
+            (Object::BigInteger(a), Object::BigInteger(b)) => a == b,
+            (Object::Float(a), Object::Float(b)) => a == b,
+            (Object::Boolean(a), Object::Boolean(b)) => a == b,
+            (Object::Null, Object::Null) | (Object::Continue, Object::Continue) => true,
+            (Object::ReturnValue(a), Object::ReturnValue(b))
+            | (Object::Break(a), Object::Break(b)) => a == b,
+            (
+                Object::Function {
+                    parameters,
+                    rest,
+                    body,
+                    env,
+                    doc,
+                    generator,
+                },
+                Object::Function {
+                    parameters: other_parameters,
+                    rest: other_rest,
+                    body: other_body,
+                    env: other_env,
+                    doc: other_doc,
+                    generator: other_generator,
+                },
+            ) => {
+                parameters == other_parameters
+                    && rest == other_rest
+                    && body == other_body
+                    && env == other_env
+                    && doc == other_doc
+                    && generator == other_generator
+            }
+            (Object::String(a), Object::String(b)) => a == b,
+            (Object::Char(a), Object::Char(b)) => a == b,
+            (Object::Char(c), Object::String(s)) | (Object::String(s), Object::Char(c)) => {
+                let mut chars = s.chars();
+                chars.next() == Some(*c) && chars.next().is_none()
+            }
+            (Object::Bytes(a), Object::Bytes(b)) => a == b,
+            (Object::Builtin(a), Object::Builtin(b)) => a == b,
+            (Object::Array(a), Object::Array(b)) => a == b,
+            (Object::Hash(a), Object::Hash(b)) => a == b,
+            (
+                Object::Range { start, end },
+                Object::Range {
+                    start: other_start,
+                    end: other_end,
+                },
+            ) => start == other_start && end == other_end,
+            (
+                Object::Error { message, span },
+                Object::Error {
+                    message: other_message,
+                    span: other_span,
+                },
+            ) => message == other_message && span == other_span,
+            (Object::Sender(a), Object::Sender(b)) | (Object::Receiver(a), Object::Receiver(b)) => {
+                a == b
+            }
+            (Object::Generator(a), Object::Generator(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Object {}
+
+/// A queue of values between a [`Object::Sender`] and a [`Object::Receiver`],
+/// which can be handed to different interpreters on the same thread, or kept
+/// by the host to talk to a script.
+#[derive(Debug, Clone, Default)]
+pub struct Channel(Rc<RefCell<VecDeque<Rc<Object>>>>);
+
+impl Channel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The sending end, to bind in an interpreter's environment.
+    pub fn sender(&self) -> Rc<Object> {
+        Rc::new(Object::Sender(self.clone()))
+    }
+
+    /// The receiving end, to bind in an interpreter's environment.
+    pub fn receiver(&self) -> Rc<Object> {
+        Rc::new(Object::Receiver(self.clone()))
+    }
+
+    pub fn send(&self, value: Rc<Object>) {
+        self.0.borrow_mut().push_back(value);
+    }
+
+    /// The oldest value sent and not yet received, if any.
+    pub fn receive(&self) -> Option<Rc<Object>> {
+        self.0.borrow_mut().pop_front()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().is_empty()
+    }
+}
+
+/// Channels are equal if they share their queue.
+impl PartialEq for Channel {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Channel {}
+
+/// A function implemented in Rust, identified by its name.
+#[derive(Debug, Clone, Copy)]
+pub struct Builtin {
+    pub name: &'static str,
+    /// The parameters as shown to users, e.g. `value, base?`.
+    pub params: &'static str,
+    pub func: fn(Vec<Rc<Object>>) -> Result<Rc<Object>>,
+    /// Whether it is called for its effects and always returns `null`.
+    pub returns_null: bool,
+}
+
+impl Builtin {
+    pub const fn new(
+        name: &'static str,
+        params: &'static str,
+        func: fn(Vec<Rc<Object>>) -> Result<Rc<Object>>,
+    ) -> Self {
+        Self {
+            name,
+            params,
+            func,
+            returns_null: false,
+        }
+    }
+
+    /// The builtin, marked as always returning `null`.
+    pub const fn returning_null(self) -> Self {
+        Self {
+            returns_null: true,
+            ..self
+        }
+    }
+
+    /// How the builtin is called, e.g. `len(value)`.
+    pub fn signature(&self) -> String {
+        format!("{}({})", self.name, self.params)
+    }
+}
+
+impl PartialEq for Builtin {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Eq for Builtin {}
+
+impl fmt::Display for Builtin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "builtin {}", self.signature())
+    }
 }
 
 impl fmt::Display for Object {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Object::Integer(i) => write!(f, "{}", i),
+            Object::BigInteger(i) => write!(f, "{}", i),
+            Object::Float(x) => write!(f, "{}", x),
             Object::Boolean(b) => write!(f, "{}", b),
             Object::Null => write!(f, "null"),
             Object::ReturnValue(x) => write!(f, "{}", x),
+            Object::Break(_) => write!(f, "break"),
+            Object::Continue => write!(f, "continue"),
             Object::Function {
                 parameters,
+                rest,
                 body,
                 env: _,
-            } => {
-                let params: Vec<_> = parameters.iter().map(|param| param.to_string()).collect();
-                write!(f, "fn({}){{\n{}\n}}", params.join(", "), body)
-            }
+                doc: _,
+                generator,
+            } => write!(
+                f,
+                "fn{}({}) {}",
+                if *generator { "*" } else { "" },
+                format_parameters(parameters, rest.as_ref()),
+                body
+            ),
             Object::String(s) => write!(f, "{}", s),
-            Object::Builtin(_) => write!(f, "builtin function"),
+            Object::Char(c) => write!(f, "{}", c),
+            Object::Bytes(b) => write!(f, "b\"{}\"", escape_bytes(b)),
+            Object::Builtin(builtin) => write!(f, "{}", builtin),
             Object::Array(v) => {
                 let elements: Vec<_> = v.iter().map(|it| it.to_string()).collect();
                 write!(f, "[{}]", elements.join(", "))
             }
             Object::Hash(map) => {
-                let pairs: Vec<_> = map.iter().map(|(key, val)|  format!("{}: {}", key, val) ).collect();
+                let pairs: Vec<_> = sorted_pairs(map)
+                    .iter()
+                    .map(|(key, val)| format!("{}: {}", key, val))
+                    .collect();
                 write!(f, "{{{}}}", pairs.join(", "))
             }
+            Object::Range { start, end } => write!(f, "{}..{}", start, end),
+            Object::Error { message, .. } => write!(f, "ERROR: {}", message),
+            Object::Sender(channel) => write!(f, "sender({} queued)", channel.len()),
+            Object::Receiver(channel) => write!(f, "receiver({} queued)", channel.len()),
+            Object::Generator(_) => write!(f, "generator"),
         }
     }
 }
@@ -53,37 +299,154 @@ impl fmt::Display for Object {
 impl Object {
     pub fn r#type(&self) -> String {
         match self {
-            Object::Integer(_) => "INTEGER".into(),
+            Object::Integer(_) | Object::BigInteger(_) => "INTEGER".into(),
+            Object::Float(_) => "FLOAT".into(),
             Object::Boolean(_) => "BOOLEAN".into(),
             Object::Null => "NULL".into(),
             Object::ReturnValue(_) => "RETURN_VALUE".into(),
+            Object::Break(_) => "BREAK".into(),
+            Object::Continue => "CONTINUE".into(),
             Object::Function {
                 parameters: _,
+                rest: _,
                 body: _,
                 env: _,
+                doc: _,
+                generator: _,
             } => "FUNCTION".into(),
             Object::String(_) => "STRING".into(),
-            Object::Builtin(_) => "BUITLIN".into(),
+            Object::Char(_) => "CHAR".into(),
+            Object::Bytes(_) => "BYTES".into(),
+            Object::Builtin(_) => "BUILTIN".into(),
             Object::Array(_) => "ARRAY".into(),
             Object::Hash(_) => "HASH".into(),
+            Object::Range { .. } => "RANGE".into(),
+            Object::Error { .. } => "ERROR".into(),
+            Object::Sender(_) => "SENDER".into(),
+            Object::Receiver(_) => "RECEIVER".into(),
+            Object::Generator(_) => "GENERATOR".into(),
+        }
+    }
+
+    /// Renders the object the way it would be written in source code, so that
+    /// strings are quoted, unlike with `Display`.
+    pub fn inspect(&self) -> String {
+        match self {
+            Object::String(s) => format!("\"{}\"", escape(s)),
+            Object::Char(c) => format!("'{}'", escape_char(*c)),
+            Object::Error { message, .. } => format!("error(\"{}\")", escape(message)),
+            Object::Array(v) => {
+                let elements: Vec<_> = v.iter().map(|it| it.inspect()).collect();
+                format!("[{}]", elements.join(", "))
+            }
+            Object::Hash(map) => {
+                let pairs: Vec<_> = sorted_pairs(map)
+                    .iter()
+                    .map(|(key, val)| format!("{}: {}", key.inspect(), val.inspect()))
+                    .collect();
+                format!("{{{}}}", pairs.join(", "))
+            }
+            _ => self.to_string(),
         }
     }
 
     pub fn is_hashable(&self) -> bool {
         matches!(
             self,
-            Object::Integer(_) | Object::Boolean(_) | Object::String(_)
+            Object::Integer(_)
+                | Object::BigInteger(_)
+                | Object::Boolean(_)
+                | Object::String(_)
+                | Object::Char(_)
         )
     }
+
+    /// The value of a number as a float, which integers are promoted to when
+    /// combined with a float.
+    pub(crate) fn to_f64(&self) -> Option<f64> {
+        match self {
+            Object::Integer(i) => Some(*i as f64),
+            Object::BigInteger(i) => i.to_f64(),
+            Object::Float(x) => Some(x.0),
+            _ => None,
+        }
+    }
+
+    /// The value of an integer of either size.
+    pub(crate) fn to_bigint(&self) -> Option<BigInt> {
+        match self {
+            Object::Integer(i) => Some(BigInt::from(*i)),
+            Object::BigInteger(i) => Some(i.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// An integer as an [`Object::Integer`] if it fits, else as an
+/// [`Object::BigInteger`].
+pub(crate) fn integer(value: BigInt) -> Object {
+    match isize::try_from(&value) {
+        Ok(i) => Object::Integer(i),
+        Err(_) => Object::BigInteger(value),
+    }
+}
+
+/// The pairs of a hash ordered by key, integers first, then booleans, then
+/// characters, then strings, so that printing a hash gives the same text on
+/// every run.
+#[allow(clippy::mutable_key_type)]
+pub(crate) fn sorted_pairs(
+    map: &HashMap<Rc<Object>, Rc<Object>>,
+) -> Vec<(&Rc<Object>, &Rc<Object>)> {
+    let rank = |key: &Object| match key {
+        Object::Integer(_) | Object::BigInteger(_) => 0,
+        Object::Boolean(_) => 1,
+        Object::Char(_) => 2,
+        _ => 3,
+    };
+    let mut pairs: Vec<_> = map.iter().collect();
+    pairs.sort_by(|(a, _), (b, _)| match (a.as_ref(), b.as_ref()) {
+        (Object::Integer(a), Object::Integer(b)) => a.cmp(b),
+        (
+            Object::Integer(_) | Object::BigInteger(_),
+            Object::Integer(_) | Object::BigInteger(_),
+        ) => a.to_bigint().cmp(&b.to_bigint()),
+        (Object::Boolean(a), Object::Boolean(b)) => a.cmp(b),
+        (Object::Char(a), Object::Char(b)) => a.cmp(b),
+        (Object::String(a), Object::String(b)) => a.cmp(b),
+        (a, b) => rank(a).cmp(&rank(b)),
+    });
+    pairs
+}
+
+/// Splits `s` into the characters as users perceive them, i.e. extended
+/// grapheme clusters, so that `"e\u{301}"` and `"👍🏽"` count as one. Lengths,
+/// indices, slices and iteration of strings are all in these units.
+pub(crate) fn graphemes(s: &str) -> Vec<&str> {
+    s.graphemes(true).collect()
+}
+
+/// A grapheme as an element of a string, as indexing and iteration give it: a
+/// [`Object::Char`] if it is a single code point, else a [`Object::String`],
+/// e.g. for `"e\u{301}"`.
+pub(crate) fn grapheme_object(grapheme: &str) -> Object {
+    let mut chars = grapheme.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Object::Char(c),
+        _ => Object::String(grapheme.into()),
+    }
 }
 
 impl hash::Hash for Object {
     fn hash<H: hash::Hasher>(&self, state: &mut H) {
         match self {
             Object::Integer(i) => i.hash(state),
+            Object::BigInteger(i) => i.hash(state),
             Object::Boolean(b) => b.hash(state),
             Object::String(s) => s.hash(state),
-            _ => panic!("Only Integers, Booleans and Strings are allowed as keys in a map"),
+            // Like the string of just the character, which it equals.
+            Object::Char(c) => c.encode_utf8(&mut [0; 4]).hash(state),
+            _ => panic!("Only Integers, Booleans, Chars and Strings are allowed as keys in a map"),
         }
     }
 }
@@ -92,6 +455,15 @@ impl hash::Hash for Object {
 pub struct Environment {
     pub store: HashMap<String, Rc<Object>>,
     pub outer: Option<Rc<RefCell<Environment>>>,
+    /// Whether this is a read-only base layer, whose bindings can neither be
+    /// assigned nor redefined by the scope directly on top of it.
+    frozen: bool,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Environment {
@@ -99,9 +471,27 @@ impl Environment {
         Self {
             store: HashMap::new(),
             outer: None,
+            frozen: false,
+        }
+    }
+
+    /// The frozen base layer holding the builtins and the functions of the
+    /// prelude.
+    pub fn base() -> Self {
+        Self {
+            store: base_bindings(),
+            outer: None,
+            frozen: true,
         }
     }
 
+    /// A global scope on top of a fresh [`Environment::base`], so that
+    /// `let len = 5;` is an error instead of silently hiding `len`. Use
+    /// [`Environment::new`] to allow redefining builtins.
+    pub fn new_global() -> Self {
+        Self::new_enclosed(Rc::new(RefCell::new(Self::base())))
+    }
+
     pub fn new_enclosed(outer: Rc<RefCell<Environment>>) -> Self {
         let mut env = Environment::new();
         env.outer = Some(outer);
@@ -125,4 +515,114 @@ impl Environment {
     pub fn set(&mut self, name: String, val: Rc<Object>) {
         self.store.insert(name, val);
     }
+
+    /// Whether defining `name` in this scope would hide a binding of the
+    /// frozen layer directly below it.
+    pub fn shadows_frozen(&self, name: &str) -> bool {
+        self.outer.as_ref().is_some_and(|outer| {
+            let outer = outer.borrow();
+            outer.frozen && outer.store.contains_key(name)
+        })
+    }
+
+    /// Whether `name` resolves to a binding of a frozen layer.
+    pub fn is_frozen(&self, name: &str) -> bool {
+        match (self.store.contains_key(name), &self.outer) {
+            (true, _) => self.frozen,
+            (false, Some(outer_env)) => outer_env.borrow().is_frozen(name),
+            (false, None) => false,
+        }
+    }
+
+    /// Rebinds an existing `name` in the innermost scope that defines it.
+    /// Returns `false` if no scope defines `name`.
+    pub fn assign(&mut self, name: &str, val: Rc<Object>) -> bool {
+        if let Some(slot) = self.store.get_mut(name) {
+            *slot = val;
+            return true;
+        }
+        match &self.outer {
+            Some(outer_env) => outer_env.borrow_mut().assign(name, val),
+            None => false,
+        }
+    }
+
+    /// Removes `name` from the innermost scope that defines it, uncovering
+    /// any binding of the same name in an outer scope. Returns the removed
+    /// value, or `None` if no scope defines `name`.
+    pub fn remove(&mut self, name: &str) -> Option<Rc<Object>> {
+        if self.frozen {
+            return None;
+        }
+        match self.store.remove(name) {
+            Some(val) => Some(val),
+            None => self.outer.as_ref()?.borrow_mut().remove(name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remove_respects_scopes() {
+        let outer = Rc::new(RefCell::new(Environment::new()));
+        outer
+            .borrow_mut()
+            .set("x".into(), Rc::new(Object::Integer(1)));
+        outer
+            .borrow_mut()
+            .set("y".into(), Rc::new(Object::Integer(2)));
+        let mut inner = Environment::new_enclosed(Rc::clone(&outer));
+        inner.set("x".into(), Rc::new(Object::Integer(3)));
+
+        assert_eq!(inner.remove("x"), Some(Rc::new(Object::Integer(3))));
+        assert_eq!(inner.get("x"), Some(Rc::new(Object::Integer(1))));
+        assert_eq!(inner.remove("y"), Some(Rc::new(Object::Integer(2))));
+        assert_eq!(outer.borrow().get("y"), None);
+        assert_eq!(inner.remove("z"), None);
+    }
+
+    #[test]
+    fn test_frozen_base() {
+        let global = Rc::new(RefCell::new(Environment::new_global()));
+        let mut local = Environment::new_enclosed(Rc::clone(&global));
+        assert!(global.borrow().shadows_frozen("len"));
+        assert!(!global.borrow().shadows_frozen("x"));
+        assert!(!local.shadows_frozen("len"));
+        assert!(local.is_frozen("len"));
+
+        local.set("len".into(), Rc::new(Object::Integer(5)));
+        assert!(!local.is_frozen("len"));
+        assert_eq!(local.remove("len"), Some(Rc::new(Object::Integer(5))));
+        assert_eq!(local.remove("len"), None);
+        assert!(global.borrow().get("len").is_some());
+        assert!(!Environment::new().shadows_frozen("len"));
+    }
+
+    #[test]
+    #[allow(clippy::mutable_key_type)]
+    fn test_hashes_print_in_key_order() {
+        let map: HashMap<_, _> = [
+            Object::String("b".into()),
+            Object::Boolean(true),
+            Object::Integer(10),
+            Object::String("a".into()),
+            Object::Integer(-1),
+            Object::Boolean(false),
+        ]
+        .into_iter()
+        .map(|key| (Rc::new(key), Rc::new(Object::Null)))
+        .collect();
+        let hash = Object::Hash(map);
+        assert_eq!(
+            hash.to_string(),
+            "{-1: null, 10: null, false: null, true: null, a: null, b: null}"
+        );
+        assert_eq!(
+            hash.inspect(),
+            r#"{-1: null, 10: null, false: null, true: null, "a": null, "b": null}"#
+        );
+    }
 }
@@ -1,15 +1,22 @@
 use crate::{
-    ast::{BlockStatement, Expression, Identifier, Program, Statement},
+    ast::{BlockStatement, Expression, Float, Identifier, Pattern, Program, Statement, Target},
+    builtins::{is_builtin, returns_null},
     lexer::Lexer,
-    token::{Span, Token, TokenKind},
+    programs::is_prelude_name,
+    token::{keyword_in_other_case, Span, Token, TokenKind},
 };
 use miette::Result;
+use num_bigint::BigInt;
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 enum Precedence {
     Lowest,
+    Assign,
+    Pipe,
+    Coalesce,
     Equals,
     LessGreater,
+    Range,
     Sum,
     Product,
     Prefix,
@@ -20,28 +27,162 @@ enum Precedence {
 impl From<&Token> for Precedence {
     fn from(value: &Token) -> Self {
         match value.kind {
+            TokenKind::Assign
+            | TokenKind::PlusAssign
+            | TokenKind::MinusAssign
+            | TokenKind::AsteriskAssign
+            | TokenKind::SlashAssign => Self::Assign,
+            TokenKind::Pipe => Self::Pipe,
+            TokenKind::Coalesce => Self::Coalesce,
             TokenKind::Equal => Self::Equals,
             TokenKind::NotEqual => Self::Equals,
             TokenKind::LessThan => Self::LessGreater,
             TokenKind::GreaterThan => Self::LessGreater,
+            TokenKind::DotDot => Self::Range,
             TokenKind::Plus => Self::Sum,
             TokenKind::Minus => Self::Sum,
             TokenKind::Slash => Self::Product,
             TokenKind::Asterisk => Self::Product,
+            TokenKind::Percent => Self::Product,
             TokenKind::LParen => Self::Call,
-            TokenKind::LBracket => Self::Index,
+            TokenKind::LBracket | TokenKind::Dot | TokenKind::QuestionDot => Self::Index,
             _ => Self::Lowest,
         }
     }
 }
 
+/// Desugars a chain like `0 < x < 10`, which compares `left` with the first
+/// operand of `rest` and each operand with the next, into
+/// `if (0 < x) { x < 10 } else { false }`. Operands compared twice are bound
+/// to a name like `$chain1` first, unless evaluating them again is harmless.
+fn comparison_chain(left: Expression, rest: &[(Token, Expression)], index: usize) -> Expression {
+    let infix = |token: &Token, left, right| Expression::Infix {
+        token: token.clone(),
+        operator: token.kind.to_string(),
+        left: Box::new(left),
+        right: Box::new(right),
+    };
+    let [(token, right), tail @ ..] = rest else {
+        unreachable!("a chain has operands after the first");
+    };
+    if tail.is_empty() {
+        return infix(token, left, right.clone());
+    }
+
+    let simple = matches!(
+        right,
+        Expression::Ident(_)
+            | Expression::IntegerLiteral(_)
+            | Expression::BigIntegerLiteral(_)
+            | Expression::FloatLiteral(_)
+            | Expression::StringLiteral(_)
+            | Expression::CharLiteral(_)
+            | Expression::Boolean(_)
+            | Expression::Null
+    );
+    let operand = match simple {
+        true => right.clone(),
+        false => Expression::Ident(Identifier::new(format!("$chain{}", index))),
+    };
+    let mut consequence = BlockStatement::new();
+    consequence.push(Statement::Expr(comparison_chain(
+        operand.clone(),
+        tail,
+        index + 1,
+    )));
+    let mut alternative = BlockStatement::new();
+    alternative.push(Statement::Expr(Expression::Boolean(false)));
+    let chain = Expression::If {
+        condition: Box::new(infix(token, left, operand)),
+        consequence,
+        alternative: Some(alternative),
+    };
+    if simple {
+        return chain;
+    }
+
+    let mut block = BlockStatement::new();
+    block.push(Statement::Let {
+        token: Token::new(TokenKind::Let, token.span.start, token.span.end),
+        name: format!("$chain{}", index),
+        value: right.clone(),
+    });
+    block.push(Statement::Expr(chain));
+    Expression::Block(block)
+}
+
+/// Everything [`Parser::parse`] produces: the program, which contains the
+/// statements that parsed despite any errors, and the diagnostics.
+pub struct ParseOutcome {
+    pub program: Program,
+    pub errors: Vec<miette::Report>,
+    pub warnings: Vec<miette::Report>,
+}
+
+impl ParseOutcome {
+    /// Whether there are neither errors nor warnings.
+    pub fn is_clean(&self) -> bool {
+        self.errors.is_empty() && self.warnings.is_empty()
+    }
+
+    /// The errors followed by the warnings.
+    pub fn diagnostics(&self) -> impl Iterator<Item = &miette::Report> {
+        self.errors.iter().chain(&self.warnings)
+    }
+
+    /// The program if there were no errors, discarding the warnings.
+    pub fn into_result(self) -> std::result::Result<Program, Vec<miette::Report>> {
+        if self.errors.is_empty() {
+            Ok(self.program)
+        } else {
+            Err(self.errors)
+        }
+    }
+}
+
 pub struct Parser<'a> {
     lexer: Lexer<'a>,
     current_token: Token,
     peek_token: Token,
+    /// Number of loops enclosing the current token within the current function.
+    loop_depth: usize,
+    /// Whether the current function is a generator, written `fn*`, the only
+    /// place where `yield` is allowed.
+    in_generator: bool,
+    /// Number of expressions enclosing the current token.
+    depth: usize,
+    max_depth: usize,
+    /// Whether `max_depth` was exceeded, after which parsing stops.
+    too_deep: bool,
+    /// Whether to warn about discarded values, see [`Parser::with_strict`].
+    strict: bool,
+    warnings: Vec<miette::Report>,
+}
+
+/// Whether `expr` is an identifier, or an element or field of an assignable
+/// expression like `a[0]` or `h.name`.
+fn is_assignable(expr: &Expression) -> bool {
+    match expr {
+        Expression::Ident(_) => true,
+        Expression::IndexExpr {
+            left,
+            optional: false,
+            ..
+        }
+        | Expression::Field {
+            left,
+            optional: false,
+            ..
+        } => is_assignable(left),
+        _ => false,
+    }
 }
 
 impl<'a> Parser<'a> {
+    /// How deeply expressions may nest unless changed with
+    /// [`Parser::with_max_depth`], low enough for the 1 MiB stack of wasm.
+    pub const DEFAULT_MAX_DEPTH: usize = 128;
+
     pub fn new(mut lexer: Lexer<'a>) -> Self {
         let current_token = lexer.next_token();
         let peek_token = lexer.next_token();
@@ -50,9 +191,39 @@ impl<'a> Parser<'a> {
             lexer,
             current_token,
             peek_token,
+            loop_depth: 0,
+            in_generator: false,
+            depth: 0,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            too_deep: false,
+            strict: false,
+            warnings: Vec::new(),
         }
     }
 
+    /// Limits how deeply expressions, including blocks and function bodies,
+    /// may nest before parsing fails with an error instead of overflowing the
+    /// stack.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Enables warnings for expression statements whose value is thrown away,
+    /// e.g. `add(1, 2);` followed by more statements, which can be silenced
+    /// with `let _ = add(1, 2);` or `void(add(1, 2));`. The last statement of
+    /// a block is its value and never warned about.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Returns the warnings for code that parses but is likely a mistake,
+    /// e.g. bindings that shadow a builtin.
+    pub fn take_warnings(&mut self) -> Vec<miette::Report> {
+        std::mem::take(&mut self.warnings)
+    }
+
     fn next_token(&mut self) {
         self.current_token = self.lexer.next_token();
         std::mem::swap(&mut self.current_token, &mut self.peek_token);
@@ -75,19 +246,44 @@ impl<'a> Parser<'a> {
     //    }
     //}
 
+    /// Parses the whole input, collecting the errors and warnings.
+    pub fn parse(&mut self) -> ParseOutcome {
+        let (program, errors) = self.parse_program();
+        ParseOutcome {
+            program,
+            errors,
+            warnings: self.take_warnings(),
+        }
+    }
+
+    /// Like [`Parser::parse`], but leaves the warnings to
+    /// [`Parser::take_warnings`].
     pub fn parse_program(&mut self) -> (Program, Vec<miette::Report>) {
         let mut program = Program::new();
         let mut errors = Vec::new();
 
+        let mut discarded = None;
         while self.current_token.kind != TokenKind::Eof {
+            let comments = std::mem::take(&mut self.current_token.comments);
+            let start = self.current_token.span.start;
             match self.parse_statement() {
-                Ok(stmt) => program.push(stmt),
+                Ok(stmt) => {
+                    self.warn_discarded(discarded.take());
+                    discarded = self.discarded_value(&stmt, start);
+                    program.push_with_comments(stmt, comments)
+                }
                 Err(e) => {
                     errors.push(e);
+                    // Recovering would only hit the limit again further in.
+                    if self.too_deep {
+                        break;
+                    }
                 }
             }
             self.next_token();
         }
+        program.set_trailing_comments(std::mem::take(&mut self.current_token.comments));
+        errors.extend(self.lexer.take_errors());
 
         (program, errors)
     }
@@ -96,17 +292,77 @@ impl<'a> Parser<'a> {
         match &self.current_token.kind {
             TokenKind::Let => self.parse_let_statement(),
             TokenKind::Return => self.parse_return_statement(),
+            TokenKind::Throw => self.parse_throw_statement(),
+            TokenKind::Yield => self.parse_yield_statement(),
+            TokenKind::Break | TokenKind::Continue => self.parse_loop_control_statement(),
             _ => self.parse_expression_statement(),
         }
     }
 
+    /// In strict mode, the span of `stmt`, which started at `start`, if it is
+    /// an expression whose value is unused unless it ends the block.
+    fn discarded_value(&self, stmt: &Statement, start: usize) -> Option<Span> {
+        let Statement::Expr(expr) = stmt else {
+            return None;
+        };
+        let has_value = match expr {
+            // Evaluated for their effects.
+            Expression::If { .. }
+            | Expression::While { .. }
+            | Expression::DoWhile { .. }
+            | Expression::Loop { .. }
+            | Expression::For { .. }
+            | Expression::Try { .. }
+            | Expression::Match { .. }
+            | Expression::Block(_)
+            | Expression::Assign { .. }
+            | Expression::IndexAssign { .. } => false,
+            Expression::Call { function, .. } => !matches!(
+                function.as_ref(),
+                Expression::Ident(name) if returns_null(name.value())
+            ),
+            _ => true,
+        };
+        let end = self.current_token.span.end;
+        (self.strict && has_value).then_some(Span { start, end })
+    }
+
+    fn warn_discarded(&mut self, discarded: Option<Span>) {
+        let Some(Span { start, end }) = discarded else {
+            return;
+        };
+        self.warnings.push(
+            miette::miette!(
+                severity = miette::Severity::Warning,
+                labels = vec![miette::LabeledSpan::at(
+                    start..end + 1,
+                    "value is discarded"
+                )],
+                help = "Use `let _ = ...` or `void(...)` if this is intended",
+                "unused value"
+            )
+            .with_source_code(self.lexer.source_code()),
+        );
+    }
+
     fn parse_let_statement(&mut self) -> Result<Statement> {
         let current_token = self.current_token.clone();
         self.next_token();
-        let name = match &self.current_token.kind {
-            TokenKind::Ident(ident) => ident.clone(),
-            t => miette::bail!("Expected Ident, got: {}", t),
-        };
+        if matches!(
+            self.current_token.kind,
+            TokenKind::LBracket | TokenKind::LBrace
+        ) {
+            return self.parse_destructure_statement(current_token);
+        }
+        let name = self.parse_binding_name()?;
+
+        // `let x, y = value;` is short for `let [x, y] = value;`.
+        let mut names = vec![];
+        while self.peek_token.kind == TokenKind::Comma {
+            self.next_token();
+            self.next_token();
+            names.push(Identifier::new(self.parse_binding_name()?));
+        }
 
         if self.peek_token.kind != TokenKind::Assign {
             //miette::bail!("Expected Assign");
@@ -118,7 +374,7 @@ impl<'a> Parser<'a> {
                 help = "Use `=` after the identifier",
                 "Expected Assignment"
             )
-            .with_source_code(self.lexer.source_code().to_string()));
+            .with_source_code(self.lexer.source_code()));
         }
         self.next_token();
         self.next_token();
@@ -129,6 +385,14 @@ impl<'a> Parser<'a> {
             self.next_token();
         }
 
+        if !names.is_empty() {
+            names.insert(0, Identifier::new(name));
+            return Ok(Statement::Destructure {
+                token: current_token,
+                target: Target::Array(names),
+                value,
+            });
+        }
         Ok(Statement::Let {
             token: current_token,
             name,
@@ -140,7 +404,18 @@ impl<'a> Parser<'a> {
         let current_token = self.current_token.clone();
         self.next_token();
 
-        let return_value = self.parse_expression(Precedence::Lowest)?;
+        let mut return_value = self.parse_expression(Precedence::Lowest)?;
+
+        // `return a, b;` returns the array `[a, b]`, for `let a, b = f();`.
+        if self.peek_token.kind == TokenKind::Comma {
+            let mut values = vec![return_value];
+            while self.peek_token.kind == TokenKind::Comma {
+                self.next_token();
+                self.next_token();
+                values.push(self.parse_expression(Precedence::Lowest)?);
+            }
+            return_value = Expression::ArrayLiteral(values);
+        }
 
         if self.peek_token.kind == TokenKind::Semicolon {
             self.next_token();
@@ -152,7 +427,222 @@ impl<'a> Parser<'a> {
         })
     }
 
+    fn parse_throw_statement(&mut self) -> Result<Statement> {
+        let current_token = self.current_token.clone();
+        self.next_token();
+
+        let value = self.parse_expression(Precedence::Lowest)?;
+
+        if self.peek_token.kind == TokenKind::Semicolon {
+            self.next_token();
+        }
+
+        Ok(Statement::Throw {
+            token: current_token,
+            value,
+        })
+    }
+
+    fn parse_yield_statement(&mut self) -> Result<Statement> {
+        let current_token = self.current_token.clone();
+        if !self.in_generator {
+            let Span { start, end } = current_token.span;
+            return Err(miette::miette!(
+                severity = miette::Severity::Error,
+                labels = vec![miette::LabeledSpan::at(start..end + 1, "here")],
+                help = "Write the function as `fn*(...) { ... }` to make it a generator",
+                "`yield` outside of a generator"
+            )
+            .with_source_code(self.lexer.source_code()));
+        }
+        self.next_token();
+
+        let value = self.parse_expression(Precedence::Lowest)?;
+
+        if self.peek_token.kind == TokenKind::Semicolon {
+            self.next_token();
+        }
+
+        Ok(Statement::Yield {
+            token: current_token,
+            value,
+        })
+    }
+
+    /// Parses `break` and `continue`, which are only valid inside a loop body.
+    fn parse_loop_control_statement(&mut self) -> Result<Statement> {
+        let current_token = self.current_token.clone();
+        if self.loop_depth == 0 {
+            let Span { start, end } = current_token.span;
+            return Err(miette::miette!(
+                severity = miette::Severity::Error,
+                labels = vec![miette::LabeledSpan::at(start..end + 1, "here")],
+                help = "Only loops can be left with `break` and `continue`",
+                "`{}` outside of a loop",
+                current_token.kind
+            )
+            .with_source_code(self.lexer.source_code()));
+        }
+
+        let value = match (&current_token.kind, &self.peek_token.kind) {
+            (TokenKind::Break, TokenKind::Semicolon | TokenKind::RBrace | TokenKind::Eof) => None,
+            (TokenKind::Break, _) => {
+                self.next_token();
+                Some(self.parse_expression(Precedence::Lowest)?)
+            }
+            _ => None,
+        };
+
+        if self.peek_token.kind == TokenKind::Semicolon {
+            self.next_token();
+        }
+
+        Ok(match current_token.kind {
+            TokenKind::Break => Statement::Break {
+                token: current_token,
+                value,
+            },
+            _ => Statement::Continue {
+                token: current_token,
+            },
+        })
+    }
+
+    /// Parses `let [a, b] = value;` or `let {a, b: c} = value;` from the `[`
+    /// or `{`.
+    fn parse_destructure_statement(&mut self, token: Token) -> Result<Statement> {
+        let target = match self.current_token.kind {
+            TokenKind::LBracket => {
+                let mut names = Vec::new();
+                while self.peek_token.kind != TokenKind::RBracket {
+                    self.next_token();
+                    names.push(Identifier::new(self.parse_binding_name()?));
+                    self.expect_separator(TokenKind::RBracket)?;
+                }
+                Target::Array(names)
+            }
+            _ => {
+                let mut entries = Vec::new();
+                while self.peek_token.kind != TokenKind::RBrace {
+                    self.next_token();
+                    entries.push(self.parse_destructure_entry()?);
+                    self.expect_separator(TokenKind::RBrace)?;
+                }
+                Target::Hash(entries)
+            }
+        };
+        self.next_token();
+
+        if self.peek_token.kind != TokenKind::Assign {
+            let Span { start, end } = self.peek_token.span;
+            return Err(miette::miette!(
+                severity = miette::Severity::Error,
+                labels = vec![miette::LabeledSpan::at(start..end + 1, "here")],
+                help = format!("Use `=` after the `{}`", self.current_token.kind),
+                "Expected Assignment"
+            )
+            .with_source_code(self.lexer.source_code()));
+        }
+        self.next_token();
+        self.next_token();
+
+        let value = self.parse_expression(Precedence::Lowest)?;
+
+        if self.peek_token.kind == TokenKind::Semicolon {
+            self.next_token();
+        }
+
+        Ok(Statement::Destructure {
+            token,
+            target,
+            value,
+        })
+    }
+
+    /// Parses `key` or `key: name` in a hash destructuring `let`.
+    fn parse_destructure_entry(&mut self) -> Result<(String, Identifier)> {
+        let TokenKind::Ident(key) = &self.current_token.kind else {
+            miette::bail!("Expected Ident, got: {}", self.current_token.kind);
+        };
+        if self.peek_token.kind != TokenKind::Colon {
+            return Ok((key.clone(), Identifier::new(self.parse_binding_name()?)));
+        }
+        let key = key.clone();
+        self.next_token();
+        self.next_token();
+        Ok((key, Identifier::new(self.parse_binding_name()?)))
+    }
+
+    /// Skips a `,` after a list item, unless the list ends with `close`.
+    fn expect_separator(&mut self, close: TokenKind) -> Result<()> {
+        if self.peek_token.kind == TokenKind::Comma {
+            self.next_token();
+        } else if self.peek_token.kind != close {
+            miette::bail!("Expected `,` or `{}`, got: {}", close, self.peek_token.kind);
+        }
+        Ok(())
+    }
+
+    /// Parses the name bound by `let`, `for` or a function parameter.
+    fn parse_binding_name(&mut self) -> Result<String> {
+        match &self.current_token.kind {
+            TokenKind::Ident(ident) => {
+                // The prelude sits next to the builtins, so its functions
+                // count as builtins too. Outside of any expression the name
+                // is bound by a top-level `let`, which the frozen layer of
+                // builtins refuses with an error of its own.
+                if self.depth > 0 && (is_builtin(ident) || is_prelude_name(ident)) {
+                    let Span { start, end } = self.current_token.span;
+                    self.warnings.push(
+                        miette::miette!(
+                            severity = miette::Severity::Warning,
+                            labels =
+                                vec![miette::LabeledSpan::at(start..end + 1, "shadows a builtin")],
+                            help = format!(
+                                "Rename it to keep calling the builtin `{}` in this scope",
+                                ident
+                            ),
+                            "`{}` shadows the builtin of the same name",
+                            ident
+                        )
+                        .with_source_code(self.lexer.source_code()),
+                    );
+                }
+                Ok(ident.clone())
+            }
+            kind if kind.is_keyword() => {
+                let Span { start, end } = self.current_token.span;
+                Err(miette::miette!(
+                    severity = miette::Severity::Error,
+                    labels = vec![miette::LabeledSpan::at(start..end + 1, "reserved word")],
+                    help = "Choose a different name",
+                    "`{}` is a reserved word and cannot be used as a name",
+                    kind
+                )
+                .with_source_code(self.lexer.source_code()))
+            }
+            t => miette::bail!("Expected Ident, got: {}", t),
+        }
+    }
+
     fn parse_expression_statement(&mut self) -> Result<Statement> {
+        // `Let x = 5` would otherwise parse as the identifier `Let` followed by
+        // an unrelated assignment.
+        if let (TokenKind::Ident(ident), TokenKind::Ident(_)) =
+            (&self.current_token.kind, &self.peek_token.kind)
+        {
+            if let Some(keyword) = keyword_in_other_case(ident) {
+                let Span { start, end } = self.current_token.span;
+                return Err(miette::miette!(
+                    severity = miette::Severity::Error,
+                    labels = vec![miette::LabeledSpan::at(start..end + 1, "not a keyword")],
+                    help = format!("Keywords are lowercase, did you mean `{}`?", keyword),
+                    "Unexpected identifier `{}`",
+                    ident
+                )
+                .with_source_code(self.lexer.source_code()));
+            }
+        }
         let expression = self.parse_expression(Precedence::Lowest)?;
         if self.peek_token.kind == TokenKind::Semicolon {
             self.next_token()
@@ -161,23 +651,64 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_expression(&mut self, precedence: Precedence) -> Result<Expression> {
+        if self.depth == self.max_depth {
+            self.too_deep = true;
+            let Span { start, end } = self.current_token.span;
+            return Err(miette::miette!(
+                severity = miette::Severity::Error,
+                labels = vec![miette::LabeledSpan::at(start..end + 1, "too deep")],
+                help = format!(
+                    "Expressions can nest at most {} levels deep, bind parts of it with `let`",
+                    self.max_depth
+                ),
+                "expression too deeply nested"
+            )
+            .with_source_code(self.lexer.source_code()));
+        }
+        self.depth += 1;
+        let expression = self.parse_nested_expression(precedence);
+        self.depth -= 1;
+        expression
+    }
+
+    fn parse_nested_expression(&mut self, precedence: Precedence) -> Result<Expression> {
+        let start = self.current_token.span.start;
         let mut left_exp = match &self.current_token.kind {
             // Prefix operators
             TokenKind::Ident(ident) => Expression::Ident(Identifier::new(ident.clone())),
-            TokenKind::Int(i) => {
-                Expression::IntegerLiteral(i.parse().expect("Failed parsing Token::Int(i)"))
-            }
+            TokenKind::Int(literal) => self.parse_integer(literal)?,
+            TokenKind::Float(literal) => self.parse_float(literal)?,
             TokenKind::True => Expression::Boolean(true),
             TokenKind::False => Expression::Boolean(false),
+            TokenKind::Null => Expression::Null,
             TokenKind::LParen => self.parse_grouped_expression()?,
             TokenKind::If => self.parse_if_expression()?,
+            TokenKind::While => self.parse_while_expression()?,
+            TokenKind::Loop => self.parse_loop_expression()?,
+            TokenKind::Do => self.parse_do_while_expression()?,
+            TokenKind::Try => self.parse_try_expression()?,
+            TokenKind::Match => self.parse_match_expression()?,
+            TokenKind::For => self.parse_for_expression()?,
             TokenKind::Function => self.parse_function_literal()?,
+            TokenKind::Bar => self.parse_lambda()?,
             TokenKind::Minus | TokenKind::Bang => self.parse_prefix_expression()?,
             TokenKind::String(s) => Expression::StringLiteral(s.into()),
+            TokenKind::Char(c) => Expression::CharLiteral(*c),
+            TokenKind::Bytes(b) => Expression::BytesLiteral(b.clone()),
             TokenKind::LBracket => {
                 Expression::ArrayLiteral(self.parse_expression_list(TokenKind::RBracket)?)
-            },
-            TokenKind::LBrace => self.parse_hash_literal()?,
+            }
+            TokenKind::LBrace => self.parse_brace_expression()?,
+            TokenKind::Illegal(c) => {
+                let Span { start, end } = self.current_token.span;
+                return Err(miette::miette!(
+                    severity = miette::Severity::Error,
+                    labels = vec![miette::LabeledSpan::at(start..end + 1, "not valid here")],
+                    "unexpected character '{}'",
+                    c.escape_debug()
+                )
+                .with_source_code(self.lexer.source_code()));
+            }
             _ => miette::bail!("Unexpected Token: {}", &self.current_token.kind),
         };
 
@@ -189,23 +720,40 @@ impl<'a> Parser<'a> {
                 | TokenKind::Minus
                 | TokenKind::Slash
                 | TokenKind::Asterisk
+                | TokenKind::Percent
                 | TokenKind::Equal
                 | TokenKind::NotEqual
                 | TokenKind::LessThan
-                | TokenKind::GreaterThan => {
-                    if let Ok(expr) = self.parse_infix_expression(left_exp.clone()) {
-                        left_exp = expr;
-                    }
+                | TokenKind::GreaterThan
+                | TokenKind::Coalesce
+                | TokenKind::DotDot => {
+                    left_exp = self.parse_infix_expression(left_exp)?;
                 }
                 TokenKind::LParen => {
-                    if let Ok(expr) = self.parse_call_expression(left_exp.clone()) {
-                        left_exp = expr;
-                    }
+                    left_exp = self.parse_call_expression(left_exp, start)?;
+                }
+                TokenKind::Pipe => {
+                    left_exp = self.parse_pipe_expression(left_exp, start)?;
                 }
                 TokenKind::LBracket => {
-                    if let Ok(expr) = self.parse_index_expression(left_exp.clone()) {
-                        left_exp = expr;
-                    }
+                    left_exp = self.parse_index_expression(left_exp, false)?;
+                }
+                TokenKind::Dot => {
+                    left_exp = self.parse_field_expression(left_exp, false)?;
+                }
+                TokenKind::QuestionDot if self.peek_token.kind == TokenKind::LBracket => {
+                    self.next_token();
+                    left_exp = self.parse_index_expression(left_exp, true)?;
+                }
+                TokenKind::QuestionDot => {
+                    left_exp = self.parse_field_expression(left_exp, true)?;
+                }
+                TokenKind::Assign
+                | TokenKind::PlusAssign
+                | TokenKind::MinusAssign
+                | TokenKind::AsteriskAssign
+                | TokenKind::SlashAssign => {
+                    left_exp = self.parse_assign_expression(left_exp)?;
                 }
                 _ => return Ok(left_exp),
             };
@@ -237,6 +785,19 @@ impl<'a> Parser<'a> {
 
         let right = self.parse_expression(precedence)?;
 
+        let is_comparison =
+            |kind: &TokenKind| matches!(kind, TokenKind::LessThan | TokenKind::GreaterThan);
+        if is_comparison(&current_token.kind) && is_comparison(&self.peek_token.kind) {
+            let mut rest = vec![(current_token, right)];
+            while is_comparison(&self.peek_token.kind) {
+                self.next_token();
+                let token = self.current_token.clone();
+                self.next_token();
+                rest.push((token, self.parse_expression(Precedence::LessGreater)?));
+            }
+            return Ok(comparison_chain(left, &rest, 1));
+        }
+
         Ok(Expression::Infix {
             token: current_token,
             operator,
@@ -245,10 +806,59 @@ impl<'a> Parser<'a> {
         })
     }
 
+    /// Parses `x = value` as well as the compound forms `x += value`, `x -= value`,
+    /// `x *= value` and `x /= value`, which are desugared into `x = x <op> value`.
+    fn parse_assign_expression(&mut self, left: Expression) -> Result<Expression> {
+        let current_token = self.current_token.clone();
+        if !is_assignable(&left) {
+            let Span { start, end } = current_token.span;
+            return Err(miette::miette!(
+                severity = miette::Severity::Error,
+                labels = vec![miette::LabeledSpan::at(start..end + 1, "here")],
+                help = "Only identifiers and their elements, e.g. `a[0]` or `h.name`, can be assigned to",
+                "Invalid assignment target"
+            )
+            .with_source_code(self.lexer.source_code()));
+        }
+
+        self.next_token();
+
+        let value = self.parse_expression(Precedence::Lowest)?;
+        let operator = match current_token.kind {
+            TokenKind::PlusAssign => Some(TokenKind::Plus),
+            TokenKind::MinusAssign => Some(TokenKind::Minus),
+            TokenKind::AsteriskAssign => Some(TokenKind::Asterisk),
+            TokenKind::SlashAssign => Some(TokenKind::Slash),
+            _ => None,
+        };
+        let value = match operator {
+            Some(kind) => Expression::Infix {
+                operator: kind.to_string(),
+                token: Token::new(kind, current_token.span.start, current_token.span.end),
+                left: Box::new(left.clone()),
+                right: Box::new(value),
+            },
+            None => value,
+        };
+
+        Ok(match left {
+            Expression::Ident(name) => Expression::Assign {
+                token: current_token,
+                name,
+                value: Box::new(value),
+            },
+            target => Expression::IndexAssign {
+                token: current_token,
+                target: Box::new(target),
+                value: Box::new(value),
+            },
+        })
+    }
+
     fn parse_grouped_expression(&mut self) -> Result<Expression> {
         self.next_token();
 
-        let expression = self.parse_expression(Precedence::Lowest);
+        let expression = self.parse_expression(Precedence::Lowest)?;
 
         if self.peek_token.kind != TokenKind::RParen {
             let Span { start, end } = self.peek_token.span;
@@ -259,134 +869,610 @@ impl<'a> Parser<'a> {
                 help = "Use `)` to end the grouping",
                 "Expected `)`"
             )
-            .with_source_code(self.lexer.source_code().to_string()));
+            .with_source_code(self.lexer.source_code()));
         }
 
         self.next_token();
 
-        expression
+        Ok(expression)
     }
 
     fn parse_if_expression(&mut self) -> Result<Expression> {
         //let token = self.current_token.clone();
+        let condition = self.parse_condition()?;
+
+        if self.peek_token.kind != TokenKind::LBrace {
+            miette::bail!("Expected Left Brace at beginning of block");
+        }
+        self.next_token(); // jump over LBrace
+
+        let consequence = self.parse_block_statement()?;
+
+        let alternative = if self.peek_token.kind == TokenKind::Else {
+            self.next_token(); // jump over the else
+            if self.peek_token.kind == TokenKind::If {
+                // `else if` nests the following `if` as the alternative.
+                self.next_token();
+                let mut alternative = BlockStatement::new();
+                alternative.push(Statement::Expr(self.parse_if_expression()?));
+                return Ok(Expression::If {
+                    condition: Box::new(condition),
+                    consequence,
+                    alternative: Some(alternative),
+                });
+            }
+            if self.peek_token.kind != TokenKind::LBrace {
+                miette::bail!("Expected Left Brace or `if` after `else`")
+            }
+            self.next_token(); // jump over LBrace
+            self.parse_block_statement().ok()
+        } else {
+            None
+        };
+
+        Ok(Expression::If {
+            condition: Box::new(condition),
+            consequence,
+            alternative,
+        })
+    }
+
+    fn parse_while_expression(&mut self) -> Result<Expression> {
+        let condition = self.parse_condition()?;
+
+        if self.peek_token.kind != TokenKind::LBrace {
+            miette::bail!("Expected Left Brace at beginning of loop body");
+        }
+        self.next_token(); // jump over LBrace
+
+        self.loop_depth += 1;
+        let body = self.parse_block_statement();
+        self.loop_depth -= 1;
+
+        Ok(Expression::While {
+            condition: Box::new(condition),
+            body: body?,
+        })
+    }
+
+    /// Parses `do { body } while (condition)`.
+    fn parse_do_while_expression(&mut self) -> Result<Expression> {
+        if self.peek_token.kind != TokenKind::LBrace {
+            miette::bail!("Expected Left Brace at beginning of loop body");
+        }
+        self.next_token(); // jump over LBrace
+
+        self.loop_depth += 1;
+        let body = self.parse_block_statement();
+        self.loop_depth -= 1;
+        let body = body?;
+
+        if self.peek_token.kind != TokenKind::While {
+            let Span { start, end } = self.peek_token.span;
+            return Err(miette::miette!(
+                severity = miette::Severity::Error,
+                labels = vec![miette::LabeledSpan::at(start..end + 1, "here")],
+                help = "Write the condition after the body, as in `do { ... } while (x);`",
+                "Expected `while` after the body of `do`"
+            )
+            .with_source_code(self.lexer.source_code()));
+        }
+        self.next_token();
+        let condition = self.parse_condition()?;
+
+        Ok(Expression::DoWhile {
+            body,
+            condition: Box::new(condition),
+        })
+    }
+
+    /// Parses `loop { body }`.
+    fn parse_loop_expression(&mut self) -> Result<Expression> {
+        if self.peek_token.kind != TokenKind::LBrace {
+            miette::bail!("Expected Left Brace at beginning of loop body");
+        }
+        self.next_token(); // jump over LBrace
+
+        self.loop_depth += 1;
+        let body = self.parse_block_statement();
+        self.loop_depth -= 1;
+
+        Ok(Expression::Loop { body: body? })
+    }
+
+    /// Parses `try { body } catch (name) { handler }`.
+    fn parse_try_expression(&mut self) -> Result<Expression> {
+        if self.peek_token.kind != TokenKind::LBrace {
+            miette::bail!("Expected Left Brace after `try`");
+        }
+        self.next_token(); // jump over LBrace
+        let body = self.parse_block_statement()?;
+
+        if self.peek_token.kind != TokenKind::Catch {
+            let Span { start, end } = self.peek_token.span;
+            return Err(miette::miette!(
+                severity = miette::Severity::Error,
+                labels = vec![miette::LabeledSpan::at(start..end + 1, "here")],
+                help = "Handle errors with `catch (e) { ... }` after the `try` block",
+                "Expected `catch`"
+            )
+            .with_source_code(self.lexer.source_code()));
+        }
+        self.next_token(); // jump over Catch
+
         if self.peek_token.kind != TokenKind::LParen {
             let Span { start, end } = self.peek_token.span;
             return Err(miette::miette!(
                 severity = miette::Severity::Error,
-                labels = vec![miette::LabeledSpan::at(start..end, "here")],
-                //url = "https://example.com",
-                help = "Use parentheses around condition",
+                labels = vec![miette::LabeledSpan::at(start..end + 1, "here")],
+                help = "Name the caught error in parentheses, as in `catch (e)`",
                 "Expected `(`"
             )
-            .with_source_code(self.lexer.source_code().to_string()));
+            .with_source_code(self.lexer.source_code()));
         }
         self.next_token(); // jump over LParen
         self.next_token();
+        let error = Identifier::new(self.parse_binding_name()?);
 
-        let condition = self.parse_expression(Precedence::Lowest)?;
         if self.peek_token.kind != TokenKind::RParen {
             let Span { start, end } = self.peek_token.span;
             return Err(miette::miette!(
                 severity = miette::Severity::Error,
-                labels = vec![miette::LabeledSpan::at(start..end, "here")],
-                //url = "https://example.com",
-                help = "Use parentheses around condition",
+                labels = vec![miette::LabeledSpan::at(start..end + 1, "here")],
+                help = "Name the caught error in parentheses, as in `catch (e)`",
                 "Expected `)`"
             )
-            .with_source_code(self.lexer.source_code().to_string()));
+            .with_source_code(self.lexer.source_code()));
         }
         self.next_token(); // jump over RParen
 
         if self.peek_token.kind != TokenKind::LBrace {
-            miette::bail!("Expected Left Brace at beginning of block");
+            miette::bail!("Expected Left Brace after `catch`");
         }
         self.next_token(); // jump over LBrace
+        let handler = self.parse_block_statement()?;
 
-        let consequence = self.parse_block_statement()?;
+        Ok(Expression::Try {
+            body,
+            error,
+            handler,
+        })
+    }
 
-        let alternative = if self.peek_token.kind == TokenKind::Else {
-            self.next_token(); // jump over the else
-            if self.peek_token.kind != TokenKind::LBrace {
-                miette::bail!("Expected Left Brace after `else`")
+    /// Parses `for (name in iterable) { body }`.
+    fn parse_for_expression(&mut self) -> Result<Expression> {
+        if self.peek_token.kind != TokenKind::LParen {
+            let Span { start, end } = self.peek_token.span;
+            return Err(miette::miette!(
+                severity = miette::Severity::Error,
+                labels = vec![miette::LabeledSpan::at(start..end + 1, "here")],
+                help = "Use parentheses around the loop head, as in `for (x in xs)`",
+                "Expected `(`"
+            )
+            .with_source_code(self.lexer.source_code()));
+        }
+        self.next_token(); // jump over LParen
+        self.next_token();
+
+        let variable = Identifier::new(self.parse_binding_name()?);
+        if self.peek_token.kind != TokenKind::In {
+            let Span { start, end } = self.peek_token.span;
+            return Err(miette::miette!(
+                severity = miette::Severity::Error,
+                labels = vec![miette::LabeledSpan::at(start..end + 1, "here")],
+                help = "Use `in` after the loop variable",
+                "Expected `in`"
+            )
+            .with_source_code(self.lexer.source_code()));
+        }
+        self.next_token(); // jump over In
+        self.next_token();
+
+        let iterable = self.parse_expression(Precedence::Lowest)?;
+        if self.peek_token.kind != TokenKind::RParen {
+            let Span { start, end } = self.peek_token.span;
+            return Err(miette::miette!(
+                severity = miette::Severity::Error,
+                labels = vec![miette::LabeledSpan::at(start..end + 1, "here")],
+                help = "Use parentheses around the loop head, as in `for (x in xs)`",
+                "Expected `)`"
+            )
+            .with_source_code(self.lexer.source_code()));
+        }
+        self.next_token(); // jump over RParen
+
+        if self.peek_token.kind != TokenKind::LBrace {
+            miette::bail!("Expected Left Brace at beginning of loop body");
+        }
+        self.next_token(); // jump over LBrace
+
+        self.loop_depth += 1;
+        let body = self.parse_block_statement();
+        self.loop_depth -= 1;
+
+        Ok(Expression::For {
+            variable,
+            iterable: Box::new(iterable),
+            body: body?,
+        })
+    }
+
+    /// Parses `match value { pattern => body, ... }`, where a body is either a
+    /// block or a single expression and the commas between arms are optional.
+    fn parse_match_expression(&mut self) -> Result<Expression> {
+        self.next_token();
+        let value = self.parse_expression(Precedence::Lowest)?;
+
+        if self.peek_token.kind != TokenKind::LBrace {
+            let Span { start, end } = self.peek_token.span;
+            return Err(miette::miette!(
+                severity = miette::Severity::Error,
+                labels = vec![miette::LabeledSpan::at(start..end + 1, "here")],
+                help = "Put the arms of `match` in braces",
+                "Expected `{{`"
+            )
+            .with_source_code(self.lexer.source_code()));
+        }
+        self.next_token(); // jump over LBrace
+
+        let mut arms = Vec::new();
+        while self.peek_token.kind != TokenKind::RBrace {
+            if self.peek_token.kind == TokenKind::Eof {
+                miette::bail!("Expected `}}` at the end of `match`");
             }
-            self.next_token(); // jump over LBrace
-            self.parse_block_statement().ok()
-        } else {
-            None
-        };
+            self.next_token();
+            let pattern = self.parse_pattern()?;
+
+            if self.peek_token.kind != TokenKind::FatArrow {
+                let Span { start, end } = self.peek_token.span;
+                return Err(miette::miette!(
+                    severity = miette::Severity::Error,
+                    labels = vec![miette::LabeledSpan::at(start..end + 1, "here")],
+                    help = "Separate the pattern from the arm's body with `=>`",
+                    "Expected `=>`"
+                )
+                .with_source_code(self.lexer.source_code()));
+            }
+            self.next_token(); // jump over FatArrow
+            self.next_token();
 
-        Ok(Expression::If {
-            condition: Box::new(condition),
-            consequence,
-            alternative,
+            let body = if self.current_token.kind == TokenKind::LBrace {
+                self.parse_block_statement()?
+            } else {
+                let mut body = BlockStatement::new();
+                body.push(Statement::Expr(self.parse_expression(Precedence::Lowest)?));
+                body
+            };
+            arms.push((pattern, body));
+
+            if self.peek_token.kind == TokenKind::Comma {
+                self.next_token();
+            }
+        }
+        self.next_token(); // jump over RBrace
+
+        Ok(Expression::Match {
+            value: Box::new(value),
+            arms,
+        })
+    }
+
+    /// Parses a float literal, failing if it is too large to be finite.
+    fn parse_float(&self, literal: &str) -> Result<Expression> {
+        let value: f64 = literal.parse().expect("the lexer reads digits");
+        if value.is_infinite() {
+            let Span { start, end } = self.current_token.span;
+            return Err(miette::miette!(
+                severity = miette::Severity::Error,
+                labels = vec![miette::LabeledSpan::at(start..end + 1, "here")],
+                help = format!("Floats go up to about {:e}", f64::MAX),
+                "Float literal `{}` is too large",
+                literal
+            )
+            .with_source_code(self.lexer.source_code()));
+        }
+        Ok(Expression::FloatLiteral(Float(value)))
+    }
+
+    /// Parses a decimal literal or one prefixed by `0x`, `0o` or `0b`, into an
+    /// [`Expression::BigIntegerLiteral`] if it doesn't fit in an `isize`.
+    fn parse_integer(&self, literal: &str) -> Result<Expression> {
+        let Span { start, end } = self.current_token.span;
+        let (radix, name, allowed) = match literal.get(..2) {
+            Some("0x") => (16, "hexadecimal", "`0`-`9` and `a`-`f`"),
+            Some("0o") => (8, "octal", "`0`-`7`"),
+            Some("0b") => (2, "binary", "`0` and `1`"),
+            _ => (10, "decimal", "`0`-`9`"),
+        };
+        let offset = if radix == 10 { 0 } else { 2 };
+        let digits = &literal[offset..];
+        if digits.is_empty() {
+            return Err(miette::miette!(
+                severity = miette::Severity::Error,
+                labels = vec![miette::LabeledSpan::at(start..end + 1, "here")],
+                help = format!("Write {} digits after `{}`", name, literal),
+                "Missing digits in {} literal",
+                name
+            )
+            .with_source_code(self.lexer.source_code()));
+        }
+        if let Some((idx, c)) = digits.char_indices().find(|(_, c)| !c.is_digit(radix)) {
+            let position = start + offset + idx;
+            return Err(miette::miette!(
+                severity = miette::Severity::Error,
+                labels = vec![miette::LabeledSpan::at(
+                    position..position + 1,
+                    "invalid digit"
+                )],
+                help = format!("The digits of {} literals are {}", name, allowed),
+                "Invalid digit `{}` in {} literal",
+                c,
+                name
+            )
+            .with_source_code(self.lexer.source_code()));
+        }
+        let value = BigInt::parse_bytes(digits.as_bytes(), radix).expect("the digits are valid");
+        Ok(match isize::try_from(&value) {
+            Ok(i) => Expression::IntegerLiteral(i),
+            Err(_) => Expression::BigIntegerLiteral(value),
         })
     }
 
+    fn parse_pattern(&mut self) -> Result<Pattern> {
+        if self.current_token.kind == TokenKind::Ident("_".into()) {
+            return Ok(Pattern::Wildcard);
+        }
+        let start = self.current_token.span.start;
+        let expr = self.parse_expression(Precedence::Prefix)?;
+        match &expr {
+            Expression::IntegerLiteral(_)
+            | Expression::BigIntegerLiteral(_)
+            | Expression::StringLiteral(_)
+            | Expression::CharLiteral(_)
+            | Expression::Boolean(_)
+            | Expression::Null => Ok(Pattern::Literal(expr)),
+            Expression::Prefix {
+                operator, right, ..
+            } if operator == "-"
+                && matches!(
+                    **right,
+                    Expression::IntegerLiteral(_) | Expression::BigIntegerLiteral(_)
+                ) =>
+            {
+                Ok(Pattern::Literal(expr))
+            }
+            _ => {
+                let end = self.current_token.span.end;
+                Err(miette::miette!(
+                    severity = miette::Severity::Error,
+                    labels = vec![miette::LabeledSpan::at(start..end + 1, "here")],
+                    help = "Match against an integer, string, character, boolean or `null` literal, or `_`",
+                    "Invalid pattern"
+                )
+                .with_source_code(self.lexer.source_code()))
+            }
+        }
+    }
+
+    /// Parses the parenthesized condition following `if` or `while`.
+    fn parse_condition(&mut self) -> Result<Expression> {
+        if self.peek_token.kind != TokenKind::LParen {
+            let Span { start, end } = self.peek_token.span;
+            return Err(miette::miette!(
+                severity = miette::Severity::Error,
+                labels = vec![miette::LabeledSpan::at(start..end, "here")],
+                //url = "https://example.com",
+                help = "Use parentheses around condition",
+                "Expected `(`"
+            )
+            .with_source_code(self.lexer.source_code()));
+        }
+        self.next_token(); // jump over LParen
+        self.next_token();
+
+        let condition = self.parse_expression(Precedence::Lowest)?;
+        if self.peek_token.kind != TokenKind::RParen {
+            let Span { start, end } = self.peek_token.span;
+            return Err(miette::miette!(
+                severity = miette::Severity::Error,
+                labels = vec![miette::LabeledSpan::at(start..end, "here")],
+                //url = "https://example.com",
+                help = "Use parentheses around condition",
+                "Expected `)`"
+            )
+            .with_source_code(self.lexer.source_code()));
+        }
+        self.next_token(); // jump over RParen
+
+        Ok(condition)
+    }
+
     fn parse_block_statement(&mut self) -> Result<BlockStatement> {
-        let mut block_statement = BlockStatement::new();
         self.next_token();
+        self.parse_block_rest(BlockStatement::new(), None)
+    }
 
+    /// Parses the statements of a block from the current token up to its `}`,
+    /// appending them to `block_statement`. `discarded` is the value left
+    /// unused by the statement parsed before, if any.
+    fn parse_block_rest(
+        &mut self,
+        mut block_statement: BlockStatement,
+        mut discarded: Option<Span>,
+    ) -> Result<BlockStatement> {
         while self.current_token.kind != TokenKind::RBrace
             && self.current_token.kind != TokenKind::Eof
         {
-            if let Ok(stmt) = self.parse_statement() {
-                block_statement.push(stmt);
-            };
+            let comments = std::mem::take(&mut self.current_token.comments);
+            let start = self.current_token.span.start;
+            let stmt = self.parse_statement()?;
+            self.warn_discarded(discarded.take());
+            discarded = self.discarded_value(&stmt, start);
+            block_statement.push_with_comments(stmt, comments);
             self.next_token();
         }
+        block_statement.set_trailing_comments(std::mem::take(&mut self.current_token.comments));
 
         Ok(block_statement)
     }
 
     fn parse_function_literal(&mut self) -> Result<Expression> {
+        let generator = self.peek_token.kind == TokenKind::Asterisk;
+        if generator {
+            self.next_token();
+        }
         if self.peek_token.kind != TokenKind::LParen {
             miette::bail!("Expeced LParen after `fn`");
         }
         self.next_token();
 
-        let parameters = self.parse_function_parameters()?;
+        let (parameters, rest) = self.parse_function_parameters(TokenKind::RParen)?;
 
         if self.peek_token.kind != TokenKind::LBrace {
             miette::bail!("Expeced LBrace after parameter list");
         }
         self.next_token();
 
-        let body = self.parse_block_statement()?;
+        // `break` and `continue` cannot leave the function body.
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        let in_generator = std::mem::replace(&mut self.in_generator, generator);
+        let body = self.parse_block_statement();
+        self.loop_depth = loop_depth;
+        self.in_generator = in_generator;
+
+        Ok(Expression::FunctionLiteral {
+            parameters,
+            rest,
+            body: body?,
+            generator,
+        })
+    }
+
+    /// Parses `|x, y| x + y` into the same function literal as
+    /// `fn(x, y) { x + y }`, the body being a block or a single expression.
+    fn parse_lambda(&mut self) -> Result<Expression> {
+        let (parameters, rest) = self.parse_function_parameters(TokenKind::Bar)?;
+        self.next_token();
 
-        Ok(Expression::FunctionLiteral { parameters, body })
+        // `break` and `continue` cannot leave the function body.
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        let in_generator = std::mem::take(&mut self.in_generator);
+        let body = if self.current_token.kind == TokenKind::LBrace {
+            self.parse_block_statement()
+        } else {
+            self.parse_expression(Precedence::Lowest).map(|expr| {
+                let mut body = BlockStatement::new();
+                body.push(Statement::Expr(expr));
+                body
+            })
+        };
+        self.loop_depth = loop_depth;
+        self.in_generator = in_generator;
+
+        Ok(Expression::FunctionLiteral {
+            parameters,
+            rest,
+            body: body?,
+            generator: false,
+        })
     }
 
-    fn parse_function_parameters(&mut self) -> Result<Vec<Identifier>> {
+    /// Parses the parameters up to `end`, `)` or the closing `|` of a lambda,
+    /// and the optional `..rest` parameter, which must come last.
+    fn parse_function_parameters(
+        &mut self,
+        end: TokenKind,
+    ) -> Result<(Vec<Identifier>, Option<Identifier>)> {
         let mut identifiers = Vec::new();
+        let mut rest = None;
 
-        if self.peek_token.kind == TokenKind::RParen {
+        if self.peek_token.kind == end {
             self.next_token();
-            return Ok(identifiers);
+            return Ok((identifiers, rest));
         }
-        self.next_token();
 
-        let identifier = Identifier::new(self.current_token.kind.to_string());
-        identifiers.push(identifier);
-
-        while self.peek_token.kind == TokenKind::Comma {
+        loop {
             self.next_token();
+            if self.current_token.kind == TokenKind::DotDot {
+                self.next_token();
+                rest = Some(Identifier::new(self.parse_binding_name()?));
+                if self.peek_token.kind == TokenKind::Comma {
+                    self.next_token();
+                }
+                if self.peek_token.kind != end {
+                    let Span { start, end: stop } = self.peek_token.span;
+                    return Err(miette::miette!(
+                        severity = miette::Severity::Error,
+                        labels = vec![miette::LabeledSpan::at(start..stop + 1, "here")],
+                        help = "Move the rest parameter to the end of the list",
+                        "Expected `{}` after the rest parameter",
+                        end
+                    )
+                    .with_source_code(self.lexer.source_code()));
+                }
+                break;
+            }
+            identifiers.push(Identifier::new(self.parse_binding_name()?));
+
+            if self.peek_token.kind != TokenKind::Comma {
+                break;
+            }
             self.next_token();
-            identifiers.push(Identifier::new(self.current_token.kind.to_string()));
+            // A trailing comma
+            if self.peek_token.kind == end {
+                break;
+            }
         }
 
-        if self.peek_token.kind != TokenKind::RParen {
-            miette::bail!("Expected RParen")
+        if self.peek_token.kind != end {
+            match end {
+                TokenKind::RParen => miette::bail!("Expected RParen"),
+                _ => miette::bail!("Expected `{}` after the parameters", end),
+            }
         }
         self.next_token();
 
-        Ok(identifiers)
+        Ok((identifiers, rest))
     }
 
-    fn parse_call_expression(&mut self, function: Expression) -> Result<Expression> {
+    fn parse_call_expression(&mut self, function: Expression, start: usize) -> Result<Expression> {
         let arguments = self.parse_expression_list(TokenKind::RParen)?;
         Ok(Expression::Call {
             function: Box::new(function),
             arguments,
+            span: Span {
+                start,
+                end: self.current_token.span.end,
+            },
+        })
+    }
+
+    /// Desugars `x |> f` to `f(x)` and `x |> g(2)` to `g(x, 2)`.
+    fn parse_pipe_expression(&mut self, left: Expression, start: usize) -> Result<Expression> {
+        self.next_token();
+        let right = self.parse_expression(Precedence::Pipe)?;
+        let span = Span {
+            start,
+            end: self.current_token.span.end,
+        };
+        Ok(match right {
+            Expression::Call {
+                function,
+                mut arguments,
+                ..
+            } => {
+                arguments.insert(0, left);
+                Expression::Call {
+                    function,
+                    arguments,
+                    span,
+                }
+            }
+            function => Expression::Call {
+                function: Box::new(function),
+                arguments: vec![left],
+                span,
+            },
         })
     }
 
@@ -419,6 +1505,17 @@ impl<'a> Parser<'a> {
     //    Ok(args)
     //}
 
+    /// Parses an element of an array literal or an argument, which may be
+    /// spread with `..`.
+    fn parse_list_item(&mut self) -> Result<Expression> {
+        if self.current_token.kind != TokenKind::DotDot {
+            return self.parse_expression(Precedence::Lowest);
+        }
+        self.next_token();
+        let value = self.parse_expression(Precedence::Lowest)?;
+        Ok(Expression::Spread(Box::new(value)))
+    }
+
     fn parse_expression_list(&mut self, end: TokenKind) -> Result<Vec<Expression>> {
         let mut list = Vec::new();
 
@@ -428,12 +1525,16 @@ impl<'a> Parser<'a> {
         }
         self.next_token();
 
-        list.push(self.parse_expression(Precedence::Lowest)?);
+        list.push(self.parse_list_item()?);
 
         while self.peek_token.kind == TokenKind::Comma {
             self.next_token();
+            // A trailing comma
+            if self.peek_token.kind == end {
+                break;
+            }
             self.next_token();
-            list.push(self.parse_expression(Precedence::Lowest)?);
+            list.push(self.parse_list_item()?);
         }
 
         if self.peek_token.kind != end {
@@ -448,34 +1549,163 @@ impl<'a> Parser<'a> {
         Ok(list)
     }
 
-    fn parse_index_expression(&mut self, left: Expression) -> Result<Expression> {
+    /// Parses `left.name` from the `.`, or `left?.name` from the `?.` if
+    /// `optional`.
+    fn parse_field_expression(&mut self, left: Expression, optional: bool) -> Result<Expression> {
+        let TokenKind::Ident(name) = &self.peek_token.kind else {
+            let Span { start, end } = self.peek_token.span;
+            return Err(miette::miette!(
+                severity = miette::Severity::Error,
+                labels = vec![miette::LabeledSpan::at(start..end + 1, "here")],
+                help = "Use `[...]` for keys that are not names, e.g. `hash[\"key\"]`",
+                "Expected a field name after `{}`",
+                self.current_token.kind
+            )
+            .with_source_code(self.lexer.source_code()));
+        };
+        let name = Identifier::new(name.clone());
         self.next_token();
-        let index = self.parse_expression(Precedence::Lowest)?;
+        Ok(Expression::Field {
+            left: Box::new(left),
+            name,
+            optional,
+        })
+    }
 
+    /// Parses `left[index]` as well as the slice forms `left[start:end]`,
+    /// `left[start:]`, `left[:end]` and `left[:]`, or `left?.[index]` if
+    /// `optional`.
+    fn parse_index_expression(&mut self, left: Expression, optional: bool) -> Result<Expression> {
+        let bracket = self.current_token.span;
+        self.next_token();
+
+        let start = if self.current_token.kind == TokenKind::Colon {
+            None
+        } else {
+            let index = self.parse_expression(Precedence::Lowest)?;
+            if self.peek_token.kind != TokenKind::Colon {
+                self.expect_peek_rbracket()?;
+                return Ok(Expression::IndexExpr {
+                    left: Box::new(left),
+                    index: Box::new(index),
+                    optional,
+                });
+            }
+            self.next_token();
+            Some(Box::new(index))
+        };
+
+        let end = if self.peek_token.kind == TokenKind::RBracket {
+            None
+        } else {
+            self.next_token();
+            Some(Box::new(self.parse_expression(Precedence::Lowest)?))
+        };
+        self.expect_peek_rbracket()?;
+
+        if optional {
+            return Err(miette::miette!(
+                severity = miette::Severity::Error,
+                labels = vec![miette::LabeledSpan::at(
+                    bracket.start..self.current_token.span.end + 1,
+                    "here"
+                )],
+                help = "Slice with `[start:end]` after checking for `null`",
+                "Slices cannot be optional"
+            )
+            .with_source_code(self.lexer.source_code()));
+        }
+
+        Ok(Expression::Slice {
+            left: Box::new(left),
+            start,
+            end,
+        })
+    }
+
+    fn expect_peek_rbracket(&mut self) -> Result<()> {
         if self.peek_token.kind != TokenKind::RBracket {
             return Err(miette::miette!(
                 "Expected RBracket, got {}",
                 self.peek_token.kind
             ));
         }
-
         self.next_token();
+        Ok(())
+    }
+
+    /// Parses what follows `{`: a hash literal if it is empty or its first
+    /// key is followed by `:`, and a block expression otherwise.
+    fn parse_brace_expression(&mut self) -> Result<Expression> {
+        if self.peek_token.kind == TokenKind::RBrace {
+            self.next_token();
+            return Ok(Expression::HashLiteral(Vec::new()));
+        }
+        self.next_token();
+
+        if matches!(
+            self.current_token.kind,
+            TokenKind::Let
+                | TokenKind::Return
+                | TokenKind::Throw
+                | TokenKind::Yield
+                | TokenKind::Break
+                | TokenKind::Continue
+        ) && self.peek_token.kind != TokenKind::Colon
+        {
+            let block = self.parse_block_rest(BlockStatement::new(), None)?;
+            return Ok(Expression::Block(block));
+        }
+
+        let comments = std::mem::take(&mut self.current_token.comments);
+        let start = self.current_token.span.start;
+        let first = self.parse_hash_key()?;
+        if self.peek_token.kind == TokenKind::Colon {
+            return self.parse_hash_literal(first);
+        }
+
+        if self.peek_token.kind == TokenKind::Semicolon {
+            self.next_token();
+        }
+        let stmt = Statement::Expr(first);
+        let discarded = self.discarded_value(&stmt, start);
+        let mut block = BlockStatement::new();
+        block.push_with_comments(stmt, comments);
+        self.next_token();
+        Ok(Expression::Block(self.parse_block_rest(block, discarded)?))
+    }
 
-        Ok(Expression::IndexExpr {
-            left: Box::new(left),
-            index: Box::new(index),
-        })
+    fn parse_hash_key(&mut self) -> Result<Expression> {
+        // A keyword right before the colon reads as a string key, so
+        // `{fn: 1}` means `{"fn": 1}`. The literals keep their values.
+        if self.current_token.kind.is_keyword()
+            && !matches!(
+                self.current_token.kind,
+                TokenKind::True | TokenKind::False | TokenKind::Null
+            )
+            && self.peek_token.kind == TokenKind::Colon
+        {
+            return Ok(Expression::StringLiteral(
+                self.current_token.kind.to_string(),
+            ));
+        }
+        self.parse_expression(Precedence::Lowest)
     }
 
-    fn parse_hash_literal(&mut self) -> Result<Expression> {
+    /// Parses the rest of a hash literal after its first `key`.
+    fn parse_hash_literal(&mut self, mut key: Expression) -> Result<Expression> {
         let mut pairs = Vec::new();
 
-        while self.peek_token.kind != TokenKind::RBrace {
-            self.next_token();
-            let key = self.parse_expression(Precedence::Lowest)?;
-
+        loop {
             if self.peek_token.kind != TokenKind::Colon {
-                return Err(miette::miette!("Expected Colon"));
+                let Span { start, end } = self.peek_token.span;
+                return Err(miette::miette!(
+                    severity = miette::Severity::Error,
+                    labels = vec![miette::LabeledSpan::at(start..end + 1, "here")],
+                    help = "Hash entries are written as `key: value`",
+                    "Expected `:` after the hash key"
+                )
+                .with_source_code(self.lexer.source_code()));
             }
             self.next_token();
             self.next_token();
@@ -483,19 +1713,27 @@ impl<'a> Parser<'a> {
             let value = self.parse_expression(Precedence::Lowest)?;
             pairs.push((key, value));
 
-            if self.peek_token.kind != TokenKind::RBrace && self.peek_token.kind != TokenKind::Comma {
-                return Err(miette::miette!("Expected RBrace or Comma"))
+            if self.peek_token.kind != TokenKind::RBrace && self.peek_token.kind != TokenKind::Comma
+            {
+                let Span { start, end } = self.peek_token.span;
+                return Err(miette::miette!(
+                    severity = miette::Severity::Error,
+                    labels = vec![miette::LabeledSpan::at(start..end + 1, "here")],
+                    help = "Separate hash entries with `,` and close the hash with `}`",
+                    "Expected `,` or `}}` after the hash value"
+                )
+                .with_source_code(self.lexer.source_code()));
             }
 
             if self.peek_token.kind == TokenKind::Comma {
                 self.next_token();
             }
+            if self.peek_token.kind == TokenKind::RBrace {
+                break;
+            }
+            self.next_token();
+            key = self.parse_hash_key()?;
         }
-
-        if self.peek_token.kind != TokenKind::RBrace {
-            return Err(miette::miette!("Expected RBrace"))
-        }
-
         self.next_token();
 
         Ok(Expression::HashLiteral(pairs))
@@ -509,7 +1747,7 @@ mod tests {
     fn program_from_input(input: &str) -> Program {
         let lexer = Lexer::new(input);
         let mut parser = Parser::new(lexer);
-        parser.parse_program()
+        parser.parse_program().0
     }
 
     #[test]
@@ -772,6 +2010,8 @@ return 993322;
         assert_eq!(program_from_input("a + b - c").to_string(), "((a + b) - c)");
         assert_eq!(program_from_input("a * b * c").to_string(), "((a * b) * c)");
         assert_eq!(program_from_input("a * b / c").to_string(), "((a * b) / c)");
+        assert_eq!(program_from_input("a % b * c").to_string(), "((a % b) * c)");
+        assert_eq!(program_from_input("a + b % c").to_string(), "(a + (b % c))");
         assert_eq!(
             program_from_input("a + b * c + d / e - f").to_string(),
             "(((a + (b * c)) + (d / e)) - f)"
@@ -852,6 +2092,15 @@ return 993322;
         assert_eq!(program[0], Statement::Expr(Expression::Boolean(true)));
     }
 
+    #[test]
+    fn test_parsing_null() {
+        let program = program_from_input("null;");
+        assert_eq!(program.len(), 1);
+        assert_eq!(program[0], Statement::Expr(Expression::Null));
+
+        assert_eq!(program_from_input("x == null").to_string(), "(x == null)");
+    }
+
     #[test]
     fn test_if_expression() {
         let input = "if (x < y) { x }";
@@ -905,6 +2154,50 @@ return 993322;
         );
     }
 
+    #[test]
+    fn test_else_if_expression() {
+        let program = program_from_input("if (x < y) { x } else if (x > y) { y } else { 0 }");
+        assert_eq!(program.len(), 1);
+        let Statement::Expr(Expression::If { alternative, .. }) = &program[0] else {
+            panic!("expected if expression, got {}", program[0]);
+        };
+        let alternative = alternative.as_ref().unwrap();
+        assert_eq!(alternative.len(), 1);
+        assert_eq!(
+            alternative[0].to_string(),
+            "if (x > y) {\n    y\n} else {\n    0\n}"
+        );
+        assert_eq!(
+            program.to_string(),
+            "if (x < y) {\n    x\n} else if (x > y) {\n    y\n} else {\n    0\n}"
+        );
+    }
+
+    #[test]
+    fn test_match_expression() {
+        let program = program_from_input(r#"match x + 1 { 1 => "a", -2 => { b } _ => null }"#);
+        assert_eq!(program.len(), 1);
+        assert_eq!(
+            program.to_string(),
+            "match (x + 1) {
+    1 => {
+        a
+    },
+    (-2) => {
+        b
+    },
+    _ => {
+        null
+    },
+}"
+        );
+
+        let lexer = Lexer::new("match x { y => 1 }");
+        let mut parser = Parser::new(lexer);
+        let (_, errors) = parser.parse_program();
+        assert_eq!(errors[0].to_string(), "Invalid pattern");
+    }
+
     #[test]
     fn test_function_literal() {
         let input = "fn(x, y) { x + y; }";
@@ -922,7 +2215,9 @@ return 993322;
             program[0],
             Statement::Expr(Expression::FunctionLiteral {
                 parameters: vec![Identifier::new("x".into()), Identifier::new("y".into())],
+                rest: None,
                 body,
+                generator: false,
             })
         )
     }
@@ -935,7 +2230,9 @@ return 993322;
             program[0],
             Statement::Expr(Expression::FunctionLiteral {
                 parameters: vec![],
+                rest: None,
                 body: BlockStatement::new(),
+                generator: false,
             })
         );
 
@@ -945,7 +2242,9 @@ return 993322;
             program[0],
             Statement::Expr(Expression::FunctionLiteral {
                 parameters: vec![Identifier::new("x".into())],
+                rest: None,
                 body: BlockStatement::new(),
+                generator: false,
             })
         );
 
@@ -959,11 +2258,41 @@ return 993322;
                     Identifier::new("y".into()),
                     Identifier::new("z".into())
                 ],
+                rest: None,
                 body: BlockStatement::new(),
+                generator: false,
             })
         );
     }
 
+    #[test]
+    fn test_lambda_parsing() {
+        let tests = [
+            ("|x, y| x + y", "fn(x, y) { x + y }"),
+            ("|| 1", "fn() { 1 }"),
+            ("|x| { let y = x; y }", "fn(x) { let y = x; y }"),
+            ("|x, ..xs| xs", "fn(x, ..xs) { xs }"),
+            ("map(xs, |x| x * 2)", "map(xs, fn(x) { x * 2 })"),
+            ("|f| |x| f(x)", "fn(f) { fn(x) { f(x) } }"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(
+                program_from_input(input).to_string(),
+                program_from_input(expected).to_string(),
+                "{}",
+                input
+            );
+        }
+
+        for (input, expected) in [
+            ("|x 1", "Expected `|` after the parameters"),
+            ("|..xs, y| y", "Expected `|` after the rest parameter"),
+        ] {
+            let (_, errors) = Parser::new(Lexer::new(input)).parse_program();
+            assert_eq!(errors[0].to_string(), expected, "{}", input);
+        }
+    }
+
     #[test]
     fn test_call_expression_parsing() {
         let program = program_from_input("add(1, 2 * 3, 4 + 5)");
@@ -986,7 +2315,8 @@ return 993322;
                         left: Box::new(Expression::IntegerLiteral(4)),
                         right: Box::new(Expression::IntegerLiteral(5)),
                     },
-                ]
+                ],
+                span: Span { start: 0, end: 19 },
             })
         );
     }
@@ -1035,7 +2365,8 @@ return 993322;
                     operator: "+".into(),
                     left: Box::new(Expression::IntegerLiteral(1)),
                     right: Box::new(Expression::IntegerLiteral(1)),
-                })
+                }),
+                optional: false,
             })
         )
     }
@@ -1106,4 +2437,747 @@ return 993322;
             ]))
         );
     }
+
+    #[test]
+    fn test_parsing_hash_literal_keyword_keys() {
+        let program = program_from_input("{fn: 1, let: 2, true: 3}");
+        assert_eq!(
+            program[0],
+            Statement::Expr(Expression::HashLiteral(vec![
+                (
+                    Expression::StringLiteral("fn".into()),
+                    Expression::IntegerLiteral(1)
+                ),
+                (
+                    Expression::StringLiteral("let".into()),
+                    Expression::IntegerLiteral(2)
+                ),
+                (Expression::Boolean(true), Expression::IntegerLiteral(3)),
+            ]))
+        );
+
+        for (input, expected) in [
+            ("{1: 2, 3 4}", "Expected `:` after the hash key"),
+            ("{1: 2 3}", "Expected `,` or `}` after the hash value"),
+        ] {
+            let (_, errors) = Parser::new(Lexer::new(input)).parse_program();
+            assert_eq!(errors[0].to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_block_expressions() {
+        let tests = [
+            (
+                "{ let tmp = 1; tmp + 2 }",
+                "{\n    let tmp = 1;\n    (tmp + 2)\n}",
+            ),
+            ("{ x; y }", "{\n    x\n    y\n}"),
+            ("{ return 1; }", "{\n    return 1;\n}"),
+            (r#"{"a": 1}"#, "{a:1}"),
+            ("{}", "{}"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(program_from_input(input).to_string(), expected, "{}", input);
+        }
+        assert!(matches!(
+            program_from_input("{ x }")[0],
+            Statement::Expr(Expression::Block(_))
+        ));
+    }
+
+    #[test]
+    fn test_assign_expression() {
+        let program = program_from_input("x = 5;");
+        assert_eq!(program.len(), 1);
+        assert_eq!(
+            program[0],
+            Statement::Expr(Expression::Assign {
+                token: Token::new(TokenKind::Assign, 2, 2),
+                name: Identifier::new("x".into()),
+                value: Box::new(Expression::IntegerLiteral(5)),
+            })
+        );
+
+        assert_eq!(
+            program_from_input("x = y = 1 + 2").to_string(),
+            "x = y = (1 + 2)"
+        );
+    }
+
+    #[test]
+    fn test_compound_assign_expression() {
+        let program = program_from_input("x += 5;");
+        assert_eq!(program.len(), 1);
+        assert_eq!(
+            program[0],
+            Statement::Expr(Expression::Assign {
+                token: Token::new(TokenKind::PlusAssign, 2, 3),
+                name: Identifier::new("x".into()),
+                value: Box::new(Expression::Infix {
+                    token: Token::new(TokenKind::Plus, 2, 3),
+                    operator: "+".into(),
+                    left: Box::new(Expression::Ident(Identifier::new("x".into()))),
+                    right: Box::new(Expression::IntegerLiteral(5)),
+                }),
+            })
+        );
+
+        assert_eq!(program_from_input("x -= 1").to_string(), "x = (x - 1)");
+        assert_eq!(
+            program_from_input("x *= 2 + 3").to_string(),
+            "x = (x * (2 + 3))"
+        );
+        assert_eq!(program_from_input("x /= 2").to_string(), "x = (x / 2)");
+    }
+
+    #[test]
+    fn test_index_assign_expression() {
+        let tests = [
+            ("a[0] = 42;", "(a[0]) = 42"),
+            ("a[i][j + 1] = b[0] = 1", "((a[i])[(j + 1)]) = (b[0]) = 1"),
+            ("a[0] += 2", "(a[0]) = ((a[0]) + 2)"),
+            ("h.k = 1", "(h.k) = 1"),
+            ("h[\"k\"].n -= 1", "((h[k]).n) = (((h[k]).n) - 1)"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(program_from_input(input).to_string(), expected, "{}", input);
+        }
+
+        for input in [
+            "f()[0] = 1",
+            "a?.[0] = 1",
+            "a?.k = 1",
+            "a[0:1] = [2]",
+            "1 = 2",
+        ] {
+            let (_, errors) = Parser::new(Lexer::new(input)).parse_program();
+            assert_eq!(
+                errors[0].to_string(),
+                "Invalid assignment target",
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_assign_target() {
+        let lexer = Lexer::new("1 + 2 = 3");
+        let mut parser = Parser::new(lexer);
+        let (_, errors) = parser.parse_program();
+        assert_eq!(errors[0].to_string(), "Invalid assignment target");
+        let label = errors[0].labels().unwrap().next().unwrap();
+        assert_eq!((label.offset(), label.len()), (6, 1));
+    }
+
+    #[test]
+    fn test_invalid_escape_is_parse_error() {
+        let lexer = Lexer::new(r#"let s = "a\qb";"#);
+        let mut parser = Parser::new(lexer);
+        let (program, errors) = parser.parse_program();
+        assert_eq!(program.len(), 1);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].to_string(), "Invalid escape sequence `\\q`");
+    }
+
+    #[test]
+    fn test_comments_are_preserved() {
+        let input = "// adds two numbers
+let add = fn(x, y) {
+    // the sum
+    x + y
+    // done
+};
+add(1, 2) // call it
+";
+        let program = program_from_input(input);
+        assert_eq!(program.len(), 2);
+        assert_eq!(program.comments(0)[0].text, "// adds two numbers");
+        assert!(program.comments(1).is_empty());
+        assert_eq!(program.trailing_comments()[0].text, "// call it");
+        assert_eq!(
+            program.to_string(),
+            "// adds two numbers
+let add = fn(x, y) {
+    // the sum
+    (x + y)
+    // done
+};add(1, 2)
+// call it"
+        );
+    }
+
+    #[test]
+    fn test_while_expression() {
+        let program =
+            program_from_input("while (x < 10) { if (x == 5) { break; } x += 1; continue }");
+        assert_eq!(program.len(), 1);
+        assert_eq!(
+            program.to_string(),
+            "while (x < 10) {
+    if (x == 5) {
+        break;
+    }
+    x = (x + 1)
+    continue;
+}"
+        );
+    }
+
+    #[test]
+    fn test_do_while_expression() {
+        let program = program_from_input("do { x += 1; } while (x < 10);");
+        assert_eq!(program.len(), 1);
+        assert_eq!(
+            program.to_string(),
+            "do {
+    x = (x + 1)
+} while (x < 10)"
+        );
+
+        for (input, expected) in [
+            (
+                "do { x += 1; } until (x > 10);",
+                "Expected `while` after the body of `do`",
+            ),
+            (
+                "do x += 1; while (x < 10);",
+                "Expected Left Brace at beginning of loop body",
+            ),
+        ] {
+            let lexer = Lexer::new(input);
+            let mut parser = Parser::new(lexer);
+            let (_, errors) = parser.parse_program();
+            assert_eq!(errors[0].to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_chained_comparisons() {
+        let tests = [
+            ("0 < x < 10", "if (0 < x) {\n    (x < 10)\n} else {\n    false\n}"),
+            (
+                "a > f() > c",
+                "{\n    let $chain1 = f();\n    if (a > $chain1) {\n        ($chain1 > c)\n    } else {\n        false\n    }\n}",
+            ),
+            ("(0 < x) < 10", "((0 < x) < 10)"),
+            ("0 < x == true", "((0 < x) == true)"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(program_from_input(input).to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_loop_expression() {
+        let program = program_from_input("let x = loop { if (done) { break 1 + 2; } break }");
+        assert_eq!(
+            program.to_string(),
+            "let x = loop {
+    if done {
+        break (1 + 2);
+    }
+    break;
+};"
+        );
+    }
+
+    #[test]
+    fn test_for_expression() {
+        let program = program_from_input("for (i in 0..n + 1) { if (i == 5) { break; } puts(i) }");
+        assert_eq!(program.len(), 1);
+        assert_eq!(
+            program.to_string(),
+            "for (i in (0 .. (n + 1))) {
+    if (i == 5) {
+        break;
+    }
+    puts(i)
+}"
+        );
+
+        let program = program_from_input("a < 1..2 == b");
+        assert_eq!(program.to_string(), "((a < (1 .. 2)) == b)");
+
+        let lexer = Lexer::new("for (i of xs) {}");
+        let mut parser = Parser::new(lexer);
+        let (_, errors) = parser.parse_program();
+        assert_eq!(errors[0].to_string(), "Expected `in`");
+    }
+
+    #[test]
+    fn test_field_expression() {
+        let tests = [
+            ("person.name", "(person.name)"),
+            ("a.b.c[0]", "(((a.b).c)[0])"),
+            ("f(x).name + -p.age", "((f(x).name) + (-(p.age)))"),
+            ("p.greet(1)", "(p.greet)(1)"),
+            ("a?.b.c", "((a?.b).c)"),
+            ("a?.[i + 1]?.b", "((a?.[(i + 1)])?.b)"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(program_from_input(input).to_string(), expected, "{}", input);
+        }
+
+        for input in ["p.1", "p.", "p.\"name\""] {
+            let lexer = Lexer::new(input);
+            let mut parser = Parser::new(lexer);
+            let (_, errors) = parser.parse_program();
+            assert_eq!(
+                errors[0].to_string(),
+                "Expected a field name after `.`",
+                "{}",
+                input
+            );
+        }
+
+        let (_, errors) = Parser::new(Lexer::new("a?.(1)")).parse_program();
+        assert_eq!(errors[0].to_string(), "Expected a field name after `?.`");
+        let (_, errors) = Parser::new(Lexer::new("a?.[1:]")).parse_program();
+        assert_eq!(errors[0].to_string(), "Slices cannot be optional");
+    }
+
+    #[test]
+    fn test_nesting_depth() {
+        let nested =
+            |open: &str, close: &str, depth| open.repeat(depth) + "1" + &close.repeat(depth);
+        // Unoptimized builds need far more stack per level than the 2 MiB of
+        // test threads, so this runs with the 8 MiB of a main thread.
+        let deepest = std::thread::Builder::new()
+            .stack_size(8 << 20)
+            .spawn(move || {
+                for (open, close) in [
+                    ("(", ")"),
+                    ("-", ""),
+                    ("if (true) { ", " }"),
+                    ("fn() { ", " }"),
+                ] {
+                    let input = nested(open, close, Parser::DEFAULT_MAX_DEPTH - 1);
+                    let (_, errors) = Parser::new(Lexer::new(&input)).parse_program();
+                    assert!(errors.is_empty(), "{}", open);
+
+                    let input = nested(open, close, 100_000);
+                    let (_, errors) = Parser::new(Lexer::new(&input)).parse_program();
+                    assert_eq!(errors.len(), 1, "{}", open);
+                    assert_eq!(errors[0].to_string(), "expression too deeply nested");
+                }
+            })
+            .unwrap();
+        deepest.join().unwrap();
+
+        let input = nested("[", "]", 10);
+        let (_, errors) = Parser::new(Lexer::new(&input))
+            .with_max_depth(5)
+            .parse_program();
+        assert_eq!(errors[0].to_string(), "expression too deeply nested");
+    }
+
+    #[test]
+    fn test_coalesce_expression() {
+        let tests = [
+            ("a ?? b ?? c", "((a ?? b) ?? c)"),
+            ("h.x ?? 1 + 2", "((h.x) ?? (1 + 2))"),
+            ("a ?? b == c", "(a ?? (b == c))"),
+            ("x = a ?? 0", "x = (a ?? 0)"),
+            ("a ?? 0 |> f", "f((a ?? 0))"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(program_from_input(input).to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_pipe_expression() {
+        let tests = [
+            ("x |> f |> g(2)", "g(f(x), 2)"),
+            ("a + 1 |> f", "f((a + 1))"),
+            (
+                "let y = [1] |> push(2) |> len;",
+                "let y = len(push([1], 2));",
+            ),
+            ("x |> make()(1)", "make()(x, 1)"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(program_from_input(input).to_string(), expected, "{}", input);
+        }
+
+        let program = program_from_input("x |> f");
+        let Statement::Expr(Expression::Call { span, .. }) = &program[0] else {
+            panic!("expected a call, got {}", program[0]);
+        };
+        assert_eq!(*span, Span { start: 0, end: 5 });
+    }
+
+    #[test]
+    fn test_destructure_statement() {
+        let program = program_from_input("let [a, b,] = f(x);");
+        assert_eq!(
+            program[0],
+            Statement::Destructure {
+                token: Token::new(TokenKind::Let, 0, 2),
+                target: Target::Array(vec![
+                    Identifier::new("a".into()),
+                    Identifier::new("b".into())
+                ]),
+                value: Expression::Call {
+                    function: Box::new(Expression::Ident(Identifier::new("f".into()))),
+                    arguments: vec![Expression::Ident(Identifier::new("x".into()))],
+                    span: Span { start: 14, end: 17 },
+                },
+            }
+        );
+        assert_eq!(program.to_string(), "let [a, b] = f(x);");
+
+        let program = program_from_input("let {name, age: a,} = person;");
+        assert_eq!(
+            program[0],
+            Statement::Destructure {
+                token: Token::new(TokenKind::Let, 0, 2),
+                target: Target::Hash(vec![
+                    ("name".into(), Identifier::new("name".into())),
+                    ("age".into(), Identifier::new("a".into())),
+                ]),
+                value: Expression::Ident(Identifier::new("person".into())),
+            }
+        );
+        assert_eq!(program.to_string(), "let {name, age: a} = person;");
+
+        let program = program_from_input("let q, r = f(x);");
+        assert_eq!(program.to_string(), "let [q, r] = f(x);");
+        let program = program_from_input("fn() { return a, b + 1; }");
+        assert_eq!(program.to_string(), "fn() {\n    return [a, (b + 1)];\n}");
+
+        for (input, expected) in [
+            ("let [a b] = x;", "Expected `,` or `]`, got: b"),
+            ("let [a, 1] = x;", "Expected Ident, got: 1"),
+            ("let [a] x;", "Expected Assignment"),
+            ("let a, 1 = x;", "Expected Ident, got: 1"),
+            ("let {a b} = x;", "Expected `,` or `}`, got: b"),
+            ("let {\"a\": b} = x;", "Expected Ident, got: \"a\""),
+            ("let {a: 1} = x;", "Expected Ident, got: 1"),
+        ] {
+            let lexer = Lexer::new(input);
+            let mut parser = Parser::new(lexer);
+            let (_, errors) = parser.parse_program();
+            assert_eq!(errors[0].to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_trailing_commas() {
+        let tests = [
+            ("[1, 2, 3,]", "[1, 2, 3]"),
+            ("add(1, 2,)", "add(1, 2)"),
+            ("fn(x, y,) { x }", "fn(x, y) {\n    x\n}"),
+            ("fn(x, ..xs,) { x }", "fn(x, ..xs) {\n    x\n}"),
+            ("[..a, 1, ..b,]", "[..a, 1, ..b]"),
+            ("f(..xs, 0..2)", "f(..xs, (0 .. 2))"),
+            (r#"{"a": 1, "b": 2,}"#, "{a:1, b:2}"),
+            ("{fn: 1, let: 2,}", "{fn:1, let:2}"),
+            ("|x, y,| x", "fn(x, y) {\n    x\n}"),
+            ("let [a, b,] = c;", "let [a, b] = c;"),
+            ("let {a, b,} = c;", "let {a, b} = c;"),
+            ("[\n    1,\n    2,\n]", "[1, 2]"),
+        ];
+        for (input, expected) in tests {
+            let lexer = Lexer::new(input);
+            let mut parser = Parser::new(lexer);
+            let (program, errors) = parser.parse_program();
+            assert!(errors.is_empty(), "{}", input);
+            assert_eq!(program.to_string(), expected, "{}", input);
+        }
+
+        for input in [
+            "[,]",
+            "f(1,,)",
+            "fn(,) {}",
+            "{,}",
+            "fn(..a, b) {}",
+            "fn(..) {}",
+        ] {
+            let lexer = Lexer::new(input);
+            let mut parser = Parser::new(lexer);
+            let (_, errors) = parser.parse_program();
+            assert!(!errors.is_empty(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_integer_literals() {
+        let tests = [
+            ("0xFF", 255),
+            ("0x1f", 31),
+            ("0o755", 493),
+            ("0b1010", 10),
+            ("007", 7),
+        ];
+        for (input, expected) in tests {
+            let program = program_from_input(input);
+            assert_eq!(
+                program[0],
+                Statement::Expr(Expression::IntegerLiteral(expected))
+            );
+        }
+
+        let tests = [
+            ("9223372036854775807", "9223372036854775807"),
+            ("9223372036854775808", "9223372036854775808"),
+            ("99999999999999999999999", "99999999999999999999999"),
+            ("0xffffffffffffffffff", "4722366482869645213695"),
+        ];
+        for (input, expected) in tests {
+            let program = program_from_input(input);
+            assert_eq!(program.to_string(), expected, "{}", input);
+        }
+        assert_eq!(
+            program_from_input("9223372036854775808")[0],
+            Statement::Expr(Expression::BigIntegerLiteral(
+                "9223372036854775808".parse().unwrap()
+            ))
+        );
+
+        let tests = [
+            ("0b102", "Invalid digit `2` in binary literal", 4),
+            ("x + 0o78", "Invalid digit `8` in octal literal", 7),
+            ("0xfg", "Invalid digit `g` in hexadecimal literal", 3),
+            ("0x;", "Missing digits in hexadecimal literal", 0),
+            ("x + 1e999", "Float literal `1e999` is too large", 4),
+        ];
+        for (input, expected, offset) in tests {
+            let lexer = Lexer::new(input);
+            let mut parser = Parser::new(lexer);
+            let (_, errors) = parser.parse_program();
+            assert_eq!(errors[0].to_string(), expected, "{}", input);
+            let label = errors[0].labels().unwrap().next().unwrap();
+            assert_eq!(label.offset(), offset, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_reserved_words() {
+        let tests = [
+            (
+                "let true = 5;",
+                "`true` is a reserved word and cannot be used as a name",
+            ),
+            (
+                "let f = fn(x, in) { x };",
+                "`in` is a reserved word and cannot be used as a name",
+            ),
+            (
+                "for (fn in xs) {}",
+                "`fn` is a reserved word and cannot be used as a name",
+            ),
+            ("Let x = 5;", "Unexpected identifier `Let`"),
+            ("let f = fn(1) { 1 };", "Expected Ident, got: 1"),
+        ];
+        for (input, expected) in tests {
+            let lexer = Lexer::new(input);
+            let mut parser = Parser::new(lexer);
+            let (_, errors) = parser.parse_program();
+            assert_eq!(errors[0].to_string(), expected, "{}", input);
+        }
+
+        let lexer = Lexer::new("let x = 1;\nLet y = 2;");
+        let mut parser = Parser::new(lexer);
+        let (_, errors) = parser.parse_program();
+        assert_eq!(errors.len(), 1);
+        let help = errors[0].help().unwrap().to_string();
+        assert_eq!(help, "Keywords are lowercase, did you mean `let`?");
+        let label = errors[0].labels().unwrap().next().unwrap();
+        assert_eq!((label.offset(), label.len()), (11, 3));
+    }
+
+    #[test]
+    fn test_parse_outcome() {
+        let outcome = Parser::new(Lexer::new("let x = 1; x")).parse();
+        assert!(outcome.is_clean());
+        assert_eq!(outcome.into_result().unwrap().len(), 2);
+
+        let outcome = Parser::new(Lexer::new("let len = 1; let = 2; len")).parse();
+        assert!(!outcome.is_clean());
+        let diagnostics: Vec<_> = outcome.diagnostics().map(|d| d.to_string()).collect();
+        assert_eq!(diagnostics, vec!["Expected Ident, got: ="]);
+        assert_eq!(outcome.program.len(), 3);
+        assert_eq!(outcome.into_result().unwrap_err().len(), 1);
+
+        let outcome = Parser::new(Lexer::new("let f = fn(len) { len };")).parse();
+        assert!(!outcome.is_clean());
+        assert!(outcome.into_result().is_ok());
+    }
+
+    #[test]
+    fn test_shadowed_builtin_warnings() {
+        let input = "let f = fn(len, x) { let first = x; for (i in len) { i } };";
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        let (_, errors) = parser.parse_program();
+        assert!(errors.is_empty());
+        let warnings = parser.take_warnings();
+        let messages: Vec<_> = warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "`len` shadows the builtin of the same name",
+                "`first` shadows the builtin of the same name",
+            ]
+        );
+        assert_eq!(warnings[0].severity(), Some(miette::Severity::Warning));
+        let label = warnings[0].labels().unwrap().next().unwrap();
+        assert_eq!((label.offset(), label.len()), (11, 3));
+        assert!(parser.take_warnings().is_empty());
+
+        let outcome = Parser::new(Lexer::new(
+            "let f = fn() { let map = 1; let math = 2; let mapped = 3; };",
+        ))
+        .parse();
+        let messages: Vec<_> = outcome.warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "`map` shadows the builtin of the same name",
+                "`math` shadows the builtin of the same name",
+            ]
+        );
+
+        // A top-level `let` of a builtin is refused when evaluated, so it
+        // only gets that error.
+        let outcome = Parser::new(Lexer::new("let len = 1; { let len = 2; }")).parse();
+        let messages: Vec<_> = outcome.warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(messages, vec!["`len` shadows the builtin of the same name"]);
+        let label = outcome.warnings[0].labels().unwrap().next().unwrap();
+        assert_eq!(label.offset(), 19);
+    }
+
+    #[test]
+    fn test_unused_value_warnings() {
+        let input = "add(1, 2);
+let _ = add(1, 2);
+void(add(1, 2));
+puts(1);
+print(1);
+send(tx, 1);
+let f = fn(x) { x + 1; x };
+if (x) { 1 } else { 2 }
+x = 3;
+[1, 2]";
+        let warnings = |strict| {
+            let mut parser = Parser::new(Lexer::new(input)).with_strict(strict);
+            let (_, errors) = parser.parse_program();
+            assert!(errors.is_empty());
+            parser.take_warnings()
+        };
+        assert!(warnings(false).is_empty());
+
+        let warnings = warnings(true);
+        let labels: Vec<_> = warnings
+            .iter()
+            .map(|w| {
+                let label = w.labels().unwrap().next().unwrap();
+                &input[label.offset()..label.offset() + label.len()]
+            })
+            .collect();
+        assert_eq!(labels, vec!["add(1, 2);", "x + 1;"]);
+        assert_eq!(warnings[0].to_string(), "unused value");
+        assert_eq!(warnings[0].severity(), Some(miette::Severity::Warning));
+    }
+
+    #[test]
+    fn test_try_expression() {
+        let program = program_from_input("try { throw 1 + 2; } catch (e) { e }");
+        assert_eq!(program.len(), 1);
+        assert_eq!(
+            program.to_string(),
+            "try {
+    throw (1 + 2);
+} catch (e) {
+    e
+}"
+        );
+
+        let tests = [
+            ("try { 1 }", "Expected `catch`"),
+            ("try { 1 } catch e { e }", "Expected `(`"),
+            (
+                "try { 1 } catch (if) { 2 }",
+                "`if` is a reserved word and cannot be used as a name",
+            ),
+        ];
+        for (input, expected) in tests {
+            let lexer = Lexer::new(input);
+            let mut parser = Parser::new(lexer);
+            let (_, errors) = parser.parse_program();
+            assert_eq!(errors[0].to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_loop_control_outside_of_loop() {
+        let tests = [
+            ("break;", "`break` outside of a loop"),
+            (
+                "while (true) { fn() { continue; } }",
+                "`continue` outside of a loop",
+            ),
+        ];
+        for (input, expected) in tests {
+            let lexer = Lexer::new(input);
+            let mut parser = Parser::new(lexer);
+            let (_, errors) = parser.parse_program();
+            assert_eq!(errors[0].to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_generators() {
+        let lexer = Lexer::new("fn*(n) { while (true) { yield n; } }");
+        let mut parser = Parser::new(lexer);
+        let (program, errors) = parser.parse_program();
+        assert!(errors.is_empty(), "{:?}", errors);
+        let Statement::Expr(Expression::FunctionLiteral {
+            generator, body, ..
+        }) = &program.statements()[0]
+        else {
+            panic!("not a function literal: {}", program);
+        };
+        assert!(generator);
+        assert_eq!(
+            body.to_string(),
+            "{\n    while true {\n        yield n;\n    }\n}"
+        );
+
+        for input in [
+            "yield 1;",
+            "fn*() { fn() { yield 1; } }",
+            "fn*() { |x| { yield x; } }",
+        ] {
+            let lexer = Lexer::new(input);
+            let mut parser = Parser::new(lexer);
+            let (_, errors) = parser.parse_program();
+            assert_eq!(
+                errors[0].to_string(),
+                "`yield` outside of a generator",
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_parsing_slice_expressions() {
+        let program = program_from_input("s[1:3]");
+        assert_eq!(
+            program[0],
+            Statement::Expr(Expression::Slice {
+                left: Box::new(Expression::Ident(Identifier::new("s".into()))),
+                start: Some(Box::new(Expression::IntegerLiteral(1))),
+                end: Some(Box::new(Expression::IntegerLiteral(3))),
+            })
+        );
+
+        assert_eq!(program_from_input("s[1 + 1:]").to_string(), "(s[(1 + 1):])");
+        assert_eq!(program_from_input("s[:n - 1]").to_string(), "(s[:(n - 1)])");
+        assert_eq!(program_from_input("s[:]").to_string(), "(s[:])");
+    }
 }
@@ -0,0 +1,138 @@
+//! The Monkey programs embedded at build time from `programs/`, see
+//! `build.rs`: the prelude, whose functions sit next to the builtins, and the
+//! examples of `:examples` and `load_example`.
+
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::{
+    builtins::builtins, evaluator, lexer::Lexer, object::Environment, object::Object,
+    parser::Parser, Program,
+};
+
+include!(concat!(env!("OUT_DIR"), "/programs.rs"));
+
+thread_local! {
+    static BASE: HashMap<String, Rc<Object>> = evaluate_prelude();
+}
+
+/// The names of the examples and their source code, sorted by name.
+pub(crate) fn examples() -> &'static [(&'static str, &'static str)] {
+    EXAMPLES
+}
+
+pub(crate) fn example(name: &str) -> Option<&'static str> {
+    EXAMPLES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, src)| *src)
+}
+
+/// The parsed prelude. It is part of the crate, so errors are bugs.
+pub(crate) fn prelude() -> Program {
+    Parser::new(Lexer::new(PRELUDE))
+        .parse()
+        .into_result()
+        .unwrap_or_else(|errors| panic!("invalid prelude: {:?}", errors))
+}
+
+/// Whether `name` is bound by the prelude.
+pub(crate) fn is_prelude_name(name: &str) -> bool {
+    PRELUDE_NAMES.contains(&name)
+}
+
+/// The builtins together with the functions of the prelude.
+pub(crate) fn base_bindings() -> HashMap<String, Rc<Object>> {
+    BASE.with(|base| base.clone())
+}
+
+fn evaluate_prelude() -> HashMap<String, Rc<Object>> {
+    let mut env = Environment::new();
+    for (name, builtin) in builtins() {
+        env.set(name, builtin);
+    }
+    let env = Rc::new(RefCell::new(env));
+    if let Err(e) = evaluator::eval_program(&prelude(), &env) {
+        panic!("invalid prelude: {:?}", e);
+    }
+    let bindings = env.borrow().store.clone();
+    bindings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Interpreter;
+
+    fn eval(interpreter: &Interpreter, input: &str) -> String {
+        let outcome = Parser::new(Lexer::new(input)).parse();
+        assert!(outcome.is_clean(), "{}", input);
+        match interpreter.eval(outcome.program) {
+            Ok(value) => value.to_string(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_examples_evaluate() {
+        for (name, src) in examples() {
+            let outcome = Parser::new(Lexer::new(src)).parse();
+            assert!(outcome.is_clean(), "{}", name);
+            assert!(Interpreter::new().eval(outcome.program).is_ok(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_prelude() {
+        let interpreter = Interpreter::new();
+        let tests = [
+            ("map([1, 2, 3], |x| x * 2)", "[2, 4, 6]"),
+            ("filter([1, 5, 2, 6], |x| x > 3)", "[5, 6]"),
+            ("collect(filter(1..7, |x| x > 3))", "[4, 5, 6]"),
+            ("reduce([1, 2, 3], 10, |sum, x| sum + x)", "16"),
+            ("collect(take(1..100, 3))", "[1, 2, 3]"),
+            ("take([1, 2, 3], 2)", "[1, 2]"),
+            (r#"map("ab", |c| str(c) + "!")"#, "[a!, b!]"),
+            ("take([1, 2], 5)", "[1, 2]"),
+            ("take([1, 2], 0)", "[]"),
+            ("collect(range(2, 5))", "[2, 3, 4]"),
+            ("range(0, 1000000000) |> map(|x| x * x) |> take(4) |> collect", "[0, 1, 4, 9]"),
+            ("range(0, 1000000000) |> filter(|x| x > 5) |> take(2) |> collect", "[6, 7]"),
+            ("map(range(0, 3), |x| x)", "generator"),
+            ("is_generator(map(0..3, |x| x))", "true"),
+            ("map(0..1000000, |x| x * 2) |> take(5) |> collect", "[0, 2, 4, 6, 8]"),
+            ("filter(0..1000000, |x| x > 10) |> take(2) |> collect", "[11, 12]"),
+            ("len(map(collect(0..20000), |x| x * 2))", "20000"),
+            ("collect([1, 2])", "[1, 2]"),
+            (
+                "{ let calls = 0; collect(range(0, 10) |> map(|x| { calls += 1; x }) |> take(2)); calls }",
+                "2",
+            ),
+            ("is_generator(range(0, 1))", "true"),
+            ("is_generator(0..1)", "false"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(eval(&interpreter, input), expected, "{}", input);
+        }
+
+        let outcome = Parser::new(Lexer::new("let map = 1;")).parse();
+        assert!(outcome.warnings.is_empty());
+        let error = interpreter.eval(outcome.program).unwrap_err();
+        assert_eq!(error.to_string(), "cannot redefine builtin `map`");
+        assert!(interpreter.globals().next().is_none());
+    }
+
+    #[test]
+    fn test_load_example() {
+        let interpreter = Interpreter::new();
+        assert_eq!(eval(&interpreter, r#"load_example("recursion")"#), "610");
+        assert_eq!(eval(&interpreter, "fib(10)"), "55");
+        assert_eq!(
+            eval(&interpreter, "examples()"),
+            "[closures, functions, hashes, loops, match, prelude, recursion]"
+        );
+        assert_eq!(
+            eval(&interpreter, r#"load_example("nope")"#),
+            "unknown example `nope`, see `examples()` for the available ones"
+        );
+    }
+}
@@ -0,0 +1,68 @@
+//! The pseudo-random numbers behind `random`, `random_int` and `shuffle`,
+//! from a SplitMix64 generator, which an [`Interpreter`](crate::Interpreter)
+//! can seed to make runs repeatable.
+
+use std::cell::Cell;
+
+thread_local! {
+    static STATE: Cell<u64> = Cell::new(clock_seed());
+}
+
+/// A seed that differs between runs. wasm32-unknown-unknown has no clock, so
+/// there hosts seed the interpreter themselves.
+pub(crate) fn clock_seed() -> u64 {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use std::time::{SystemTime, UNIX_EPOCH};
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        0
+    }
+}
+
+/// Continues the sequence from `state`, returning where the previous one was.
+pub(crate) fn set_state(state: u64) -> u64 {
+    STATE.replace(state)
+}
+
+fn next_u64() -> u64 {
+    let state = STATE.get().wrapping_add(0x9e37_79b9_7f4a_7c15);
+    STATE.set(state);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// A float in `[0, 1)`.
+pub(crate) fn next_f64() -> f64 {
+    (next_u64() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// An integer in `[0, n)`.
+pub(crate) fn below(n: u64) -> u64 {
+    ((next_u64() as u128 * n as u128) >> 64) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded() {
+        let previous = set_state(42);
+        let first: Vec<_> = (0..4).map(|_| below(10)).collect();
+        set_state(42);
+        let second: Vec<_> = (0..4).map(|_| below(10)).collect();
+        assert_eq!(first, second);
+        assert!(first.iter().all(|&n| n < 10));
+        assert!((0..100)
+            .map(|_| next_f64())
+            .all(|x| (0.0..1.0).contains(&x)));
+        set_state(previous);
+    }
+}
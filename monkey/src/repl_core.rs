@@ -0,0 +1,133 @@
+//! Evaluating a line of a REPL, shared by the native REPL and the browser
+//! playground so that both behave the same.
+
+use crate::{
+    commands::{CommandOutput, Commands},
+    interpreter::Interpreter,
+    lexer::Lexer,
+    parser::Parser,
+};
+
+/// An interpreter session together with the `:` commands.
+#[derive(Default)]
+pub struct ReplCore {
+    interpreter: Interpreter,
+    commands: Commands,
+}
+
+/// What a line produced, for a frontend to show.
+#[derive(Debug)]
+pub enum LineOutput {
+    /// The line was a `:` command.
+    Command(CommandOutput),
+    /// The line was evaluated. The diagnostics are the parse errors and
+    /// warnings, the result the value or the evaluation error.
+    Evaluated {
+        diagnostics: Vec<miette::Report>,
+        result: Result<String, miette::Report>,
+    },
+}
+
+impl ReplCore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn interpreter(&self) -> &Interpreter {
+        &self.interpreter
+    }
+
+    /// The commands, to register ones only a frontend provides.
+    pub fn commands_mut(&mut self) -> &mut Commands {
+        &mut self.commands
+    }
+
+    /// Runs `line` as a command if it starts with `:`, else parses and
+    /// evaluates it in the session.
+    pub fn eval_line(&self, line: &str) -> LineOutput {
+        if let Some(output) = self.commands.dispatch(line, &self.interpreter) {
+            return LineOutput::Command(output);
+        }
+
+        let outcome = Parser::new(Lexer::new(line))
+            .with_strict(self.interpreter.is_strict())
+            .parse();
+        let mut diagnostics = outcome.errors;
+        diagnostics.extend(outcome.warnings);
+        let result = self
+            .interpreter
+            .eval(outcome.program)
+            .map(|value| value.to_string())
+            // Runtime errors carry labels but not the source they point into.
+            .map_err(|e| e.with_source_code(line.to_string()));
+        LineOutput::Evaluated {
+            diagnostics,
+            result,
+        }
+    }
+}
+
+impl LineOutput {
+    /// The text to print, one entry per message, which may span several
+    /// lines. Reports are rendered graphically with the `fancy` feature.
+    pub fn lines(&self) -> Vec<String> {
+        match self {
+            LineOutput::Command(CommandOutput::Print(text)) => vec![text.clone()],
+            LineOutput::Command(CommandOutput::Clear) => Vec::new(),
+            LineOutput::Evaluated {
+                diagnostics,
+                result,
+            } => {
+                let mut lines: Vec<_> = diagnostics.iter().map(render).collect();
+                lines.push(match result {
+                    Ok(value) => value.clone(),
+                    Err(e) => render(e),
+                });
+                lines
+            }
+        }
+    }
+}
+
+fn render(report: &miette::Report) -> String {
+    if cfg!(feature = "fancy") {
+        format!("{:?}", report)
+    } else {
+        report.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eval_line() {
+        let repl = ReplCore::new();
+        assert!(matches!(
+            repl.eval_line(":clear"),
+            LineOutput::Command(CommandOutput::Clear)
+        ));
+        assert_eq!(repl.eval_line("let x = 2;").lines(), vec!["null"]);
+        assert_eq!(repl.eval_line("x * 3").lines(), vec!["6"]);
+        assert_eq!(repl.eval_line(":unset x").lines(), vec!["Removed `x`"]);
+
+        let LineOutput::Evaluated {
+            diagnostics,
+            result,
+        } = repl.eval_line("let  = 1; y")
+        else {
+            panic!("not evaluated");
+        };
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(result.unwrap_err().to_string(), "identifier not found: y");
+
+        let warnings = |repl: &ReplCore| match repl.eval_line("1 + 2; 3") {
+            LineOutput::Evaluated { diagnostics, .. } => diagnostics.len(),
+            output => panic!("unexpected {:?}", output),
+        };
+        assert_eq!(warnings(&repl), 0);
+        repl.eval_line(":set strict on");
+        assert_eq!(warnings(&repl), 1);
+    }
+}
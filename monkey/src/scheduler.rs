@@ -0,0 +1,56 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::object::Object;
+
+thread_local! {
+    /// Callbacks scheduled by `set_timeout` and `defer` with their delay in
+    /// milliseconds, until the interpreter that ran them collects them.
+    static SCHEDULED: RefCell<Vec<(u64, Rc<Object>)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Queues `callback` to run `delay` milliseconds from now.
+pub(crate) fn schedule(delay: u64, callback: Rc<Object>) {
+    SCHEDULED.with_borrow_mut(|scheduled| scheduled.push((delay, callback)));
+}
+
+#[derive(Debug)]
+struct Timer {
+    due: u64,
+    callback: Rc<Object>,
+}
+
+/// The callbacks of one interpreter, ordered by a clock the host advances.
+#[derive(Debug, Default)]
+pub(crate) struct Scheduler {
+    /// Milliseconds the host has advanced the clock by so far.
+    clock: u64,
+    /// Sorted by due time, callbacks due at the same time in the order they
+    /// were scheduled.
+    timers: Vec<Timer>,
+}
+
+impl Scheduler {
+    /// Moves the callbacks scheduled since the last call into the queue.
+    pub(crate) fn collect(&mut self) {
+        for (delay, callback) in SCHEDULED.take() {
+            let due = self.clock.saturating_add(delay);
+            let idx = self.timers.partition_point(|timer| timer.due <= due);
+            self.timers.insert(idx, Timer { due, callback });
+        }
+    }
+
+    /// Advances the clock by `elapsed` milliseconds and removes the callbacks
+    /// that are due by then.
+    pub(crate) fn advance(&mut self, elapsed: u64) -> Vec<Rc<Object>> {
+        self.clock = self.clock.saturating_add(elapsed);
+        let due = self.timers.partition_point(|timer| timer.due <= self.clock);
+        self.timers
+            .drain(..due)
+            .map(|timer| timer.callback)
+            .collect()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.timers.len()
+    }
+}
@@ -0,0 +1,63 @@
+use std::rc::Rc;
+
+use crate::{ast::Program, lexer::Lexer, parser::Parser};
+
+/// A parsed program that can be evaluated any number of times, e.g. by a
+/// host running the same script for every request or event, without parsing
+/// it again. Cloning is cheap, the program is shared.
+#[derive(Debug, Clone)]
+pub struct CompiledScript {
+    program: Rc<Program>,
+}
+
+impl CompiledScript {
+    /// Parses `src`, failing with the parse errors if there are any.
+    pub fn compile(src: &str) -> Result<Self, Vec<miette::Report>> {
+        let program = Parser::new(Lexer::new(src)).parse().into_result()?;
+        Ok(Self {
+            program: Rc::new(program),
+        })
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{interpreter::Interpreter, object::Object};
+
+    #[test]
+    fn test_run_in_different_environments() {
+        let script = CompiledScript::compile("let total = total + 1; total").unwrap();
+        let first = Interpreter::new();
+        let second = Interpreter::new();
+        first
+            .eval(Parser::new(Lexer::new("let total = 10;")).parse_program().0)
+            .unwrap();
+        second
+            .eval(Parser::new(Lexer::new("let total = 20;")).parse_program().0)
+            .unwrap();
+
+        assert_eq!(
+            first.eval_script(&script).unwrap(),
+            Rc::new(Object::Integer(11))
+        );
+        assert_eq!(
+            first.eval_script(&script).unwrap(),
+            Rc::new(Object::Integer(12))
+        );
+        assert_eq!(
+            second.eval_script(&script.clone()).unwrap(),
+            Rc::new(Object::Integer(21))
+        );
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        let errors = CompiledScript::compile("let = 1; let x 2;").unwrap_err();
+        assert_eq!(errors.len(), 2);
+    }
+}
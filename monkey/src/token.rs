@@ -4,6 +4,8 @@ use core::fmt;
 pub struct Token {
     pub kind: TokenKind,
     pub span: Span,
+    /// Comments between the previous token and this one.
+    pub comments: Vec<Comment>,
 }
 
 impl Token {
@@ -11,10 +13,19 @@ impl Token {
         Self {
             kind,
             span: Span { start, end },
+            comments: Vec::new(),
         }
     }
 }
 
+/// A `//` line comment, with `text` holding the comment as written in the
+/// source (including the slashes, excluding the newline).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    pub text: String,
+    pub span: Span,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Span {
     pub start: usize,
@@ -23,17 +34,24 @@ pub struct Span {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TokenKind {
-    Illegal,
+    /// A character that starts no token.
+    Illegal(char),
     Eof,
 
     Ident(String),
     Int(String),
+    Float(String),
     Assign,
+    PlusAssign,
+    MinusAssign,
+    AsteriskAssign,
+    SlashAssign,
     Plus,
     Minus,
     Bang,
     Asterisk,
     Slash,
+    Percent,
 
     LessThan,
     GreaterThan,
@@ -51,17 +69,47 @@ pub enum TokenKind {
     Let,
     True,
     False,
+    Null,
     If,
     Else,
     Return,
+    While,
+    Loop,
+    Do,
+    Break,
+    Continue,
+    Match,
+    For,
+    In,
+    Try,
+    Catch,
+    Throw,
+    Yield,
 
     String(String),
+    Char(char),
+    Bytes(Vec<u8>),
     LBracket,
     RBracket,
     Colon,
+    FatArrow,
+    Dot,
+    /// `?.`, accessing a field or index unless the left side is `null`.
+    QuestionDot,
+    /// `??`, the left side unless it is `null`, else the right side.
+    Coalesce,
+    DotDot,
+    Pipe,
+    /// `|`, around the parameters of a lambda like `|x| x + 1`.
+    Bar,
 }
 
 impl TokenKind {
+    /// Whether this is one of the [`KEYWORDS`].
+    pub fn is_keyword(&self) -> bool {
+        !matches!(self, TokenKind::Ident(_)) && KEYWORDS.contains(&self.to_string().as_str())
+    }
+
     pub fn lookup_ident(self) -> TokenKind {
         if let TokenKind::Ident(ident) = &self {
             match ident.as_str() {
@@ -69,9 +117,22 @@ impl TokenKind {
                 "let" => TokenKind::Let,
                 "true" => TokenKind::True,
                 "false" => TokenKind::False,
+                "null" => TokenKind::Null,
                 "if" => TokenKind::If,
                 "else" => TokenKind::Else,
                 "return" => TokenKind::Return,
+                "while" => TokenKind::While,
+                "loop" => TokenKind::Loop,
+                "do" => TokenKind::Do,
+                "break" => TokenKind::Break,
+                "continue" => TokenKind::Continue,
+                "match" => TokenKind::Match,
+                "for" => TokenKind::For,
+                "in" => TokenKind::In,
+                "try" => TokenKind::Try,
+                "catch" => TokenKind::Catch,
+                "throw" => TokenKind::Throw,
+                "yield" => TokenKind::Yield,
                 _ => self,
             }
         } else {
@@ -80,19 +141,39 @@ impl TokenKind {
     }
 }
 
+/// The words reserved by the language, which cannot be used as names.
+pub const KEYWORDS: &[&str] = &[
+    "fn", "let", "true", "false", "null", "if", "else", "return", "while", "loop", "do", "break",
+    "continue", "match", "for", "in", "try", "catch", "throw", "yield",
+];
+
+/// The keyword `ident` spells with different letter case, like `let` for `Let`.
+pub(crate) fn keyword_in_other_case(ident: &str) -> Option<&'static str> {
+    KEYWORDS
+        .iter()
+        .copied()
+        .find(|keyword| *keyword != ident && keyword.eq_ignore_ascii_case(ident))
+}
+
 impl fmt::Display for TokenKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self {
-            TokenKind::Illegal => write!(f, "Illegal"),
+            TokenKind::Illegal(c) => write!(f, "{}", c),
             TokenKind::Eof => write!(f, "Eof"),
             TokenKind::Ident(x) => write!(f, "{}", x),
             TokenKind::Int(x) => write!(f, "{}", x),
+            TokenKind::Float(x) => write!(f, "{}", x),
             TokenKind::Assign => write!(f, "="),
+            TokenKind::PlusAssign => write!(f, "+="),
+            TokenKind::MinusAssign => write!(f, "-="),
+            TokenKind::AsteriskAssign => write!(f, "*="),
+            TokenKind::SlashAssign => write!(f, "/="),
             TokenKind::Plus => write!(f, "+",),
             TokenKind::Minus => write!(f, "-"),
             TokenKind::Bang => write!(f, "!"),
             TokenKind::Asterisk => write!(f, "*"),
             TokenKind::Slash => write!(f, "/"),
+            TokenKind::Percent => write!(f, "%"),
             TokenKind::LessThan => write!(f, "<"),
             TokenKind::GreaterThan => write!(f, ">"),
             TokenKind::Equal => write!(f, "=="),
@@ -107,13 +188,76 @@ impl fmt::Display for TokenKind {
             TokenKind::Let => write!(f, "let"),
             TokenKind::True => write!(f, "true"),
             TokenKind::False => write!(f, "false"),
+            TokenKind::Null => write!(f, "null"),
             TokenKind::If => write!(f, "if"),
             TokenKind::Else => write!(f, "else"),
             TokenKind::Return => write!(f, "return"),
-            TokenKind::String(s) => write!(f, "\"{}\"", s),
+            TokenKind::While => write!(f, "while"),
+            TokenKind::Loop => write!(f, "loop"),
+            TokenKind::Do => write!(f, "do"),
+            TokenKind::Break => write!(f, "break"),
+            TokenKind::Continue => write!(f, "continue"),
+            TokenKind::Match => write!(f, "match"),
+            TokenKind::For => write!(f, "for"),
+            TokenKind::In => write!(f, "in"),
+            TokenKind::Try => write!(f, "try"),
+            TokenKind::Catch => write!(f, "catch"),
+            TokenKind::Throw => write!(f, "throw"),
+            TokenKind::Yield => write!(f, "yield"),
+            TokenKind::String(s) => write!(f, "\"{}\"", escape(s)),
+            TokenKind::Char(c) => write!(f, "'{}'", escape_char(*c)),
+            TokenKind::Bytes(b) => write!(f, "b\"{}\"", escape_bytes(b)),
             TokenKind::LBracket => write!(f, "["),
             TokenKind::RBracket => write!(f, "]"),
             TokenKind::Colon => write!(f, ":"),
+            TokenKind::FatArrow => write!(f, "=>"),
+            TokenKind::Dot => write!(f, "."),
+            TokenKind::QuestionDot => write!(f, "?."),
+            TokenKind::Coalesce => write!(f, "??"),
+            TokenKind::DotDot => write!(f, ".."),
+            TokenKind::Pipe => write!(f, "|>"),
+            TokenKind::Bar => write!(f, "|"),
+        }
+    }
+}
+
+/// Escapes `s` so that it lexes back to the same string literal.
+pub(crate) fn escape(s: &str) -> String {
+    let mut escaped = String::new();
+    for c in s.chars() {
+        match c {
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escapes `bytes` so that they lex back to the same byte string literal,
+/// writing bytes outside of printable ASCII as `\xNN`.
+pub(crate) fn escape_bytes(bytes: &[u8]) -> String {
+    let mut escaped = String::new();
+    for byte in bytes {
+        match byte {
+            b'\n' => escaped.push_str("\\n"),
+            b'\t' => escaped.push_str("\\t"),
+            b'\\' => escaped.push_str("\\\\"),
+            b'"' => escaped.push_str("\\\""),
+            b' '..=b'~' => escaped.push(*byte as char),
+            _ => escaped.push_str(&format!("\\x{:02x}", byte)),
         }
     }
+    escaped
+}
+
+/// Escapes `c` so that it lexes back to the same character literal.
+pub(crate) fn escape_char(c: char) -> String {
+    match c {
+        '\'' => "\\'".into(),
+        '"' => "\"".into(),
+        c => escape(&c.to_string()),
+    }
 }
@@ -0,0 +1,905 @@
+//! Compiles the integer/boolean subset of Monkey to WebAssembly text.
+//!
+//! This backend is experimental. Every value is an `i64`, booleans being `0`
+//! or `1`. Top-level `let f = fn(...) { ... }` bindings become exported
+//! functions taking and returning integers, while the remaining top-level
+//! statements make up the exported `main` function, which returns the value of
+//! the last one. Anything outside of this subset, such as strings, collections,
+//! closures or builtins, is reported as an error.
+
+use std::collections::HashMap;
+
+use miette::{miette, Result};
+
+use crate::ast::{Expression, Program, Statement};
+
+/// The static type of a compiled expression. `Never` is the type of `return`,
+/// which leaves the function and fits wherever a value is expected.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Ty {
+    Int,
+    Bool,
+    Never,
+}
+
+impl Ty {
+    fn unify(self, other: Ty) -> Option<Ty> {
+        match (self, other) {
+            (Ty::Never, ty) | (ty, Ty::Never) => Some(ty),
+            (a, b) if a == b => Some(a),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Ty::Int => "INTEGER",
+            Ty::Bool => "BOOLEAN",
+            Ty::Never => "NEVER",
+        }
+    }
+}
+
+struct Compiler<'a> {
+    /// Arity of every top-level function.
+    functions: HashMap<&'a str, usize>,
+    /// Locals of the function being compiled, parameters first.
+    locals: Vec<(String, Ty)>,
+    /// The variables in scope, innermost last, with the index of their local.
+    scope: Vec<(String, usize)>,
+    /// Where the variables of the innermost block start in `scope`.
+    block_start: usize,
+    /// Whether the function being compiled is `main`, the only one allowed to
+    /// return booleans.
+    in_main: bool,
+    /// Number of loops enclosing the statement being compiled, used to label
+    /// the targets of `break` and `continue`.
+    loops: usize,
+}
+
+/// Compiles `program` to a WebAssembly text module.
+pub fn to_wat(program: &Program) -> Result<String> {
+    let mut compiler = Compiler {
+        functions: HashMap::new(),
+        locals: Vec::new(),
+        scope: Vec::new(),
+        block_start: 0,
+        in_main: false,
+        loops: 0,
+    };
+    let mut functions = Vec::new();
+    let mut main = Vec::new();
+    for stmt in program.statements() {
+        match stmt {
+            Statement::Let {
+                name,
+                value:
+                    Expression::FunctionLiteral {
+                        parameters,
+                        rest,
+                        body,
+                        generator,
+                    },
+                ..
+            } => {
+                if *generator {
+                    return Err(miette!("generators are not supported in the wasm target"));
+                }
+                if rest.is_some() {
+                    return Err(miette!(
+                        "rest parameters are not supported in the wasm target"
+                    ));
+                }
+                if name == "main" {
+                    return Err(miette!(
+                        help = "rename the function",
+                        "`main` is reserved for the top-level statements"
+                    ));
+                }
+                if compiler
+                    .functions
+                    .insert(name.as_str(), parameters.len())
+                    .is_some()
+                {
+                    return Err(miette!("function `{}` is defined more than once", name));
+                }
+                functions.push((name.as_str(), parameters, body));
+            }
+            stmt => main.push(stmt.clone()),
+        }
+    }
+
+    let mut out = String::from("(module\n");
+    for (name, parameters, body) in functions {
+        compiler.in_main = false;
+        compiler.locals = parameters
+            .iter()
+            .map(|param| (param.value().to_string(), Ty::Int))
+            .collect();
+        compiler.scope = parameters
+            .iter()
+            .enumerate()
+            .map(|(idx, param)| (param.value().to_string(), idx))
+            .collect();
+        let (body, ty) = compiler.tail(body.statements())?;
+        if ty == Ty::Bool {
+            return Err(miette!(
+                "function `{}` must return INTEGER, got BOOLEAN",
+                name
+            ));
+        }
+        let params: String = parameters
+            .iter()
+            .map(|param| format!(" (param ${} i64)", param))
+            .collect();
+        let header = format!(
+            "(func ${} (export \"{}\"){} (result i64)",
+            name, name, params
+        );
+        out.push_str(&compiler.function(&header, parameters.len(), body));
+    }
+
+    compiler.in_main = true;
+    compiler.locals = Vec::new();
+    compiler.scope = Vec::new();
+    let body = match main.last() {
+        Some(Statement::Expr(Expression::If {
+            alternative: None, ..
+        }))
+        | Some(Statement::Expr(Expression::While { .. }))
+        | Some(Statement::Expr(Expression::DoWhile { .. }))
+        | Some(Statement::Expr(Expression::Loop { .. }))
+        | Some(Statement::Expr(Expression::For { .. }))
+        | Some(Statement::Let { .. })
+        | Some(Statement::Destructure { .. })
+        | None => {
+            let mut body = compiler.statements(&main)?;
+            body.push("(i64.const 0)".into());
+            body
+        }
+        Some(_) => compiler.tail(&main)?.0,
+    };
+    out.push_str(&compiler.function("(func $main (export \"main\") (result i64)", 0, body));
+    out.push_str(")\n");
+    Ok(out)
+}
+
+impl Compiler<'_> {
+    /// Renders a function from its header, the number of parameters among the
+    /// locals, and its body instructions.
+    fn function(&self, header: &str, params: usize, body: Vec<String>) -> String {
+        let mut out = format!("  {}\n", header);
+        for (name, _) in &self.locals[params..] {
+            out.push_str(&format!("    (local ${} i64)\n", name));
+        }
+        for instr in body {
+            out.push_str(&format!("    {}\n", instr));
+        }
+        out.insert(out.len() - 1, ')');
+        out
+    }
+
+    /// The local the variable `name` refers to, and its type.
+    fn local(&self, name: &str) -> Option<(String, Ty)> {
+        let (_, idx) = self.scope.iter().rev().find(|(bound, _)| bound == name)?;
+        let (local, ty) = &self.locals[*idx];
+        Some((local.clone(), *ty))
+    }
+
+    /// Binds `name` in the innermost block, returning its local. A name
+    /// already bound in an outer block gets a local of its own, as the one
+    /// there must survive the block.
+    fn declare(&mut self, name: &str, ty: Ty) -> Result<String> {
+        let bound = self.scope[self.block_start..]
+            .iter()
+            .rev()
+            .find(|(bound, _)| bound == name);
+        if let Some((_, idx)) = bound {
+            let (local, local_ty) = &self.locals[*idx];
+            if local_ty.unify(ty).is_none() {
+                return Err(miette!(
+                    "cannot rebind `{}` from {} to {}",
+                    name,
+                    local_ty.name(),
+                    ty.name()
+                ));
+            }
+            return Ok(local.clone());
+        }
+        let mut local = name.to_string();
+        for n in 1.. {
+            if !self.locals.iter().any(|(existing, _)| *existing == local) {
+                break;
+            }
+            local = format!("{}.{}", name, n);
+        }
+        self.locals.push((local.clone(), ty));
+        self.scope.push((name.to_string(), self.locals.len() - 1));
+        Ok(local)
+    }
+
+    /// Runs `compile` in a block of its own, so that the variables it binds
+    /// end with it.
+    fn scoped<T>(&mut self, compile: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let (len, block_start) = (self.scope.len(), self.block_start);
+        self.block_start = len;
+        let result = compile(self);
+        self.scope.truncate(len);
+        self.block_start = block_start;
+        result
+    }
+
+    /// Compiles statements whose values are discarded.
+    fn statements(&mut self, statements: &[Statement]) -> Result<Vec<String>> {
+        statements.iter().map(|stmt| self.statement(stmt)).collect()
+    }
+
+    /// Compiles statements producing the value of the last one.
+    fn tail(&mut self, statements: &[Statement]) -> Result<(Vec<String>, Ty)> {
+        let Some((last, init)) = statements.split_last() else {
+            return Err(miette!("empty blocks have no value in the wasm target"));
+        };
+        let mut instrs = self.statements(init)?;
+        let (instr, ty) = match last {
+            Statement::Expr(expr) => self.expression(expr)?,
+            Statement::Return { .. }
+            | Statement::Break { .. }
+            | Statement::Continue { .. }
+            | Statement::Throw { .. }
+            | Statement::Yield { .. } => (self.statement(last)?, Ty::Never),
+            Statement::Let { .. } | Statement::Destructure { .. } => {
+                return Err(miette!(
+                    help = "end the block with an expression",
+                    "`let` statements have no value in the wasm target"
+                ))
+            }
+        };
+        instrs.push(instr);
+        Ok((instrs, ty))
+    }
+
+    fn statement(&mut self, stmt: &Statement) -> Result<String> {
+        match stmt {
+            Statement::Let { name, value, .. } => {
+                let (value, ty) = self.expression(value)?;
+                let ty = match ty {
+                    Ty::Never => Ty::Int,
+                    ty => ty,
+                };
+                let local = self.declare(name, ty)?;
+                Ok(format!("(local.set ${} {})", local, value))
+            }
+            Statement::Return { value, .. } => {
+                let (value, ty) = self.expression(value)?;
+                if ty == Ty::Bool && !self.in_main {
+                    return Err(miette!("functions must return INTEGER, got BOOLEAN"));
+                }
+                Ok(format!("(return {})", value))
+            }
+            Statement::Destructure { .. } => Err(miette!(
+                "destructuring `let` is not supported in the wasm target"
+            )),
+            Statement::Throw { .. } => Err(miette!("`throw` is not supported in the wasm target")),
+            Statement::Yield { .. } => Err(miette!("`yield` is not supported in the wasm target")),
+            Statement::Break { value: Some(_), .. } => Err(miette!(
+                help = "assign the value to a variable before the `break`",
+                "`break` with a value is not supported in the wasm target"
+            )),
+            Statement::Break { value: None, .. } => Ok(format!("(br $break_{})", self.loops - 1)),
+            Statement::Continue { .. } => Ok(format!("(br $continue_{})", self.loops - 1)),
+            Statement::Expr(Expression::While { condition, body }) => {
+                let depth = self.loops;
+                let condition = self.condition(condition)?;
+                let mut out = format!(
+                    "(block $break_{} (loop $continue_{} (br_if $break_{} (i32.eqz {}))",
+                    depth, depth, depth, condition
+                );
+                self.loops += 1;
+                let body = self.scoped(|this| this.statements(body.statements()));
+                self.loops -= 1;
+                for instr in body? {
+                    out.push_str(&format!(" {}", instr));
+                }
+                out.push_str(&format!(" (br $continue_{})))", depth));
+                Ok(out)
+            }
+            // `continue` leaves the body for the condition, the loop goes back
+            // to the start of the body.
+            Statement::Expr(Expression::DoWhile { body, condition }) => {
+                let depth = self.loops;
+                let mut out = format!(
+                    "(block $break_{} (loop $do_{} (block $continue_{}",
+                    depth, depth, depth
+                );
+                self.loops += 1;
+                let body = self.scoped(|this| this.statements(body.statements()));
+                self.loops -= 1;
+                for instr in body? {
+                    out.push_str(&format!(" {}", instr));
+                }
+                let condition = self.condition(condition)?;
+                out.push_str(&format!(") (br_if $do_{} {})))", depth, condition));
+                Ok(out)
+            }
+            Statement::Expr(Expression::Loop { body }) => {
+                let depth = self.loops;
+                let mut out = format!("(block $break_{} (loop $continue_{}", depth, depth);
+                self.loops += 1;
+                let body = self.scoped(|this| this.statements(body.statements()));
+                self.loops -= 1;
+                for instr in body? {
+                    out.push_str(&format!(" {}", instr));
+                }
+                out.push_str(&format!(" (br $continue_{})))", depth));
+                Ok(out)
+            }
+            Statement::Expr(Expression::For {
+                variable,
+                iterable,
+                body,
+            }) => {
+                let Expression::Infix {
+                    operator,
+                    left,
+                    right,
+                    ..
+                } = iterable.as_ref()
+                else {
+                    return Err(unsupported_iterable());
+                };
+                if operator != ".." {
+                    return Err(unsupported_iterable());
+                }
+                let (start, start_ty) = self.expression(left)?;
+                let (end, end_ty) = self.expression(right)?;
+                if start_ty
+                    .unify(Ty::Int)
+                    .and_then(|ty| ty.unify(end_ty))
+                    .is_none()
+                {
+                    return Err(type_mismatch(start_ty, operator, end_ty));
+                }
+                // The counter is kept apart from the loop variable, which the
+                // body may reassign.
+                let depth = self.loops;
+                let counter = format!("for.{}", depth);
+                let limit = format!("for.{}.end", depth);
+                let counter = self.declare(&counter, Ty::Int)?;
+                let limit = self.declare(&limit, Ty::Int)?;
+                self.loops += 1;
+                let body = self.scoped(|this| {
+                    let variable = this.declare(variable.value(), Ty::Int)?;
+                    Ok((variable, this.statements(body.statements())?))
+                });
+                self.loops -= 1;
+                let (variable, body) = body?;
+                let mut out = format!(
+                    "(local.set ${} {}) (local.set ${} {}) (block $break_{} (loop $loop_{} (br_if $break_{} (i64.ge_s (local.get ${}) (local.get ${}))) (local.set ${} (local.get ${})) (block $continue_{}",
+                    counter, start, limit, end, depth, depth, depth, counter, limit, variable, counter, depth
+                );
+                for instr in body {
+                    out.push_str(&format!(" {}", instr));
+                }
+                out.push_str(&format!(
+                    ") (local.set ${} (i64.add (local.get ${}) (i64.const 1))) (br $loop_{})))",
+                    counter, counter, depth
+                ));
+                Ok(out)
+            }
+            Statement::Expr(Expression::If {
+                condition,
+                consequence,
+                alternative,
+            }) => {
+                let condition = self.condition(condition)?;
+                let mut out = format!("(if {} (then", condition);
+                for instr in self.scoped(|this| this.statements(consequence.statements()))? {
+                    out.push_str(&format!(" {}", instr));
+                }
+                out.push(')');
+                if let Some(alt) = alternative {
+                    out.push_str(" (else");
+                    for instr in self.scoped(|this| this.statements(alt.statements()))? {
+                        out.push_str(&format!(" {}", instr));
+                    }
+                    out.push(')');
+                }
+                out.push(')');
+                Ok(out)
+            }
+            Statement::Expr(expr) => match self.expression(expr)? {
+                (value, Ty::Never) => Ok(value),
+                (value, _) => Ok(format!("(drop {})", value)),
+            },
+        }
+    }
+
+    /// Compiles `expr` to an `i32` suitable as the condition of an `if`.
+    fn condition(&mut self, expr: &Expression) -> Result<String> {
+        if let Expression::Infix {
+            operator,
+            left,
+            right,
+            ..
+        } = expr
+        {
+            if let Some(comparison) = self.comparison(operator, left, right)? {
+                return Ok(comparison);
+            }
+        }
+        match self.expression(expr)? {
+            (value, Ty::Bool) => Ok(format!("(i32.wrap_i64 {})", value)),
+            // Integers are always truthy.
+            (value, Ty::Int) => Ok(format!(
+                "(block (result i32) (drop {}) (i32.const 1))",
+                value
+            )),
+            (value, Ty::Never) => Ok(value),
+        }
+    }
+
+    /// Compiles a comparison to an `i32`, or returns `None` if `operator` does
+    /// not compare.
+    fn comparison(
+        &mut self,
+        operator: &str,
+        left: &Expression,
+        right: &Expression,
+    ) -> Result<Option<String>> {
+        let instr = match operator {
+            "<" => "i64.lt_s",
+            ">" => "i64.gt_s",
+            "==" => "i64.eq",
+            "!=" => "i64.ne",
+            _ => return Ok(None),
+        };
+        let (left, left_ty) = self.expression(left)?;
+        let (right, right_ty) = self.expression(right)?;
+        let valid = match operator {
+            "<" | ">" => left_ty.unify(Ty::Int).and_then(|ty| ty.unify(right_ty)),
+            _ => left_ty.unify(right_ty),
+        };
+        if valid.is_none() {
+            return Err(type_mismatch(left_ty, operator, right_ty));
+        }
+        Ok(Some(format!("({} {} {})", instr, left, right)))
+    }
+
+    fn expression(&mut self, expr: &Expression) -> Result<(String, Ty)> {
+        match expr {
+            Expression::IntegerLiteral(i) => Ok((format!("(i64.const {})", i), Ty::Int)),
+            Expression::BigIntegerLiteral(i) => Err(miette!(
+                "integer literal `{}` does not fit in 64 bits in the wasm target",
+                i
+            )),
+            Expression::Boolean(b) => Ok((format!("(i64.const {})", *b as i64), Ty::Bool)),
+            Expression::Ident(ident) => match self.local(ident.value()) {
+                Some((local, ty)) => Ok((format!("(local.get ${})", local), ty)),
+                None if self.functions.contains_key(ident.value()) => Err(miette!(
+                    "functions are not values in the wasm target: {}",
+                    ident
+                )),
+                None => Err(miette!("identifier not found: {}", ident)),
+            },
+            Expression::Prefix {
+                operator, right, ..
+            } => {
+                let (right, ty) = self.expression(right)?;
+                match (operator.as_str(), ty) {
+                    ("-", Ty::Int | Ty::Never) => {
+                        Ok((format!("(i64.sub (i64.const 0) {})", right), Ty::Int))
+                    }
+                    ("!", Ty::Bool | Ty::Never) => {
+                        Ok((format!("(i64.extend_i32_u (i64.eqz {}))", right), Ty::Bool))
+                    }
+                    // Integers are always truthy.
+                    ("!", Ty::Int) => Ok((
+                        format!("(block (result i64) (drop {}) (i64.const 0))", right),
+                        Ty::Bool,
+                    )),
+                    _ => Err(miette!("unknown operator: {}{}", operator, ty.name())),
+                }
+            }
+            Expression::Infix {
+                operator,
+                left,
+                right,
+                ..
+            } => {
+                if let Some(comparison) = self.comparison(operator, left, right)? {
+                    return Ok((format!("(i64.extend_i32_u {})", comparison), Ty::Bool));
+                }
+                let instr = match operator.as_str() {
+                    "+" => "i64.add",
+                    "-" => "i64.sub",
+                    "*" => "i64.mul",
+                    "/" => "i64.div_s",
+                    "%" => "i64.rem_s",
+                    _ => {
+                        return Err(miette!(
+                            "unsupported operator in the wasm target: {}",
+                            operator
+                        ))
+                    }
+                };
+                let (left, left_ty) = self.expression(left)?;
+                let (right, right_ty) = self.expression(right)?;
+                match left_ty.unify(Ty::Int).and_then(|ty| ty.unify(right_ty)) {
+                    Some(_) => Ok((format!("({} {} {})", instr, left, right), Ty::Int)),
+                    None => Err(type_mismatch(left_ty, operator, right_ty)),
+                }
+            }
+            Expression::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                let Some(alternative) = alternative else {
+                    return Err(miette!(
+                        help = "add an `else` branch",
+                        "`if` without `else` has no value in the wasm target"
+                    ));
+                };
+                let condition = self.condition(condition)?;
+                let (consequence, cons_ty) =
+                    self.scoped(|this| this.tail(consequence.statements()))?;
+                let (alternative, alt_ty) =
+                    self.scoped(|this| this.tail(alternative.statements()))?;
+                let Some(ty) = cons_ty.unify(alt_ty) else {
+                    return Err(miette!(
+                        "branches of `if` differ in type: {} and {}",
+                        cons_ty.name(),
+                        alt_ty.name()
+                    ));
+                };
+                Ok((
+                    format!(
+                        "(if (result i64) {} (then {}) (else {}))",
+                        condition,
+                        consequence.join(" "),
+                        alternative.join(" ")
+                    ),
+                    ty,
+                ))
+            }
+            Expression::Call {
+                function,
+                arguments,
+                ..
+            } => {
+                let name = match function.as_ref() {
+                    Expression::Ident(ident) if self.local(ident.value()).is_none() => {
+                        ident.value()
+                    }
+                    _ => {
+                        return Err(miette!(
+                            "only top-level functions can be called in the wasm target"
+                        ))
+                    }
+                };
+                let Some(&arity) = self.functions.get(name) else {
+                    return Err(miette!("identifier not found: {}", name));
+                };
+                if arguments.len() != arity {
+                    return Err(miette!(
+                        "wrong number of arguments to `{}`: expected {}, got {}",
+                        name,
+                        arity,
+                        arguments.len()
+                    ));
+                }
+                let mut out = format!("(call ${}", name);
+                for arg in arguments {
+                    let (arg, ty) = self.expression(arg)?;
+                    if ty == Ty::Bool {
+                        return Err(miette!(
+                            "arguments to `{}` must be INTEGER, got BOOLEAN",
+                            name
+                        ));
+                    }
+                    out.push_str(&format!(" {}", arg));
+                }
+                out.push(')');
+                Ok((out, Ty::Int))
+            }
+            Expression::Assign { name, value, .. } => {
+                let Some((local, local_ty)) = self.local(name.value()) else {
+                    return Err(miette!("identifier not found: {}", name));
+                };
+                let (value, ty) = self.expression(value)?;
+                match local_ty.unify(ty) {
+                    Some(_) => Ok((format!("(local.tee ${} {})", local, value), local_ty)),
+                    None => Err(miette!(
+                        "cannot assign {} to `{}` of type {}",
+                        ty.name(),
+                        name,
+                        local_ty.name()
+                    )),
+                }
+            }
+            Expression::Try { .. } => Err(miette!("`try` is not supported in the wasm target")),
+            Expression::Spread(_) => Err(miette!(
+                "`..` spreading is not supported in the wasm target"
+            )),
+            Expression::Block(_) => Err(miette!(
+                help = "move the statements out of the block",
+                "block expressions are not supported in the wasm target"
+            )),
+            Expression::Match { .. } => Err(miette!(
+                help = "use `if` and `else` instead",
+                "`match` is not supported in the wasm target"
+            )),
+            Expression::While { .. } => Err(miette!(
+                help = "use a variable to hold the result",
+                "`while` has no value in the wasm target"
+            )),
+            Expression::DoWhile { .. } => Err(miette!(
+                help = "use a variable to hold the result",
+                "`do`-`while` has no value in the wasm target"
+            )),
+            Expression::Loop { .. } => Err(miette!(
+                help = "use a variable to hold the result",
+                "`loop` has no value in the wasm target"
+            )),
+            Expression::For { .. } => Err(miette!(
+                help = "use a variable to hold the result",
+                "`for` has no value in the wasm target"
+            )),
+            Expression::FunctionLiteral { .. } => Err(miette!(
+                help = "bind functions with `let` at the top level",
+                "closures are not supported in the wasm target"
+            )),
+            Expression::Null
+            | Expression::StringLiteral(_)
+            | Expression::CharLiteral(_)
+            | Expression::FloatLiteral(_)
+            | Expression::BytesLiteral(_)
+            | Expression::ArrayLiteral(_)
+            | Expression::HashLiteral(_)
+            | Expression::IndexExpr { .. }
+            | Expression::IndexAssign { .. }
+            | Expression::Field { .. }
+            | Expression::Slice { .. } => Err(miette!(
+                "only integers and booleans are supported in the wasm target, got: {}",
+                expr
+            )),
+        }
+    }
+}
+
+fn unsupported_iterable() -> miette::Report {
+    miette!("`for` only iterates over ranges like `0..n` in the wasm target")
+}
+
+fn type_mismatch(left: Ty, operator: &str, right: Ty) -> miette::Report {
+    miette!(
+        "type mismatch: {} {} {}",
+        left.name(),
+        operator,
+        right.name()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser::Parser};
+
+    fn compile(input: &str) -> Result<String> {
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().0;
+        to_wat(&program)
+    }
+
+    #[test]
+    fn test_functions() {
+        let input = "
+let fib = fn(n) { if (n < 2) { return n; } fib(n - 1) + fib(n - 2) };
+let x = 10;
+fib(x) == 55";
+        assert_eq!(
+            compile(input).unwrap(),
+            r#"(module
+  (func $fib (export "fib") (param $n i64) (result i64)
+    (if (i64.lt_s (local.get $n) (i64.const 2)) (then (return (local.get $n))))
+    (i64.add (call $fib (i64.sub (local.get $n) (i64.const 1))) (call $fib (i64.sub (local.get $n) (i64.const 2)))))
+  (func $main (export "main") (result i64)
+    (local $x i64)
+    (local.set $x (i64.const 10))
+    (i64.extend_i32_u (i64.eq (call $fib (local.get $x)) (i64.const 55))))
+)
+"#
+        );
+    }
+
+    #[test]
+    fn test_if_expressions() {
+        assert_eq!(
+            compile("let b = !true; if (b) { 1 } else { x = 2; -x }")
+                .unwrap_err()
+                .to_string(),
+            "identifier not found: x"
+        );
+        assert_eq!(
+            compile("let b = !0; let x = if (b) { 1 } else { -2 };").unwrap(),
+            r#"(module
+  (func $main (export "main") (result i64)
+    (local $b i64)
+    (local $x i64)
+    (local.set $b (block (result i64) (drop (i64.const 0)) (i64.const 0)))
+    (local.set $x (if (result i64) (i32.wrap_i64 (local.get $b)) (then (i64.const 1)) (else (i64.sub (i64.const 0) (i64.const 2)))))
+    (i64.const 0))
+)
+"#
+        );
+    }
+
+    #[test]
+    fn test_block_scoping() {
+        assert_eq!(
+            compile("let x = 1; if (true) { let x = x + 1; x = 3; } x").unwrap(),
+            r#"(module
+  (func $main (export "main") (result i64)
+    (local $x i64)
+    (local $x.1 i64)
+    (local.set $x (i64.const 1))
+    (if (i32.wrap_i64 (i64.const 1)) (then (local.set $x.1 (i64.add (local.get $x) (i64.const 1))) (drop (local.tee $x.1 (i64.const 3)))))
+    (local.get $x))
+)
+"#
+        );
+        assert_eq!(
+            compile("if (true) { let y = 1; } y")
+                .unwrap_err()
+                .to_string(),
+            "identifier not found: y"
+        );
+    }
+
+    #[test]
+    fn test_modulo() {
+        assert_eq!(
+            compile("17 % 5").unwrap(),
+            r#"(module
+  (func $main (export "main") (result i64)
+    (i64.rem_s (i64.const 17) (i64.const 5)))
+)
+"#
+        );
+    }
+
+    #[test]
+    fn test_while_loops() {
+        assert_eq!(
+            compile("let i = 0; while (true) { if (i > 9) { break; } i += 1; continue; } i")
+                .unwrap(),
+            r#"(module
+  (func $main (export "main") (result i64)
+    (local $i i64)
+    (local.set $i (i64.const 0))
+    (block $break_0 (loop $continue_0 (br_if $break_0 (i32.eqz (i32.wrap_i64 (i64.const 1)))) (if (i64.gt_s (local.get $i) (i64.const 9)) (then (br $break_0))) (drop (local.tee $i (i64.add (local.get $i) (i64.const 1)))) (br $continue_0) (br $continue_0)))
+    (local.get $i))
+)
+"#
+        );
+    }
+
+    #[test]
+    fn test_loop() {
+        assert_eq!(
+            compile("let i = 0; loop { i += 1; if (i > 9) { break; } } i").unwrap(),
+            r#"(module
+  (func $main (export "main") (result i64)
+    (local $i i64)
+    (local.set $i (i64.const 0))
+    (block $break_0 (loop $continue_0 (drop (local.tee $i (i64.add (local.get $i) (i64.const 1)))) (if (i64.gt_s (local.get $i) (i64.const 9)) (then (br $break_0))) (br $continue_0)))
+    (local.get $i))
+)
+"#
+        );
+        assert_eq!(
+            compile("loop { break 1; }").unwrap_err().to_string(),
+            "`break` with a value is not supported in the wasm target"
+        );
+    }
+
+    #[test]
+    fn test_chained_comparisons() {
+        assert_eq!(
+            compile("let x = 5; 0 < x < 10").unwrap(),
+            r#"(module
+  (func $main (export "main") (result i64)
+    (local $x i64)
+    (local.set $x (i64.const 5))
+    (if (result i64) (i64.lt_s (i64.const 0) (local.get $x)) (then (i64.extend_i32_u (i64.lt_s (local.get $x) (i64.const 10)))) (else (i64.const 0))))
+)
+"#
+        );
+    }
+
+    #[test]
+    fn test_do_while_loops() {
+        assert_eq!(
+            compile("let i = 0; do { i += 1; if (i == 2) { continue; } } while (i < 5); i")
+                .unwrap(),
+            r#"(module
+  (func $main (export "main") (result i64)
+    (local $i i64)
+    (local.set $i (i64.const 0))
+    (block $break_0 (loop $do_0 (block $continue_0 (drop (local.tee $i (i64.add (local.get $i) (i64.const 1)))) (if (i64.eq (local.get $i) (i64.const 2)) (then (br $continue_0)))) (br_if $do_0 (i64.lt_s (local.get $i) (i64.const 5)))))
+    (local.get $i))
+)
+"#
+        );
+    }
+
+    #[test]
+    fn test_for_loops() {
+        assert_eq!(
+            compile("let sum = 0; for (i in 1..4) { if (i == 2) { continue; } sum += i; } sum")
+                .unwrap(),
+            r#"(module
+  (func $main (export "main") (result i64)
+    (local $sum i64)
+    (local $for.0 i64)
+    (local $for.0.end i64)
+    (local $i i64)
+    (local.set $sum (i64.const 0))
+    (local.set $for.0 (i64.const 1)) (local.set $for.0.end (i64.const 4)) (block $break_0 (loop $loop_0 (br_if $break_0 (i64.ge_s (local.get $for.0) (local.get $for.0.end))) (local.set $i (local.get $for.0)) (block $continue_0 (if (i64.eq (local.get $i) (i64.const 2)) (then (br $continue_0))) (drop (local.tee $sum (i64.add (local.get $sum) (local.get $i))))) (local.set $for.0 (i64.add (local.get $for.0) (i64.const 1))) (br $loop_0)))
+    (local.get $sum))
+)
+"#
+        );
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let tests = [
+            (
+                "[1, 2]",
+                "only integers and booleans are supported in the wasm target, got: [1, 2]",
+            ),
+            (
+                "let f = fn(x) { fn(y) { x + y } };",
+                "closures are not supported in the wasm target",
+            ),
+            ("1 + true", "type mismatch: INTEGER + BOOLEAN"),
+            (
+                "let x = 1; x = false;",
+                "cannot assign BOOLEAN to `x` of type INTEGER",
+            ),
+            (
+                "if (true) { 1 } + 1",
+                "`if` without `else` has no value in the wasm target",
+            ),
+            (
+                "let f = fn(x) { x }; f(1, 2)",
+                "wrong number of arguments to `f`: expected 1, got 2",
+            ),
+            (
+                "let f = fn(x) { x == 1 };",
+                "function `f` must return INTEGER, got BOOLEAN",
+            ),
+            ("len(1)", "identifier not found: len"),
+            (
+                "for (x in [1]) {}",
+                "`for` only iterates over ranges like `0..n` in the wasm target",
+            ),
+            ("0..3", "unsupported operator in the wasm target: .."),
+            ("throw 1;", "`throw` is not supported in the wasm target"),
+            (
+                "99999999999999999999",
+                "integer literal `99999999999999999999` does not fit in 64 bits in the wasm target",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(
+                compile(input).unwrap_err().to_string(),
+                expected,
+                "{}",
+                input
+            );
+        }
+    }
+}
@@ -0,0 +1,68 @@
+//! Snapshots of the diagnostics for the broken programs in `parse_errors/`.
+//!
+//! Each `name.mky` is parsed and its errors and warnings are rendered as plain
+//! text, which must match `name.stderr`. Run with `BLESS=1` to write the
+//! snapshots after an intended change to the wording or labels.
+
+use std::{fmt::Write, fs, path::Path};
+
+use miette::NarratableReportHandler;
+use monkey::{Lexer, Parser};
+
+fn render(src: &str) -> String {
+    let outcome = Parser::new(Lexer::new(src)).parse();
+    let handler = NarratableReportHandler::new();
+    let mut out = String::new();
+    for diagnostic in outcome.diagnostics() {
+        if !out.is_empty() {
+            writeln!(out).unwrap();
+        }
+        handler
+            .render_report(&mut out, diagnostic.as_ref())
+            .unwrap();
+    }
+    out
+}
+
+#[test]
+fn parse_errors() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/parse_errors");
+    let bless = std::env::var_os("BLESS").is_some();
+    let mut paths: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "mky"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no programs in {}", dir.display());
+
+    let mut mismatches = Vec::new();
+    for path in paths {
+        let src = fs::read_to_string(&path).unwrap();
+        let actual = render(&src);
+        assert!(
+            !actual.is_empty(),
+            "{} parses without diagnostics",
+            path.display()
+        );
+        let snapshot = path.with_extension("stderr");
+        if bless {
+            fs::write(&snapshot, &actual).unwrap();
+            continue;
+        }
+        let expected = fs::read_to_string(&snapshot).unwrap_or_default();
+        if actual != expected {
+            mismatches.push(format!(
+                "{}\n--- expected\n{}\n--- actual\n{}",
+                snapshot.display(),
+                expected,
+                actual
+            ));
+        }
+    }
+    assert!(
+        mismatches.is_empty(),
+        "diagnostics differ, rerun with BLESS=1 if intended:\n\n{}",
+        mismatches.join("\n")
+    );
+}
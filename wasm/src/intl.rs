@@ -0,0 +1,54 @@
+use std::cmp::Ordering;
+
+use js_sys::{Array, Function, Intl, Object};
+use monkey::Locale;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+extern "C" {
+    /// `Intl.getCanonicalLocales`, which throws on malformed tags, where the
+    /// `Intl` constructors would abort the module instead.
+    #[wasm_bindgen(catch, js_namespace = Intl, js_name = getCanonicalLocales)]
+    fn canonical_locales(locales: &JsValue) -> Result<Array, JsValue>;
+}
+
+/// The browser's `Intl` API for the locale builtins of Monkey.
+pub struct BrowserLocale;
+
+impl BrowserLocale {
+    fn locales(locale: Option<&str>) -> Result<Array, String> {
+        match locale {
+            Some(locale) => canonical_locales(&locale.into()).map_err(|e| {
+                let message = e
+                    .dyn_ref::<js_sys::Error>()
+                    .map(|e| String::from(e.message()))
+                    .unwrap_or_else(|| format!("{:?}", e));
+                format!("invalid locale `{}`: {}", locale, message)
+            }),
+            None => Ok(Array::new()),
+        }
+    }
+}
+
+impl Locale for BrowserLocale {
+    fn compare(&self, a: &str, b: &str, locale: Option<&str>) -> Result<Ordering, String> {
+        let collator = Intl::Collator::new(&Self::locales(locale)?, &Object::new());
+        let compare: Function = collator.compare();
+        let result = compare
+            .call2(&JsValue::NULL, &a.into(), &b.into())
+            .map_err(|e| format!("{:?}", e))?;
+        let result = result.as_f64().unwrap_or_default();
+        Ok(result.partial_cmp(&0.0).unwrap_or(Ordering::Equal))
+    }
+
+    fn format_number(&self, n: isize, locale: Option<&str>) -> Result<String, String> {
+        let format = Intl::NumberFormat::new(&Self::locales(locale)?, &Object::new());
+        let formatted = format
+            .format()
+            .call1(&JsValue::NULL, &(n as f64).into())
+            .map_err(|e| format!("{:?}", e))?;
+        formatted
+            .as_string()
+            .ok_or_else(|| "`Intl.NumberFormat` returned no string".into())
+    }
+}
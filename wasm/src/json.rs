@@ -0,0 +1,34 @@
+//! Minimal JSON serialization for the data handed to JavaScript.
+
+/// Renders `s` as a JSON string literal.
+pub fn string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Renders `fields` as a JSON object, the values being JSON already.
+pub fn object(fields: &[(&str, String)]) -> String {
+    let fields: Vec<_> = fields
+        .iter()
+        .map(|(key, value)| format!("{}:{}", string(key), value))
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+/// Renders `items` as a JSON array, the items being JSON already.
+pub fn array(items: impl IntoIterator<Item = String>) -> String {
+    let items: Vec<_> = items.into_iter().collect();
+    format!("[{}]", items.join(","))
+}
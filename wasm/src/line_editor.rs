@@ -1,7 +1,5 @@
-use anyhow::Result;
 use xterm_js_rs::Terminal;
 
-
 pub struct KeyEvent {
     pub code: KeyCode,
     pub modifiers: KeyModifiers,
@@ -39,50 +37,50 @@ pub enum KeyModifiers {
     None,
 }
 
-pub fn parse_key_event(buffer: &[u8]) -> Result<KeyEvent> {
+pub fn parse_key_event(buffer: &[u8]) -> KeyEvent {
     match buffer[0] {
         b'\x1B' => {
             // ESC
             if buffer.len() == 1 {
-                Ok(KeyEvent::new(KeyCode::Esc, KeyModifiers::None))
+                KeyEvent::new(KeyCode::Esc, KeyModifiers::None)
             } else {
                 match buffer[1] {
                     b'[' => match buffer[2] {
-                        b'A' => Ok(KeyEvent::new(KeyCode::Up, KeyModifiers::None)),
-                        b'B' => Ok(KeyEvent::new(KeyCode::Down, KeyModifiers::None)),
-                        b'C' => Ok(KeyEvent::new(KeyCode::Right, KeyModifiers::None)),
-                        b'D' => Ok(KeyEvent::new(KeyCode::Left, KeyModifiers::None)),
-                        b'H' => Ok(KeyEvent::new(KeyCode::Home, KeyModifiers::None)),
-                        b'F' => Ok(KeyEvent::new(KeyCode::End, KeyModifiers::None)),
+                        b'A' => KeyEvent::new(KeyCode::Up, KeyModifiers::None),
+                        b'B' => KeyEvent::new(KeyCode::Down, KeyModifiers::None),
+                        b'C' => KeyEvent::new(KeyCode::Right, KeyModifiers::None),
+                        b'D' => KeyEvent::new(KeyCode::Left, KeyModifiers::None),
+                        b'H' => KeyEvent::new(KeyCode::Home, KeyModifiers::None),
+                        b'F' => KeyEvent::new(KeyCode::End, KeyModifiers::None),
                         // TODO Delete is: "\x1B[3~"
-                        b'3' => Ok(KeyEvent::new(KeyCode::Delete, KeyModifiers::None)),
+                        b'3' => KeyEvent::new(KeyCode::Delete, KeyModifiers::None),
                         _ => unimplemented!(),
                     },
-                    b'\x1B' => Ok(KeyEvent::new(KeyCode::Esc, KeyModifiers::None)),
-                    b'b' => Ok(KeyEvent::new(KeyCode::Left, KeyModifiers::Alt)),
-                    b'f' => Ok(KeyEvent::new(KeyCode::Right, KeyModifiers::Alt)),
+                    b'\x1B' => KeyEvent::new(KeyCode::Esc, KeyModifiers::None),
+                    b'b' => KeyEvent::new(KeyCode::Left, KeyModifiers::Alt),
+                    b'f' => KeyEvent::new(KeyCode::Right, KeyModifiers::Alt),
                     _ => unimplemented!("or not? buffer = {:?}", buffer),
                 }
             }
         }
-        b'\r' => Ok(KeyEvent::new(KeyCode::Enter, KeyModifiers::None)),
-        // b'\n' => Ok(KeyEvent::new(KeyCode::Enter, KeyModifiers::None)),
-        b'\t' => Ok(KeyEvent::new(KeyCode::Tab, KeyModifiers::None)),
-        b'\x7F' => Ok(KeyEvent::new(KeyCode::Backspace, KeyModifiers::None)),
-        c @ b'\x01'..=b'\x1A' => Ok(KeyEvent::new(
+        b'\r' => KeyEvent::new(KeyCode::Enter, KeyModifiers::None),
+        // b'\n' => KeyEvent::new(KeyCode::Enter, KeyModifiers::None),
+        b'\t' => KeyEvent::new(KeyCode::Tab, KeyModifiers::None),
+        b'\x7F' => KeyEvent::new(KeyCode::Backspace, KeyModifiers::None),
+        c @ b'\x01'..=b'\x1A' => KeyEvent::new(
             KeyCode::Char((c - 0x1 + b'a') as char),
             KeyModifiers::Control,
-        )),
-        c @ b'\x1C'..=b'\x1F' => Ok(KeyEvent::new(
+        ),
+        c @ b'\x1C'..=b'\x1F' => KeyEvent::new(
             KeyCode::Char((c - 0x1C + b'4') as char),
             KeyModifiers::Control,
-        )),
-        b'\0' => Ok(KeyEvent::new(KeyCode::Char(' '), KeyModifiers::Control)),
+        ),
+        b'\0' => KeyEvent::new(KeyCode::Char(' '), KeyModifiers::Control),
 
-        c => Ok(KeyEvent {
+        c => KeyEvent {
             code: KeyCode::Char(c as char),
             modifiers: KeyModifiers::None,
-        }),
+        },
     }
 }
 
@@ -171,7 +169,6 @@ impl LineEditor {
         }
     }
 
-
     pub fn delete_left(&mut self) {
         if self.cursor > 0 {
             self.buffer = self
@@ -206,19 +203,18 @@ impl LineEditor {
         }
     }
 
-    pub fn enter(&mut self, msg: &str) {
-        self.csi_new_line();
-        self.term.write(msg);
-        self.flush();
+    /// Moves below the line being edited, where evaluating it prints.
+    pub fn submit(&self) {
         self.csi_new_line();
-        self.prompt();
     }
 
-    pub fn write_line(&mut self, msg: &str) {
-        self.csi_new_line();
+    /// Prints `msg` after what the submitted line printed and shows a new
+    /// prompt.
+    pub fn enter(&mut self, msg: &str) {
         self.term.write(msg);
         self.flush();
         self.csi_new_line();
+        self.prompt();
     }
 
     pub fn clear_screen(&self) {
@@ -0,0 +1,186 @@
+//! The terminal playground with its side panels, only built with the
+//! `playground` feature.
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::console::clear;
+use xterm_js_rs::addons::fit::FitAddon;
+use xterm_js_rs::BellStyle;
+use xterm_js_rs::{Terminal, TerminalOptions, Theme};
+
+use crate::line_editor::parse_key_event;
+use crate::line_editor::KeyCode;
+use crate::line_editor::KeyModifiers;
+use crate::line_editor::LineEditor;
+use crate::{lex_to_json, log, parse_to_json, REPL};
+use monkey::CommandOutput;
+use monkey::LineOutput;
+
+const PROMPT: &str = "monkey❯ ";
+
+#[wasm_bindgen(module = "/www/inspector.js")]
+extern "C" {
+    /// Renders the outputs of `parse_to_json` and `lex_to_json` into the
+    /// inspector tabs.
+    fn render_inspector(ast_json: &str, tokens_json: &str);
+}
+
+/// Renders the global bindings into the `#environment` side panel.
+fn update_environment_panel() -> Result<(), JsValue> {
+    let document = web_sys::window().unwrap().document().unwrap();
+    let Some(panel) = document.get_element_by_id("environment") else {
+        return Ok(());
+    };
+    panel.set_inner_html("");
+    REPL.with(|repl| {
+        for (name, r#type, _, inspect) in repl.interpreter().globals() {
+            let row = document.create_element("tr")?;
+            for text in [name, r#type, inspect] {
+                let cell = document.create_element("td")?;
+                cell.set_text_content(Some(&text));
+                row.append_child(&cell)?;
+            }
+            panel.append_child(&row)?;
+        }
+        Ok(())
+    })
+}
+
+/// Runs the line being edited as a command or evaluates it, and prints the
+/// output.
+fn evaluate_line(line_editor: &mut LineEditor) {
+    line_editor.submit();
+    match REPL.with(|repl| repl.eval_line(line_editor.buffer())) {
+        LineOutput::Command(CommandOutput::Clear) => {
+            line_editor.enter("");
+            line_editor.clear_screen();
+        }
+        output => {
+            // The terminal needs carriage returns to start lines at the left
+            // edge.
+            line_editor.enter(&output.lines().join("\n").replace('\n', "\r\n"));
+            // Evaluation and commands like `:unset` change the bindings.
+            if let Err(e) = update_environment_panel() {
+                log!("Failed updating the environment panel: {:?}", e);
+            }
+        }
+    }
+}
+
+#[wasm_bindgen(start)]
+pub fn main() -> Result<(), JsValue> {
+    #[cfg(feature = "console_error_panic_hook")]
+    console_error_panic_hook::set_once();
+
+    let terminal: Terminal = Terminal::new(
+        TerminalOptions::new()
+            .with_cursor_blink(false)
+            .with_cursor_width(10)
+            .with_font_size(16)
+            .with_draw_bold_text_in_bright_colors(true)
+            .with_right_click_selects_word(true)
+            .with_bell_style(BellStyle::Both)
+            .with_theme(
+                Theme::new()
+                    .with_foreground("#98FB98")
+                    .with_background("#000000"),
+            ),
+    );
+
+    let terminal_element = web_sys::window()
+        .unwrap()
+        .document()
+        .unwrap()
+        .get_element_by_id("terminal")
+        .unwrap();
+
+    terminal.open(terminal_element.dyn_into()?);
+
+    // `puts` and `print` write straight to the terminal.
+    let output: Terminal = terminal.clone().dyn_into()?;
+    REPL.with(|repl| {
+        repl.interpreter()
+            .set_output(move |text| output.write(&text.replace('\n', "\r\n")))
+    });
+
+    let term: Terminal = terminal.clone().dyn_into()?;
+    let mut line_editor = LineEditor::new(term, PROMPT);
+    line_editor.prompt();
+
+    let callback_ondata = Closure::wrap(Box::new(move |e: String| {
+        let input_bytes = e.as_bytes();
+        let key = parse_key_event(input_bytes);
+        log!("{}", e);
+        match key.modifiers {
+            KeyModifiers::None => match key.code {
+                KeyCode::Char(c) => {
+                    line_editor.insert_char(c);
+                }
+                KeyCode::Enter => evaluate_line(&mut line_editor),
+                KeyCode::Backspace => {
+                    line_editor.delete_left();
+                }
+                KeyCode::Delete => {
+                    line_editor.delete_right();
+                }
+                KeyCode::Left => {
+                    line_editor.move_left(1);
+                }
+                KeyCode::Right => {
+                    line_editor.move_right(1);
+                }
+                KeyCode::Home => {
+                    line_editor.move_start();
+                }
+                KeyCode::End => {
+                    line_editor.move_end();
+                }
+                _ => {}
+            },
+            KeyModifiers::Control => match key.code {
+                KeyCode::Char(c) => match c {
+                    'l' => line_editor.clear_screen(),
+                    'a' => line_editor.move_start(),
+                    'e' => line_editor.move_end(),
+                    'b' => line_editor.move_left(1),
+                    'f' => line_editor.move_right(1),
+                    'd' => line_editor.delete_right(),
+                    'h' => line_editor.delete_left(),
+                    'u' => line_editor.delete_line(),
+                    'k' => line_editor.delete_from_cursor(),
+                    // 'c' => line_buffer.term.write(&format!("\x1b[{}D", 3)),
+                    _ => {}
+                },
+                _ => {}
+            },
+            KeyModifiers::Alt => match key.code {
+                KeyCode::Char(c) => match c {
+                    'b' => line_editor.word_left(),
+                    'f' => line_editor.word_right(),
+                    _ => {}
+                },
+                KeyCode::Left => {
+                    line_editor.word_left();
+                }
+                KeyCode::Right => {
+                    line_editor.word_right();
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        let src = line_editor.buffer();
+        render_inspector(&parse_to_json(src), &lex_to_json(src));
+    }) as Box<dyn FnMut(_)>);
+
+    terminal.on_data(callback_ondata.as_ref().unchecked_ref());
+
+    callback_ondata.forget();
+
+    let addon = FitAddon::new();
+    terminal.load_addon(addon.clone().dyn_into::<FitAddon>()?.into());
+    addon.fit();
+    terminal.focus();
+
+    Ok(())
+}
@@ -0,0 +1,47 @@
+// Renders the AST and token inspector tabs. Called from the wasm crate with
+// the JSON produced by `parse_to_json` and `lex_to_json` whenever the input
+// line changes.
+
+function renderNode(node) {
+  const label = node.edge ? `${node.edge}: ${node.label}` : node.label;
+  if (node.children.length === 0) {
+    const leaf = document.createElement("div");
+    leaf.className = "leaf";
+    leaf.textContent = label;
+    return leaf;
+  }
+  const details = document.createElement("details");
+  details.open = true;
+  const summary = document.createElement("summary");
+  summary.textContent = label;
+  details.append(summary, ...node.children.map(renderNode));
+  return details;
+}
+
+export function render_inspector(astJson, tokensJson) {
+  const { ast, errors } = JSON.parse(astJson);
+  const astPanel = document.getElementById("ast");
+  if (astPanel) {
+    const messages = errors.map((error) => {
+      const item = document.createElement("div");
+      item.className = "error";
+      item.textContent = error;
+      return item;
+    });
+    astPanel.replaceChildren(...messages, renderNode(ast));
+  }
+
+  const tokensPanel = document.getElementById("tokens");
+  if (tokensPanel) {
+    const rows = JSON.parse(tokensJson).map((token) => {
+      const row = document.createElement("tr");
+      for (const text of [token.kind, token.text, `${token.start}..=${token.end}`]) {
+        const cell = document.createElement("td");
+        cell.textContent = text;
+        row.append(cell);
+      }
+      return row;
+    });
+    tokensPanel.replaceChildren(...rows);
+  }
+}
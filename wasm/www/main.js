@@ -2,4 +2,14 @@ import "@xterm/xterm/css/xterm.css";
 import "@xterm/xterm/lib/xterm.js";
 import "./style.css";
 import * as wasm from "../pkg/wasm.js";
+
+for (const button of document.querySelectorAll("aside nav button")) {
+  button.addEventListener("click", () => {
+    for (const other of document.querySelectorAll("aside nav button")) {
+      other.classList.toggle("active", other === button);
+      document.getElementById(other.dataset.tab).hidden = other !== button;
+    }
+  });
+}
+
 export default wasm;